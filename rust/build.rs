@@ -0,0 +1,50 @@
+//! Records the resolved verus_syn version for `capabilities()`
+
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let manifest_dir = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default());
+    let version = locked_version(&manifest_dir)
+        .or_else(|| resolved_version(&manifest_dir))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=VERUS_SYN_VERSION={}", version);
+}
+
+/// The verus_syn version in the Cargo.lock of this package or of the
+/// workspace it is a member of
+fn locked_version(manifest_dir: &Path) -> Option<String> {
+    let lock_path = manifest_dir.ancestors().map(|dir| dir.join("Cargo.lock")).find(|path| path.is_file())?;
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    let lock = std::fs::read_to_string(&lock_path).ok()?;
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == r#"name = "verus_syn""# {
+            let version = lines.next()?.trim().strip_prefix("version = ")?;
+            return Some(version.trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// The verus_syn version cargo resolves for this package, for builds without
+/// a lock file (as a dependency, or from a source distribution)
+fn resolved_version(manifest_dir: &Path) -> Option<String> {
+    let lock_path = manifest_dir.join("Cargo.lock");
+    let had_lock = lock_path.is_file();
+    let output = std::process::Command::new(std::env::var_os("CARGO")?)
+        .args(["metadata", "--format-version", "1", "--offline", "--manifest-path"])
+        .arg(manifest_dir.join("Cargo.toml"))
+        .output()
+        .ok();
+    // Resolving writes a lock file, which must not be left in the sources
+    if !had_lock {
+        let _ = std::fs::remove_file(&lock_path);
+    }
+    let output = output?;
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let root = metadata["resolve"]["root"].as_str()?;
+    let node = metadata["resolve"]["nodes"].as_array()?.iter().find(|node| node["id"] == root)?;
+    let dep = node["deps"].as_array()?.iter().find(|dep| dep["name"] == "verus_syn")?;
+    let package = metadata["packages"].as_array()?.iter().find(|package| package["id"] == dep["pkg"])?;
+    package["version"].as_str().map(str::to_string)
+}
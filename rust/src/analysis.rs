@@ -0,0 +1,8641 @@
+//! Lints, queries and reports built on the extracted specs
+
+use crate::extract::*;
+use crate::model::*;
+use crate::visitor::*;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::Path;
+use verus_syn::spanned::Spanned;
+use verus_syn::visit::Visit;
+use verus_syn::{Expr, FnMode, ImplItemFn, Item, ItemFn, ItemMacro, Signature, TraitItemFn};
+
+/// Non-generic type aliases by name (the first definition of a name wins)
+type AliasMap = HashMap<String, verus_syn::Type>;
+
+/// Replace each use of an alias of `aliases` with the aliased type, expanded
+/// in turn, spanned like the name it replaces; report whether there was any
+///
+/// Names right after `::` or `.` are left alone, and so is an alias met again
+/// while its own expansion is under way (listed in `active`), which breaks cycles.
+fn substitute_aliases(tokens: TokenStream, aliases: &AliasMap, active: &mut Vec<String>) -> (TokenStream, bool) {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut out = Vec::with_capacity(tts.len());
+    let mut replaced = false;
+    for (i, tt) in tts.iter().enumerate() {
+        match tt {
+            TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                let qualified = i > 0 && matches!(&tts[i - 1], TokenTree::Punct(p) if matches!(p.as_char(), ':' | '.'));
+                let Some(ty) = aliases.get(&name).filter(|_| !qualified && !active.contains(&name)) else {
+                    out.push(tt.clone());
+                    continue;
+                };
+                let in_path = match tts.get(i + 1) {
+                    Some(TokenTree::Punct(p)) => p.as_char() == ':' && p.spacing() == Spacing::Joint,
+                    Some(TokenTree::Group(g)) => g.delimiter() == Delimiter::Parenthesis,
+                    _ => false,
+                };
+                active.push(name);
+                let (expanded, _) = substitute_aliases(quote::quote!(#ty), aliases, active);
+                active.pop();
+                let expanded = verus_syn::parse2::<verus_syn::Type>(expanded).unwrap_or_else(|_| ty.clone());
+                out.extend(respan(type_tokens(&expanded, in_path), ident.span()));
+                replaced = true;
+            }
+            TokenTree::Group(group) => {
+                let (stream, inner) = substitute_aliases(group.stream(), aliases, active);
+                let mut new_group = proc_macro2::Group::new(group.delimiter(), stream);
+                new_group.set_span(group.span());
+                out.push(TokenTree::Group(new_group));
+                replaced |= inner;
+            }
+            _ => out.push(tt.clone()),
+        }
+    }
+    (out.into_iter().collect(), replaced)
+}
+
+/// `node` with the aliases of `aliases` expanded, or as is if there are none
+/// or the result does not parse back
+fn with_aliases_expanded<T: verus_syn::parse::Parse + quote::ToTokens + Clone>(node: &T, aliases: &AliasMap) -> T {
+    match substitute_aliases(quote::quote!(#node), aliases, &mut Vec::new()) {
+        (tokens, true) => verus_syn::parse2::<T>(tokens).unwrap_or_else(|_| node.clone()),
+        _ => node.clone(),
+    }
+}
+
+/// Combine the fingerprints of all functions into one order-insensitive digest
+pub fn combine_fingerprints(functions: &[FunctionSpecs]) -> String {
+    let mut fingerprints: Vec<&str> = functions.iter().map(|f| f.spec_fingerprint.as_str()).collect();
+    fingerprints.sort_unstable();
+    let mut hasher = Sha256::new();
+    for fingerprint in fingerprints {
+        hasher.update(fingerprint.as_bytes());
+        hasher.update(b"\n");
+    }
+    to_hex(&hasher.finalize())
+}
+
+/// The trait name of a bound, without path prefix or generic arguments
+fn bound_name(bound: &str) -> &str {
+    let end = bound.find(['<', '(']).unwrap_or(bound.len());
+    let path = &bound[..end];
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Whether every bounded generic parameter of a function could be instantiated
+/// with one of the known types, given `known_bounds` (type -> traits it implements).
+/// Lifetime and `?Sized` bounds are ignored.
+fn bounds_satisfied(specs: &FunctionSpecs, known_bounds: &HashMap<String, Vec<String>>) -> bool {
+    specs.bounds.iter().all(|(_, required)| {
+        let required: Vec<&str> = required
+            .iter()
+            .filter(|b| !b.starts_with('\'') && !b.starts_with('?'))
+            .map(|b| bound_name(b))
+            .collect();
+        required.is_empty()
+            || known_bounds.values().any(|known| {
+                required
+                    .iter()
+                    .all(|r| known.iter().any(|k| bound_name(k) == *r))
+            })
+    })
+}
+
+/// Roll up counts over extracted functions
+pub(crate) fn compute_stats(functions: &[FunctionSpecs]) -> SpecStats {
+    let mut stats = SpecStats::default();
+    for f in functions {
+        stats.functions += 1;
+        match f.mode.as_str() {
+            "proof" => stats.proof_functions += 1,
+            "spec" | "spec(checked)" => stats.spec_functions += 1,
+            _ => stats.exec_functions += 1,
+        }
+        stats.requires_clauses += f.requires.len();
+        stats.ensures_clauses += f.ensures.len();
+        stats.decreases_clauses += f.decreases.len();
+        stats.with_requires += usize::from(f.has_requires);
+        stats.with_ensures += usize::from(f.has_ensures);
+        stats.with_decreases += usize::from(f.has_decreases);
+        stats.memoized_functions += usize::from(f.is_memoized);
+        stats.inlined_spec_functions += usize::from(f.is_inlined_spec);
+        for attr in &f.attributes {
+            *stats.attributes.entry(attr.clone()).or_default() += 1;
+        }
+        stats.compute_proofs += f.asserts.iter().filter(|a| is_compute_prover(a.prover.as_deref())).count();
+    }
+    stats
+}
+
+/// Whether an assert's prover evaluates the assertion (`compute`, `compute_only`)
+fn is_compute_prover(prover: Option<&str>) -> bool {
+    matches!(prover, Some("compute" | "compute_only"))
+}
+
+/// The asserts proved by evaluation, in source order
+pub fn compute_proofs_core(content: &str) -> Result<Vec<ComputeProof>, String> {
+    let functions = parse_verus_file_core(content)?;
+    Ok(functions
+        .into_iter()
+        .flat_map(|f| {
+            let function = f.name;
+            f.asserts.into_iter().filter_map(move |a| {
+                is_compute_prover(a.prover.as_deref()).then(|| ComputeProof {
+                    function: function.clone(),
+                    line_number: a.line_number,
+                    expr: a.expr,
+                    prover: a.prover.unwrap_or_default(),
+                })
+            })
+        })
+        .collect())
+}
+
+/// The asserts of `files` (`(path, content)` pairs) with `label` as a string
+/// literal argument or as their trailing comment, in file and line order
+pub fn find_assert_by_label_core(files: &[(String, String)], label: &str) -> Result<Vec<LabeledAssert>, String> {
+    let label = label.trim();
+    let mut found = Vec::new();
+    for (path, content) in files {
+        let functions = parse_verus_file_core(content).map_err(|e| format!("{}: {}", path, e))?;
+        for f in &functions {
+            let matching = f.asserts.iter().filter(|a| {
+                a.labels.iter().any(|l| l == label) || a.comment.as_deref().is_some_and(|c| c == label)
+            });
+            found.extend(matching.map(|a| LabeledAssert {
+                file_path: path.clone(),
+                function: f.qualified_name(),
+                line_number: a.line_number,
+                expr: a.expr.clone(),
+                labels: a.labels.clone(),
+                comment: a.comment.clone(),
+            }));
+        }
+    }
+    Ok(found)
+}
+
+/// The integer casts in exec code of `files` (`(path, content)` pairs), per file
+pub fn truncation_report_core(files: &[(String, String)]) -> Result<BTreeMap<String, Vec<FunctionCast>>, String> {
+    let mut report = BTreeMap::new();
+    for (path, content) in files {
+        let functions = parse_verus_file_core(content).map_err(|e| format!("{}: {}", path, e))?;
+        let casts: Vec<FunctionCast> = functions
+            .into_iter()
+            .flat_map(|f| {
+                let function = f.name;
+                f.casts.into_iter().map(move |cast| FunctionCast { function: function.clone(), cast })
+            })
+            .collect();
+        report.insert(path.clone(), casts);
+    }
+    Ok(report)
+}
+
+/// Options for `render_markdown`
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    /// Show each function's full source instead of just its clauses
+    pub include_bodies: bool,
+    /// Only show proof functions
+    pub proof_only: bool,
+}
+
+/// Render the clauses of a function as a Verus snippet
+fn render_clauses(f: &FunctionSpecs) -> String {
+    let mut out = String::new();
+    for (keyword, clauses) in [("requires", &f.requires), ("ensures", &f.ensures), ("decreases", &f.decreases)] {
+        if clauses.is_empty() {
+            continue;
+        }
+        out.push_str(keyword);
+        out.push('\n');
+        for clause in clauses {
+            out.push_str(&format!("    {},\n", clause));
+        }
+    }
+    out
+}
+
+/// Source lines `start..=end` (1-indexed) of `content`
+fn source_lines(content: &str, start: usize, end: usize) -> String {
+    content
+        .lines()
+        .skip(start.saturating_sub(1))
+        .take(end + 1 - start.max(1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render extraction results for a set of files as a Markdown report.
+///
+/// `files` holds `(path, content)` pairs; the path may be empty for a single
+/// in-memory file. Sections follow file order, then the order in which modules
+/// first appear.
+pub fn render_markdown_core(files: &[(String, String)], options: &MarkdownOptions) -> String {
+    let mut sections: Vec<(String, Vec<(FunctionSpecs, &str)>)> = Vec::new();
+    let mut errors: Vec<(String, String)> = Vec::new();
+    let mut shown: Vec<FunctionSpecs> = Vec::new();
+
+    for (path, content) in files {
+        let functions = match parse_verus_file_core(content) {
+            Ok(functions) => functions,
+            Err(e) => {
+                errors.push((path.clone(), e));
+                continue;
+            }
+        };
+        for f in functions {
+            if options.proof_only && !f.is_proof {
+                continue;
+            }
+            let module = if f.module_path.is_empty() { "(root)" } else { f.module_path.as_str() };
+            let title = if path.is_empty() { module.to_string() } else { format!("{} — {}", path, module) };
+            shown.push(f.clone());
+            match sections.iter_mut().find(|(t, _)| *t == title) {
+                Some((_, entries)) => entries.push((f, content.as_str())),
+                None => sections.push((title, vec![(f, content.as_str())])),
+            }
+        }
+    }
+
+    let stats = compute_stats(&shown);
+    let mut out = String::from("# Verus spec report\n\n");
+    out.push_str("| Functions | proof | spec | exec | requires | ensures | decreases |\n");
+    out.push_str("|---:|---:|---:|---:|---:|---:|---:|\n");
+    out.push_str(&format!(
+        "| {} | {} | {} | {} | {} | {} | {} |\n",
+        stats.functions,
+        stats.proof_functions,
+        stats.spec_functions,
+        stats.exec_functions,
+        stats.requires_clauses,
+        stats.ensures_clauses,
+        stats.decreases_clauses
+    ));
+
+    for (title, entries) in &sections {
+        out.push_str(&format!("\n## `{}`\n\n", title));
+        out.push_str("| Name | Mode | Lines | requires | ensures | decreases |\n");
+        out.push_str("|---|---|---|---:|---:|---:|\n");
+        for (f, _) in entries {
+            out.push_str(&format!(
+                "| `{}` | {} | {}–{} | {} | {} | {} |\n",
+                f.name,
+                f.mode,
+                f.line_number.unwrap_or(0),
+                f.end_line.unwrap_or(0),
+                f.requires.len(),
+                f.ensures.len(),
+                f.decreases.len()
+            ));
+        }
+        for (f, content) in entries {
+            let code = if options.include_bodies {
+                source_lines(content, f.line_number.unwrap_or(1), f.end_line.unwrap_or(0))
+            } else {
+                render_clauses(f).trim_end().to_string()
+            };
+            if code.is_empty() {
+                continue;
+            }
+            out.push_str(&format!(
+                "\n<details>\n<summary><code>{}</code></summary>\n\n```verus\n{}\n```\n\n</details>\n",
+                f.name, code
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        out.push_str("\n## Errors\n\n");
+        for (path, error) in &errors {
+            let path = if path.is_empty() { "(input)" } else { path.as_str() };
+            out.push_str(&format!("- `{}`: {}\n", path, error));
+        }
+    }
+    out
+}
+
+/// The operator with its operands swapped (`a > b` is `b < a`)
+fn mirrored_op(op: &str) -> Option<&'static str> {
+    match op {
+        "<" => Some(">"),
+        ">" => Some("<"),
+        "<=" => Some(">="),
+        ">=" => Some("<="),
+        "==" => Some("=="),
+        "!=" => Some("!="),
+        _ => None,
+    }
+}
+
+/// Parameter name standing for a `_` hole of a `find_pattern` pattern, which
+/// matches anything without binding
+const ANONYMOUS_HOLE: &str = "__hole";
+
+/// Syntactic matcher for `requires` clauses against known facts.
+///
+/// Lemma parameters are wildcards: the first occurrence binds the parameter
+/// to the (normalized) fact subexpression it lines up with, and every later
+/// occurrence must line up with the same text.
+struct ClauseMatcher<'a> {
+    params: &'a [String],
+    bindings: HashMap<String, String>,
+}
+
+impl<'a> ClauseMatcher<'a> {
+    fn new(params: &'a [String]) -> Self {
+        Self {
+            params,
+            bindings: HashMap::new(),
+        }
+    }
+
+    fn param_of(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Path(path) if path.qself.is_none() => path
+                .path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .filter(|name| self.params.contains(name)),
+            _ => None,
+        }
+    }
+
+    fn mentions_param(&self, expr: &Expr) -> bool {
+        fn walk(tokens: TokenStream, params: &[String]) -> bool {
+            tokens.into_iter().any(|tt| match tt {
+                TokenTree::Ident(ident) => params.iter().any(|p| ident == p),
+                TokenTree::Group(group) => walk(group.stream(), params),
+                _ => false,
+            })
+        }
+        walk(quote::quote!(#expr), self.params)
+    }
+
+    /// Unify `pattern` (from the lemma) with `fact`, extending the bindings.
+    /// On failure the bindings may be partially extended; callers snapshot them.
+    fn unify(&mut self, pattern: &Expr, fact: &Expr) -> bool {
+        let pattern = strip_parens(pattern);
+        let fact = strip_parens(fact);
+
+        if let Some(param) = self.param_of(pattern) {
+            if param == ANONYMOUS_HOLE {
+                return true;
+            }
+            let value = quote::quote!(#fact).to_string();
+            return match self.bindings.get(&param) {
+                Some(bound) => *bound == value,
+                None => {
+                    self.bindings.insert(param, value);
+                    true
+                }
+            };
+        }
+
+        match (pattern, fact) {
+            (Expr::Binary(p), Expr::Binary(f)) => {
+                let (p_op, f_op) = (bin_op_text(&p.op), bin_op_text(&f.op));
+                if p_op == f_op {
+                    let saved = self.bindings.clone();
+                    if self.unify(&p.left, &f.left) && self.unify(&p.right, &f.right) {
+                        return true;
+                    }
+                    self.bindings = saved;
+                }
+                if mirrored_op(&p_op) == Some(f_op.as_str()) {
+                    return self.unify(&p.left, &f.right) && self.unify(&p.right, &f.left);
+                }
+                false
+            }
+            (Expr::Unary(p), Expr::Unary(f)) => {
+                let (p_op, f_op) = (&p.op, &f.op);
+                quote::quote!(#p_op).to_string() == quote::quote!(#f_op).to_string() && self.unify(&p.expr, &f.expr)
+            }
+            (Expr::Call(p), Expr::Call(f)) => {
+                p.args.len() == f.args.len()
+                    && self.unify(&p.func, &f.func)
+                    && p.args.iter().zip(f.args.iter()).all(|(pa, fa)| self.unify(pa, fa))
+            }
+            (Expr::MethodCall(p), Expr::MethodCall(f)) => {
+                let (p_turbofish, f_turbofish) = (&p.turbofish, &f.turbofish);
+                p.method == f.method
+                    && p.args.len() == f.args.len()
+                    && quote::quote!(#p_turbofish).to_string() == quote::quote!(#f_turbofish).to_string()
+                    && self.unify(&p.receiver, &f.receiver)
+                    && p.args.iter().zip(f.args.iter()).all(|(pa, fa)| self.unify(pa, fa))
+            }
+            (Expr::Field(p), Expr::Field(f)) => p.member == f.member && self.unify(&p.base, &f.base),
+            (Expr::Index(p), Expr::Index(f)) => self.unify(&p.expr, &f.expr) && self.unify(&p.index, &f.index),
+            (Expr::Cast(p), Expr::Cast(f)) => {
+                let (p_ty, f_ty) = (&p.ty, &f.ty);
+                quote::quote!(#p_ty).to_string() == quote::quote!(#f_ty).to_string() && self.unify(&p.expr, &f.expr)
+            }
+            (Expr::Reference(p), Expr::Reference(f)) => {
+                p.mutability.is_some() == f.mutability.is_some() && self.unify(&p.expr, &f.expr)
+            }
+            (Expr::Tuple(p), Expr::Tuple(f)) => {
+                p.elems.len() == f.elems.len() && p.elems.iter().zip(f.elems.iter()).all(|(pe, fe)| self.unify(pe, fe))
+            }
+            // Anything else must be parameter-free and textually identical
+            _ => !self.mentions_param(pattern) && quote::quote!(#pattern).to_string() == quote::quote!(#fact).to_string(),
+        }
+    }
+
+    /// Find consistent bindings under which every clause matches some fact
+    fn entails_all(&mut self, clauses: &[&Expr], facts: &[&Expr]) -> bool {
+        let Some((first, rest)) = clauses.split_first() else {
+            return true;
+        };
+        for fact in facts {
+            let saved = self.bindings.clone();
+            if self.unify(first, fact) && self.entails_all(rest, facts) {
+                return true;
+            }
+            self.bindings = saved;
+        }
+        false
+    }
+}
+
+/// Expression tree of a spec clause, serialized as JSON by `clause_ast`
+///
+/// Every node is an object whose `"node"` field names its kind:
+/// - `binary`: `{"op", "left", "right"}` for arithmetic, comparison, logical and
+///   Verus operators (`==>`, `<==>`, `=~=`, ...)
+/// - `unary`: `{"op", "operand"}` for `!`, `-` and `*`
+/// - `call`: `{"callee", "args"}`, the callee as a path (`vstd::seq::Seq::empty`)
+///   or as source text when it is not a path
+/// - `method_call`: `{"receiver", "method", "args"}`
+/// - `quantifier`: `{"quantifier", "binders", "body"}` for `forall`, `exists` and
+///   `choose`; each binder is `{"name", "ty"}` with `ty` null when omitted
+///   (trigger attributes are dropped)
+/// - `field`: `{"base", "member"}`, the member a name or tuple index
+/// - `index`: `{"base", "index"}`
+/// - `cast`: `{"expr", "ty"}`
+/// - `if`: `{"cond", "then_branch", "else_branch"}`, `else_branch` null if absent
+/// - `path`: `{"path"}` for variables and constants
+/// - `lit`: `{"kind", "value", "suffix"}`, `kind` one of "int", "float", "bool",
+///   "str", "char" or "other", `suffix` the type suffix (`u64` in `1u64`) or null
+/// - `opaque`: `{"text"}`, the source text of anything else
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "node", rename_all = "snake_case")]
+pub enum ClauseAst {
+    Binary { op: String, left: Box<ClauseAst>, right: Box<ClauseAst> },
+    Unary { op: String, operand: Box<ClauseAst> },
+    Call { callee: String, args: Vec<ClauseAst> },
+    MethodCall { receiver: Box<ClauseAst>, method: String, args: Vec<ClauseAst> },
+    Quantifier { quantifier: String, binders: Vec<Binder>, body: Box<ClauseAst> },
+    Field { base: Box<ClauseAst>, member: String },
+    Index { base: Box<ClauseAst>, index: Box<ClauseAst> },
+    Cast { expr: Box<ClauseAst>, ty: String },
+    If { cond: Box<ClauseAst>, then_branch: Box<ClauseAst>, else_branch: Option<Box<ClauseAst>> },
+    Path { path: String },
+    Lit { kind: String, value: String, suffix: Option<String> },
+    Opaque { text: String },
+}
+
+/// A variable bound by a quantifier
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Binder {
+    pub name: String,
+    pub ty: Option<String>,
+}
+
+/// Source text of a token stream without the spaces quote inserts around punctuation
+fn compact_text(tokens: TokenStream) -> String {
+    tokens.to_string().replace(" :: ", "::").replace(" < ", "<").replace(" >", ">").replace(" ,", ",")
+}
+
+/// The tree of a block that consists of a single expression, else opaque
+fn block_ast(block: &verus_syn::Block) -> ClauseAst {
+    match block.stmts.as_slice() {
+        [verus_syn::Stmt::Expr(expr, None)] => clause_ast_of(expr),
+        _ => ClauseAst::Opaque {
+            text: quote::quote!(#block).to_string(),
+        },
+    }
+}
+
+/// Parse a quantifier (`forall|x: T, y| body`) from its tokens
+///
+/// Done on tokens so it does not depend on how verus_syn represents quantifiers.
+fn quantifier_ast(tokens: TokenStream) -> Option<ClauseAst> {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let quantifier = match tts.first() {
+        Some(TokenTree::Ident(ident)) if ident == "forall" || ident == "exists" || ident == "choose" => ident.to_string(),
+        _ => return None,
+    };
+    let is_bar = |tt: &TokenTree| matches!(tt, TokenTree::Punct(p) if p.as_char() == '|');
+    if !tts.get(1).is_some_and(is_bar) {
+        return None;
+    }
+    let close = 2 + tts[2..].iter().position(is_bar)?;
+
+    // Split the binders at commas outside generic arguments
+    let mut binders = Vec::new();
+    let mut angle_depth = 0usize;
+    let mut start = 2;
+    for i in 2..=close {
+        let is_separator = match &tts[i] {
+            _ if i == close => true,
+            TokenTree::Punct(p) if p.as_char() == '<' => {
+                angle_depth += 1;
+                false
+            }
+            TokenTree::Punct(p) if p.as_char() == '>' => {
+                angle_depth = angle_depth.saturating_sub(1);
+                false
+            }
+            TokenTree::Punct(p) => p.as_char() == ',' && angle_depth == 0,
+            _ => false,
+        };
+        if !is_separator {
+            continue;
+        }
+        if let [TokenTree::Ident(name), rest @ ..] = &tts[start..i] {
+            let ty = match rest {
+                [TokenTree::Punct(colon), ty @ ..] if colon.as_char() == ':' => {
+                    Some(compact_text(ty.iter().cloned().collect()))
+                }
+                _ => None,
+            };
+            binders.push(Binder {
+                name: name.to_string(),
+                ty,
+            });
+        }
+        start = i + 1;
+    }
+
+    // Drop `#![trigger ...]` attributes at the start of the body
+    let mut rest = &tts[close + 1..];
+    while let [TokenTree::Punct(hash), TokenTree::Punct(bang), TokenTree::Group(_), tail @ ..] = rest {
+        if hash.as_char() != '#' || bang.as_char() != '!' {
+            break;
+        }
+        rest = tail;
+    }
+    let body_tokens: TokenStream = rest.iter().cloned().collect();
+    let body = match verus_syn::parse2::<Expr>(body_tokens.clone()) {
+        Ok(expr) => clause_ast_of(&expr),
+        Err(_) => ClauseAst::Opaque {
+            text: body_tokens.to_string(),
+        },
+    };
+    Some(ClauseAst::Quantifier {
+        quantifier,
+        binders,
+        body: Box::new(body),
+    })
+}
+
+/// Map a clause expression to its tree
+fn clause_ast_of(expr: &Expr) -> ClauseAst {
+    let expr = strip_parens(expr);
+    let boxed = |e: &Expr| Box::new(clause_ast_of(e));
+    match expr {
+        Expr::Binary(bin) => ClauseAst::Binary {
+            op: bin_op_text(&bin.op),
+            left: boxed(&bin.left),
+            right: boxed(&bin.right),
+        },
+        Expr::Unary(unary) if matches!(unary.op, verus_syn::UnOp::Not(_) | verus_syn::UnOp::Neg(_) | verus_syn::UnOp::Deref(_)) => {
+            let op = &unary.op;
+            ClauseAst::Unary {
+                op: quote::quote!(#op).to_string(),
+                operand: boxed(&unary.expr),
+            }
+        }
+        Expr::Call(call) => ClauseAst::Call {
+            callee: call_path(call).unwrap_or_else(|| {
+                let func = &call.func;
+                quote::quote!(#func).to_string()
+            }),
+            args: call.args.iter().map(clause_ast_of).collect(),
+        },
+        Expr::MethodCall(call) => ClauseAst::MethodCall {
+            receiver: boxed(&call.receiver),
+            method: call.method.to_string(),
+            args: call.args.iter().map(clause_ast_of).collect(),
+        },
+        Expr::Field(field) => {
+            let member = &field.member;
+            ClauseAst::Field {
+                base: boxed(&field.base),
+                member: quote::quote!(#member).to_string(),
+            }
+        }
+        Expr::Index(index) => ClauseAst::Index {
+            base: boxed(&index.expr),
+            index: boxed(&index.index),
+        },
+        Expr::Cast(cast) => {
+            let ty = &cast.ty;
+            ClauseAst::Cast {
+                expr: boxed(&cast.expr),
+                ty: compact_text(quote::quote!(#ty)),
+            }
+        }
+        Expr::If(if_expr) => ClauseAst::If {
+            cond: boxed(&if_expr.cond),
+            then_branch: Box::new(block_ast(&if_expr.then_branch)),
+            else_branch: if_expr.else_branch.as_ref().map(|(_, e)| boxed(e)),
+        },
+        Expr::Block(block) if block.label.is_none() => block_ast(&block.block),
+        Expr::Path(path) => ClauseAst::Path {
+            path: compact_text(quote::quote!(#path)),
+        },
+        Expr::Lit(lit) => {
+            let (kind, value, suffix) = match &lit.lit {
+                verus_syn::Lit::Int(i) => ("int", i.base10_digits().to_string(), i.suffix()),
+                verus_syn::Lit::Float(f) => ("float", f.base10_digits().to_string(), f.suffix()),
+                verus_syn::Lit::Bool(b) => ("bool", b.value.to_string(), ""),
+                verus_syn::Lit::Str(s) => ("str", s.value(), s.suffix()),
+                verus_syn::Lit::Char(c) => ("char", c.value().to_string(), c.suffix()),
+                other => ("other", quote::quote!(#other).to_string(), ""),
+            };
+            ClauseAst::Lit {
+                kind: kind.to_string(),
+                value,
+                suffix: if suffix.is_empty() { None } else { Some(suffix.to_string()) },
+            }
+        }
+        _ => quantifier_ast(quote::quote!(#expr)).unwrap_or_else(|| ClauseAst::Opaque {
+            text: quote::quote!(#expr).to_string(),
+        }),
+    }
+}
+
+/// Word for an operator in a lemma name (`*` is "mul", `<=` is "le")
+fn op_word(op: &str) -> Option<&'static str> {
+    Some(match op {
+        "*" => "mul",
+        "+" => "add",
+        "-" => "sub",
+        "/" => "div",
+        "%" => "mod",
+        "<<" => "shl",
+        ">>" => "shr",
+        "&" => "bitand",
+        "|" => "bitor",
+        "^" => "xor",
+        "==" | "===" | "=~=" => "eq",
+        "!=" => "ne",
+        "<" => "lt",
+        "<=" => "le",
+        ">" => "gt",
+        ">=" => "ge",
+        _ => return None,
+    })
+}
+
+/// The pieces a lemma name is made of: the operators and functions the clause
+/// is about (outermost first), its relation and an algebraic property
+#[derive(Debug, Default, PartialEq)]
+struct NameParts {
+    subjects: Vec<String>,
+    relation: Option<&'static str>,
+    property: Option<&'static str>,
+}
+
+/// Break an ensures clause into name parts; quantifiers and the premise of an
+/// implication are looked through
+fn name_parts(clause: &ClauseAst) -> NameParts {
+    fn collect_subjects(ast: &ClauseAst, subjects: &mut Vec<String>) {
+        let word = match ast {
+            ClauseAst::Binary { op, .. } => op_word(op).filter(|w| !is_relation_word(w)).map(str::to_string),
+            ClauseAst::Call { callee, .. } => callee.rsplit("::").next().map(str::to_string),
+            ClauseAst::MethodCall { method, .. } => Some(method.clone()),
+            _ => None,
+        };
+        if let Some(word) = word {
+            if !subjects.contains(&word) {
+                subjects.push(word);
+            }
+        }
+        match ast {
+            ClauseAst::Binary { left, right, .. } => {
+                collect_subjects(left, subjects);
+                collect_subjects(right, subjects);
+            }
+            ClauseAst::Unary { operand, .. } => collect_subjects(operand, subjects),
+            ClauseAst::Call { args, .. } => args.iter().for_each(|a| collect_subjects(a, subjects)),
+            ClauseAst::MethodCall { receiver, args, .. } => {
+                collect_subjects(receiver, subjects);
+                args.iter().for_each(|a| collect_subjects(a, subjects));
+            }
+            ClauseAst::Cast { expr, .. } => collect_subjects(expr, subjects),
+            _ => {}
+        }
+    }
+
+    let mut ast = clause;
+    loop {
+        match ast {
+            ClauseAst::Quantifier { body, .. } => ast = body,
+            ClauseAst::Binary { op, right, .. } if op == "==>" => ast = right,
+            _ => break,
+        }
+    }
+    let mut parts = NameParts::default();
+    collect_subjects(ast, &mut parts.subjects);
+    if let ClauseAst::Binary { op, left, right } = ast {
+        if let Some(relation) = op_word(op).filter(|w| is_relation_word(w)) {
+            parts.relation = Some(relation);
+            parts.property = algebraic_property(relation, left, right);
+        }
+    }
+    parts
+}
+
+fn is_relation_word(word: &str) -> bool {
+    ["eq", "ne", "lt", "le", "gt", "ge"].contains(&word)
+}
+
+/// "comm", "assoc" or "distrib" for equations and "mono" for inequalities
+/// between two applications of the same operator
+fn algebraic_property(relation: &str, left: &ClauseAst, right: &ClauseAst) -> Option<&'static str> {
+    type Operation<'a> = (&'a str, &'a ClauseAst, &'a ClauseAst);
+    fn binary(ast: &ClauseAst) -> Option<Operation<'_>> {
+        match ast {
+            ClauseAst::Binary { op, left, right } => Some((op.as_str(), left, right)),
+            _ => None,
+        }
+    }
+    let has_op = |ast: &ClauseAst, want: &str| matches!(ast, ClauseAst::Binary { op, .. } if op == want);
+    // x * (y + z) == x * y + x * z
+    let distributes = |(outer, a, b): Operation, (inner, c, d): Operation| {
+        outer != inner && (has_op(a, inner) || has_op(b, inner)) && has_op(c, outer) && has_op(d, outer)
+    };
+    let (l, r) = (binary(left)?, binary(right)?);
+    let nested_left = has_op(l.1, l.0) && has_op(r.2, l.0);
+    let nested_right = has_op(l.2, l.0) && has_op(r.1, l.0);
+    match relation {
+        "eq" if l.0 == r.0 && l.1 == r.2 && l.2 == r.1 => Some("comm"),
+        "eq" if l.0 == r.0 && (nested_left || nested_right) => Some("assoc"),
+        "eq" => (distributes(l, r) || distributes(r, l)).then_some("distrib"),
+        "lt" | "le" | "gt" | "ge" if l.0 == r.0 => Some("mono"),
+        _ => None,
+    }
+}
+
+/// Candidate lemma names for an ensures clause, best first
+///
+/// `order` lists the name parts after the prefix, from "subject" (the outermost
+/// operator or function), "relation" (omitted for equations that have a
+/// property) and "property". Candidates are the full name, the name with both
+/// leading subjects, the name without the property and the bare subject; one
+/// that collides with `existing` (or an earlier candidate) gets the first free
+/// `_2`, `_3`, ... suffix.
+pub fn suggest_lemma_name_core(
+    ensures: &str,
+    existing: &[String],
+    prefix: &str,
+    order: &[String],
+    limit: usize,
+) -> Result<Vec<String>, String> {
+    if let Some(part) = order.iter().find(|p| !["subject", "relation", "property"].contains(&p.as_str())) {
+        return Err(format!("Unknown name part '{}' (expected subject, relation or property)", part));
+    }
+    let expr: Expr = verus_syn::parse_str(ensures).map_err(|e| format!("Parse error: {}", e))?;
+    let parts = name_parts(&clause_ast_of(&expr));
+    let primary = parts.subjects.first().cloned().unwrap_or_else(|| "fact".to_string());
+    let relation = parts.relation.filter(|r| *r != "eq" || parts.property.is_none());
+
+    let compose = |subject: &str, property: Option<&str>| -> String {
+        let words: Vec<&str> = order
+            .iter()
+            .filter_map(|part| match part.as_str() {
+                "subject" => Some(subject),
+                "relation" => relation,
+                _ => property,
+            })
+            .collect();
+        format!("{}{}", prefix, words.join("_"))
+    };
+    let mut candidates = vec![compose(&primary, parts.property)];
+    if let Some(secondary) = parts.subjects.get(1) {
+        candidates.push(compose(&format!("{}_{}", primary, secondary), parts.property));
+    }
+    if parts.property.is_some() {
+        candidates.push(compose(&primary, None));
+    }
+    candidates.push(format!("{}{}", prefix, primary));
+
+    let mut taken: std::collections::HashSet<String> = existing.iter().cloned().collect();
+    let mut names = Vec::new();
+    for candidate in candidates {
+        if names.len() == limit {
+            break;
+        }
+        if names.iter().any(|n: &String| n == &candidate) {
+            continue;
+        }
+        let name = (1..)
+            .map(|n| if n == 1 { candidate.clone() } else { format!("{}_{}", candidate, n) })
+            .find(|name| !taken.contains(name))
+            .unwrap_or(candidate);
+        taken.insert(name.clone());
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Draft a standalone lemma whose ensures is a (failing) assert of
+/// `function_name`, as Verus source
+///
+/// `assert_text` is the asserted expression, with or without the surrounding
+/// `assert(...)`. The parameters are the assert's free variables: the
+/// function's parameters it uses, in signature order and with their types (a
+/// `&mut` one is taken by shared reference), then any other variable (a body
+/// local) typed `int` with a comment saying so. Names used as calls,
+/// functions of the file and UPPER_CASE constants are not variables. The
+/// requires are the function's requires clauses that mention only those
+/// variables. The name comes from `suggest_lemma_name_core`, avoiding the
+/// file's function names; the draft is checked by parsing it back.
+pub fn draft_lemma_from_assert_core(content: &str, function_name: &str, assert_text: &str) -> Result<String, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let (specs, sig) = finder
+        .functions
+        .iter()
+        .zip(&finder.signatures)
+        .find(|(specs, _)| specs.name == function_name)
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+
+    let tokens: TokenStream = assert_text.parse().map_err(|e| format!("Parse error: {}", e))?;
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let tokens = match tts.as_slice() {
+        [TokenTree::Ident(assert), TokenTree::Group(args)]
+            if assert == "assert" && args.delimiter() == Delimiter::Parenthesis =>
+        {
+            args.stream()
+        }
+        _ => tts.iter().cloned().collect(),
+    };
+    let expr: Expr = verus_syn::parse2(tokens.clone()).map_err(|e| format!("Parse error: {}", e))?;
+
+    // Names directly followed by an argument list are calls, not variables
+    fn call_heads(tokens: TokenStream, heads: &mut Vec<String>) {
+        let tts: Vec<TokenTree> = tokens.into_iter().collect();
+        for (i, tt) in tts.iter().enumerate() {
+            match (tt, tts.get(i + 1)) {
+                (TokenTree::Ident(ident), Some(TokenTree::Group(args)))
+                    if args.delimiter() == Delimiter::Parenthesis =>
+                {
+                    heads.push(ident.to_string())
+                }
+                (TokenTree::Group(group), _) => call_heads(group.stream(), heads),
+                _ => {}
+            }
+        }
+    }
+    let mut heads = Vec::new();
+    call_heads(tokens, &mut heads);
+
+    let mentions = clause_mentions(&expr, &param_names(sig));
+    let is_variable = |name: &String| {
+        !name.contains("::")
+            && !heads.contains(name)
+            && !finder.functions.iter().any(|f| &f.name == name)
+            && name.chars().any(|c| c.is_ascii_lowercase())
+    };
+    let mut params: Vec<ParamInfo> = specs
+        .params
+        .iter()
+        .filter(|p| mentions.params.contains(&p.name))
+        .cloned()
+        .map(|mut p| {
+            if p.reference == "&mut" {
+                p.reference = "&".to_string();
+            }
+            p
+        })
+        .collect();
+    let mut notes = String::new();
+    for name in mentions.externals.iter().filter(|name| is_variable(name)) {
+        notes.push_str(&format!("// NOTE: type of `{}` unknown, assumed int\n", name));
+        params.push(ParamInfo {
+            name: name.clone(),
+            ty: "int".to_string(),
+            reference: "by_value".to_string(),
+            ..Default::default()
+        });
+    }
+
+    let variables: Vec<String> = params.iter().map(|p| p.name.clone()).collect();
+    let requires = clause_exprs(sig, "requires")?
+        .into_iter()
+        .filter(|req| {
+            let used = clause_mentions(req, &param_names(sig)).params;
+            !used.is_empty() && used.iter().all(|name| variables.contains(name))
+        })
+        .map(|req| quote::quote!(#req).to_string())
+        .collect();
+    let ensures = quote::quote!(#expr).to_string();
+    let existing: Vec<String> = finder.functions.iter().map(|f| f.name.clone()).collect();
+    let order = ["subject", "relation", "property"].map(str::to_string);
+    let name = suggest_lemma_name_core(&ensures, &existing, "lemma_", &order, 1)?.remove(0);
+
+    let draft = FunctionSpecs {
+        name,
+        mode: "proof".to_string(),
+        is_proof: true,
+        params,
+        requires,
+        ensures: vec![ensures],
+        ..Default::default()
+    };
+    Ok(notes + &draft.to_verus_source(false)?)
+}
+
+/// The tree of clause `index` of `kind` in function `function_name`
+pub fn clause_ast_core(content: &str, function_name: &str, kind: &str, index: usize) -> Result<ClauseAst, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(Some(function_name.to_string()));
+    finder.visit_file(&file);
+    let sig = finder
+        .signatures
+        .first()
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+    let exprs = clause_exprs(sig, kind)?;
+    let expr = exprs
+        .get(index)
+        .ok_or_else(|| format!("'{}' has {} {} clauses, no index {}", function_name, exprs.len(), kind, index))?;
+    Ok(clause_ast_of(expr))
+}
+
+/// `ast` with quantifier-bound variables renamed by binding position, so
+/// `forall|i| f(i)` and `forall|j| f(j)` compare equal. `scope` holds the
+/// renamings of the enclosing binders.
+fn alpha_normalized(ast: &ClauseAst, scope: &mut Vec<(String, String)>) -> ClauseAst {
+    let mut norm = |ast: &ClauseAst| Box::new(alpha_normalized(ast, scope));
+    match ast {
+        ClauseAst::Quantifier { quantifier, binders, body } => {
+            let outer = scope.len();
+            let binders = binders
+                .iter()
+                .map(|binder| {
+                    let name = format!("${}", scope.len());
+                    scope.push((binder.name.clone(), name.clone()));
+                    Binder { name, ty: binder.ty.clone() }
+                })
+                .collect();
+            let body = Box::new(alpha_normalized(body, scope));
+            scope.truncate(outer);
+            ClauseAst::Quantifier { quantifier: quantifier.clone(), binders, body }
+        }
+        ClauseAst::Path { path } => ClauseAst::Path {
+            path: scope.iter().rev().find(|(from, _)| from == path).map_or_else(|| path.clone(), |(_, to)| to.clone()),
+        },
+        ClauseAst::Binary { op, left, right } => {
+            ClauseAst::Binary { op: op.clone(), left: norm(left), right: norm(right) }
+        }
+        ClauseAst::Unary { op, operand } => ClauseAst::Unary { op: op.clone(), operand: norm(operand) },
+        ClauseAst::Call { callee, args } => ClauseAst::Call {
+            callee: callee.clone(),
+            args: args.iter().map(|arg| alpha_normalized(arg, scope)).collect(),
+        },
+        ClauseAst::MethodCall { receiver, method, args } => {
+            let receiver = norm(receiver);
+            ClauseAst::MethodCall {
+                receiver,
+                method: method.clone(),
+                args: args.iter().map(|arg| alpha_normalized(arg, scope)).collect(),
+            }
+        }
+        ClauseAst::Field { base, member } => ClauseAst::Field { base: norm(base), member: member.clone() },
+        ClauseAst::Index { base, index } => ClauseAst::Index { base: norm(base), index: norm(index) },
+        ClauseAst::Cast { expr, ty } => ClauseAst::Cast { expr: norm(expr), ty: ty.clone() },
+        ClauseAst::If { cond, then_branch, else_branch } => ClauseAst::If {
+            cond: norm(cond),
+            then_branch: norm(then_branch),
+            else_branch: else_branch.as_deref().map(norm),
+        },
+        ClauseAst::Lit { .. } | ClauseAst::Opaque { .. } => ast.clone(),
+    }
+}
+
+/// Whether a tree is built from literals alone (`1 + 1`, `-3`, `true`)
+fn literal_only(ast: &ClauseAst) -> bool {
+    match ast {
+        ClauseAst::Lit { .. } => true,
+        ClauseAst::Unary { operand, .. } => literal_only(operand),
+        ClauseAst::Binary { left, right, .. } => literal_only(left) && literal_only(right),
+        _ => false,
+    }
+}
+
+/// Whether an ensures conjunct is literally `true`, compares two literal
+/// expressions, or restates one of the (alpha-normalized) requires conjuncts
+fn trivial_ensures(ensures: &ClauseAst, requires: &[ClauseAst]) -> bool {
+    let tautology = match ensures {
+        ClauseAst::Lit { kind, value, .. } => kind == "bool" && value == "true",
+        ClauseAst::Binary { op, left, right } => {
+            (mirrored_op(op).is_some() || op == "===") && literal_only(left) && literal_only(right)
+        }
+        _ => false,
+    };
+    tautology || requires.contains(ensures)
+}
+
+/// Proof functions whose every ensures conjunct is trivial (see `trivial_ensures`)
+pub fn lint_trivial_lemmas_core(content: &str) -> Result<LintResult<FunctionSpecs>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let normalized = |exprs: Vec<&Expr>| -> Vec<ClauseAst> {
+        exprs
+            .into_iter()
+            .flat_map(conjuncts)
+            .map(|e| alpha_normalized(&clause_ast_of(e), &mut Vec::new()))
+            .collect()
+    };
+    let (mut trivial, mut suppressed) = (Vec::new(), 0);
+    for (specs, sig) in finder.functions.into_iter().zip(finder.signatures.iter()) {
+        if !specs.is_proof {
+            continue;
+        }
+        let requires = normalized(clause_exprs(sig, "requires")?);
+        let ensures = normalized(clause_exprs(sig, "ensures")?);
+        if ensures.is_empty() || !ensures.iter().all(|e| trivial_ensures(e, &requires)) {
+            continue;
+        }
+        if is_suppressed(&specs, Lint::TrivialLemma, &lines) {
+            suppressed += 1;
+        } else {
+            trivial.push(specs);
+        }
+    }
+    Ok((trivial, suppressed))
+}
+
+/// The access chain of a tree rooted at one of `roots`, one part per hop:
+/// `self.bytes.len()` gives `["self", "bytes", "len()"]`; `old(x)` counts as
+/// `x`, and indexing ends the chain
+fn access_chain(ast: &ClauseAst, roots: &[String]) -> Option<Vec<String>> {
+    match ast {
+        ClauseAst::Path { path } if roots.contains(path) => Some(vec![path.clone()]),
+        ClauseAst::Call { callee, args } if callee == "old" && args.len() == 1 => access_chain(&args[0], roots),
+        ClauseAst::Field { base, member } => {
+            let mut chain = access_chain(base, roots)?;
+            chain.push(member.clone());
+            Some(chain)
+        }
+        ClauseAst::MethodCall { receiver, method, .. } => {
+            let mut chain = access_chain(receiver, roots)?;
+            chain.push(format!("{}()", method));
+            Some(chain)
+        }
+        _ => None,
+    }
+}
+
+/// Collect the field paths and method-call chains (see `ClauseFieldPaths`) of
+/// a tree; quantified variables shadow roots of the same name
+fn collect_field_paths(
+    ast: &ClauseAst,
+    roots: &[String],
+    fields: &mut BTreeSet<String>,
+    method_calls: &mut BTreeSet<String>,
+) {
+    if let Some(chain) = access_chain(ast, roots).filter(|chain| chain.len() > 1) {
+        let field_hops = chain.iter().take_while(|part| !part.ends_with("()")).count();
+        if field_hops > 1 {
+            fields.insert(chain[..field_hops].join("."));
+        }
+        if field_hops < chain.len() {
+            method_calls.insert(chain.join("."));
+        }
+        // Only the method arguments along the chain remain to be searched
+        let mut link = ast;
+        loop {
+            match link {
+                ClauseAst::Field { base, .. } => link = base,
+                ClauseAst::MethodCall { receiver, args, .. } => {
+                    for arg in args {
+                        collect_field_paths(arg, roots, fields, method_calls);
+                    }
+                    link = receiver;
+                }
+                _ => return,
+            }
+        }
+    }
+    let mut recurse = |ast: &ClauseAst| collect_field_paths(ast, roots, fields, method_calls);
+    match ast {
+        ClauseAst::Quantifier { binders, body, .. } => {
+            let inner: Vec<String> = roots.iter().filter(|r| !binders.iter().any(|b| &b.name == *r)).cloned().collect();
+            collect_field_paths(body, &inner, fields, method_calls);
+        }
+        ClauseAst::Binary { left, right, .. } => {
+            recurse(left);
+            recurse(right);
+        }
+        ClauseAst::Unary { operand, .. } => recurse(operand),
+        ClauseAst::Call { args, .. } => args.iter().for_each(recurse),
+        ClauseAst::MethodCall { receiver, args, .. } => {
+            recurse(receiver);
+            args.iter().for_each(recurse);
+        }
+        ClauseAst::Field { base, .. } => recurse(base),
+        ClauseAst::Index { base, index } => {
+            recurse(base);
+            recurse(index);
+        }
+        ClauseAst::Cast { expr, .. } => recurse(expr),
+        ClauseAst::If { cond, then_branch, else_branch } => {
+            recurse(cond);
+            recurse(then_branch);
+            if let Some(else_branch) = else_branch {
+                recurse(else_branch);
+            }
+        }
+        ClauseAst::Path { .. } | ClauseAst::Lit { .. } | ClauseAst::Opaque { .. } => {}
+    }
+}
+
+/// The field paths of each requires and ensures clause of a function
+fn signature_field_paths(sig: &Signature) -> Result<Vec<ClauseFieldPaths>, String> {
+    let mut roots = param_names(sig);
+    roots.extend(return_parts(sig).and_then(|(binding, _)| binding));
+    let mut clauses = Vec::new();
+    for kind in ["requires", "ensures"] {
+        for expr in clause_exprs(sig, kind)? {
+            let (mut fields, mut method_calls) = (BTreeSet::new(), BTreeSet::new());
+            collect_field_paths(&clause_ast_of(expr), &roots, &mut fields, &mut method_calls);
+            clauses.push(ClauseFieldPaths {
+                clause_kind: kind.to_string(),
+                clause: render_tokens(quote::quote!(#expr)),
+                fields: fields.into_iter().collect(),
+                method_calls: method_calls.into_iter().collect(),
+            });
+        }
+    }
+    Ok(clauses)
+}
+
+/// The field paths of the clauses of `function_name` (its first definition)
+pub fn clause_field_paths_core(content: &str, function_name: &str) -> Result<Vec<ClauseFieldPaths>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let sig = finder
+        .signatures
+        .iter()
+        .find(|sig| sig.ident == function_name)
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+    signature_field_paths(sig)
+}
+
+/// The functions of `files` (`(path, content)` pairs) with an ensures clause
+/// accessing `field_name` on a parameter, `self` or the return value of type
+/// `type_name` (compared without module path, generics and references)
+pub fn lemmas_constraining_field_core(
+    files: &[(String, String)],
+    type_name: &str,
+    field_name: &str,
+) -> Result<Vec<FunctionSpecs>, String> {
+    let mut found = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| {
+            if path.is_empty() {
+                format!("Parse error: {}", e)
+            } else {
+                format!("{}: Parse error: {}", path, e)
+            }
+        })?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        set_file_path(&mut finder.functions, path);
+        for (specs, sig) in finder.functions.into_iter().zip(finder.signatures.iter()) {
+            let self_type = specs
+                .path
+                .iter()
+                .rev()
+                .find_map(|crumb| crumb.strip_prefix("impl "))
+                .map(|imp| scip_type_name(imp.rsplit(" for ").next().unwrap_or(imp)));
+            let type_of = |root: &str| -> Option<String> {
+                // A receiver's type is `Self`
+                let ty = match specs.params.iter().find(|p| p.name == root) {
+                    Some(param) => scip_type_name(&param.ty),
+                    None => scip_type_name(&specs.returns.as_ref()?.ty),
+                };
+                if ty == "Self" {
+                    self_type.clone()
+                } else {
+                    Some(ty)
+                }
+            };
+            let constrains = signature_field_paths(sig)?.iter().filter(|c| c.clause_kind == "ensures").any(|c| {
+                c.fields.iter().any(|field| {
+                    let mut parts = field.split('.');
+                    let root = parts.next().unwrap_or_default();
+                    parts.next() == Some(field_name) && type_of(root).as_deref() == Some(type_name)
+                })
+            });
+            if constrains {
+                found.push(specs);
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// How a predicate argument relates to the function (see `PredicateCall`)
+fn predicate_arg_kind(arg: &Expr, params: &[String], result: Option<&str>) -> &'static str {
+    let name = |expr: &Expr| match strip_parens(expr) {
+        Expr::Path(path) if path.qself.is_none() => path.path.get_ident().map(|ident| ident.to_string()),
+        _ => None,
+    };
+    match strip_parens(arg) {
+        Expr::Call(call) if call_path(call).as_deref() == Some("old") && call.args.len() == 1 => {
+            match name(&call.args[0]) {
+                Some(param) if params.contains(&param) => "old",
+                _ => "complex",
+            }
+        }
+        expr => match name(expr) {
+            Some(ident) if result == Some(ident.as_str()) => "result",
+            Some(ident) if params.contains(&ident) => "param",
+            _ => "complex",
+        },
+    }
+}
+
+/// The requires and ensures conjuncts of a function that are a single call
+fn signature_predicate_calls(sig: &Signature) -> Result<Vec<PredicateCall>, String> {
+    let params = param_names(sig);
+    let result = return_binding(sig);
+    let arg = |expr: &Expr| {
+        let kind = predicate_arg_kind(expr, &params, result.as_deref());
+        (kind.to_string(), render_tokens(quote::quote!(#expr)))
+    };
+    let mut calls = Vec::new();
+    for kind in ["requires", "ensures"] {
+        for conjunct in clause_exprs(sig, kind)?.into_iter().flat_map(conjuncts) {
+            let (callee, is_method, args) = match conjunct {
+                Expr::Call(call) => match call_path(call) {
+                    Some(callee) => (callee, false, call.args.iter().map(arg).collect()),
+                    None => continue,
+                },
+                Expr::MethodCall(call) => {
+                    let args = std::iter::once(&*call.receiver).chain(call.args.iter()).map(arg).collect();
+                    (call.method.to_string(), true, args)
+                }
+                _ => continue,
+            };
+            calls.push(PredicateCall {
+                clause_kind: kind.to_string(),
+                conjunct: render_tokens(quote::quote!(#conjunct)),
+                callee,
+                is_method,
+                args,
+            });
+        }
+    }
+    Ok(calls)
+}
+
+/// The predicate calls of `function_name` (its first definition)
+pub fn predicate_calls_core(content: &str, function_name: &str) -> Result<Vec<PredicateCall>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let sig = finder
+        .signatures
+        .iter()
+        .find(|sig| sig.ident == function_name)
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+    signature_predicate_calls(sig)
+}
+
+/// The functions of `files` (`(path, content)` pairs) with an ensures
+/// conjunct calling `predicate_name` on their return value or `self`; a name
+/// without `::` also matches qualified callees (`inv` matches `Self::inv`)
+pub fn establishes_predicate_core(files: &[(String, String)], predicate_name: &str) -> Result<Vec<FunctionSpecs>, String> {
+    let matches_name = |callee: &str| {
+        callee == predicate_name
+            || (!predicate_name.contains("::") && callee.rsplit("::").next() == Some(predicate_name))
+    };
+    let mut found = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| {
+            if path.is_empty() {
+                format!("Parse error: {}", e)
+            } else {
+                format!("{}: Parse error: {}", path, e)
+            }
+        })?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        set_file_path(&mut finder.functions, path);
+        for (specs, sig) in finder.functions.into_iter().zip(finder.signatures.iter()) {
+            let establishes = signature_predicate_calls(sig)?.iter().any(|call| {
+                call.clause_kind == "ensures"
+                    && matches_name(&call.callee)
+                    && call.args.iter().any(|(kind, text)| kind == "result" || (kind == "param" && text == "self"))
+            });
+            if establishes {
+                found.push(specs);
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Subject name of an arithmetic or bitwise operator
+fn operator_subject(op: &str) -> Option<&'static str> {
+    match op {
+        "*" => Some("multiplication"),
+        "/" => Some("division"),
+        "%" => Some("modular arithmetic"),
+        "+" => Some("addition"),
+        "-" => Some("subtraction"),
+        "&" => Some("bitwise and"),
+        "|" => Some("bitwise or"),
+        "^" => Some("bitwise xor"),
+        "<<" => Some("shift left"),
+        ">>" => Some("shift right"),
+        _ => None,
+    }
+}
+
+/// Methods that only observe a value; the subject is whatever they are applied to
+const ACCESSOR_METHODS: &[&str] = &["len", "view", "deep_view", "spec_index", "index"];
+
+/// Relations whose operands are classified separately
+const RELATION_OPS: &[&str] = &["==", "!=", "<", "<=", ">", ">=", "<==>", "=~=", "!~=", "=~~=", "!~~="];
+
+/// The outermost non-trivial symbol of an expression: an arithmetic operator
+/// category or a called function (`Seq::push` for `s.push(x)` with `s: Seq<_>`).
+/// Returns the subject and whether it is a call (calls win ties over operators).
+fn head_symbol(expr: &Expr, type_heads: &HashMap<String, String>) -> Option<(String, bool)> {
+    match strip_parens(expr) {
+        Expr::Binary(bin) => operator_subject(&bin_op_text(&bin.op)).map(|subject| (subject.to_string(), false)),
+        Expr::Call(call) => match strip_parens(&call.func) {
+            Expr::Path(path) => {
+                let segments: Vec<String> = path.path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+                let start = segments.len().saturating_sub(2);
+                Some((segments[start..].join("::"), true))
+            }
+            _ => None,
+        },
+        Expr::MethodCall(call) => {
+            let method = call.method.to_string();
+            if ACCESSOR_METHODS.contains(&method.as_str()) {
+                return head_symbol(&call.receiver, type_heads);
+            }
+            let receiver_type = match strip_parens(&call.receiver) {
+                Expr::Path(path) => path.path.get_ident().and_then(|ident| type_heads.get(&ident.to_string())),
+                _ => None,
+            };
+            match receiver_type {
+                Some(ty) => Some((format!("{}::{}", ty, method), true)),
+                None => Some((method, true)),
+            }
+        }
+        Expr::Unary(unary) => head_symbol(&unary.expr, type_heads),
+        Expr::Cast(cast) => head_symbol(&cast.expr, type_heads),
+        Expr::Index(index) => head_symbol(&index.expr, type_heads),
+        Expr::Field(field) => head_symbol(&field.base, type_heads),
+        _ => None,
+    }
+}
+
+/// Pick the subject of a lemma: the most frequent head symbol over the operands
+/// of its ensures relations. Ties go to calls over operators, then to the
+/// lexicographically smallest name.
+fn lemma_subject(sig: &Signature) -> String {
+    let type_heads = param_type_heads(sig);
+    let mut counts: Vec<(String, bool, usize)> = Vec::new();
+    let clauses = sig.spec.ensures.iter().flat_map(|ens| ens.exprs.exprs.iter()).flat_map(conjuncts);
+    for clause in clauses {
+        let mut clause = clause;
+        while let Expr::Binary(bin) = clause {
+            if bin_op_text(&bin.op) != "==>" {
+                break;
+            }
+            clause = strip_parens(&bin.right);
+        }
+        let operands: Vec<&Expr> = match clause {
+            Expr::Binary(bin) if RELATION_OPS.contains(&bin_op_text(&bin.op).as_str()) => vec![&bin.left, &bin.right],
+            other => vec![other],
+        };
+        for (subject, is_call) in operands.into_iter().filter_map(|e| head_symbol(e, &type_heads)) {
+            match counts.iter_mut().find(|(s, _, _)| *s == subject) {
+                Some(entry) => entry.2 += 1,
+                None => counts.push((subject, is_call, 1)),
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .min_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)))
+        .map(|(subject, _, _)| subject)
+        .unwrap_or_else(|| "unclassified".to_string())
+}
+
+/// Group the proof functions of a file by subject
+pub fn group_lemmas_core(content: &str) -> Result<BTreeMap<String, Vec<FunctionSpecs>>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let mut groups: BTreeMap<String, Vec<FunctionSpecs>> = BTreeMap::new();
+    for (specs, sig) in finder.functions.into_iter().zip(finder.signatures.iter()) {
+        if specs.is_proof {
+            groups.entry(lemma_subject(sig)).or_default().push(specs);
+        }
+    }
+    Ok(groups)
+}
+
+/// Whether the `_`-separated tokens of `spec_name` occur consecutively in
+/// those of `lemma_name` (`pow2` in `lemma_pow2_adds`, but not `pow` in it)
+fn name_has_tokens(lemma_name: &str, spec_name: &str) -> bool {
+    let lemma_tokens: Vec<&str> = lemma_name.split('_').collect();
+    let spec_tokens: Vec<&str> = spec_name.split('_').filter(|t| !t.is_empty()).collect();
+    !spec_tokens.is_empty() && lemma_tokens.windows(spec_tokens.len()).any(|window| window == spec_tokens)
+}
+
+/// Pair the proof functions of a file with the spec functions they are about,
+/// by ensures references and by name tokens
+pub fn lemma_spec_associations_core(content: &str) -> Result<LemmaSpecAssociations, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let mut associations = LemmaSpecAssociations::default();
+    for specs in finder.functions.iter().filter(|f| f.mode.starts_with("spec")) {
+        associations.by_spec.entry(specs.name.clone()).or_default();
+    }
+    for (specs, sig) in finder.functions.iter().zip(finder.signatures.iter()) {
+        if !specs.is_proof {
+            continue;
+        }
+        let mentioned: BTreeSet<String> =
+            clause_exprs(sig, "ensures")?.into_iter().flat_map(referenced_names).collect();
+        let lemma_entry = associations.by_lemma.entry(specs.name.clone()).or_default();
+        for (spec_fn, lemmas) in associations.by_spec.iter_mut() {
+            let mut reasons = Vec::new();
+            if mentioned.contains(spec_fn) {
+                reasons.push("ensures_mentions".to_string());
+            }
+            if name_has_tokens(&specs.name, spec_fn) {
+                reasons.push("name_token".to_string());
+            }
+            if reasons.is_empty() {
+                continue;
+            }
+            let association = LemmaAssociation {
+                spec_fn: spec_fn.clone(),
+                lemma: specs.name.clone(),
+                line_number: specs.line_number,
+                reasons,
+            };
+            lemmas.push(association.clone());
+            lemma_entry.push(association);
+        }
+    }
+    Ok(associations)
+}
+
+/// Start of an item's span, identifying it as a scope
+type ScopeId = (usize, usize);
+
+/// AST visitor recording the innermost enclosing impl, trait, module or
+/// function of every function, identified by the start of its span (so two
+/// impl blocks for the same type are different scopes); `verus!` blocks are
+/// not scopes of their own
+#[derive(Default)]
+struct ScopeFinder {
+    /// (function name, line) -> enclosing scope, (0, 0) being the file
+    scopes: HashMap<(String, usize), ScopeId>,
+    /// Innermost scope being visited
+    current: ScopeId,
+}
+
+impl ScopeFinder {
+    fn enter<S: Spanned>(&mut self, node: &S) -> ScopeId {
+        let start = node.span().start();
+        std::mem::replace(&mut self.current, (start.line, start.column))
+    }
+
+    fn visit_fn<S: Spanned>(&mut self, sig: &Signature, node: &S, visit_rest: impl FnOnce(&mut Self)) {
+        self.scopes.insert((sig.ident.to_string(), node.span().start().line), self.current);
+        let outer = self.enter(node);
+        visit_rest(self);
+        self.current = outer;
+    }
+}
+
+impl<'ast> Visit<'ast> for ScopeFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.sig, node, |v| verus_syn::visit::visit_item_fn(v, node));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.sig, node, |v| verus_syn::visit::visit_impl_item_fn(v, node));
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn(&node.sig, node, |v| verus_syn::visit::visit_trait_item_fn(v, node));
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        let outer = self.enter(node);
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+        self.current = outer;
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        let outer = self.enter(node);
+        verus_syn::visit::visit_item_trait(self, node);
+        self.current = outer;
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        let outer = self.enter(node);
+        verus_syn::visit::visit_item_mod(self, node);
+        self.current = outer;
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// The other functions of the impl, trait or module (for free functions)
+/// that defines `function_name`; the first definition is used if there are several
+pub fn siblings_core(content: &str, function_name: &str) -> Result<Vec<FunctionSpecs>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut scopes = ScopeFinder::default();
+    scopes.visit_file(&file);
+    let mut finder = FunctionFinder::new(None);
+    finder.keep_signatures = false;
+    finder.visit_file(&file);
+
+    let scope_of = |f: &FunctionSpecs| scopes.scopes.get(&(f.name.clone(), f.line_number.unwrap_or_default())).copied();
+    let target = finder
+        .functions
+        .iter()
+        .position(|f| f.name == function_name)
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+    let scope = scope_of(&finder.functions[target]);
+    Ok(finder
+        .functions
+        .iter()
+        .enumerate()
+        .filter(|&(i, f)| i != target && scope_of(f) == scope)
+        .map(|(_, f)| f.clone())
+        .collect())
+}
+
+/// The file's inner attributes (`#![...]`) followed by the inner attributes
+/// and `global` items at the top level of its `verus!` blocks, rendered
+pub fn file_attributes_core(content: &str) -> Result<Vec<String>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut attributes: Vec<String> = file.attrs.iter().map(|attr| render_tokens(quote::quote!(#attr))).collect();
+    for item in &file.items {
+        if let Item::Macro(node) = item {
+            if is_verus_macro(&node.mac.path, &[]) {
+                attributes.extend(verus_global_items(node.mac.tokens.clone()));
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+/// Inner attributes and `global ...;` items found where an item may start in
+/// a `verus!` body
+///
+/// Works on tokens, so global items are found whether or not the body parses.
+fn verus_global_items(tokens: TokenStream) -> Vec<String> {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let is_punct = |i: usize, ch: char| matches!(tts.get(i), Some(TokenTree::Punct(p)) if p.as_char() == ch);
+    let is_bracket = |i: usize| matches!(tts.get(i), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket);
+    let mut found = Vec::new();
+    let mut item_start = true;
+    let mut i = 0;
+    while i < tts.len() {
+        if item_start && is_punct(i, '#') && is_punct(i + 1, '!') && is_bracket(i + 2) {
+            found.push(render_tokens(tts[i..i + 3].iter().cloned().collect()));
+            i += 3;
+            continue;
+        }
+        if item_start && is_punct(i, '#') && is_bracket(i + 1) {
+            // An outer attribute; the item it belongs to starts after it
+            i += 2;
+            continue;
+        }
+        if item_start && matches!(&tts[i], TokenTree::Ident(ident) if ident == "global") {
+            let end = (i..tts.len()).find(|&j| is_punct(j, ';')).unwrap_or(tts.len() - 1);
+            found.push(render_tokens(tts[i..=end].iter().cloned().collect()));
+            i = end + 1;
+            continue;
+        }
+        item_start = is_punct(i, ';') || matches!(&tts[i], TokenTree::Group(g) if g.delimiter() == Delimiter::Brace);
+        i += 1;
+    }
+    found
+}
+
+/// The parameter bindings under which all of a function's requires are met by
+/// the facts, or None if they cannot all be met
+fn requires_bindings(sig: &Signature, facts: &[Expr]) -> Option<HashMap<String, String>> {
+    let params = param_names(sig);
+    let clauses: Vec<&Expr> = sig
+        .spec
+        .requires
+        .as_ref()
+        .map(|req| req.exprs.exprs.iter().flat_map(conjuncts).collect())
+        .unwrap_or_default();
+    let fact_conjuncts: Vec<&Expr> = facts.iter().flat_map(conjuncts).collect();
+    let mut matcher = ClauseMatcher::new(&params);
+    matcher.entails_all(&clauses, &fact_conjuncts).then_some(matcher.bindings)
+}
+
+/// Parse a list of fact strings as expressions
+pub fn parse_facts(facts: &[String]) -> Result<Vec<Expr>, String> {
+    facts
+        .iter()
+        .map(|fact| verus_syn::parse_str::<Expr>(fact).map_err(|e| format!("Invalid fact `{}`: {}", fact, e)))
+        .collect()
+}
+
+/// Known types of the names in the facts, to check the types of the lemma
+/// parameters bound to them
+#[derive(Debug, Clone, Default)]
+pub struct FactTypes {
+    /// Type of each name, e.g. "n" -> "usize"
+    pub types: HashMap<String, String>,
+    /// Let a machine integer stand for an `int` parameter, and an unsigned one
+    /// for a `nat` parameter
+    pub coerce_int_types: bool,
+}
+
+/// An integer coercion a match relies on: (parameter, type of what it is
+/// bound to, parameter type)
+pub type Coercion = (String, String, String);
+
+/// Type of a fact expression a parameter is bound to, if known: a name of
+/// `types`, a suffixed integer literal or a cast
+fn bound_type(text: &str, types: &HashMap<String, String>) -> Option<String> {
+    let expr = verus_syn::parse_str::<Expr>(text).ok()?;
+    match strip_parens(&expr) {
+        Expr::Path(path) => types.get(&path.path.get_ident()?.to_string()).cloned(),
+        Expr::Lit(lit) => match &lit.lit {
+            verus_syn::Lit::Int(int) if !int.suffix().is_empty() => Some(int.suffix().to_string()),
+            _ => None,
+        },
+        Expr::Cast(cast) => {
+            let ty = &cast.ty;
+            Some(render_tokens(quote::quote!(#ty)))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a value of type `actual` can stand for a parameter of type
+/// `expected`: Some(false) for the same type, Some(true) through an integer
+/// coercion (with `coerce`: a machine integer or `nat` as `int`, an unsigned
+/// integer as `nat`, never a signed one), None otherwise
+fn type_compatibility(expected: &str, actual: &str, coerce: bool) -> Option<bool> {
+    if expected == actual {
+        return Some(false);
+    }
+    let coercible = coerce
+        && match expected {
+            "int" => actual == "nat" || int_type_info(actual).is_some(),
+            "nat" => int_type_info(actual).is_some_and(|(signed, _, _)| !signed),
+            _ => false,
+        };
+    coercible.then_some(true)
+}
+
+/// The coercions under which the parameters of a lemma accept what
+/// `bindings` binds them to, or None if one has an incompatible type;
+/// parameters bound to something of unknown type are not checked
+fn binding_coercions(
+    specs: &FunctionSpecs,
+    bindings: &HashMap<String, String>,
+    fact_types: &FactTypes,
+) -> Option<Vec<Coercion>> {
+    let mut coercions = Vec::new();
+    for param in &specs.params {
+        let Some(actual) = bindings.get(&param.name).and_then(|text| bound_type(text, &fact_types.types)) else {
+            continue;
+        };
+        if type_compatibility(&param.ty, &actual, fact_types.coerce_int_types)? {
+            coercions.push((param.name.clone(), actual, param.ty.clone()));
+        }
+    }
+    Some(coercions)
+}
+
+/// Keep the proof functions whose requires are all entailed by the given facts
+/// (and, if `known_bounds` is given, whose generic bounds can be satisfied, and
+/// if `fact_types` is, whose parameters accept the types they bind to),
+/// optionally with the file's type aliases expanded on both sides
+pub fn filter_by_requires_core(
+    content: &str,
+    facts: &[Expr],
+    known_bounds: Option<&HashMap<String, Vec<String>>>,
+    expand_aliases: bool,
+    fact_types: Option<&FactTypes>,
+) -> Result<Vec<FunctionSpecs>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let mut facts = facts.to_vec();
+    if expand_aliases {
+        expand_file_aliases(&file, &mut finder.signatures, &mut facts);
+    }
+    Ok(finder
+        .functions
+        .into_iter()
+        .zip(finder.signatures.iter())
+        .filter(|(specs, sig)| {
+            specs.is_proof
+                && requires_bindings(sig, &facts).is_some_and(|bindings| {
+                    fact_types.iter().all(|types| binding_coercions(specs, &bindings, types).is_some())
+                })
+        })
+        .filter(|(specs, _)| known_bounds.iter().all(|known| bounds_satisfied(specs, known)))
+        .map(|(specs, _)| specs)
+        .collect())
+}
+
+/// Lemma parameters mapped to the fact text they stand for
+pub type Bindings = BTreeMap<String, String>;
+
+/// The proof functions applicable under the hypotheses `facts`, with the
+/// bindings of their parameters, most specific first
+///
+/// Lemmas with requires come first, then those without. Within each group,
+/// lemmas with more ensures conjuncts about bound parameters only (or no
+/// parameters) rank higher, then those with more requires conjuncts, then
+/// those needing fewer coercions, then by name. With `expand_aliases`, the
+/// file's type aliases are expanded on both sides; with `fact_types`, lemmas
+/// whose parameters do not accept the types they bind to are left out, and
+/// each match lists the integer coercions it relies on.
+pub fn applicable_lemmas_core(
+    content: &str,
+    facts: &[Expr],
+    expand_aliases: bool,
+    fact_types: Option<&FactTypes>,
+) -> Result<Vec<(FunctionSpecs, Bindings, Vec<Coercion>)>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let mut facts = facts.to_vec();
+    if expand_aliases {
+        expand_file_aliases(&file, &mut finder.signatures, &mut facts);
+    }
+    let mut ranked = Vec::new();
+    for (specs, sig) in finder.functions.into_iter().zip(finder.signatures.iter()) {
+        if !specs.is_proof {
+            continue;
+        }
+        let Some(bindings) = requires_bindings(sig, &facts) else {
+            continue;
+        };
+        let coercions = match fact_types {
+            Some(types) => match binding_coercions(&specs, &bindings, types) {
+                Some(coercions) => coercions,
+                None => continue,
+            },
+            None => Vec::new(),
+        };
+        let params = param_names(sig);
+        let exprs = |kind| clause_exprs(sig, kind).unwrap_or_default().into_iter().flat_map(conjuncts);
+        let specific = exprs("ensures")
+            .filter(|e| clause_mentions(e, &params).params.iter().all(|p| bindings.contains_key(p)))
+            .count();
+        let requires_count = exprs("requires").count();
+        let key = (
+            requires_count == 0,
+            std::cmp::Reverse(specific),
+            std::cmp::Reverse(requires_count),
+            coercions.len(),
+        );
+        ranked.push((key, specs, bindings.into_iter().collect::<Bindings>(), coercions));
+    }
+    ranked.sort_by(|(a, a_specs, _, _), (b, b_specs, _, _)| a.cmp(b).then_with(|| a_specs.name.cmp(&b_specs.name)));
+    Ok(ranked.into_iter().map(|(_, specs, bindings, coercions)| (specs, bindings, coercions)).collect())
+}
+
+/// A `find_pattern` pattern as an expression over hole parameters: `_`
+/// becomes `__hole` and `?x` becomes `__hole_x`. Returns the expression and
+/// the hole names.
+fn compile_pattern(pattern: &str) -> Result<(Expr, Vec<String>), String> {
+    let tokens: TokenStream = pattern.parse().map_err(|e| format!("Invalid pattern: {}", e))?;
+    fn rewrite(tokens: TokenStream, holes: &mut Vec<String>) -> TokenStream {
+        let mut out = Vec::new();
+        let mut tts = tokens.into_iter().peekable();
+        while let Some(tt) = tts.next() {
+            let hole = match (&tt, tts.peek()) {
+                (TokenTree::Ident(ident), _) if ident == "_" => Some(ANONYMOUS_HOLE.to_string()),
+                (TokenTree::Punct(p), Some(TokenTree::Ident(name))) if p.as_char() == '?' => {
+                    let hole = format!("{}_{}", ANONYMOUS_HOLE, name);
+                    tts.next();
+                    Some(hole)
+                }
+                _ => None,
+            };
+            out.push(match (hole, tt) {
+                (Some(hole), tt) => {
+                    if !holes.contains(&hole) {
+                        holes.push(hole.clone());
+                    }
+                    TokenTree::Ident(proc_macro2::Ident::new(&hole, tt.span()))
+                }
+                (None, TokenTree::Group(group)) => {
+                    let mut inner = proc_macro2::Group::new(group.delimiter(), rewrite(group.stream(), holes));
+                    inner.set_span(group.span());
+                    TokenTree::Group(inner)
+                }
+                (None, tt) => tt,
+            });
+        }
+        out.into_iter().collect()
+    }
+    let mut holes = Vec::new();
+    let rewritten = rewrite(tokens, &mut holes);
+    let expr = verus_syn::parse2::<Expr>(rewritten).map_err(|e| format!("Invalid pattern: {}", e))?;
+    Ok((expr, holes))
+}
+
+/// Every sub-expression of an expression, outermost first, parentheses skipped
+#[derive(Default)]
+struct SubExprs<'ast> {
+    exprs: Vec<&'ast Expr>,
+}
+
+impl<'ast> Visit<'ast> for SubExprs<'ast> {
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        if !matches!(node, Expr::Paren(_)) {
+            self.exprs.push(node);
+        }
+        verus_syn::visit::visit_expr(self, node);
+    }
+}
+
+/// Match a structural pattern against every requires/ensures clause and their
+/// sub-expressions
+pub fn find_pattern_core(content: &str, pattern: &str, expand_aliases: bool) -> Result<Vec<PatternMatch>, String> {
+    let (mut pattern, holes) = compile_pattern(pattern)?;
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    if expand_aliases {
+        expand_file_aliases(&file, &mut finder.signatures, std::slice::from_mut(&mut pattern));
+    }
+
+    let mut matches = Vec::new();
+    for sig in &finder.signatures {
+        for kind in ["requires", "ensures"] {
+            for clause in clause_exprs(sig, kind)? {
+                let mut sub_exprs = SubExprs::default();
+                sub_exprs.visit_expr(clause);
+                for expr in sub_exprs.exprs {
+                    let mut matcher = ClauseMatcher::new(&holes);
+                    if !matcher.unify(&pattern, expr) {
+                        continue;
+                    }
+                    let bindings = matcher
+                        .bindings
+                        .into_iter()
+                        .map(|(hole, text)| (hole[ANONYMOUS_HOLE.len() + 1..].to_string(), text))
+                        .collect();
+                    matches.push(PatternMatch {
+                        function: sig.ident.to_string(),
+                        clause_kind: kind.to_string(),
+                        clause: quote::quote!(#clause).to_string(),
+                        expr: quote::quote!(#expr).to_string(),
+                        bindings,
+                        line_number: expr.span().start().line,
+                    });
+                }
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Whether two sets of cfg conditions cannot both hold: one has a condition
+/// `c` and the other `not(c)`
+fn cfgs_exclusive(a: &[String], b: &[String]) -> bool {
+    let negates = |x: &[String], y: &[String]| x.iter().any(|c| y.contains(&format!("not({})", c)));
+    negates(a, b) || negates(b, a)
+}
+
+/// Whether a loop expression's header (everything before its body) has a `decreases` clause
+fn loop_has_decreases(tokens: TokenStream) -> bool {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let body = tts
+        .iter()
+        .rposition(|tt| matches!(tt, TokenTree::Group(g) if g.delimiter() == Delimiter::Brace))
+        .unwrap_or(tts.len());
+    tts[..body].iter().any(|tt| matches!(tt, TokenTree::Ident(ident) if ident == "decreases"))
+}
+
+/// AST visitor collecting loops and direct recursion for termination checks
+#[derive(Default)]
+struct LoopFinder {
+    /// Loops found so far
+    loops: Vec<LoopInfo>,
+    /// Recursive functions lacking a decreases clause and a waiver
+    recursion_issues: Vec<TerminationIssue>,
+    /// Name of the function being visited
+    current_fn: String,
+    /// Whether the function being visited waives termination checking
+    fn_waived: bool,
+    /// Whether the function being visited calls itself
+    fn_recursive: bool,
+}
+
+impl LoopFinder {
+    fn visit_fn<S: Spanned>(
+        &mut self,
+        attrs: &[verus_syn::Attribute],
+        sig: &Signature,
+        block: Option<&verus_syn::Block>,
+        spanned: &S,
+    ) {
+        // Bodies of external functions are not verified
+        let Some(block) = block else { return };
+        if attrs.iter().any(|a| is_verifier_attr(a, "external_body") || is_verifier_attr(a, "external")) {
+            return;
+        }
+
+        let outer = (
+            std::mem::replace(&mut self.current_fn, sig.ident.to_string()),
+            std::mem::replace(
+                &mut self.fn_waived,
+                attrs.iter().any(|a| is_verifier_attr(a, "exec_allows_no_decreases_clause")),
+            ),
+            std::mem::replace(&mut self.fn_recursive, false),
+        );
+        self.visit_block(block);
+        if self.fn_recursive && sig.spec.decreases.is_none() && !self.fn_waived {
+            let span = spanned.span();
+            self.recursion_issues.push(TerminationIssue {
+                function: self.current_fn.clone(),
+                kind: "recursion".to_string(),
+                line_number: Some(span.start().line),
+                end_line: Some(span.end().line),
+            });
+        }
+        (self.current_fn, self.fn_waived, self.fn_recursive) = outer;
+    }
+
+    fn record_loop<S: Spanned + quote::ToTokens>(&mut self, kind: &str, attrs: &[verus_syn::Attribute], node: &S) {
+        let span = node.span();
+        let attributes = verifier_attributes(attrs);
+        let loop_waived = attrs.iter().any(|a| is_verifier_attr(a, "exec_allows_no_decreases_clause"));
+        self.loops.push(LoopInfo {
+            function: self.current_fn.clone(),
+            kind: kind.to_string(),
+            line_number: Some(span.start().line),
+            end_line: Some(span.end().line),
+            has_decreases: loop_has_decreases(node.to_token_stream()),
+            termination_waived: self.fn_waived || loop_waived,
+            attributes,
+        });
+    }
+
+    /// Termination issues: unwaived `while`/`loop` loops without decreases
+    /// (iterator `for` loops need none) and unwaived recursion without decreases
+    fn termination_issues(self) -> Vec<TerminationIssue> {
+        let mut issues: Vec<TerminationIssue> = self
+            .loops
+            .into_iter()
+            .filter(|l| l.kind != "for" && !l.has_decreases && !l.termination_waived)
+            .map(|l| TerminationIssue {
+                function: l.function,
+                kind: l.kind,
+                line_number: l.line_number,
+                end_line: l.end_line,
+            })
+            .chain(self.recursion_issues)
+            .collect();
+        issues.sort_by_key(|issue| issue.line_number);
+        issues
+    }
+}
+
+impl<'ast> Visit<'ast> for LoopFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, Some(&node.block), node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, Some(&node.block), node);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, node.default.as_ref(), node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast verus_syn::ExprWhile) {
+        self.record_loop("while", &node.attrs, node);
+        verus_syn::visit::visit_expr_while(self, node);
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast verus_syn::ExprLoop) {
+        self.record_loop("loop", &node.attrs, node);
+        verus_syn::visit::visit_expr_loop(self, node);
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast verus_syn::ExprForLoop) {
+        self.record_loop("for", &node.attrs, node);
+        verus_syn::visit::visit_expr_for_loop(self, node);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast verus_syn::ExprCall) {
+        if let Expr::Path(path) = strip_parens(&node.func) {
+            let segments = &path.path.segments;
+            let is_self_call = segments.last().is_some_and(|seg| seg.ident == self.current_fn)
+                && (segments.len() == 1 || segments.first().is_some_and(|seg| seg.ident == "Self"));
+            self.fn_recursive |= is_self_call;
+        }
+        verus_syn::visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast verus_syn::ExprMethodCall) {
+        let on_self = matches!(strip_parens(&node.receiver), Expr::Path(p) if p.path.is_ident("self"));
+        self.fn_recursive |= on_self && node.method == self.current_fn;
+        verus_syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// Collect every loop in the file
+pub fn extract_loops_core(content: &str) -> Result<Vec<LoopInfo>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = LoopFinder::default();
+    finder.visit_file(&file);
+    Ok(finder.loops)
+}
+
+/// Collect loops and recursive functions lacking both a decreases clause and a waiver
+pub fn termination_report_core(content: &str) -> Result<Vec<TerminationIssue>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = LoopFinder::default();
+    finder.visit_file(&file);
+    Ok(finder.termination_issues())
+}
+
+/// The findings of a lint, and how many more were suppressed at the source
+pub type LintResult<T> = (Vec<T>, usize);
+
+/// Whether a function suppresses `lint`, by attribute or by a comment above
+/// it in `lines` (those of its file)
+fn is_suppressed(f: &FunctionSpecs, lint: Lint, lines: &[&str]) -> bool {
+    let name = lint.name().to_string();
+    f.suppressed.contains(&name) || comment_suppressions(lines, f.line_number.unwrap_or_default()).contains(&name)
+}
+
+/// Collects spec fn and const definitions with their visibility, and the
+/// public functions whose clauses should be checked
+#[derive(Default)]
+struct VisibilityFinder {
+    /// Name -> (any definition is pub, item kind)
+    definitions: HashMap<String, (bool, &'static str)>,
+    /// Public functions: (signature, line number)
+    pub_fns: Vec<(Signature, Option<usize>)>,
+}
+
+impl VisibilityFinder {
+    fn define(&mut self, name: String, is_pub: bool, kind: &'static str) {
+        let entry = self.definitions.entry(name).or_insert((false, kind));
+        entry.0 |= is_pub;
+    }
+
+    fn visit_fn<S: Spanned>(&mut self, vis: &verus_syn::Visibility, sig: &Signature, node: &S) {
+        if matches!(sig.mode, FnMode::Spec(_) | FnMode::SpecChecked(_)) {
+            self.define(sig.ident.to_string(), is_pub(vis), "spec fn");
+        }
+        if is_pub(vis) {
+            self.pub_fns.push((sig.clone(), Some(node.span().start().line)));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for VisibilityFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.vis, &node.sig, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.vis, &node.sig, node);
+    }
+
+    fn visit_item_const(&mut self, node: &'ast verus_syn::ItemConst) {
+        self.define(node.ident.to_string(), is_pub(&node.vis), "const");
+    }
+
+    fn visit_impl_item_const(&mut self, node: &'ast verus_syn::ImplItemConst) {
+        self.define(node.ident.to_string(), is_pub(&node.vis), "const");
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// A spec function: (name, opacity, line number, names its body refers to
+/// besides its parameters)
+type SpecBody = (String, &'static str, usize, Vec<String>);
+
+/// Collects the spec functions of a file with how their definitions are
+/// exposed and the names their bodies refer to
+#[derive(Default)]
+struct SpecBodyFinder {
+    spec_fns: Vec<SpecBody>,
+}
+
+impl SpecBodyFinder {
+    fn visit_fn<S: Spanned>(
+        &mut self,
+        sig: &Signature,
+        attrs: &[verus_syn::Attribute],
+        block: &verus_syn::Block,
+        node: &S,
+    ) {
+        if !matches!(sig.mode, FnMode::Spec(_) | FnMode::SpecChecked(_)) {
+            return;
+        }
+        let has_attr = |name: &str| attrs.iter().any(|a| is_verifier_attr(a, name));
+        let opacity = if has_prefix_keyword(sig, "uninterp") || has_attr("external_body") {
+            "uninterp"
+        } else if has_attr("opaque") {
+            "opaque"
+        } else if has_prefix_keyword(sig, "closed") {
+            "closed"
+        } else {
+            "open"
+        };
+        let params = param_names(sig);
+        let mut referenced = ReferencedNames::default();
+        referenced.visit_block(block);
+        let mut names: Vec<String> = Vec::new();
+        for name in referenced.names {
+            if !params.contains(&name) && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        self.spec_fns.push((sig.ident.to_string(), opacity, node.span().start().line, names));
+    }
+}
+
+impl<'ast> Visit<'ast> for SpecBodyFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.sig, &node.attrs, &node.block, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.sig, &node.attrs, &node.block, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// Collects the paths an expression refers to, except closure (quantifier)
+/// parameters within their body and constructors like `Some`
+#[derive(Default)]
+struct FreePaths {
+    bound: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for FreePaths {
+    fn visit_expr_closure(&mut self, node: &'ast verus_syn::ExprClosure) {
+        let outer = self.bound.len();
+        self.bound.extend(closure_param_names(node));
+        self.visit_expr(&node.body);
+        self.bound.truncate(outer);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast verus_syn::ExprPath) {
+        let path = &node.path;
+        let name = render_tokens(quote::quote!(#path));
+        if !self.bound.contains(&name) && !BUILTIN_CALLS.contains(&name.as_str()) && !self.paths.contains(&name) {
+            self.paths.push(name);
+        }
+    }
+}
+
+/// Parse one candidate clause for `validate_spec_exprs_core`
+fn validate_spec_expr(text: &str) -> SpecExprValidation {
+    match verus_syn::parse_str::<Expr>(text) {
+        Ok(expr) => {
+            let mut free = FreePaths::default();
+            free.visit_expr(&expr);
+            SpecExprValidation {
+                valid: true,
+                normalized: Some(quote::quote!(#expr).to_string()),
+                identifiers: free.paths,
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            let start = e.span().start();
+            let message = e.to_string();
+            // Errors at the end of the input, and tokenizer errors, carry no position
+            let column = if message.starts_with("unexpected end of input") {
+                Some(text.trim_end().chars().count())
+            } else if text.parse::<TokenStream>().is_err() {
+                None
+            } else {
+                Some(text[..LineIndex::new(text).offset(start.line, start.column)].chars().count())
+            };
+            SpecExprValidation { error: Some(message), error_column: column, ..Default::default() }
+        }
+    }
+}
+
+/// Validate candidate clauses, in input order (parsed on the rayon pool if `parallel`)
+pub fn validate_spec_exprs_core(exprs: &[String], parallel: bool) -> Vec<SpecExprValidation> {
+    if parallel {
+        exprs.par_iter().map(|text| validate_spec_expr(text)).collect()
+    } else {
+        exprs.iter().map(|text| validate_spec_expr(text)).collect()
+    }
+}
+
+/// Collects the numeric, string and character literals of an expression as
+/// (grouping key, text as written, line)
+struct LiteralFinder {
+    /// Group integers by value (`0x80` with `128`) rather than as written
+    normalize_radix: bool,
+    literals: Vec<(String, String, usize)>,
+}
+
+impl<'ast> Visit<'ast> for LiteralFinder {
+    fn visit_expr_lit(&mut self, node: &'ast verus_syn::ExprLit) {
+        let lit = &node.lit;
+        let text = quote::quote!(#lit).to_string();
+        let key = match &node.lit {
+            verus_syn::Lit::Int(int) if self.normalize_radix => int.base10_digits().to_string(),
+            verus_syn::Lit::Int(int) => text.strip_suffix(int.suffix()).unwrap_or(&text).replace('_', ""),
+            verus_syn::Lit::Float(float) => text.strip_suffix(float.suffix()).unwrap_or(&text).replace('_', ""),
+            verus_syn::Lit::Bool(_) => return,
+            _ => text.clone(),
+        };
+        self.literals.push((key, text, node.span().start().line));
+    }
+}
+
+/// Find requires/ensures clauses of `pub` functions that reference non-`pub`
+/// spec fns or consts defined in the same file
+pub fn spec_visibility_lint_core(content: &str) -> Result<LintResult<VisibilityIssue>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = VisibilityFinder::default();
+    finder.visit_file(&file);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut functions = FunctionFinder::new(None);
+    functions.keep_signatures = false;
+    functions.visit_file(&file);
+    let silenced: Vec<(String, Option<usize>)> = functions
+        .functions
+        .iter()
+        .filter(|f| is_suppressed(f, Lint::SpecVisibility, &lines))
+        .map(|f| (f.name.clone(), f.line_number))
+        .collect();
+
+    let (mut issues, mut suppressed) = (Vec::new(), 0);
+    for (sig, line_number) in &finder.pub_fns {
+        let is_silenced = silenced.contains(&(sig.ident.to_string(), *line_number));
+        let params = param_names(sig);
+        for kind in ["requires", "ensures"] {
+            for expr in clause_exprs(sig, kind)? {
+                let mut referenced = ReferencedNames::default();
+                referenced.visit_expr(expr);
+                let mut seen = Vec::new();
+                for name in referenced.names {
+                    if params.contains(&name) || seen.contains(&name) {
+                        continue;
+                    }
+                    match finder.definitions.get(&name) {
+                        Some((false, _)) if is_silenced => suppressed += 1,
+                        Some((false, item_kind)) => issues.push(VisibilityIssue {
+                            function: sig.ident.to_string(),
+                            line_number: *line_number,
+                            clause_kind: kind.to_string(),
+                            clause: quote::quote!(#expr).to_string(),
+                            item: name.clone(),
+                            item_kind: item_kind.to_string(),
+                        }),
+                        _ => {}
+                    }
+                    seen.push(name);
+                }
+            }
+        }
+    }
+    Ok((issues, suppressed))
+}
+
+/// Macros that build spec values and may appear in clauses
+const SPEC_MACROS: &[&str] = &["seq", "set", "map"];
+
+/// Compound assignment operators (`+=`, ...), which parse as binary expressions
+const COMPOUND_ASSIGN_OPS: &[&str] = &["+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<=", ">>="];
+
+/// Collects the sub-expressions of a clause that are exec-only:
+/// (kind, sub-expression text, start)
+#[derive(Default)]
+struct ExecOnlyFinder {
+    found: Vec<(&'static str, String, proc_macro2::LineColumn)>,
+}
+
+impl ExecOnlyFinder {
+    fn flag<T: quote::ToTokens + Spanned>(&mut self, kind: &'static str, node: &T) {
+        self.found.push((kind, quote::quote!(#node).to_string(), node.span().start()));
+    }
+}
+
+impl<'ast> Visit<'ast> for ExecOnlyFinder {
+    fn visit_expr_reference(&mut self, node: &'ast verus_syn::ExprReference) {
+        if node.mutability.is_some() {
+            self.flag("mut_borrow", node);
+        }
+        verus_syn::visit::visit_expr_reference(self, node);
+    }
+
+    fn visit_expr_assign(&mut self, node: &'ast verus_syn::ExprAssign) {
+        self.flag("assignment", node);
+        verus_syn::visit::visit_expr_assign(self, node);
+    }
+
+    fn visit_expr_binary(&mut self, node: &'ast verus_syn::ExprBinary) {
+        if COMPOUND_ASSIGN_OPS.contains(&bin_op_text(&node.op).as_str()) {
+            self.flag("assignment", node);
+        }
+        verus_syn::visit::visit_expr_binary(self, node);
+    }
+
+    fn visit_expr_return(&mut self, node: &'ast verus_syn::ExprReturn) {
+        self.flag("control_flow", node);
+        verus_syn::visit::visit_expr_return(self, node);
+    }
+
+    fn visit_expr_break(&mut self, node: &'ast verus_syn::ExprBreak) {
+        self.flag("control_flow", node);
+        verus_syn::visit::visit_expr_break(self, node);
+    }
+
+    // The macro's tokens are not expressions to look into
+    fn visit_expr_macro(&mut self, node: &'ast verus_syn::ExprMacro) {
+        let name = node.mac.path.segments.last().map(|seg| seg.ident.to_string()).unwrap_or_default();
+        if !SPEC_MACROS.contains(&name.as_str()) {
+            self.flag("macro", node);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast verus_syn::ExprMethodCall) {
+        if node.method == "clone" && node.args.is_empty() {
+            self.flag("clone", node);
+        }
+        verus_syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// Flag clause sub-expressions that are almost certainly wrong in spec
+/// position: `&mut` borrows, assignments, `return`/`break`, macro calls
+/// other than `seq!`/`set!`/`map!` (so `vec!` too), and `.clone()`
+pub fn lint_spec_exprs_core(content: &str) -> Result<LintResult<SpecExprIssue>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = FunctionFinder::new(None);
+    finder.visit_file(&file);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (mut issues, mut suppressed) = (Vec::new(), 0);
+    for (f, sig) in finder.functions.iter().zip(&finder.signatures) {
+        let is_silenced = is_suppressed(f, Lint::SpecExpr, &lines);
+        for kind in ["requires", "ensures", "decreases"] {
+            for expr in clause_exprs(sig, kind)? {
+                let mut exec_only = ExecOnlyFinder::default();
+                exec_only.visit_expr(expr);
+                if is_silenced {
+                    suppressed += exec_only.found.len();
+                    continue;
+                }
+                for (issue_kind, text, start) in exec_only.found {
+                    issues.push(SpecExprIssue {
+                        function: sig.ident.to_string(),
+                        clause_kind: kind.to_string(),
+                        clause: quote::quote!(#expr).to_string(),
+                        kind: issue_kind.to_string(),
+                        expr: text,
+                        line_number: start.line,
+                        column: start.column,
+                    });
+                }
+            }
+        }
+    }
+    Ok((issues, suppressed))
+}
+
+/// Whether a type name is an invariant type, and if so whether it is atomic
+fn invariant_type(name: &verus_syn::Ident) -> Option<bool> {
+    match name.to_string().as_str() {
+        "LocalInvariant" => Some(false),
+        "AtomicInvariant" => Some(true),
+        _ => None,
+    }
+}
+
+/// Split macro tokens at top-level `=>` arrows
+fn split_fat_arrows(tokens: TokenStream) -> Vec<TokenStream> {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut pieces = vec![TokenStream::new()];
+    let mut i = 0;
+    while i < tts.len() {
+        let is_arrow = matches!(&tts[i], TokenTree::Punct(p) if p.as_char() == '=' && p.spacing() == Spacing::Joint)
+            && matches!(tts.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '>');
+        if is_arrow {
+            pieces.push(TokenStream::new());
+            i += 2;
+        } else {
+            pieces.last_mut().unwrap().extend([tts[i].clone()]);
+            i += 1;
+        }
+    }
+    pieces
+}
+
+/// AST visitor collecting invariant openings, signature mentions and creations
+#[derive(Default)]
+struct InvariantFinder {
+    /// Usages found so far
+    found: Vec<InvariantUsage>,
+    /// Name of the function being visited
+    current_fn: String,
+    /// Whether the visitor is inside the parameter or return types
+    in_signature: bool,
+}
+
+impl InvariantFinder {
+    fn visit_fn(&mut self, sig: &Signature, block: Option<&verus_syn::Block>) {
+        let outer = std::mem::replace(&mut self.current_fn, sig.ident.to_string());
+        self.in_signature = true;
+        for input in &sig.inputs {
+            self.visit_fn_arg(input);
+        }
+        // Through `return_parts` so a named return `-> (r: T)` is seen too
+        if let Some(Ok(ty)) = return_parts(sig).map(|(_, ty)| verus_syn::parse2::<verus_syn::Type>(ty)) {
+            self.visit_type(&ty);
+        }
+        self.in_signature = false;
+        if let Some(block) = block {
+            self.visit_block(block);
+        }
+        self.current_fn = outer;
+    }
+
+    fn record<S: Spanned>(&mut self, kind: &str, atomic: bool, expr: String, binding: Option<String>, node: &S) {
+        let span = node.span();
+        self.found.push(InvariantUsage {
+            function: self.current_fn.clone(),
+            kind: kind.to_string(),
+            atomic,
+            expr,
+            binding,
+            line_number: span.start().line,
+            end_line: span.end().line,
+        });
+    }
+
+    /// Record an `open_*_invariant!(inv => name => { ... })` block and look
+    /// for nested openings in its body
+    fn visit_open_macro(&mut self, mac: &verus_syn::Macro) {
+        let atomic = match mac.path.segments.last().map(|seg| seg.ident.to_string()).as_deref() {
+            Some("open_local_invariant") => false,
+            Some("open_atomic_invariant") => true,
+            _ => return,
+        };
+        let mut pieces = split_fat_arrows(mac.tokens.clone()).into_iter();
+        let expr = pieces.next().unwrap_or_default().to_string();
+        let binding = pieces.next().map(|tokens| tokens.to_string()).filter(|name| !name.is_empty());
+        self.record("open", atomic, expr, binding, mac);
+        if let Some(Ok(block)) = pieces.next().map(verus_syn::parse2::<verus_syn::Block>) {
+            self.visit_block(&block);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for InvariantFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.sig, Some(&node.block));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.sig, Some(&node.block));
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn(&node.sig, node.default.as_ref());
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+
+    fn visit_type_path(&mut self, node: &'ast verus_syn::TypePath) {
+        match node.path.segments.last().and_then(|seg| invariant_type(&seg.ident)) {
+            Some(atomic) if self.in_signature => {
+                self.record("signature", atomic, render_tokens(quote::quote!(#node)), None, node)
+            }
+            _ => verus_syn::visit::visit_type_path(self, node),
+        }
+    }
+
+    fn visit_stmt_macro(&mut self, node: &'ast verus_syn::StmtMacro) {
+        self.visit_open_macro(&node.mac);
+    }
+
+    fn visit_expr_macro(&mut self, node: &'ast verus_syn::ExprMacro) {
+        self.visit_open_macro(&node.mac);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast verus_syn::ExprCall) {
+        if let Expr::Path(path) = strip_parens(&node.func) {
+            let segments = &path.path.segments;
+            let is_new = segments.len() >= 2 && segments.last().is_some_and(|seg| seg.ident == "new");
+            let ty = segments.iter().nth_back(1).and_then(|seg| invariant_type(&seg.ident));
+            if let (true, Some(atomic)) = (is_new, ty) {
+                self.record("create", atomic, quote::quote!(#node).to_string(), None, node);
+            }
+        }
+        verus_syn::visit::visit_expr_call(self, node);
+    }
+}
+
+/// Collect where each function opens, takes or returns, and creates invariants
+pub fn invariant_usage_core(content: &str) -> Result<Vec<InvariantUsage>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = InvariantFinder::default();
+    finder.visit_file(&file);
+    finder.found.sort_by_key(|usage| usage.line_number);
+    Ok(finder.found)
+}
+
+/// The lines of the outer (`///` or `#[doc = ...]`) doc attributes with their
+/// line numbers, the space after `///` removed
+fn outer_doc_lines(attrs: &[verus_syn::Attribute]) -> Vec<(String, usize)> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if !matches!(attr.style, verus_syn::AttrStyle::Outer) || !attr.path().is_ident("doc") {
+            continue;
+        }
+        let verus_syn::Meta::NameValue(nv) = &attr.meta else { continue };
+        let Expr::Lit(lit) = &nv.value else { continue };
+        let verus_syn::Lit::Str(text) = &lit.lit else { continue };
+        let line = attr.span().start().line;
+        for (i, text) in text.value().lines().enumerate() {
+            lines.push((text.strip_prefix(' ').unwrap_or(text).to_string(), line + i));
+        }
+    }
+    lines
+}
+
+/// The Rust code blocks of doc comment lines as `(code, first line)`: fences
+/// without a language or tagged `rust` or `verus` (attributes like `ignore`
+/// allowed); an unclosed fence runs to the end
+fn doc_code_blocks(lines: &[(String, usize)]) -> Vec<(String, usize)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(bool, Vec<&str>, usize)> = None;
+    for (text, line) in lines {
+        let trimmed = text.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            match open.take() {
+                Some((true, code, first)) => blocks.push((code.join("\n"), first)),
+                Some((false, ..)) => {}
+                None => {
+                    let language = info.split(',').next().unwrap_or_default().trim();
+                    open = Some((["", "rust", "verus"].contains(&language), Vec::new(), line + 1));
+                }
+            }
+        } else if let Some((_, code, _)) = open.as_mut() {
+            // Lines rustdoc hides are still part of the example
+            let code_line = if trimmed == "#" { "" } else { trimmed.strip_prefix("# ").unwrap_or(text) };
+            code.push(code_line);
+        }
+    }
+    if let Some((true, code, first)) = open {
+        blocks.push((code.join("\n"), first));
+    }
+    blocks
+}
+
+/// Parse a doc example as Verus items or, failing that, as the statements of
+/// a block; on failure, the error that got furthest into the code
+fn parse_doc_example(code: &str) -> Result<Vec<Item>, String> {
+    let position = |e: &verus_syn::Error| (e.span().start().line, e.span().start().column);
+    let tokens: TokenStream = code.parse().map_err(|e: proc_macro2::LexError| format!("Parse error: {}", e))?;
+    let items_error = match verus_syn::parse2::<VerusMacroBody>(tokens.clone()) {
+        Ok(body) => {
+            // A `verus!` block in the example must parse too
+            let nested = body.items.iter().find_map(|item| match item {
+                Item::Macro(node) if is_verus_macro(&node.mac.path, &[]) => {
+                    verus_syn::parse2::<VerusMacroBody>(node.mac.tokens.clone()).err()
+                }
+                _ => None,
+            });
+            match nested {
+                None => return Ok(body.items),
+                Some(e) => e,
+            }
+        }
+        Err(e) => e,
+    };
+    match verus_syn::parse2::<verus_syn::Block>(quote::quote!({ #tokens })) {
+        Ok(block) => Ok(vec![verus_syn::parse_quote!(fn example() #block)]),
+        Err(e) if position(&e) > position(&items_error) => Err(format!("Parse error: {}", e)),
+        Err(_) => Err(format!("Parse error: {}", items_error)),
+    }
+}
+
+/// AST visitor collecting the code examples in function doc comments
+#[derive(Default)]
+struct DocExampleFinder {
+    examples: Vec<DocExample>,
+    /// Doc comment of every function, by (name, line number)
+    docs: HashMap<(String, usize), String>,
+}
+
+impl DocExampleFinder {
+    fn visit_fn<S: Spanned>(&mut self, attrs: &[verus_syn::Attribute], sig: &Signature, node: &S) {
+        let doc_lines = outer_doc_lines(attrs);
+        let doc: Vec<&str> = doc_lines.iter().map(|(text, _)| text.as_str()).collect();
+        self.docs.insert((sig.ident.to_string(), node.span().start().line), doc.join("\n"));
+        for (code, line_number) in doc_code_blocks(&doc_lines) {
+            let (parsed, error, calls) = match parse_doc_example(&code) {
+                Ok(items) => {
+                    let mut finder = CallFinder::default();
+                    for item in &items {
+                        finder.visit_item(item);
+                    }
+                    let mut calls: Vec<String> = Vec::new();
+                    for call in finder.calls {
+                        if call.is_unresolved_method() {
+                            continue;
+                        }
+                        if !BUILTIN_CALLS.contains(&call.callee.as_str()) && !calls.contains(&call.callee) {
+                            calls.push(call.callee);
+                        }
+                    }
+                    (true, None, calls)
+                }
+                Err(error) => (false, Some(error), Vec::new()),
+            };
+            self.examples.push(DocExample {
+                function: sig.ident.to_string(),
+                line_number,
+                code,
+                parsed,
+                error,
+                calls,
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for DocExampleFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, node);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn(&node.attrs, &node.sig, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// The code examples in the doc comments of the functions of a file
+pub fn extract_doc_examples_core(content: &str) -> Result<Vec<DocExample>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = DocExampleFinder::default();
+    finder.visit_file(&file);
+    finder.examples.sort_by_key(|example| example.line_number);
+    Ok(finder.examples)
+}
+
+/// AST visitor collecting trait declarations
+#[derive(Default)]
+struct TraitFinder {
+    traits: Vec<TraitInfo>,
+    /// Names of the enclosing modules
+    module_stack: Vec<String>,
+    /// Breadcrumbs of the enclosing modules and macro invocations
+    context: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for TraitFinder {
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        let mut methods = FunctionFinder::new(None);
+        methods.module_stack = self.module_stack.clone();
+        methods.context = self.context.clone();
+        methods.visit_item_trait(node);
+
+        let generics = &node.generics;
+        let mut info = TraitInfo {
+            name: node.ident.to_string(),
+            module_path: self.module_stack.join("::"),
+            line_number: Some(node.span().start().line),
+            end_line: Some(node.span().end().line),
+            generics: render_tokens(quote::quote!(#generics)),
+            supertraits: node.supertraits.iter().map(bound_text).collect(),
+            methods: methods.functions,
+            ..Default::default()
+        };
+        for item in &node.items {
+            match item {
+                verus_syn::TraitItem::Type(ty) => info.associated_types.push(AssociatedItem {
+                    name: ty.ident.to_string(),
+                    bounds: ty.bounds.iter().map(bound_text).collect(),
+                    ty: None,
+                    default: ty.default.as_ref().map(|(_, default)| render_tokens(quote::quote!(#default))),
+                }),
+                verus_syn::TraitItem::Const(c) => {
+                    let ty = &c.ty;
+                    info.associated_consts.push(AssociatedItem {
+                        name: c.ident.to_string(),
+                        bounds: Vec::new(),
+                        ty: Some(render_tokens(quote::quote!(#ty))),
+                        default: c.default.as_ref().map(|(_, default)| quote::quote!(#default).to_string()),
+                    });
+                }
+                verus_syn::TraitItem::Fn(f) if f.default.is_some() => info.provided_methods.push(f.sig.ident.to_string()),
+                _ => {}
+            }
+        }
+        self.traits.push(info);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.module_stack.push(node.ident.to_string());
+        self.context.push(format!("mod {}", node.ident));
+        verus_syn::visit::visit_item_mod(self, node);
+        self.context.pop();
+        self.module_stack.pop();
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        self.context.push(format!("{}!", macro_name(&node.mac)));
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+        self.context.pop();
+    }
+}
+
+/// Methods of a trait or impl, with their signatures
+type MethodSigs = Vec<(FunctionSpecs, Signature)>;
+
+/// Collects the methods of trait declarations and of trait impls, with their signatures
+#[derive(Default)]
+struct TraitImplPairs {
+    /// Trait name -> declared methods
+    traits: HashMap<String, MethodSigs>,
+    /// (trait name, self type, implemented methods) per trait impl
+    impls: Vec<(String, String, MethodSigs)>,
+}
+
+impl<'ast> Visit<'ast> for TraitImplPairs {
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        let mut methods = FunctionFinder::new(None);
+        methods.visit_item_trait(node);
+        let methods = methods.functions.into_iter().zip(methods.signatures).collect();
+        self.traits.insert(node.ident.to_string(), methods);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        if let Some((_, path, _)) = &node.trait_ {
+            let Some(trait_name) = path.segments.last().map(|seg| seg.ident.to_string()) else {
+                return;
+            };
+            let self_ty = &node.self_ty;
+            let mut methods = FunctionFinder::new(None);
+            methods.visit_item_impl(node);
+            let methods = methods.functions.into_iter().zip(methods.signatures).collect();
+            self.impls.push((trait_name, render_tokens(quote::quote!(#self_ty)), methods));
+        }
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// Re-render a clause with identifiers renamed according to `renames`
+fn rename_clause_idents(clause: &str, renames: &HashMap<String, String>) -> String {
+    fn rename(tokens: TokenStream, renames: &HashMap<String, String>) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTree::Ident(ident) => match renames.get(&ident.to_string()) {
+                    Some(new) => TokenTree::Ident(proc_macro2::Ident::new(new, ident.span())),
+                    None => TokenTree::Ident(ident),
+                },
+                TokenTree::Group(group) => {
+                    let mut renamed = proc_macro2::Group::new(group.delimiter(), rename(group.stream(), renames));
+                    renamed.set_span(group.span());
+                    TokenTree::Group(renamed)
+                }
+                other => other,
+            })
+            .collect()
+    }
+    match clause.parse::<TokenStream>() {
+        Ok(tokens) => rename(tokens, renames).to_string(),
+        Err(_) => clause.to_string(),
+    }
+}
+
+/// Compare each trait impl method's clauses with the trait's declaration
+pub fn refinement_lint_core(content: &str) -> Result<LintResult<RefinementIssue>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut pairs = TraitImplPairs::default();
+    pairs.visit_file(&file);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (mut issues, mut suppressed) = (Vec::new(), 0);
+    for (trait_name, impl_type, methods) in &pairs.impls {
+        let Some(declared) = pairs.traits.get(trait_name) else {
+            continue;
+        };
+        for (imp, imp_sig) in methods {
+            let Some((decl, decl_sig)) = declared.iter().find(|(d, _)| d.name == imp.name) else {
+                continue;
+            };
+            // Name the impl's parameters and return value as the trait does
+            let mut renames: HashMap<String, String> =
+                param_names(imp_sig).into_iter().zip(param_names(decl_sig)).collect();
+            if let (Some(imp_ret), Some(decl_ret)) = (return_binding(imp_sig), return_binding(decl_sig)) {
+                renames.insert(imp_ret, decl_ret);
+            }
+            let normalize = |clauses: &[String]| -> Vec<String> {
+                clauses.iter().map(|c| rename_clause_idents(c, &renames)).collect()
+            };
+            let issue = |kind: &str, clause: &String| RefinementIssue {
+                trait_name: trait_name.clone(),
+                impl_type: impl_type.clone(),
+                method: imp.name.clone(),
+                line_number: imp.line_number,
+                kind: kind.to_string(),
+                clause: clause.clone(),
+            };
+
+            // An impl without an ensures block inherits the trait's unchanged
+            let mut found = Vec::new();
+            if imp.has_ensures {
+                let imp_ensures = normalize(&imp.ensures);
+                for clause in &decl.ensures {
+                    if !imp_ensures.contains(clause) {
+                        found.push(issue("syntactic_missing_ensures", clause));
+                    }
+                }
+            }
+            for clause in normalize(&imp.requires) {
+                if !decl.requires.contains(&clause) {
+                    found.push(issue("syntactic_added_requires", &clause));
+                }
+            }
+            if is_suppressed(imp, Lint::Refinement, &lines) {
+                suppressed += found.len();
+            } else {
+                issues.extend(found);
+            }
+        }
+    }
+    Ok((issues, suppressed))
+}
+
+/// Collect every trait declared in the file
+pub fn extract_traits_core(content: &str) -> Result<Vec<TraitInfo>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = TraitFinder::default();
+    finder.visit_file(&file);
+    Ok(finder.traits)
+}
+
+/// AST visitor collecting `type` alias items, in modules and `verus!` blocks included
+#[derive(Default)]
+struct TypeAliasFinder {
+    aliases: Vec<(TypeAlias, Option<verus_syn::Type>)>,
+    /// Names of the enclosing modules
+    module_stack: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for TypeAliasFinder {
+    fn visit_item_type(&mut self, node: &'ast verus_syn::ItemType) {
+        let (generics, ty) = (&node.generics, &node.ty);
+        let alias = TypeAlias {
+            name: node.ident.to_string(),
+            generics: render_tokens(quote::quote!(#generics)),
+            target: render_tokens(quote::quote!(#ty)),
+            module_path: self.module_stack.join("::"),
+            line_number: node.span().start().line,
+        };
+        // Generic aliases are listed but not expanded
+        let expandable = node.generics.params.is_empty().then(|| (*node.ty).clone());
+        self.aliases.push((alias, expandable));
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.module_stack.push(node.ident.to_string());
+        verus_syn::visit::visit_item_mod(self, node);
+        self.module_stack.pop();
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// The `type` aliases of a file in source order
+pub fn extract_type_aliases_core(content: &str) -> Result<Vec<TypeAlias>, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = TypeAliasFinder::default();
+    finder.visit_file(&file);
+    Ok(finder.aliases.into_iter().map(|(alias, _)| alias).collect())
+}
+
+/// Expand the non-generic aliases of `file` in `signatures` (parameter and
+/// return types, clauses) and in `exprs` (the query side: facts or a pattern)
+fn expand_file_aliases(file: &verus_syn::File, signatures: &mut [Signature], exprs: &mut [Expr]) {
+    let mut finder = TypeAliasFinder::default();
+    finder.visit_file(file);
+    let mut aliases = AliasMap::new();
+    for (alias, ty) in finder.aliases {
+        if let Some(ty) = ty {
+            aliases.entry(alias.name).or_insert(ty);
+        }
+    }
+    for sig in signatures {
+        *sig = with_aliases_expanded(sig, &aliases);
+    }
+    for expr in exprs {
+        *expr = with_aliases_expanded(expr, &aliases);
+    }
+}
+
+/// Flatten a `use` tree into `alias -> full path` entries (globs are skipped)
+fn collect_use_aliases(tree: &verus_syn::UseTree, prefix: &mut Vec<String>, aliases: &mut HashMap<String, String>) {
+    match tree {
+        verus_syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            collect_use_aliases(&path.tree, prefix, aliases);
+            prefix.pop();
+        }
+        verus_syn::UseTree::Name(name) if name.ident == "self" => {
+            if let Some(last) = prefix.last() {
+                aliases.insert(last.clone(), prefix.join("::"));
+            }
+        }
+        verus_syn::UseTree::Name(name) => {
+            let mut full = prefix.clone();
+            full.push(name.ident.to_string());
+            aliases.insert(name.ident.to_string(), full.join("::"));
+        }
+        verus_syn::UseTree::Rename(rename) => {
+            let mut full = prefix.clone();
+            if rename.ident != "self" {
+                full.push(rename.ident.to_string());
+            }
+            aliases.insert(rename.rename.to_string(), full.join("::"));
+        }
+        verus_syn::UseTree::Group(group) => {
+            for item in &group.items {
+                collect_use_aliases(item, prefix, aliases);
+            }
+        }
+        verus_syn::UseTree::Glob(_) => {}
+    }
+}
+
+/// A call in a function body
+struct CallSite {
+    /// Name of the calling function
+    caller: String,
+    /// Called path as written; `Self::method` and `self.method()` in an impl
+    /// or trait become `Type::method`, any other method call the method name
+    callee: String,
+    /// For a call through `self` or `Self::`, the enclosing impl's self type
+    /// (or trait); for a method call on another receiver, "unknown"; None
+    /// for other path calls
+    receiver_type: Option<String>,
+    /// Line number of the call (1-indexed)
+    line: usize,
+    /// Byte ranges of the argument expressions
+    args: Vec<Range<usize>>,
+}
+
+/// AST visitor collecting the call sites in function bodies, plus the
+/// `use` aliases and the names defined in the file
+#[derive(Default)]
+struct CallFinder {
+    /// Every path and method call in a function body
+    calls: Vec<CallSite>,
+    /// `use` aliases: local name -> full path
+    aliases: HashMap<String, String>,
+    /// Names of functions defined in the file
+    defined_fns: Vec<String>,
+    /// Names of modules and types defined in the file
+    defined_scopes: Vec<String>,
+    /// Name of the function being visited
+    current_fn: Option<String>,
+    /// Self type of the enclosing impl, or name of the enclosing trait
+    self_type: Option<String>,
+    /// Functions whose body is a single call statement
+    sole_calls: Vec<SoleCall>,
+}
+
+/// A function whose body is a single call statement, possibly inside a
+/// single `proof { ... }` block
+struct SoleCall {
+    sig: Signature,
+    /// Called path as written
+    callee: String,
+    args: Vec<Expr>,
+}
+
+/// The call a block consists of, if it is one call statement (possibly
+/// inside a single `proof { ... }` block)
+fn sole_call(block: &verus_syn::Block) -> Option<&verus_syn::ExprCall> {
+    match block.stmts.as_slice() {
+        [verus_syn::Stmt::Expr(Expr::Call(call), _)] => Some(call),
+        _ => None,
+    }
+}
+
+/// `sole_call` of a function body, looking inside a lone proof block
+fn sole_body_call(block: &verus_syn::Block) -> Option<(String, Vec<Expr>)> {
+    let call = |call: &verus_syn::ExprCall| call_path(call).map(|path| (path, call.args.iter().cloned().collect()));
+    if let Some(found) = sole_call(block) {
+        return call(found);
+    }
+    let [verus_syn::Stmt::Expr(expr, _)] = block.stmts.as_slice() else {
+        return None;
+    };
+    if !is_proof_block(expr) {
+        return None;
+    }
+    let inner = quote::quote!(#expr).into_iter().nth(1)?;
+    let inner = verus_syn::parse2::<verus_syn::Block>(TokenStream::from(inner)).ok()?;
+    sole_call(&inner).and_then(call)
+}
+
+impl CallFinder {
+    fn visit_fn_body(&mut self, sig: &Signature, block: Option<&verus_syn::Block>) {
+        self.defined_fns.push(sig.ident.to_string());
+        if let Some((callee, args)) = block.and_then(sole_body_call) {
+            self.sole_calls.push(SoleCall { sig: sig.clone(), callee, args });
+        }
+        if let Some(block) = block {
+            let outer = self.current_fn.replace(sig.ident.to_string());
+            self.visit_block(block);
+            self.current_fn = outer;
+        }
+    }
+
+    /// Expand the first segment of a path through the `use` aliases
+    fn expand(&self, path: &str) -> String {
+        let (first, rest) = path.split_once("::").map_or((path, None), |(first, rest)| (first, Some(rest)));
+        match (self.aliases.get(first), rest) {
+            (Some(full), Some(rest)) => format!("{}::{}", full, rest),
+            (Some(full), None) => full.clone(),
+            (None, _) => path.to_string(),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for CallFinder {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn_body(&node.sig, Some(&node.block));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn_body(&node.sig, Some(&node.block));
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn_body(&node.sig, node.default.as_ref());
+    }
+
+    fn visit_item_use(&mut self, node: &'ast verus_syn::ItemUse) {
+        collect_use_aliases(&node.tree, &mut Vec::new(), &mut self.aliases);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.defined_scopes.push(node.ident.to_string());
+        verus_syn::visit::visit_item_mod(self, node);
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast verus_syn::ItemStruct) {
+        self.defined_scopes.push(node.ident.to_string());
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast verus_syn::ItemEnum) {
+        self.defined_scopes.push(node.ident.to_string());
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        self.defined_scopes.push(node.ident.to_string());
+        let outer = self.self_type.replace(node.ident.to_string());
+        verus_syn::visit::visit_item_trait(self, node);
+        self.self_type = outer;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        let self_ty = &node.self_ty;
+        let outer = self.self_type.replace(render_tokens(quote::quote!(#self_ty)));
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+        self.self_type = outer;
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast verus_syn::ExprCall) {
+        if let (Some(caller), Some(callee)) = (&self.current_fn, call_path(node)) {
+            let (callee, receiver_type) = match (callee.strip_prefix("Self::"), &self.self_type) {
+                (Some(method), Some(self_type)) => (format!("{}::{}", self_type, method), Some(self_type.clone())),
+                _ => (callee, None),
+            };
+            self.calls.push(CallSite {
+                caller: caller.clone(),
+                callee,
+                receiver_type,
+                line: node.span().start().line,
+                args: node.args.iter().map(|arg| arg.span().byte_range()).collect(),
+            });
+        }
+        verus_syn::visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast verus_syn::ExprMethodCall) {
+        if let Some(caller) = &self.current_fn {
+            let method = node.method.to_string();
+            let on_self = matches!(strip_parens(&node.receiver), Expr::Path(path) if path.path.is_ident("self"));
+            let (callee, receiver_type) = match &self.self_type {
+                Some(self_type) if on_self => (format!("{}::{}", self_type, method), self_type.clone()),
+                _ => (method, "unknown".to_string()),
+            };
+            self.calls.push(CallSite {
+                caller: caller.clone(),
+                callee,
+                receiver_type: Some(receiver_type),
+                line: node.method.span().start().line,
+                args: node.args.iter().map(|arg| arg.span().byte_range()).collect(),
+            });
+        }
+        verus_syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+impl CallSite {
+    /// Whether this is a method call on a receiver of unknown type
+    fn is_unresolved_method(&self) -> bool {
+        self.receiver_type.as_deref() == Some("unknown")
+    }
+}
+
+/// Whether a called path (after alias expansion) may refer to a function of
+/// the files `finders` visited: a `crate::`/`self::`/`super::`/`Self::` path,
+/// or one whose last segment is a function of the files and whose first, if
+/// any other, a module or type of the files
+fn is_local_path(path: &str, finders: &[CallFinder]) -> bool {
+    let segments: Vec<&str> = path.split("::").collect();
+    let first = segments[0];
+    if ["crate", "self", "super", "Self"].contains(&first) {
+        return true;
+    }
+    let last = segments[segments.len() - 1];
+    finders.iter().flat_map(|f| &f.defined_fns).any(|f| f == last)
+        && (segments.len() == 1 || finders.iter().flat_map(|f| &f.defined_scopes).any(|s| s == first))
+}
+
+/// Aggregate the calls in `files` (`(path, content)` pairs) to paths not defined
+/// in any of them, grouped by leading crate segment
+pub fn external_lemma_usage_core(
+    files: &[(String, String)],
+) -> Result<BTreeMap<String, BTreeMap<String, ExternalUsage>>, String> {
+    let mut finders = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = CallFinder::default();
+        finder.visit_file(&file);
+        finders.push(finder);
+    }
+    let is_local = |path: &str| is_local_path(path, &finders);
+
+    let mut usage: BTreeMap<String, BTreeMap<String, ExternalUsage>> = BTreeMap::new();
+    for finder in &finders {
+        for CallSite { caller, callee, receiver_type, .. } in &finder.calls {
+            // Calls through `self`/`Self::` stay in the crate; other method calls are not resolved
+            if BUILTIN_CALLS.contains(&callee.as_str()) || receiver_type.is_some() {
+                continue;
+            }
+            let full = finder.expand(callee);
+            if is_local(&full) {
+                continue;
+            }
+            let krate = full.split("::").next().unwrap_or_default().to_string();
+            let entry = usage.entry(krate).or_default().entry(full).or_default();
+            entry.count += 1;
+            if !entry.callers.contains(caller) {
+                entry.callers.push(caller.clone());
+            }
+        }
+    }
+    Ok(usage)
+}
+
+/// Names bound by the quantifiers and closures of an expression
+#[derive(Default)]
+struct BoundNames {
+    names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for BoundNames {
+    fn visit_expr_closure(&mut self, node: &'ast verus_syn::ExprClosure) {
+        self.names.extend(closure_param_names(node));
+        verus_syn::visit::visit_expr_closure(self, node);
+    }
+}
+
+/// The requires and ensures conjuncts of a signature as (clause kind,
+/// alpha-normalized tree) pairs
+fn contract_asts(sig: &Signature) -> Result<Vec<(&'static str, ClauseAst)>, String> {
+    let mut asts = Vec::new();
+    for kind in ["requires", "ensures"] {
+        for conjunct in clause_exprs(sig, kind)?.into_iter().flat_map(conjuncts) {
+            asts.push((kind, alpha_normalized(&clause_ast_of(conjunct), &mut Vec::new())));
+        }
+    }
+    Ok(asts)
+}
+
+/// `tokens` with every identifier in `args` replaced by its (parenthesized) argument
+fn substituted(tokens: TokenStream, args: &HashMap<String, TokenStream>) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Ident(ident) => match args.get(&ident.to_string()) {
+                Some(arg) => TokenTree::Group(proc_macro2::Group::new(Delimiter::Parenthesis, arg.clone())),
+                None => TokenTree::Ident(ident),
+            },
+            TokenTree::Group(group) => {
+                let mut inner = proc_macro2::Group::new(group.delimiter(), substituted(group.stream(), args));
+                inner.set_span(group.span());
+                TokenTree::Group(inner)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// The contract of `callee` with the call's arguments put in for its
+/// parameters, as by `contract_asts`; None if a clause no longer parses
+fn instantiated_contract(
+    callee: &Signature,
+    args: &[Expr],
+) -> Result<Option<Vec<(&'static str, ClauseAst)>>, String> {
+    let params = param_names(callee);
+    if params.len() != args.len() {
+        return Ok(None);
+    }
+    let bindings: HashMap<String, TokenStream> =
+        params.into_iter().zip(args).map(|(param, arg)| (param, quote::quote!(#arg))).collect();
+    let mut asts = Vec::new();
+    for kind in ["requires", "ensures"] {
+        for clause in clause_exprs(callee, kind)? {
+            let Ok(expr) = verus_syn::parse2::<Expr>(substituted(quote::quote!(#clause), &bindings)) else {
+                return Ok(None);
+            };
+            for conjunct in conjuncts(&expr) {
+                asts.push((kind, alpha_normalized(&clause_ast_of(conjunct), &mut Vec::new())));
+            }
+        }
+    }
+    Ok(Some(asts))
+}
+
+/// The proof functions of `files` (`(path, content)` pairs) that only call
+/// another lemma: a body of one call statement (possibly in a single proof
+/// block) and ensures naming nothing but the parameters, the return binding,
+/// bound variables and segments of the called path
+pub fn wrapper_lemmas_core(files: &[(String, String)]) -> Result<Vec<WrapperLemma>, String> {
+    let mut finders = Vec::new();
+    let mut signatures: HashMap<String, Signature> = HashMap::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = CallFinder::default();
+        finder.visit_file(&file);
+        finders.push(finder);
+        let mut functions = FunctionFinder::new(None);
+        functions.visit_file(&file);
+        for sig in functions.signatures {
+            signatures.entry(sig.ident.to_string()).or_insert(sig);
+        }
+    }
+
+    let mut wrappers = Vec::new();
+    for ((path, _), finder) in files.iter().zip(&finders) {
+        for SoleCall { sig, callee, args } in &finder.sole_calls {
+            if !matches!(sig.mode, FnMode::Proof(_)) || BUILTIN_CALLS.contains(&callee.as_str()) {
+                continue;
+            }
+            let full = finder.expand(callee);
+            let params = param_names(sig);
+            let mut allowed: BTreeSet<String> = params.iter().cloned().chain(return_binding(sig)).collect();
+            allowed.extend(full.split("::").map(str::to_string));
+            let ensures = clause_exprs(sig, "ensures")?;
+            let restates_call = ensures.iter().all(|expr| {
+                let mut bound = BoundNames::default();
+                bound.visit_expr(expr);
+                referenced_names(expr).iter().all(|name| allowed.contains(name) || bound.names.contains(name))
+            });
+            if !restates_call {
+                continue;
+            }
+            let is_external = !is_local_path(&full, &finders);
+            let is_param =
+                |arg: &Expr, param: &String| matches!(strip_parens(arg), Expr::Path(p) if p.path.is_ident(param));
+            let forwards_params = args.len() == params.len() && args.iter().zip(&params).all(|(a, p)| is_param(a, p));
+            let wrapped = full.rsplit("::").next().and_then(|name| signatures.get(name)).filter(|_| !is_external);
+            let contract_equivalent = match wrapped {
+                Some(wrapped) => {
+                    let own = contract_asts(sig)?;
+                    Some(instantiated_contract(wrapped, args)?.is_some_and(|theirs| theirs == own))
+                }
+                None => None,
+            };
+            wrappers.push(WrapperLemma {
+                function: sig.ident.to_string(),
+                file_path: path.clone(),
+                line_number: sig.ident.span().start().line,
+                callee: full,
+                is_external,
+                forwards_params,
+                contract_equivalent,
+            });
+        }
+    }
+    Ok(wrappers)
+}
+
+/// The literals in the requires and ensures clauses of `files` (`(path,
+/// content)` pairs), grouped by their text without suffix and underscores (or,
+/// with `normalize_radix`, integers by value)
+pub fn spec_literals_core(
+    files: &[(String, String)],
+    normalize_radix: bool,
+) -> Result<BTreeMap<String, Vec<LiteralUse>>, String> {
+    let mut literals: BTreeMap<String, Vec<LiteralUse>> = BTreeMap::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        for sig in &finder.signatures {
+            for kind in ["requires", "ensures"] {
+                let mut found = LiteralFinder { normalize_radix, literals: Vec::new() };
+                for expr in clause_exprs(sig, kind)? {
+                    found.visit_expr(expr);
+                }
+                for (key, text, line_number) in found.literals {
+                    literals.entry(key).or_default().push(LiteralUse {
+                        function: sig.ident.to_string(),
+                        file_path: path.clone(),
+                        clause_kind: kind.to_string(),
+                        line_number,
+                        text,
+                    });
+                }
+            }
+        }
+    }
+    Ok(literals)
+}
+
+/// Rank the proof functions of `files` (`(path, content)` pairs) by the number
+/// of distinct functions calling them, most called first and ties by name
+///
+/// Calls are matched on the last path segment after `use` alias expansion, and
+/// method calls on the method name (whatever the receiver); a caller is a
+/// function name within a file, and recursive calls do not count.
+pub fn lemma_fan_in_core(files: &[(String, String)]) -> Result<Vec<(FunctionSpecs, usize)>, String> {
+    let mut lemmas = Vec::new();
+    let mut callers: HashMap<String, Vec<(&str, String)>> = HashMap::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        set_file_path(&mut finder.functions, path);
+        lemmas.extend(finder.functions.into_iter().filter(|f| f.is_proof));
+
+        let mut calls = CallFinder::default();
+        calls.visit_file(&file);
+        // A method call on a receiver of unknown type may be to any same-named method
+        for call in calls.calls.iter().filter(|call| !call.is_unresolved_method()) {
+            let full = calls.expand(&call.callee);
+            let callee = full.rsplit("::").next().unwrap_or_default().to_string();
+            let caller = (path.as_str(), call.caller.clone());
+            let entry = callers.entry(callee.clone()).or_default();
+            if call.caller != callee && !entry.contains(&caller) {
+                entry.push(caller);
+            }
+        }
+    }
+    let mut ranked: Vec<(FunctionSpecs, usize)> = lemmas
+        .into_iter()
+        .map(|f| {
+            let count = callers.get(&f.name).map_or(0, Vec::len);
+            (f, count)
+        })
+        .collect();
+    ranked.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.name.cmp(&b.name)));
+    Ok(ranked)
+}
+
+/// The proof functions of `files` (`(path, content)` pairs) that no function
+/// calls, broadcast lemmas excepted, in file and source order
+pub fn find_unused_lemmas_core(files: &[(String, String)]) -> Result<LintResult<FunctionSpecs>, String> {
+    let mut unused: Vec<FunctionSpecs> = lemma_fan_in_core(files)?
+        .into_iter()
+        .filter(|(f, count)| *count == 0 && !f.is_broadcast)
+        .map(|(f, _)| f)
+        .collect();
+    unused.sort_by(|a, b| a.file_path.cmp(&b.file_path).then_with(|| a.line_number.cmp(&b.line_number)));
+    let lines: HashMap<&str, Vec<&str>> =
+        files.iter().map(|(path, content)| (path.as_str(), content.lines().collect())).collect();
+    let (suppressed, unused): (Vec<_>, Vec<_>) =
+        unused.into_iter().partition(|f| is_suppressed(f, Lint::UnusedLemma, &lines[&*f.file_path]));
+    Ok((unused, suppressed.len()))
+}
+
+/// For each built-in lint, by name, the number of findings in `files` left
+/// out because they are suppressed at the source (0 for the others)
+pub fn suppressed_findings_core(files: &[(String, String)]) -> Result<BTreeMap<String, usize>, String> {
+    let mut counts: BTreeMap<String, usize> = Lint::ALL.iter().map(|lint| (lint.name().to_string(), 0)).collect();
+    let mut add = |lint: Lint, suppressed: usize| *counts.entry(lint.name().to_string()).or_default() += suppressed;
+    add(Lint::UnusedLemma, find_unused_lemmas_core(files)?.1);
+    for (path, content) in files {
+        let located = |e: String| format!("{}: {}", path, e);
+        add(Lint::TrivialLemma, lint_trivial_lemmas_core(content).map_err(located)?.1);
+        add(Lint::SpecsOutsideVerus, specs_outside_verus_core(content).map_err(located)?.1);
+        add(Lint::SpecVisibility, spec_visibility_lint_core(content).map_err(located)?.1);
+        add(Lint::SpecExpr, lint_spec_exprs_core(content).map_err(located)?.1);
+        add(Lint::Refinement, refinement_lint_core(content).map_err(located)?.1);
+    }
+    Ok(counts)
+}
+
+/// A node of a `to_dot` graph
+struct DotNode {
+    /// Mode of a function of the files (see `FunctionSpecs::mode`), None for an external path
+    mode: Option<String>,
+    /// Whether the function is a broadcast lemma
+    is_broadcast: bool,
+    /// Module path of the function within its file (empty at top level and for external paths)
+    module_path: String,
+}
+
+/// Quote a DOT identifier
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The call graph (`graph_kind` "call": calls in function bodies) or the
+/// spec-dependency graph ("spec": calls in requires, ensures and decreases
+/// clauses and in spec function bodies) of `files` (`(path, content)` pairs),
+/// as DOT text
+///
+/// Calls resolve to a function of the files by last segment after `use`
+/// alias expansion, as for fan-in, and otherwise to an external node named
+/// by the expanded path; method calls only count when the method is a
+/// function of the files, and constructors and `old` are left out. Edges are
+/// labeled with their number of call sites. With `focus`, only the nodes
+/// within `depth` edges of that function, in either direction, are kept.
+pub fn to_dot_core(
+    graph_kind: &str,
+    files: &[(String, String)],
+    cluster_by_module: bool,
+    focus: Option<&str>,
+    depth: usize,
+) -> Result<String, String> {
+    let graph_name = match graph_kind {
+        "call" => "calls",
+        "spec" => "spec_dependencies",
+        _ => return Err(format!("Unknown graph kind '{}' (expected call or spec)", graph_kind)),
+    };
+    let mut nodes: BTreeMap<String, DotNode> = BTreeMap::new();
+    let mut finders = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        let mut calls = CallFinder::default();
+        calls.visit_file(&file);
+        if graph_kind == "spec" {
+            let spec_fns: Vec<&str> =
+                finder.functions.iter().filter(|f| f.mode.starts_with("spec")).map(|f| f.name.as_str()).collect();
+            calls.calls.retain(|call| spec_fns.contains(&call.caller.as_str()));
+            for sig in &finder.signatures {
+                calls.current_fn = Some(sig.ident.to_string());
+                for kind in ["requires", "ensures", "decreases"] {
+                    for expr in clause_exprs(sig, kind)? {
+                        calls.visit_expr(expr);
+                    }
+                }
+            }
+            calls.current_fn = None;
+        }
+        for f in finder.functions {
+            nodes.entry(f.name).or_insert(DotNode {
+                mode: Some(f.mode),
+                is_broadcast: f.is_broadcast,
+                module_path: f.module_path,
+            });
+        }
+        finders.push(calls);
+    }
+
+    let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut external: Vec<String> = Vec::new();
+    for calls in &finders {
+        for call in &calls.calls {
+            let full = calls.expand(&call.callee);
+            let last = full.rsplit("::").next().unwrap_or_default();
+            if BUILTIN_CALLS.contains(&last) || call.is_unresolved_method() {
+                continue;
+            }
+            let callee = if call.receiver_type.is_some() || is_local_path(&full, &finders) {
+                if !nodes.contains_key(last) {
+                    continue;
+                }
+                last.to_string()
+            } else {
+                external.push(full.clone());
+                full
+            };
+            *edges.entry((call.caller.clone(), callee)).or_default() += 1;
+        }
+    }
+    for path in external {
+        nodes.entry(path).or_insert(DotNode { mode: None, is_broadcast: false, module_path: String::new() });
+    }
+
+    if let Some(focus) = focus {
+        if !matches!(nodes.get(focus), Some(DotNode { mode: Some(_), .. })) {
+            return Err(format!("Function '{}' not found", focus));
+        }
+        let mut kept: BTreeSet<String> = BTreeSet::from([focus.to_string()]);
+        let mut frontier = vec![focus.to_string()];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for (caller, callee) in edges.keys() {
+                for (from, to) in [(caller, callee), (callee, caller)] {
+                    if frontier.contains(from) && kept.insert(to.clone()) {
+                        next.push(to.clone());
+                    }
+                }
+            }
+            frontier = next;
+        }
+        nodes.retain(|name, _| kept.contains(name));
+        edges.retain(|(caller, callee), _| kept.contains(caller) && kept.contains(callee));
+    }
+
+    let node_line = |name: &str, node: &DotNode| {
+        let attrs = match node.mode.as_deref() {
+            None => "shape=box, fillcolor=lightgray".to_string(),
+            Some(mode) => {
+                let color = match mode {
+                    "proof" => "lightblue",
+                    "exec" => "white",
+                    _ => "lightyellow",
+                };
+                let style = if node.is_broadcast { ", style=\"filled,dashed\"" } else { "" };
+                format!("fillcolor={}{}", color, style)
+            }
+        };
+        format!("{} [{}];\n", dot_id(name), attrs)
+    };
+    let mut dot = format!("digraph {} {{\n    node [style=filled];\n", graph_name);
+    let mut modules: BTreeMap<&str, Vec<(&String, &DotNode)>> = BTreeMap::new();
+    for (name, node) in &nodes {
+        if cluster_by_module && !node.module_path.is_empty() {
+            modules.entry(node.module_path.as_str()).or_default().push((name, node));
+        } else {
+            dot += &format!("    {}", node_line(name, node));
+        }
+    }
+    for (module, members) in modules {
+        let cluster = dot_id(&format!("cluster_{}", module));
+        dot += &format!("    subgraph {} {{\n        label={};\n", cluster, dot_id(module));
+        for (name, node) in members {
+            dot += &format!("        {}", node_line(name, node));
+        }
+        dot += "    }\n";
+    }
+    for ((caller, callee), count) in &edges {
+        dot += &format!("    {} -> {} [label=\"{}\"];\n", dot_id(caller), dot_id(callee), count);
+    }
+    dot += "}\n";
+    Ok(dot)
+}
+
+/// Collects the quantifiers without a trigger annotation in the clauses of
+/// every function and in the bodies of spec functions
+struct QuantifierFinder<'a> {
+    /// Lines of the file, to take the quantifier text from
+    index: LineIndex<'a>,
+    found: Vec<UntriggeredQuantifier>,
+}
+
+impl QuantifierFinder<'_> {
+    fn visit_fn(&mut self, sig: &Signature, block: Option<&verus_syn::Block>) {
+        let spec = &sig.spec;
+        let clauses = [
+            ("requires", spec.requires.as_ref().map(|c| &c.exprs)),
+            ("recommends", spec.recommends.as_ref().map(|c| &c.exprs)),
+            ("ensures", spec.ensures.as_ref().map(|c| &c.exprs)),
+        ];
+        for (kind, exprs) in clauses {
+            for expr in exprs.into_iter().flat_map(|exprs| exprs.exprs.iter()) {
+                self.scan(kind, sig, quote::quote!(#expr));
+            }
+        }
+        if let (Some(block), FnMode::Spec(_) | FnMode::SpecChecked(_)) = (block, &sig.mode) {
+            self.scan("body", sig, quote::quote!(#block));
+        }
+    }
+
+    /// Record the untriggered quantifiers of `tokens`, nested ones included;
+    /// a quantifier's body runs to the end of the enclosing tokens
+    fn scan(&mut self, kind: &str, sig: &Signature, tokens: TokenStream) {
+        let tts: Vec<TokenTree> = tokens.into_iter().collect();
+        let is_bar = |tt: Option<&TokenTree>| matches!(tt, Some(TokenTree::Punct(p)) if p.as_char() == '|');
+        for (i, tt) in tts.iter().enumerate() {
+            match tt {
+                TokenTree::Ident(ident)
+                    if ["forall", "exists", "choose"].contains(&ident.to_string().as_str()) && is_bar(tts.get(i + 1)) =>
+                {
+                    let Some(close) = (i + 2..tts.len()).find(|&j| is_bar(tts.get(j))) else {
+                        return;
+                    };
+                    let body = &tts[close + 1..];
+                    let (triggered, is_auto) = trigger_annotations(body);
+                    if !triggered {
+                        let quantifier: TokenStream = tts[i..].iter().cloned().collect();
+                        let binders = match quantifier_ast(quantifier) {
+                            Some(ClauseAst::Quantifier { binders, .. }) => {
+                                binders.into_iter().map(|b| (b.name, b.ty)).collect()
+                            }
+                            _ => Vec::new(),
+                        };
+                        let start = ident.span().start();
+                        let end = tts[tts.len() - 1].span().end();
+                        let text = &self.index.text
+                            [self.index.offset(start.line, start.column)..self.index.offset(end.line, end.column)];
+                        self.found.push(UntriggeredQuantifier {
+                            function: sig.ident.to_string(),
+                            clause_kind: kind.to_string(),
+                            quantifier: ident.to_string(),
+                            text: text.to_string(),
+                            binders,
+                            line_number: start.line,
+                            column: start.column,
+                            is_auto,
+                            ..Default::default()
+                        });
+                    }
+                    self.scan(kind, sig, body.iter().cloned().collect());
+                    return;
+                }
+                TokenTree::Group(group) => self.scan(kind, sig, group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether a quantifier body carries a trigger (a leading `#![trigger ...]`,
+/// or a `#[trigger]` term outside any nested quantifier), and whether it
+/// starts with `#![auto]`
+fn trigger_annotations(body: &[TokenTree]) -> (bool, bool) {
+    fn attr_name(tts: &[TokenTree], inner: bool) -> Option<String> {
+        let (hash, rest) = tts.split_first()?;
+        let rest = match rest.first() {
+            Some(TokenTree::Punct(bang)) if inner && bang.as_char() == '!' => &rest[1..],
+            _ if inner => return None,
+            _ => rest,
+        };
+        match (hash, rest.first()) {
+            (TokenTree::Punct(hash), Some(TokenTree::Group(group)))
+                if hash.as_char() == '#' && group.delimiter() == Delimiter::Bracket =>
+            {
+                match group.stream().into_iter().next() {
+                    Some(TokenTree::Ident(name)) => Some(name.to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+    fn has_trigger_term(tts: &[TokenTree]) -> bool {
+        for (i, tt) in tts.iter().enumerate() {
+            match tt {
+                TokenTree::Ident(ident)
+                    if ["forall", "exists", "choose"].contains(&ident.to_string().as_str())
+                        && matches!(tts.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '|') =>
+                {
+                    return false;
+                }
+                TokenTree::Group(group) => {
+                    let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+                    if has_trigger_term(&inner) {
+                        return true;
+                    }
+                }
+                _ if attr_name(&tts[i..], false).as_deref() == Some("trigger") => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+    let mut rest = body;
+    let mut is_auto = false;
+    while let Some(name) = attr_name(rest, true) {
+        match name.as_str() {
+            "trigger" => return (true, is_auto),
+            "auto" => is_auto = true,
+            _ => {}
+        }
+        rest = &rest[3..];
+    }
+    (has_trigger_term(rest), is_auto)
+}
+
+impl<'ast> Visit<'ast> for QuantifierFinder<'_> {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.visit_fn(&node.sig, Some(&node.block));
+        verus_syn::visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.visit_fn(&node.sig, Some(&node.block));
+        verus_syn::visit::visit_impl_item_fn(self, node);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.visit_fn(&node.sig, node.default.as_ref());
+        verus_syn::visit::visit_trait_item_fn(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// The quantifiers of `files` (`(path, content)` pairs) without an explicit
+/// trigger, in file and source order
+pub fn untriggered_quantifiers_core(files: &[(String, String)]) -> Result<Vec<UntriggeredQuantifier>, String> {
+    let mut found = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = QuantifierFinder { index: LineIndex::new(content), found: Vec::new() };
+        finder.visit_file(&file);
+        finder.found.sort_by_key(|q| (q.line_number, q.column));
+        found.extend(finder.found.into_iter().map(|q| UntriggeredQuantifier { file_path: path.clone(), ..q }));
+    }
+    Ok(found)
+}
+
+/// The marker comments of `files` (`(path, content)` pairs) with the
+/// function containing each, in file and line order
+pub fn proof_todos_core(files: &[(String, String)], markers: Option<&[String]>) -> Result<Vec<ProofTodo>, String> {
+    let mut todos = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.keep_signatures = false;
+        finder.visit_file(&file);
+        let unattributed = add_todo_markers(&mut finder.functions, content, markers);
+        let mut file_todos: Vec<ProofTodo> = unattributed
+            .into_iter()
+            .map(|(line_number, text)| ProofTodo { file_path: path.clone(), line_number, text, ..Default::default() })
+            .collect();
+        for f in &finder.functions {
+            file_todos.extend(f.todo_markers.iter().map(|(line_number, text)| ProofTodo {
+                file_path: path.clone(),
+                line_number: *line_number,
+                text: text.clone(),
+                function: Some(f.name.clone()),
+                mode: Some(f.mode.clone()),
+            }));
+        }
+        file_todos.sort_by_key(|todo| todo.line_number);
+        todos.extend(file_todos);
+    }
+    Ok(todos)
+}
+
+/// Split an identifier or query into lowercase words at `_`, `::` and whitespace
+fn name_words(text: &str) -> Vec<String> {
+    text.split(|c: char| c == '_' || c == ':' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether the characters of `needle` appear in order in `haystack`
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut rest = haystack.chars();
+    needle.chars().all(|c| rest.any(|h| h == c))
+}
+
+/// Similarity in [0, 1] of a query word to a name word: exact match, then
+/// prefix ("mult" for "multiples"), then subsequence ("mltpl"), then typos
+fn word_score(query: &str, word: &str) -> f64 {
+    let ratio = query.len().min(word.len()) as f64 / query.len().max(word.len()) as f64;
+    if query == word {
+        1.0
+    } else if word.starts_with(query) {
+        0.6 + 0.3 * ratio
+    } else if is_subsequence(query, word) {
+        0.4 + 0.3 * ratio
+    } else {
+        let similarity = 1.0 - edit_distance(query, word) as f64 / query.len().max(word.len()) as f64;
+        if similarity >= 0.5 {
+            similarity * 0.7
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Score a function name against a query in [0, 1]
+///
+/// An exact match of the name or qualified name scores 1.0; anything else is
+/// capped below that. Each query word is matched to its best name word, and
+/// the average is weighted by how much of the name the query covers and
+/// whether the words appear in order.
+fn fuzzy_score(query: &str, name: &str, qualified_name: &str) -> f64 {
+    let query = query.trim();
+    if query == name || query == qualified_name {
+        return 1.0;
+    }
+    let query_words = name_words(query);
+    let target = if query.contains("::") { qualified_name } else { name };
+    let words = name_words(target);
+    if query_words.is_empty() || words.is_empty() {
+        return 0.0;
+    }
+
+    let mut total = 0.0;
+    let mut matched = vec![false; words.len()];
+    let mut positions = Vec::new();
+    for q in &query_words {
+        let best = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i, word_score(q, w)))
+            .fold(None, |best: Option<(usize, f64)>, (i, score)| match best {
+                Some((_, s)) if s >= score => best,
+                _ if score > 0.0 => Some((i, score)),
+                _ => best,
+            });
+        if let Some((i, score)) = best {
+            total += score;
+            matched[i] = true;
+            positions.push(i);
+        }
+    }
+    if positions.is_empty() {
+        return 0.0;
+    }
+    let query_score = total / query_words.len() as f64;
+    let coverage = matched.iter().filter(|m| **m).count() as f64 / words.len() as f64;
+    let in_order = if positions.windows(2).all(|p| p[0] < p[1]) { 1.0 } else { 0.0 };
+    (0.75 * query_score + 0.2 * coverage + 0.05 * in_order).min(0.99)
+}
+
+/// Rank the functions in `files` (`(path, content)` pairs) by how well their
+/// names match `query`, best first
+pub fn fuzzy_find_core(files: &[(String, String)], query: &str, limit: usize) -> Result<Vec<(FunctionSpecs, f64)>, String> {
+    let mut scored = Vec::new();
+    for (path, content) in files {
+        let mut functions = parse_verus_file_core(content).map_err(|e| {
+            if path.is_empty() {
+                e
+            } else {
+                format!("{}: {}", path, e)
+            }
+        })?;
+        set_file_path(&mut functions, path);
+        for f in functions {
+            let score = fuzzy_score(query, &f.name, &f.qualified_name());
+            if score > 0.0 {
+                scored.push((f, score));
+            }
+        }
+    }
+    scored.sort_by(|(a, x), (b, y)| y.total_cmp(x).then_with(|| a.name.cmp(&b.name)));
+    scored.truncate(limit);
+    Ok(scored)
+}
+
+/// Smallest token similarity (see `token_similarity`) at which a removed and
+/// an added clause are reported as one modified clause
+const MODIFIED_CLAUSE_THRESHOLD: f64 = 0.5;
+
+/// The tokens of a clause, delimiters as tokens of their own and
+/// multi-character operators (`<=`, `==>`, `::`) as one token
+fn clause_tokens(clause: &str) -> Vec<String> {
+    fn flatten(tokens: TokenStream, out: &mut Vec<String>) {
+        let mut op = String::new();
+        for tt in tokens {
+            if !matches!(tt, TokenTree::Punct(_)) && !op.is_empty() {
+                out.push(std::mem::take(&mut op));
+            }
+            match tt {
+                TokenTree::Punct(p) => {
+                    op.push(p.as_char());
+                    if p.spacing() == Spacing::Alone {
+                        out.push(std::mem::take(&mut op));
+                    }
+                }
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::None => ("", ""),
+                    };
+                    out.extend((!open.is_empty()).then(|| open.to_string()));
+                    flatten(group.stream(), out);
+                    out.extend((!close.is_empty()).then(|| close.to_string()));
+                }
+                other => out.push(other.to_string()),
+            }
+        }
+        if !op.is_empty() {
+            out.push(op);
+        }
+    }
+    let mut out = Vec::new();
+    match clause.parse::<TokenStream>() {
+        Ok(tokens) => flatten(tokens, &mut out),
+        Err(_) => out.extend(clause.split_whitespace().map(str::to_string)),
+    }
+    out
+}
+
+/// Dice coefficient of two token multisets: 1 for the same tokens, 0 for none in common
+fn token_similarity(a: &[String], b: &[String]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut counts: HashMap<&str, isize> = HashMap::new();
+    for token in a {
+        *counts.entry(token).or_default() += 1;
+    }
+    let mut common = 0;
+    for token in b {
+        if let Some(count) = counts.get_mut(token.as_str()).filter(|count| **count > 0) {
+            *count -= 1;
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+/// Token runs turning `old` into `new`, from a longest common subsequence
+fn token_diff(old: &[String], new: &[String]) -> Vec<TokenEdit> {
+    // lcs[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let mut edits: Vec<TokenEdit> = Vec::new();
+    let mut push = |op: &str, token: &str| match edits.last_mut() {
+        Some(last) if last.op == op => {
+            last.text.push(' ');
+            last.text.push_str(token);
+        }
+        _ => edits.push(TokenEdit { op: op.to_string(), text: token.to_string() }),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push("equal", &old[i]);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push("removed", &old[i]);
+            i += 1;
+        } else {
+            push("added", &new[j]);
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// Render token runs as text with `[-removed-]` and `{+added+}` markers
+fn render_token_diff(edits: &[TokenEdit]) -> String {
+    let pieces: Vec<String> = edits
+        .iter()
+        .map(|edit| match edit.op.as_str() {
+            "removed" => format!("[-{}-]", edit.text),
+            "added" => format!("{{+{}+}}", edit.text),
+            _ => edit.text.clone(),
+        })
+        .collect();
+    pieces.join(" ")
+}
+
+/// The clause changes of one kind: clauses present in both versions are
+/// unchanged; the remaining pairs are matched greedily, most similar first,
+/// as modified while their similarity reaches `MODIFIED_CLAUSE_THRESHOLD`;
+/// what is left is removed or added
+fn diff_clauses(kind: &str, old: &[String], new: &[String]) -> Vec<ClauseChange> {
+    let mut old_left: Vec<&String> = old.iter().collect();
+    let mut new_left: Vec<&String> = Vec::new();
+    for clause in new {
+        match old_left.iter().position(|o| *o == clause) {
+            Some(i) => {
+                old_left.remove(i);
+            }
+            None => new_left.push(clause),
+        }
+    }
+    let old_tokens: Vec<Vec<String>> = old_left.iter().map(|c| clause_tokens(c)).collect();
+    let new_tokens: Vec<Vec<String>> = new_left.iter().map(|c| clause_tokens(c)).collect();
+    let mut pairs = Vec::new();
+    for (i, a) in old_tokens.iter().enumerate() {
+        for (j, b) in new_tokens.iter().enumerate() {
+            let similarity = token_similarity(a, b);
+            if similarity >= MODIFIED_CLAUSE_THRESHOLD {
+                pairs.push((similarity, i, j));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let change = |change: &str, old: Option<&String>, new: Option<&String>| ClauseChange {
+        clause_kind: kind.to_string(),
+        change: change.to_string(),
+        old: old.cloned(),
+        new: new.cloned(),
+        ..Default::default()
+    };
+    let (mut old_paired, mut new_paired) = (vec![false; old_left.len()], vec![false; new_left.len()]);
+    let mut changes = Vec::new();
+    for (_, i, j) in pairs {
+        if old_paired[i] || new_paired[j] {
+            continue;
+        }
+        (old_paired[i], new_paired[j]) = (true, true);
+        let tokens = token_diff(&old_tokens[i], &new_tokens[j]);
+        let snippet = render_token_diff(&tokens);
+        changes.push(ClauseChange { tokens, snippet, ..change("modified", Some(old_left[i]), Some(new_left[j])) });
+    }
+    let unpaired = |clauses: Vec<&String>, paired: Vec<bool>| {
+        clauses.into_iter().zip(paired).filter(|(_, p)| !p).map(|(c, _)| c.clone()).collect::<Vec<_>>()
+    };
+    changes.extend(unpaired(old_left, old_paired).iter().map(|c| change("removed", Some(c), None)));
+    changes.extend(unpaired(new_left, new_paired).iter().map(|c| change("added", None, Some(c))));
+    changes
+}
+
+/// All clauses of an added (or removed) function as added (or removed) clauses
+fn whole_function_changes(f: &FunctionSpecs, added: bool) -> Vec<ClauseChange> {
+    let clauses = [("requires", &f.requires), ("ensures", &f.ensures), ("decreases", &f.decreases)];
+    clauses
+        .into_iter()
+        .flat_map(|(kind, clauses)| match added {
+            true => diff_clauses(kind, &[], clauses),
+            false => diff_clauses(kind, clauses, &[]),
+        })
+        .collect()
+}
+
+/// Key matching a function across two versions of a file: its name under the
+/// enclosing modules, impls and traits (macro invocations left out)
+fn spec_diff_key(f: &FunctionSpecs) -> String {
+    let scope: Vec<&str> = f.path.iter().filter(|crumb| !crumb.ends_with('!')).map(String::as_str).collect();
+    scope.join(" > ") + " > " + &f.name
+}
+
+/// Compare the specs of the functions of two versions of a file, matched by
+/// qualified name and enclosing impl or trait
+pub fn spec_diff_core(old_content: &str, new_content: &str) -> Result<Vec<SpecDiff>, String> {
+    let old = parse_verus_file_core(old_content).map_err(|e| format!("Old version: {}", e))?;
+    let new = parse_verus_file_core(new_content).map_err(|e| format!("New version: {}", e))?;
+    Ok(diff_function_specs(&old, &new))
+}
+
+/// The spec diffs of `spec_diff_core` for already parsed versions
+fn diff_function_specs(old: &[FunctionSpecs], new: &[FunctionSpecs]) -> Vec<SpecDiff> {
+    let key = spec_diff_key;
+    let mut old_by_key: BTreeMap<String, &FunctionSpecs> = old.iter().map(|f| (key(f), f)).collect();
+    let mut diffs = Vec::new();
+    for f in new {
+        let Some(before) = old_by_key.remove(&key(f)) else {
+            diffs.push(SpecDiff {
+                function: f.qualified_name(),
+                status: "added".to_string(),
+                changes: whole_function_changes(f, true),
+            });
+            continue;
+        };
+        let changes: Vec<ClauseChange> = [
+            diff_clauses("requires", &before.requires, &f.requires),
+            diff_clauses("ensures", &before.ensures, &f.ensures),
+            diff_clauses("decreases", &before.decreases, &f.decreases),
+        ]
+        .concat();
+        if !changes.is_empty() {
+            diffs.push(SpecDiff { function: f.qualified_name(), status: "modified".to_string(), changes });
+        }
+    }
+    for f in old.iter().filter(|f| old_by_key.contains_key(&key(f))) {
+        diffs.push(SpecDiff {
+            function: f.qualified_name(),
+            status: "removed".to_string(),
+            changes: whole_function_changes(f, false),
+        });
+    }
+    diffs
+}
+
+/// The spec fingerprint of a function with its name left out of the
+/// signature, equal for a function renamed without touching its spec
+fn rename_fingerprint(f: &FunctionSpecs) -> String {
+    let signature = f.signature.replacen(&format!("fn {}", f.name), "fn _", 1);
+    compute_spec_fingerprint(&signature, &f.requires, &f.ensures, &f.decreases)
+}
+
+/// The spec changes of one file between two trees; a function whose key
+/// (`spec_diff_key`) is on one side only is a rename if the other side has an
+/// unmatched function with the same `rename_fingerprint` (the first in
+/// source order)
+fn file_spec_diff(path: &str, status: &str, old: &[FunctionSpecs], new: &[FunctionSpecs]) -> FileSpecDiff {
+    let old_keys: BTreeSet<String> = old.iter().map(spec_diff_key).collect();
+    let new_keys: BTreeSet<String> = new.iter().map(spec_diff_key).collect();
+    let mut removed: Vec<&FunctionSpecs> = old.iter().filter(|f| !new_keys.contains(&spec_diff_key(f))).collect();
+    let (mut added, mut renamed) = (Vec::new(), Vec::new());
+    for f in new.iter().filter(|f| !old_keys.contains(&spec_diff_key(f))) {
+        let fingerprint = rename_fingerprint(f);
+        match removed.iter().position(|before| rename_fingerprint(before) == fingerprint) {
+            Some(i) => renamed.push((removed.remove(i).qualified_name(), f.qualified_name())),
+            None => added.push(f.qualified_name()),
+        }
+    }
+    FileSpecDiff {
+        path: path.to_string(),
+        status: status.to_string(),
+        added,
+        removed: removed.iter().map(|f| f.qualified_name()).collect(),
+        renamed,
+        modified: diff_function_specs(old, new).into_iter().filter(|d| d.status == "modified").collect(),
+    }
+}
+
+/// The `.rs` files under `root` (as listed by `verus_files_core` with the
+/// default excludes), keyed by path relative to `root`
+pub fn read_verus_tree(root: &Path) -> std::io::Result<BTreeMap<String, String>> {
+    verus_files_core(root, &[], true)?
+        .into_iter()
+        .map(|path| {
+            let rel = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+            std::fs::read_to_string(&path).map(|content| (rel, content))
+        })
+        .collect()
+}
+
+/// Compare two trees of files (relative path to content): files are matched
+/// by path, and the functions of each file as in `file_spec_diff`
+pub fn diff_directories_core(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Result<DirectoryDiff, String> {
+    let parse = |files: &BTreeMap<String, String>, path: &str, side: &str| match files.get(path) {
+        Some(content) => parse_verus_file_core(content).map_err(|e| format!("{} ({} version): {}", path, side, e)),
+        None => Ok(Vec::new()),
+    };
+    let paths: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut diff = DirectoryDiff::default();
+    for path in paths {
+        let (before, after) = (parse(old, path, "old")?, parse(new, path, "new")?);
+        let status = match (old.contains_key(path), new.contains_key(path)) {
+            (false, _) => "added",
+            (_, false) => "removed",
+            _ => "modified",
+        };
+        let file = file_spec_diff(path, status, &before, &after);
+        let summary = &mut diff.summary;
+        match status {
+            "added" => {
+                summary.files_added += 1;
+                diff.added_files.push(path.clone());
+            }
+            "removed" => {
+                summary.files_removed += 1;
+                diff.removed_files.push(path.clone());
+            }
+            _ if [file.added.len(), file.removed.len(), file.renamed.len(), file.modified.len()] == [0; 4] => continue,
+            _ => summary.files_modified += 1,
+        }
+        summary.functions_added += file.added.len();
+        summary.functions_removed += file.removed.len();
+        summary.functions_renamed += file.renamed.len();
+        summary.functions_modified += file.modified.len();
+        diff.files.push(file);
+    }
+    Ok(diff)
+}
+
+/// Magic bytes at the start of a saved specs file
+const SPECS_MAGIC: &[u8; 8] = b"VLFSPECS";
+
+/// Version of the saved specs format; bump whenever `FunctionSpecs` (or a type
+/// it contains) changes, so files written by older builds are rejected
+const SPECS_FORMAT_VERSION: u32 = 34;
+
+/// Encode specs as the magic bytes, the format version (little-endian u32) and
+/// the bincode-serialized list
+pub fn encode_specs(specs: &[FunctionSpecs]) -> Result<Vec<u8>, String> {
+    let mut bytes = SPECS_MAGIC.to_vec();
+    bytes.extend_from_slice(&SPECS_FORMAT_VERSION.to_le_bytes());
+    let body = bincode::serialize(specs).map_err(|e| format!("Encode error: {}", e))?;
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// Decode specs written by `encode_specs`, checking the header first
+pub fn decode_specs(bytes: &[u8]) -> Result<Vec<FunctionSpecs>, String> {
+    let header_len = SPECS_MAGIC.len() + 4;
+    if bytes.len() < header_len || &bytes[..SPECS_MAGIC.len()] != SPECS_MAGIC {
+        return Err("Not a verus_parser specs file (bad header)".to_string());
+    }
+    let version = u32::from_le_bytes(bytes[SPECS_MAGIC.len()..header_len].try_into().unwrap());
+    if version != SPECS_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported specs format version {} (expected {}); re-extract the specs with this version",
+            version, SPECS_FORMAT_VERSION
+        ));
+    }
+    bincode::deserialize(&bytes[header_len..]).map_err(|e| format!("Decode error: {}", e))
+}
+
+/// A minimal program per syntax feature; the feature is supported if it parses
+const GRAMMAR_PROBES: &[(&str, &str)] = &[
+    ("spec_fn", "spec fn f() -> int { 0 }"),
+    ("spec_checked", "spec(checked) fn f() -> int { 0 }"),
+    ("named_return", "fn f() -> (r: u8) ensures r == 0 { 0 }"),
+    ("returns_clause", "spec fn f() -> u8 returns 0u8 { 0 }"),
+    ("tracked_params", "proof fn f(tracked x: u8) {}"),
+    ("quantifiers", "spec fn f() -> bool { forall|i: int| i == i }"),
+    ("assert_by", "proof fn f() { assert(true) by {} }"),
+    ("decreases", "spec fn f(n: nat) -> nat decreases n { 0 }"),
+    ("opens_invariants", "fn f() opens_invariants any {}"),
+    ("broadcast_proof", "broadcast proof fn f() ensures true {}"),
+    ("broadcast_group", "broadcast group g { f }"),
+    ("broadcast_use", "broadcast use f;"),
+    ("assume_specification", "pub assume_specification[ f ](x: u8) -> u8;"),
+    ("global_size_of", "global size_of usize == 8;"),
+];
+
+/// Run every grammar probe
+fn probe_grammar() -> BTreeMap<String, bool> {
+    GRAMMAR_PROBES
+        .iter()
+        .map(|(name, program)| (name.to_string(), verus_syn::parse_file(program).is_ok()))
+        .collect()
+}
+
+/// Grammar probe results, computed once (at module init)
+pub(crate) fn grammar_features() -> &'static BTreeMap<String, bool> {
+    static FEATURES: std::sync::OnceLock<BTreeMap<String, bool>> = std::sync::OnceLock::new();
+    FEATURES.get_or_init(probe_grammar)
+}
+
+pub fn capabilities_core() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        verus_syn_version: env!("VERUS_SYN_VERSION").to_string(),
+        specs_format_version: SPECS_FORMAT_VERSION,
+        debug_build: cfg!(debug_assertions),
+        features: grammar_features().clone(),
+    }
+}
+
+/// The subset of the SCIP schema (scip.proto) written by `to_scip`, with the
+/// upstream field numbers
+mod scip {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Index {
+        #[prost(message, optional, tag = "1")]
+        pub metadata: Option<Metadata>,
+        #[prost(message, repeated, tag = "2")]
+        pub documents: Vec<Document>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Metadata {
+        /// ProtocolVersion; 0 is the only version
+        #[prost(int32, tag = "1")]
+        pub version: i32,
+        #[prost(message, optional, tag = "2")]
+        pub tool_info: Option<ToolInfo>,
+        #[prost(string, tag = "3")]
+        pub project_root: String,
+        /// TextEncoding; 1 is UTF-8
+        #[prost(int32, tag = "4")]
+        pub text_document_encoding: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ToolInfo {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Document {
+        #[prost(string, tag = "1")]
+        pub relative_path: String,
+        #[prost(message, repeated, tag = "2")]
+        pub occurrences: Vec<Occurrence>,
+        #[prost(message, repeated, tag = "3")]
+        pub symbols: Vec<SymbolInformation>,
+        #[prost(string, tag = "4")]
+        pub language: String,
+        #[prost(string, tag = "5")]
+        pub text: String,
+        /// PositionEncoding; 1 is UTF-8 byte offsets from the line start
+        #[prost(int32, tag = "6")]
+        pub position_encoding: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Occurrence {
+        /// `[start_line, start_character, end_line, end_character]`, 0-based
+        #[prost(int32, repeated, tag = "1")]
+        pub range: Vec<i32>,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        /// Bitset of SymbolRole; 1 is Definition
+        #[prost(int32, tag = "3")]
+        pub symbol_roles: i32,
+        /// Range of the whole definition, in the same format as `range`
+        #[prost(int32, repeated, tag = "7")]
+        pub enclosing_range: Vec<i32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SymbolInformation {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(string, repeated, tag = "3")]
+        pub documentation: Vec<String>,
+        #[prost(string, tag = "6")]
+        pub display_name: String,
+        #[prost(message, optional, tag = "7")]
+        pub signature_documentation: Option<Document>,
+    }
+}
+
+/// A name as a SCIP descriptor name, backquoted unless it is a simple identifier
+fn scip_escape(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_+-$".contains(c)) {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// The type name of an impl's self type for a SCIP descriptor: `&'a mut
+/// ristretto::Point<T>` becomes `Point`
+fn scip_type_name(self_ty: &str) -> String {
+    let base = self_ty.split('<').next().unwrap_or(self_ty);
+    let base = base.rsplit("::").next().unwrap_or(base);
+    base.split_whitespace().last().unwrap_or(base).trim_start_matches('&').to_string()
+}
+
+/// SCIP symbol for an extracted function, e.g.
+/// `verus-lemma-finder cargo my_crate . arith/mul/Scalar#lemma_mul().`:
+/// one namespace descriptor per module, a type descriptor for an inherent
+/// impl's self type or a trait, `impl#[Type][Trait]` for a trait impl (so the
+/// same method of two impls gets two symbols), and a method descriptor per
+/// function (enclosing ones first)
+fn scip_symbol(package: &str, f: &FunctionSpecs) -> String {
+    let mut descriptors = String::new();
+    // The first breadcrumb is always "crate"
+    for crumb in f.path.iter().skip(1) {
+        if let Some(module) = crumb.strip_prefix("mod ") {
+            descriptors.push_str(&format!("{}/", scip_escape(module)));
+        } else if let Some(trait_name) = crumb.strip_prefix("trait ") {
+            descriptors.push_str(&format!("{}#", scip_escape(trait_name)));
+        } else if let Some(imp) = crumb.strip_prefix("impl ") {
+            match imp.rsplit_once(" for ") {
+                Some((trait_name, self_ty)) => descriptors.push_str(&format!(
+                    "impl#[{}][{}]",
+                    scip_escape(&scip_type_name(self_ty)),
+                    scip_escape(trait_name)
+                )),
+                None => descriptors.push_str(&format!("{}#", scip_escape(&scip_type_name(imp)))),
+            }
+        } else if let Some(function) = crumb.strip_prefix("fn ") {
+            descriptors.push_str(&format!("{}().", scip_escape(function)));
+        }
+    }
+    if f.path.is_empty() {
+        descriptors.push_str(&format!("{}().", scip_escape(&f.name)));
+    }
+    format!("verus-lemma-finder cargo {} . {}", scip_escape(package), descriptors)
+}
+
+/// The signature followed by the spec clauses, as shown for a SCIP symbol
+fn scip_signature_text(f: &FunctionSpecs) -> String {
+    let mut text = f.signature.clone();
+    let sections = [
+        ("requires", &f.requires),
+        ("recommends", &f.recommends),
+        ("ensures", &f.ensures),
+        ("decreases", &f.decreases),
+    ];
+    for (keyword, clauses) in sections {
+        if !clauses.is_empty() {
+            text.push_str(&format!("\n    {}\n", keyword));
+            let lines: Vec<String> = clauses.iter().map(|c| format!("        {},", c)).collect();
+            text.push_str(&lines.join("\n"));
+        }
+    }
+    text
+}
+
+/// 0-based `[line, start, end]` of the name after `fn` in a function's
+/// header, with UTF-8 byte columns; `None` if it is not in the source (e.g. a
+/// function generated by a macro)
+fn scip_name_range(lines: &[&str], f: &FunctionSpecs) -> Option<Vec<i32>> {
+    let first = f.line_number?;
+    let last = f.sig_end_line.unwrap_or(first);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    for line_no in first..=last {
+        let line = lines.get(line_no - 1)?;
+        for (start, _) in line.match_indices(f.name.as_str()) {
+            let end = start + f.name.len();
+            let before = line[..start].trim_end();
+            if before.len() < line[..start].len()
+                && before.strip_suffix("fn").is_some_and(|rest| !rest.ends_with(is_ident))
+                && !line[end..].starts_with(is_ident)
+            {
+                return Some(vec![line_no as i32 - 1, start as i32, end as i32]);
+            }
+        }
+    }
+    None
+}
+
+/// Build a SCIP index with a definition occurrence and symbol per function,
+/// encoded as protobuf, and the number of symbols in it
+///
+/// `files` holds `(path, content)` pairs; paths are used as the documents'
+/// relative paths. `package` is the package name in the symbols ("." if unknown).
+/// An occurrence's range is the function's name, its enclosing range the
+/// function's lines.
+pub fn scip_index_core(files: &[(String, String)], package: &str) -> Result<(Vec<u8>, usize), String> {
+    let mut documents = Vec::new();
+    let mut symbols = 0;
+    for (path, content) in files {
+        let functions = parse_verus_file_core(content).map_err(|e| format!("{}: {}", path, e))?;
+        let lines: Vec<&str> = content.lines().collect();
+        let mut document = scip::Document {
+            relative_path: path.clone(),
+            language: "rust".to_string(),
+            position_encoding: 1,
+            ..Default::default()
+        };
+        for f in &functions {
+            let symbol = scip_symbol(package, f);
+            if let (Some(range), Some(start), Some(end)) = (scip_name_range(&lines, f), f.line_number, f.end_line) {
+                let end_column = lines.get(end - 1).map_or(0, |line| line.len());
+                document.occurrences.push(scip::Occurrence {
+                    range,
+                    symbol: symbol.clone(),
+                    symbol_roles: 1,
+                    enclosing_range: vec![start as i32 - 1, 0, end as i32 - 1, end_column as i32],
+                });
+            }
+            document.symbols.push(scip::SymbolInformation {
+                symbol,
+                documentation: Vec::new(),
+                display_name: f.name.clone(),
+                signature_documentation: Some(scip::Document {
+                    language: "verus".to_string(),
+                    text: scip_signature_text(f),
+                    ..Default::default()
+                }),
+            });
+        }
+        symbols += document.symbols.len();
+        documents.push(document);
+    }
+    let project_root = std::env::current_dir().map(|dir| format!("file://{}", dir.display())).unwrap_or_default();
+    let index = scip::Index {
+        metadata: Some(scip::Metadata {
+            version: 0,
+            tool_info: Some(scip::ToolInfo {
+                name: "verus-lemma-finder".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            }),
+            project_root,
+            text_document_encoding: 1,
+        }),
+        documents,
+    };
+    Ok((prost::Message::encode_to_vec(&index), symbols))
+}
+
+/// Collect the calls to `lemma_name` in `files` (`(path, content)` pairs) with
+/// `context_lines` lines of source on either side
+///
+/// A call matches if its path, after `use` alias expansion, ends in
+/// `lemma_name` (which may itself be a `::`-separated suffix).
+pub fn usage_examples_core(
+    files: &[(String, String)],
+    lemma_name: &str,
+    context_lines: usize,
+) -> Result<Vec<UsageExample>, String> {
+    let suffix = format!("::{}", lemma_name);
+    let mut examples = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = CallFinder::default();
+        finder.visit_file(&file);
+        let line_count = content.lines().count();
+        for call in finder.calls.iter().filter(|call| !call.is_unresolved_method()) {
+            let full = finder.expand(&call.callee);
+            if full != lemma_name && !full.ends_with(&suffix) {
+                continue;
+            }
+            let snippet_start = call.line.saturating_sub(context_lines).max(1);
+            let snippet_end = (call.line + context_lines).min(line_count);
+            examples.push(UsageExample {
+                caller: call.caller.clone(),
+                file_path: path.clone(),
+                line_number: call.line,
+                args: call.args.iter().map(|range| parsed_source(content)[range.clone()].to_string()).collect(),
+                snippet: source_lines(content, snippet_start, snippet_end),
+                snippet_start,
+                snippet_end,
+            });
+        }
+    }
+    Ok(examples)
+}
+
+/// Gather the specs relevant to a verification failure at `line` of
+/// `function_name`, across `files` (`(path, content)` pairs)
+///
+/// If several functions share the name, the one whose lines contain `line`
+/// wins, else the first. The clause is the requires or ensures clause whose
+/// lines contain `line`, else an assert starting on it. Its referenced names
+/// (minus the function's parameters) that are spec functions of the project
+/// are returned with their source, and the proof functions whose ensures
+/// refer to any of them as related lemmas.
+pub fn context_for_error_core(files: &[(String, String)], function_name: &str, line: usize) -> Result<ErrorContext, String> {
+    let mut functions = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(&file);
+        set_file_path(&mut finder.functions, path);
+        for (specs, sig) in finder.functions.into_iter().zip(finder.signatures) {
+            functions.push((specs, sig, content));
+        }
+    }
+
+    let contains = |specs: &FunctionSpecs| {
+        matches!((specs.line_number, specs.end_line), (Some(start), Some(end)) if (start..=end).contains(&line))
+    };
+    let named: Vec<&(FunctionSpecs, Signature, &String)> =
+        functions.iter().filter(|(specs, _, _)| specs.name == function_name).collect();
+    let (specs, sig, _) = named
+        .iter()
+        .find(|(specs, _, _)| contains(specs))
+        .or_else(|| named.first())
+        .ok_or_else(|| format!("Function '{}' not found", function_name))?;
+
+    let mut clause = None;
+    for kind in ["requires", "ensures"] {
+        for expr in clause_exprs(sig, kind)? {
+            let span = expr.span();
+            if clause.is_none() && (span.start().line..=span.end().line).contains(&line) {
+                clause = Some((kind, expr.clone()));
+            }
+        }
+    }
+    if clause.is_none() {
+        if let Some(assert) = specs.asserts.iter().find(|a| a.line_number == Some(line)) {
+            let expr = verus_syn::parse_str::<Expr>(&assert.expr).map_err(|e| format!("Parse error: {}", e))?;
+            clause = Some(("assert", expr));
+        }
+    }
+
+    let mut context = ErrorContext { function: specs.clone(), ..Default::default() };
+    let Some((kind, expr)) = clause else {
+        return Ok(context);
+    };
+    context.clause_kind = Some(kind.to_string());
+    context.clause = Some(quote::quote!(#expr).to_string());
+
+    let params = param_names(sig);
+    let mut spec_names = Vec::new();
+    for name in referenced_names(&expr) {
+        if params.contains(&name) {
+            continue;
+        }
+        let Some((spec, _, content)) =
+            functions.iter().find(|(f, _, _)| f.name == name && f.mode.starts_with("spec"))
+        else {
+            continue;
+        };
+        let source = match (spec.line_number, spec.end_line) {
+            (Some(start), Some(end)) => source_lines(content, start, end),
+            _ => String::new(),
+        };
+        context.spec_functions.push(SpecDefinition { specs: spec.clone(), source });
+        spec_names.push(name);
+    }
+
+    for (lemma, lemma_sig, _) in &functions {
+        if !lemma.is_proof || (lemma.name == specs.name && lemma.line_number == specs.line_number) {
+            continue;
+        }
+        let mentions = clause_exprs(lemma_sig, "ensures")?
+            .into_iter()
+            .any(|expr| referenced_names(expr).iter().any(|name| spec_names.contains(name)));
+        if mentions {
+            context.related_lemmas.push(lemma.clone());
+        }
+    }
+    Ok(context)
+}
+
+/// The caller and callee specs of the call in `file` nearest to `line`
+/// (the first of those equally near), across `files` (`(path, content)` pairs)
+///
+/// Only path calls count, constructors like `Some` excepted. A callee that
+/// `is_local_path` accepts resolves to the function of that name whose
+/// qualified name ends like the path (`crate::`, `self::` and `Self::`
+/// prefixes dropped), else the one in the same file, else the first.
+pub fn call_context_core(files: &[(String, String)], file: &str, line: usize) -> Result<CallContext, String> {
+    let mut functions = Vec::new();
+    let mut finders = Vec::new();
+    for (path, content) in files {
+        let parsed = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        let mut finder = FunctionFinder::new(None);
+        finder.keep_signatures = false;
+        finder.visit_file(&parsed);
+        set_file_path(&mut finder.functions, path);
+        functions.extend(finder.functions);
+        let mut calls = CallFinder::default();
+        calls.visit_file(&parsed);
+        finders.push(calls);
+    }
+    let index = files
+        .iter()
+        .position(|(path, _)| path == file)
+        .ok_or_else(|| format!("File '{}' is not among the paths", file))?;
+    let calls = &finders[index];
+    let call = calls
+        .calls
+        .iter()
+        .filter(|call| !BUILTIN_CALLS.contains(&call.callee.as_str()) && !call.is_unresolved_method())
+        .min_by_key(|call| call.line.abs_diff(line))
+        .ok_or_else(|| format!("No call found in '{}'", file))?;
+
+    let in_file = |f: &FunctionSpecs| &*f.file_path == file;
+    let contains_call = |f: &FunctionSpecs| {
+        matches!((f.line_number, f.end_line), (Some(start), Some(end)) if (start..=end).contains(&call.line))
+    };
+    let caller = functions
+        .iter()
+        .find(|f| in_file(f) && f.name == call.caller && contains_call(f))
+        .cloned()
+        .unwrap_or_default();
+
+    let full = calls.expand(&call.callee);
+    let mut callee = None;
+    if is_local_path(&full, &finders) {
+        let name = full.rsplit("::").next().unwrap_or_default();
+        let suffix = full.trim_start_matches("crate::").trim_start_matches("self::").trim_start_matches("Self::");
+        let named: Vec<&FunctionSpecs> = functions.iter().filter(|f| f.name == name).collect();
+        callee = named
+            .iter()
+            .find(|f| f.qualified_name().ends_with(suffix))
+            .or_else(|| named.iter().find(|f| in_file(f)))
+            .or_else(|| named.first())
+            .map(|f| (*f).clone());
+    }
+    let source = parsed_source(&files[index].1);
+    Ok(CallContext {
+        caller,
+        callee_path: call.callee.clone(),
+        resolved: callee.is_some(),
+        callee: callee.unwrap_or_default(),
+        args: call.args.iter().map(|range| source[range.clone()].to_string()).collect(),
+        line_number: call.line,
+    })
+}
+
+/// The spec functions the ensures of `lemma` unfold to across `files`
+/// (`(path, content)` pairs)
+///
+/// Names in the ensures (other than the lemma's parameters and return
+/// binding) that are spec functions of the project are at depth 1; names in
+/// the body of an open spec function at depth d are at depth d + 1, each
+/// spec function counted once at its smallest depth. Unfolding stops at
+/// closed, opaque and uninterpreted spec functions, which are still listed,
+/// and at names the files do not define as spec functions (external paths,
+/// constants, parameters). Names resolve to the first spec function of that
+/// name, as calls are matched elsewhere.
+pub fn ensures_unfolding_core(files: &[(String, String)], lemma: &str) -> Result<EnsuresUnfolding, String> {
+    let mut found = None;
+    let mut spec_fns: Vec<(&str, SpecBody)> = Vec::new();
+    for (path, content) in files {
+        let file = verus_syn::parse_file(content).map_err(|e| format!("{}: Parse error: {}", path, e))?;
+        if found.is_none() {
+            let mut finder = FunctionFinder::new(Some(lemma.to_string()));
+            finder.visit_file(&file);
+            set_file_path(&mut finder.functions, path);
+            found = finder.functions.into_iter().zip(finder.signatures).next();
+        }
+        let mut bodies = SpecBodyFinder::default();
+        bodies.visit_file(&file);
+        spec_fns.extend(bodies.spec_fns.into_iter().map(|spec_fn| (path.as_str(), spec_fn)));
+    }
+    let (specs, sig) = found.ok_or_else(|| format!("Function '{}' not found", lemma))?;
+
+    let locals = clause_locals(&sig);
+    let mut pending: std::collections::VecDeque<(String, usize)> = std::collections::VecDeque::new();
+    for expr in clause_exprs(&sig, "ensures")? {
+        for name in referenced_names(expr) {
+            if !locals.contains(&name) {
+                pending.push_back((name, 1));
+            }
+        }
+    }
+    let mut unfolding = EnsuresUnfolding { lemma: specs, ..Default::default() };
+    let mut seen: Vec<String> = Vec::new();
+    while let Some((name, depth)) = pending.pop_front() {
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name.clone());
+        let Some((path, (_, opacity, line, body_names))) = spec_fns.iter().find(|(_, spec_fn)| spec_fn.0 == name) else {
+            continue;
+        };
+        if *opacity == "open" {
+            pending.extend(body_names.iter().map(|body_name| (body_name.clone(), depth + 1)));
+        }
+        unfolding.max_depth = unfolding.max_depth.max(depth);
+        unfolding.closure.push(UnfoldedSpec {
+            name,
+            depth,
+            opacity: opacity.to_string(),
+            file_path: path.to_string(),
+            line_number: *line,
+        });
+    }
+    Ok(unfolding)
+}
+
+/// One `Explanation` per definition of `function_name` in `files` (`(path,
+/// content)` pairs), in file and source order; none if it is not defined
+///
+/// Callers are the calls ending in the name from other functions (as
+/// `usage_examples` finds them, without context lines). Callees are matched
+/// on the last path segment against the functions of the files, and spec
+/// functions resolve to the first spec function of that name, as in
+/// `context_for_error`. Files that do not parse are left out, like any other
+/// missing piece.
+pub fn explain_core(files: &[(String, String)], function_name: &str) -> Result<Vec<Explanation>, String> {
+    let parsed: Vec<(verus_syn::File, &(String, String))> =
+        files.iter().filter_map(|entry| Some((verus_syn::parse_file(&entry.1).ok()?, entry))).collect();
+    let files: Vec<(String, String)> = parsed.iter().map(|(_, entry)| (*entry).clone()).collect();
+    let mut functions = Vec::new();
+    let mut per_file = Vec::new();
+    for (index, (file, (path, _))) in parsed.iter().enumerate() {
+        let mut finder = FunctionFinder::new(None);
+        finder.visit_file(file);
+        set_file_path(&mut finder.functions, path);
+        functions.extend(finder.functions.into_iter().zip(finder.signatures).map(|(specs, sig)| (specs, sig, index)));
+        let mut calls = CallFinder::default();
+        calls.visit_file(file);
+        let mut scopes = ScopeFinder::default();
+        scopes.visit_file(file);
+        let mut docs = DocExampleFinder::default();
+        docs.visit_file(file);
+        per_file.push((calls, scopes.scopes, docs.docs));
+    }
+
+    let callers: Vec<UsageExample> = usage_examples_core(&files, function_name, 0)?
+        .into_iter()
+        .filter(|example| example.caller != function_name)
+        .collect();
+    let mut explanations = Vec::new();
+    for (specs, sig, index) in functions.iter().filter(|(specs, _, _)| specs.name == function_name) {
+        let content = &files[*index].1;
+        let (calls, scopes, docs) = &per_file[*index];
+        let (start, end) = (specs.line_number.unwrap_or_default(), specs.end_line.unwrap_or_default());
+        let key = (specs.name.clone(), start);
+
+        let mut callees = Vec::new();
+        let in_function = |call: &&CallSite| call.caller == specs.name && (start..=end).contains(&call.line);
+        for call in calls.calls.iter().filter(in_function).filter(|call| !call.is_unresolved_method()) {
+            let full = calls.expand(&call.callee);
+            let callee = full.rsplit("::").next().unwrap_or_default().to_string();
+            if !BUILTIN_CALLS.contains(&callee.as_str())
+                && !callees.contains(&callee)
+                && functions.iter().any(|(f, _, _)| f.name == callee)
+            {
+                callees.push(callee);
+            }
+        }
+
+        let scope = scopes.get(&key);
+        let siblings = functions
+            .iter()
+            .filter(|(f, _, i)| i == index && f.line_number != specs.line_number)
+            .filter(|(f, _, _)| scopes.get(&(f.name.clone(), f.line_number.unwrap_or_default())) == scope)
+            .map(|(f, _, _)| f.name.clone())
+            .collect();
+
+        let params = param_names(sig);
+        let mut spec_functions: Vec<SpecDefinition> = Vec::new();
+        for kind in ["requires", "ensures", "decreases"] {
+            for expr in clause_exprs(sig, kind)? {
+                for name in referenced_names(expr) {
+                    if params.contains(&name) || spec_functions.iter().any(|d| d.specs.name == name) {
+                        continue;
+                    }
+                    let Some((spec, _, spec_index)) =
+                        functions.iter().find(|(f, _, _)| f.name == name && f.mode.starts_with("spec"))
+                    else {
+                        continue;
+                    };
+                    let source = match (spec.line_number, spec.end_line) {
+                        (Some(start), Some(end)) => source_lines(&files[*spec_index].1, start, end),
+                        _ => String::new(),
+                    };
+                    spec_functions.push(SpecDefinition { specs: spec.clone(), source });
+                }
+            }
+        }
+
+        explanations.push(Explanation {
+            function: specs.clone(),
+            source: source_lines(content, start, end),
+            doc: docs.get(&key).cloned().unwrap_or_default(),
+            callers: callers.clone(),
+            callees,
+            siblings,
+            spec_functions,
+        });
+    }
+    Ok(explanations)
+}
+
+/// A replacement of a byte range of the original source text
+#[derive(Debug, Clone)]
+struct TextEdit {
+    range: Range<usize>,
+    replacement: String,
+}
+
+/// Apply edits to `content`, leaving every byte outside the edited ranges untouched.
+/// Edits that overlap an earlier (outer) edit are dropped.
+fn apply_text_edits(content: &str, mut edits: Vec<TextEdit>) -> String {
+    edits.sort_by(|a, b| a.range.start.cmp(&b.range.start).then(b.range.end.cmp(&a.range.end)));
+    let mut out = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for edit in edits {
+        if edit.range.start < cursor {
+            continue;
+        }
+        out.push_str(&content[cursor..edit.range.start]);
+        out.push_str(&edit.replacement);
+        cursor = edit.range.end;
+    }
+    out.push_str(&content[cursor..]);
+    out
+}
+
+/// Grow `range` to cover its whole lines when nothing else is on them
+fn expand_to_whole_lines(content: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[range.end..].find('\n').map_or(content.len(), |i| range.end + i + 1);
+    if content[line_start..range.start].trim().is_empty() && content[range.end..line_end].trim().is_empty() {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+/// Keywords that start a loop specification between the loop header and its body
+const LOOP_SPEC_KEYWORDS: &[&str] = &["invariant", "invariant_except_break", "invariant_ensures", "ensures", "decreases"];
+
+/// AST visitor that computes the text edits turning Verus source into plain Rust
+struct VerusEraser<'a> {
+    content: &'a str,
+    edits: Vec<TextEdit>,
+    /// Project macros wrapping `verus!`, unwrapped like it
+    verus_macro_names: &'a [String],
+}
+
+impl<'a> VerusEraser<'a> {
+    fn new(content: &'a str, verus_macro_names: &'a [String]) -> Self {
+        Self {
+            content,
+            edits: Vec::new(),
+            verus_macro_names,
+        }
+    }
+
+    fn replace(&mut self, range: Range<usize>, replacement: &str) {
+        self.edits.push(TextEdit {
+            range,
+            replacement: replacement.to_string(),
+        });
+    }
+
+    /// Remove a range, together with its line if nothing else is on it
+    fn remove(&mut self, range: Range<usize>) {
+        let range = expand_to_whole_lines(self.content, range);
+        self.replace(range, "");
+    }
+
+    /// Remove a single token and the whitespace following it
+    fn remove_token(&mut self, span: proc_macro2::Span) {
+        let range = span.byte_range();
+        let rest = &self.content[range.end..];
+        let end = range.end + (rest.len() - rest.trim_start().len());
+        self.replace(range.start..end, "");
+    }
+
+    fn erase_verifier_attrs(&mut self, attrs: &[verus_syn::Attribute]) {
+        for attr in attrs {
+            if attr.path().segments.first().is_some_and(|seg| seg.ident == "verifier") {
+                self.remove(attr.span().byte_range());
+            }
+        }
+    }
+
+    /// Erase the Verus parts of a function, or the whole function if it is ghost code.
+    ///
+    /// `body_start` is the byte offset of the body's `{` (or the declaration's `;`).
+    fn erase_fn(&mut self, attrs: &[verus_syn::Attribute], sig: &Signature, item_span: proc_macro2::Span, body_start: usize) {
+        if matches!(sig.mode, FnMode::Proof(_) | FnMode::Spec(_) | FnMode::SpecChecked(_)) {
+            self.remove(item_span.byte_range());
+            return;
+        }
+        self.erase_verifier_attrs(attrs);
+
+        if let FnMode::Exec(_) = &sig.mode {
+            self.remove_token(sig.mode.span());
+        }
+        for arg in &sig.inputs {
+            if let Some(TokenTree::Ident(ident)) = quote::quote!(#arg).into_iter().next() {
+                if ident == "tracked" || ident == "ghost" {
+                    self.remove_token(ident.span());
+                }
+            }
+        }
+        self.erase_return_binding(&sig.output);
+
+        // Everything between the end of the signature proper and the body is spec
+        let mut sig_end = sig.paren_token.span.close().byte_range().end;
+        if !matches!(sig.output, verus_syn::ReturnType::Default) {
+            sig_end = sig_end.max(sig.output.span().byte_range().end);
+        }
+        if let Some(where_clause) = &sig.generics.where_clause {
+            sig_end = sig_end.max(where_clause.span().byte_range().end);
+        }
+        if body_start > sig_end && !self.content[sig_end..body_start].trim().is_empty() {
+            self.replace(sig_end..body_start, " ");
+        }
+    }
+
+    /// Rewrite `-> (name: Type)` to `-> Type` and drop `tracked` on the return
+    fn erase_return_binding(&mut self, output: &verus_syn::ReturnType) {
+        for tt in quote::quote!(#output) {
+            match tt {
+                TokenTree::Ident(ident) if ident == "tracked" => self.remove_token(ident.span()),
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis => {
+                    let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+                    let skip = usize::from(matches!(inner.first(), Some(TokenTree::Ident(i)) if i == "tracked"));
+                    let is_binding = matches!(inner.get(skip), Some(TokenTree::Ident(_)))
+                        && matches!(inner.get(skip + 1), Some(TokenTree::Punct(p)) if p.as_char() == ':' && p.spacing() == Spacing::Alone);
+                    if is_binding {
+                        let ty_start = inner[skip + 1].span().byte_range().end;
+                        let ty_end = group.span_close().byte_range().start;
+                        let ty = self.content[ty_start..ty_end].trim().to_string();
+                        self.replace(group.span().byte_range(), &ty);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Scan a function body for ghost code: `proof { ... }` blocks, `assert`/`assume`
+    /// statements, ghost/tracked `let`s, loop specifications and verifier attributes.
+    fn erase_ghost_code(&mut self, tokens: TokenStream) {
+        let tts: Vec<TokenTree> = tokens.into_iter().collect();
+        let mut in_loop_header = false;
+        let mut loop_spec_start: Option<usize> = None;
+        let mut i = 0;
+        while i < tts.len() {
+            match &tts[i] {
+                TokenTree::Ident(ident) if ident == "proof" => {
+                    if let Some(TokenTree::Group(body)) = tts.get(i + 1) {
+                        if body.delimiter() == Delimiter::Brace {
+                            let end = self.statement_end(&tts, i + 1);
+                            self.remove(ident.span().byte_range().start..end);
+                            i += 2;
+                            continue;
+                        }
+                    }
+                }
+                TokenTree::Ident(ident) if ident == "assert" || ident == "assume" => {
+                    if let Some(TokenTree::Group(args)) = tts.get(i + 1) {
+                        if args.delimiter() == Delimiter::Parenthesis {
+                            // assert(...) by (prover) { ... };
+                            let mut last = i + 1;
+                            if matches!(tts.get(last + 1), Some(TokenTree::Ident(by)) if by == "by") {
+                                last += 1;
+                                if matches!(tts.get(last + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis) {
+                                    last += 1;
+                                }
+                                if matches!(tts.get(last + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace) {
+                                    last += 1;
+                                }
+                            }
+                            let end = self.statement_end(&tts, last);
+                            self.remove(ident.span().byte_range().start..end);
+                            i = last + 1;
+                            continue;
+                        }
+                    }
+                }
+                TokenTree::Ident(ident) if ident == "let" => {
+                    if matches!(tts.get(i + 1), Some(TokenTree::Ident(mode)) if mode == "ghost" || mode == "tracked") {
+                        let semi = (i..tts.len()).find(|&j| matches!(&tts[j], TokenTree::Punct(p) if p.as_char() == ';'));
+                        let last = semi.unwrap_or(tts.len() - 1);
+                        self.remove(ident.span().byte_range().start..tts[last].span().byte_range().end);
+                        i = last + 1;
+                        continue;
+                    }
+                }
+                TokenTree::Ident(ident) if ident == "while" || ident == "loop" || ident == "for" => {
+                    in_loop_header = true;
+                    loop_spec_start = None;
+                }
+                TokenTree::Ident(ident) if in_loop_header && i > 0 && LOOP_SPEC_KEYWORDS.iter().any(|k| ident == k) => {
+                    // Cut from the end of the loop header so the body brace stays on its line
+                    loop_spec_start.get_or_insert(tts[i - 1].span().byte_range().end);
+                }
+                TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                    let bang = usize::from(matches!(tts.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '!'));
+                    if let Some(TokenTree::Group(attr)) = tts.get(i + 1 + bang) {
+                        let is_verus_attr = attr.delimiter() == Delimiter::Bracket
+                            && matches!(attr.stream().into_iter().next(), Some(TokenTree::Ident(name)) if name == "verifier" || name == "trigger");
+                        if is_verus_attr {
+                            self.remove(punct.span().byte_range().start..attr.span().byte_range().end);
+                            i += 2 + bang;
+                            continue;
+                        }
+                    }
+                }
+                TokenTree::Group(group) => {
+                    if in_loop_header && group.delimiter() == Delimiter::Brace {
+                        if let Some(start) = loop_spec_start.take() {
+                            self.replace(start..group.span_open().byte_range().start, " ");
+                        }
+                        in_loop_header = false;
+                    }
+                    self.erase_ghost_code(group.stream());
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Byte offset just past the token at `last`, including a following `;`
+    fn statement_end(&self, tts: &[TokenTree], last: usize) -> usize {
+        match tts.get(last + 1) {
+            Some(TokenTree::Punct(p)) if p.as_char() == ';' => p.span().byte_range().end,
+            _ => tts[last].span().byte_range().end,
+        }
+    }
+
+    /// Replace `verus! { ... }` with its contents
+    fn unwrap_verus_macro(&mut self, mac: &verus_syn::Macro, semi: Option<proc_macro2::Span>) {
+        let delim = mac.delimiter.span();
+        let path_start = mac.path.span().byte_range().start;
+        self.remove(path_start..delim.open().byte_range().end);
+        let close_end = semi.unwrap_or_else(|| delim.close()).byte_range().end;
+        self.remove(delim.close().byte_range().start..close_end);
+    }
+}
+
+impl<'ast> Visit<'ast> for VerusEraser<'_> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        match item {
+            Item::Const(_)
+            | Item::Enum(_)
+            | Item::ExternCrate(_)
+            | Item::Fn(_)
+            | Item::ForeignMod(_)
+            | Item::Impl(_)
+            | Item::Macro(_)
+            | Item::Mod(_)
+            | Item::Static(_)
+            | Item::Struct(_)
+            | Item::Trait(_)
+            | Item::TraitAlias(_)
+            | Item::Type(_)
+            | Item::Union(_)
+            | Item::Use(_) => verus_syn::visit::visit_item(self, item),
+            // Verus-only items (broadcast groups, global settings, ...)
+            _ => self.remove(item.span().byte_range()),
+        }
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        let body_start = node.block.brace_token.span.open().byte_range().start;
+        self.erase_fn(&node.attrs, &node.sig, node.span(), body_start);
+        let block = &node.block;
+        self.erase_ghost_code(quote::quote!(#block));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        let body_start = node.block.brace_token.span.open().byte_range().start;
+        self.erase_fn(&node.attrs, &node.sig, node.span(), body_start);
+        let block = &node.block;
+        self.erase_ghost_code(quote::quote!(#block));
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        let body_start = match (&node.default, &node.semi_token) {
+            (Some(block), _) => block.brace_token.span.open().byte_range().start,
+            (None, Some(semi)) => semi.span.byte_range().start,
+            (None, None) => node.span().byte_range().end,
+        };
+        self.erase_fn(&node.attrs, &node.sig, node.span(), body_start);
+        if let Some(block) = &node.default {
+            self.erase_ghost_code(quote::quote!(#block));
+        }
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        self.erase_verifier_attrs(&node.attrs);
+        for item in &node.items {
+            match item {
+                verus_syn::ImplItem::Macro(mac) if is_verus_macro(&mac.mac.path, self.verus_macro_names) => {
+                    self.unwrap_verus_macro(&mac.mac, mac.semi_token.map(|s| s.span));
+                    if let Ok(body) = verus_syn::parse2::<VerusImplMacroBody>(mac.mac.tokens.clone()) {
+                        for impl_item in body.items {
+                            self.visit_impl_item(&impl_item);
+                        }
+                    }
+                }
+                _ => self.visit_impl_item(item),
+            }
+        }
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        if is_verus_macro(&node.mac.path, self.verus_macro_names) {
+            self.unwrap_verus_macro(&node.mac, node.semi_token.map(|s| s.span));
+            if let Ok(body) = verus_syn::parse2::<VerusMacroBody>(node.mac.tokens.clone()) {
+                for item in body.items {
+                    self.visit_item(&item);
+                }
+            }
+        }
+    }
+}
+
+/// Compute the plain-Rust ("erased") version of Verus source, unwrapping
+/// `verus!` and the project macros in `verus_macro_names`
+pub fn erase_verus_core(content: &str, verus_macro_names: &[String]) -> Result<String, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let source = parsed_source(content);
+    let mut eraser = VerusEraser::new(source, verus_macro_names);
+    eraser.visit_file(&file);
+    Ok(content[..content.len() - source.len()].to_string() + &apply_text_edits(source, eraser.edits))
+}
+
+/// Counts the definitions of functions with a given name
+struct DefinitionCounter<'a> {
+    name: &'a str,
+    /// Free functions (`fn` items)
+    free_fns: usize,
+    /// Methods in impls and traits
+    methods: usize,
+}
+
+impl<'ast> Visit<'ast> for DefinitionCounter<'_> {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.free_fns += usize::from(node.sig.ident == self.name);
+        verus_syn::visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.methods += usize::from(node.sig.ident == self.name);
+        verus_syn::visit::visit_impl_item_fn(self, node);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.methods += usize::from(node.sig.ident == self.name);
+        verus_syn::visit::visit_trait_item_fn(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &[]) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &[]) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// Names of the modules and types declared anywhere in `tokens` (idents after
+/// `mod`, `struct`, `enum`, `union`, `trait` or `type`)
+fn declared_scopes(tokens: TokenStream, scopes: &mut Vec<String>) {
+    let mut after_keyword = false;
+    for tt in tokens {
+        match &tt {
+            TokenTree::Group(group) => declared_scopes(group.stream(), scopes),
+            TokenTree::Ident(ident) if after_keyword => scopes.push(ident.to_string()),
+            _ => {}
+        }
+        after_keyword = matches!(&tt, TokenTree::Ident(ident)
+            if ["mod", "struct", "enum", "union", "trait", "type"].iter().any(|k| ident == k));
+    }
+}
+
+/// Whether `tts[i]` and `tts[i + 1]` form a `::`
+fn is_path_sep(tts: &[TokenTree], i: usize) -> bool {
+    matches!((tts.get(i), tts.get(i + 1)), (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b)))
+        if a.as_char() == ':' && a.spacing() == Spacing::Joint && b.as_char() == ':')
+}
+
+/// Collect the spans of the references to function `old_name` in `tokens`
+///
+/// Works on tokens rather than the AST, so calls inside macro bodies such as
+/// `calc!` steps are found too. A reference is the name after `fn`, the final
+/// segment of a path that is a bare name or rooted in `crate`/`self`/`super`/
+/// `Self` or a module or type of the file, or (for methods) a `.name(` call.
+fn collect_renames(tokens: TokenStream, old_name: &str, is_method: bool, scopes: &[String], spans: &mut Vec<Range<usize>>) {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, tt) in tts.iter().enumerate() {
+        let ident = match tt {
+            TokenTree::Group(group) => {
+                collect_renames(group.stream(), old_name, is_method, scopes, spans);
+                continue;
+            }
+            TokenTree::Ident(ident) if ident == old_name => ident,
+            _ => continue,
+        };
+        if is_path_sep(&tts, i + 1) {
+            // Not the final segment of a path
+            continue;
+        }
+        let prev = if i > 0 { tts.get(i - 1) } else { None };
+        let is_reference = match prev {
+            Some(TokenTree::Ident(kw)) if kw == "fn" => true,
+            Some(TokenTree::Punct(p)) if p.as_char() == '.' => {
+                is_method && matches!(tts.get(i + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+            }
+            Some(TokenTree::Punct(p)) if p.as_char() == ':' && i >= 2 && is_path_sep(&tts, i - 2) => {
+                // Walk back to the first segment of the path
+                let mut root = i;
+                while root >= 3 && is_path_sep(&tts, root - 2) && matches!(tts[root - 3], TokenTree::Ident(_)) {
+                    root -= 3;
+                }
+                match &tts[root] {
+                    TokenTree::Ident(first) => {
+                        ["crate", "self", "super", "Self"].iter().any(|k| first == k)
+                            || scopes.iter().any(|s| first == s)
+                    }
+                    _ => false,
+                }
+            }
+            // `broadcast use lemma_x;`
+            Some(TokenTree::Ident(kw)) if kw == "use" => !is_method,
+            // A bare name is only a reference when called; otherwise it is a
+            // parameter, local or field that happens to share the name
+            _ => {
+                !is_method
+                    && matches!(tts.get(i + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+            }
+        };
+        if is_reference {
+            spans.push(ident.span().byte_range());
+        }
+    }
+}
+
+/// Rename function `old_name` to `new_name` at its definition and its same-file references
+pub fn rename_function_core(content: &str, old_name: &str, new_name: &str) -> Result<String, String> {
+    verus_syn::parse_str::<verus_syn::Ident>(new_name).map_err(|_| format!("'{}' is not a valid identifier", new_name))?;
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let count = |name| {
+        let mut counter = DefinitionCounter {
+            name,
+            free_fns: 0,
+            methods: 0,
+        };
+        counter.visit_file(&file);
+        counter
+    };
+    let existing = count(new_name);
+    if existing.free_fns + existing.methods > 0 {
+        return Err(format!("A function named '{}' is already defined", new_name));
+    }
+    let counter = count(old_name);
+    match counter.free_fns + counter.methods {
+        0 => return Err(format!("Function '{}' not found", old_name)),
+        1 => {}
+        n => return Err(format!("Function '{}' is defined {} times", old_name, n)),
+    }
+
+    let tokens: TokenStream = content.parse().map_err(|e| format!("Parse error: {}", e))?;
+    let mut scopes = Vec::new();
+    declared_scopes(tokens.clone(), &mut scopes);
+    let mut spans = Vec::new();
+    collect_renames(tokens, old_name, counter.methods > 0, &scopes, &mut spans);
+    let edits = spans
+        .into_iter()
+        .map(|range| TextEdit {
+            range,
+            replacement: new_name.to_string(),
+        })
+        .collect();
+    let renamed = apply_text_edits(content, edits);
+    verus_syn::parse_file(&renamed).map_err(|e| format!("Renaming produced code that does not parse: {}", e))?;
+    Ok(renamed)
+}
+
+/// Groups of functions defined more than once at the same place: the same
+/// name in the same module, impl, trait and enclosing function, whether or not
+/// inside `verus!`. Definitions whose cfgs are mutually exclusive
+/// (`cfg(c)`/`cfg(not(c))`) do not clash with each other.
+pub fn duplicate_definitions_core(content: &str) -> Result<Vec<Vec<FunctionSpecs>>, String> {
+    let mut groups: Vec<(Vec<String>, Vec<FunctionSpecs>)> = Vec::new();
+    for f in parse_verus_file_core(content)? {
+        let key: Vec<String> = f.path.iter().filter(|crumb| !crumb.ends_with('!')).cloned().collect();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(f),
+            None => groups.push((key, vec![f])),
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .filter_map(|(_, group)| {
+            let clashing: Vec<FunctionSpecs> = group
+                .iter()
+                .enumerate()
+                .filter(|(i, f)| {
+                    group.iter().enumerate().any(|(j, g)| *i != j && !cfgs_exclusive(&f.cfgs, &g.cfgs))
+                })
+                .map(|(_, f)| f.clone())
+                .collect();
+            (clashing.len() > 1).then_some(clashing)
+        })
+        .collect())
+}
+
+/// Functions with `requires`/`ensures` clauses that are not inside a `verus!` block
+pub fn specs_outside_verus_core(content: &str) -> Result<LintResult<FunctionSpecs>, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (suppressed, outside): (Vec<_>, Vec<_>) = parse_verus_file_core(content)?
+        .into_iter()
+        .filter(|f| !f.in_verus_block && (!f.requires.is_empty() || !f.ensures.is_empty()))
+        .partition(|f| is_suppressed(f, Lint::SpecsOutsideVerus, &lines));
+    Ok((outside, suppressed.len()))
+}
+
+/// Proof functions with a non-empty body, largest first
+pub fn nontrivial_proofs_core(content: &str) -> Result<Vec<FunctionSpecs>, String> {
+    let mut proofs: Vec<FunctionSpecs> = parse_verus_file_core(content)?
+        .into_iter()
+        .filter(|f| f.is_proof && f.body_stmt_count.is_some_and(|count| count > 0))
+        .collect();
+    // Stable, so equally long proofs stay in source order
+    proofs.sort_by_key(|f| std::cmp::Reverse(f.body_stmt_count));
+    Ok(proofs)
+}
+
+/// The modules of a file as a tree, with each function placed in its module
+/// or in the impl or trait block it belongs to
+pub fn module_tree_core(content: &str) -> Result<ModuleNode, String> {
+    let functions = parse_verus_file_core(content)?;
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = ModuleDocFinder::default();
+    finder.visit_file(&file);
+
+    let mut root = ModuleNode { name: "crate".to_string(), doc: finder.docs.get("").cloned(), ..Default::default() };
+    for path in &finder.modules {
+        let mut node = &mut root;
+        for name in path.split("::") {
+            node = node.submodule(name);
+        }
+        node.doc = finder.docs.get(path).cloned();
+    }
+    for f in functions {
+        let summary = FunctionSummary { name: f.name.clone(), mode: f.mode.clone(), line_number: f.line_number };
+        let mut node = &mut root;
+        let mut block: Option<&str> = None;
+        // The crumbs between "crate" and the function's own "fn" one
+        for crumb in &f.path[1..f.path.len() - 1] {
+            if let Some(name) = crumb.strip_prefix("mod ") {
+                node = node.submodule(name);
+                block = None;
+            } else if block.is_none() && (crumb.starts_with("impl") || crumb.starts_with("trait ")) {
+                block = Some(crumb);
+            }
+        }
+        match block {
+            Some(label) => node.impl_block(label).functions.push(summary),
+            None => node.functions.push(summary),
+        }
+    }
+    Ok(root)
+}
+
+/// The public modules, `pub use` declarations and public functions of a file
+pub fn public_surface_core(content: &str) -> Result<PublicSurface, String> {
+    let file = verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+    let mut finder = SurfaceFinder::default();
+    finder.visit_file(&file);
+    Ok(PublicSurface {
+        modules: finder.modules.into_iter().filter(|m| m.is_pub).map(|m| m.path).collect(),
+        reexports: finder.reexports,
+        functions: finder
+            .functions
+            .into_iter()
+            .map(|f| {
+                let segments = [Some(f.module_path.as_str()), f.owner.as_deref(), Some(f.name.as_str())];
+                segments.into_iter().flatten().filter(|s| !s.is_empty()).collect::<Vec<_>>().join("::")
+            })
+            .collect(),
+    })
+}
+
+/// Functions whose lines intersect any of the given inclusive line ranges
+///
+/// Overlap is any intersection: a function spanning lines 10-20 is touched by
+/// a range 18-30 (only its last lines) as much as by 12-14 or 1-100. A nested
+/// function is reported along with the function containing it.
+pub fn functions_touched_core(content: &str, ranges: &[(usize, usize)]) -> Result<Vec<FunctionSpecs>, String> {
+    let functions = parse_verus_file_core(content)?;
+    Ok(functions
+        .into_iter()
+        .filter(|f| {
+            let (Some(first), Some(last)) = (f.line_number, f.end_line) else {
+                return false;
+            };
+            ranges.iter().any(|&(start, end)| first <= end && start <= last)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{GENERIC_LEMMAS, SAMPLE_VERUS};
+
+    #[test]
+    fn test_spec_fingerprint_ignores_formatting_and_position() {
+        let reformatted = r#"
+pub fn exec_add(a: u32, b: u32) -> (result: u32)
+    requires a + b <= u32::MAX,
+    ensures result == a + b,
+{
+    // a different body does not matter either
+    let sum = a + b;
+    sum
+}
+
+pub proof fn lemma_mul_inequality(
+    x: int,
+    y: int,
+    z: int,
+)
+    requires
+        z > 0,  // reordered
+        x <= y,
+    ensures
+        x * z <= y * z,
+{
+}
+"#;
+        let original = parse_verus_file_core(SAMPLE_VERUS).unwrap();
+        let moved = parse_verus_file_core(reformatted).unwrap();
+        for f in &original {
+            let other = moved.iter().find(|g| g.name == f.name).unwrap();
+            assert_eq!(f.spec_fingerprint, other.spec_fingerprint, "fingerprint of {} changed", f.name);
+            assert_eq!(f.spec_fingerprint.len(), 64);
+        }
+        assert_eq!(combine_fingerprints(&original), combine_fingerprints(&moved));
+    }
+
+    #[test]
+    fn test_spec_fingerprint_detects_clause_change() {
+        let changed = SAMPLE_VERUS.replace("x * z <= y * z", "x * z < y * z");
+        let original = parse_verus_file_core(SAMPLE_VERUS).unwrap();
+        let modified = parse_verus_file_core(&changed).unwrap();
+
+        let lemma_before = original.iter().find(|f| f.name == "lemma_mul_inequality").unwrap();
+        let lemma_after = modified.iter().find(|f| f.name == "lemma_mul_inequality").unwrap();
+        assert_ne!(lemma_before.spec_fingerprint, lemma_after.spec_fingerprint);
+
+        let add_before = original.iter().find(|f| f.name == "exec_add").unwrap();
+        let add_after = modified.iter().find(|f| f.name == "exec_add").unwrap();
+        assert_eq!(add_before.spec_fingerprint, add_after.spec_fingerprint);
+
+        assert_ne!(combine_fingerprints(&original), combine_fingerprints(&modified));
+    }
+
+    #[test]
+    fn test_erase_verus_produces_plain_rust() {
+        let code = r#"
+use vstd::prelude::*;
+
+verus! {
+
+#[verifier::external_body]
+pub fn exec_max(a: u64, b: u64) -> (r: u64)
+    ensures r >= a && r >= b,
+{
+    if a > b { a } else { b }
+}
+
+pub fn sum_to(n: u64) -> (total: u64)
+    requires n < 1000,
+    ensures total >= n,
+{
+    let mut i: u64 = 0;
+    let mut total: u64 = 0;
+    let ghost start = total;
+    while i < n
+        invariant
+            i <= n,
+            total >= i,
+        decreases n - i,
+    {
+        i = i + 1;
+        total = total + i;
+        assert(total >= i);
+    }
+    proof {
+        lemma_positive(n as int);
+    }
+    total
+}
+
+proof fn lemma_positive(x: int)
+    requires x >= 0,
+    ensures x * x >= 0,
+{
+}
+
+spec fn double(x: int) -> int {
+    2 * x
+}
+
+} // verus!
+
+fn untouched(x: u32) -> u32 {
+    x + 1 // keep me
+}
+"#;
+        let erased = erase_verus_core(code, &[]).unwrap();
+        for token in ["requires", "ensures", "invariant", "decreases", "proof", "assert", "ghost", "verus! {", "verifier", "spec fn"] {
+            assert!(!erased.contains(token), "`{}` left in erased output:\n{}", token, erased);
+        }
+        assert!(erased.contains("pub fn exec_max(a: u64, b: u64) -> u64"));
+        assert!(erased.contains("    while i < n {\n        i = i + 1;"));
+        assert!(erased.contains("fn untouched(x: u32) -> u32 {\n    x + 1 // keep me\n}\n"));
+
+        let reparsed = parse_verus_file_core(&erased).unwrap();
+        let names: Vec<&str> = reparsed.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["exec_max", "sum_to", "untouched"]);
+        assert!(reparsed.iter().all(|f| f.requires.is_empty() && f.ensures.is_empty()));
+    }
+
+    #[test]
+    fn test_erase_verus_qualified_macro_path() {
+        let code = r#"
+vstd::prelude::verus! {
+proof fn lemma_a(x: int) ensures x == x {}
+
+pub fn exec_a(x: u8) -> (r: u8) ensures r == x { x }
+}
+
+impl S {
+    builtin_macros::verus! {
+        fn get(&self) -> (r: u8) ensures r == 0 { 0 }
+    }
+}
+"#;
+        let erased = erase_verus_core(code, &[]).unwrap();
+        for token in ["verus!", "ensures", "proof fn"] {
+            assert!(!erased.contains(token), "`{}` left in erased output:\n{}", token, erased);
+        }
+        assert!(erased.contains("pub fn exec_a(x: u8) -> u8 { x }"));
+        assert!(erased.contains("fn get(&self) -> u8 { 0 }"));
+
+        let wrapped = "my_verus! {\nproof fn lemma_b() {}\nfn exec_b() {}\n}\n";
+        assert!(erase_verus_core(wrapped, &[]).unwrap().contains("my_verus!"));
+        let names = ["my_verus".to_string()];
+        assert_eq!(erase_verus_core(wrapped, &names).unwrap().trim(), "fn exec_b() {}");
+    }
+
+    #[test]
+    fn test_erase_verus_fixtures_have_no_specs() {
+        let erased = erase_verus_core(SAMPLE_VERUS, &[]).unwrap();
+        assert!(!erased.contains("requires") && !erased.contains("ensures"));
+        assert!(erased.contains("pub fn exec_add(a: u32, b: u32) -> u32 {\n    a + b\n}"));
+        let reparsed = parse_verus_file_core(&erased).unwrap();
+        assert_eq!(reparsed.len(), 1);
+        assert_eq!(reparsed[0].name, "exec_add");
+    }
+
+    const REQUIRES_LEMMAS: &str = r#"
+proof fn lemma_square_pos(x: int)
+    requires x > 0,
+    ensures x * x > 0,
+{
+}
+
+proof fn lemma_lt_bound(a: int, b: int)
+    requires
+        a < b,
+        b < 10,
+    ensures a < 10,
+{
+}
+
+proof fn lemma_no_requires(n: nat)
+    ensures n >= 0,
+{
+}
+
+fn exec_needs(x: u32)
+    requires x > 0,
+{
+}
+"#;
+
+    fn kept(facts: &[&str]) -> Vec<String> {
+        let facts: Vec<String> = facts.iter().map(|f| f.to_string()).collect();
+        let facts = parse_facts(&facts).unwrap();
+        filter_by_requires_core(REQUIRES_LEMMAS, &facts, None, false, None)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_by_requires_binds_params() {
+        assert_eq!(kept(&["y > 0"]), vec!["lemma_square_pos", "lemma_no_requires"]);
+        assert_eq!(kept(&["0 < (y + 1)"]), vec!["lemma_square_pos", "lemma_no_requires"]);
+        // No ordering fact at all: only the lemma without requires survives
+        assert_eq!(kept(&["y == 0", "z != 3"]), vec!["lemma_no_requires"]);
+    }
+
+    #[test]
+    fn test_filter_by_requires_consistent_bindings() {
+        assert!(kept(&["p < q", "q < 10"]).contains(&"lemma_lt_bound".to_string()));
+        assert!(kept(&["p < q && q < 10"]).contains(&"lemma_lt_bound".to_string()));
+        // `b` cannot bind to both `q` and `r`
+        assert!(!kept(&["p < q", "r < 10"]).contains(&"lemma_lt_bound".to_string()));
+        assert!(parse_facts(&["x >".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_filter_by_requires_known_bounds() {
+        let facts = parse_facts(&["a == a".to_string()]).unwrap();
+        let names = |known: &HashMap<String, Vec<String>>| -> Vec<String> {
+            filter_by_requires_core(GENERIC_LEMMAS, &facts, Some(known), false, None)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect()
+        };
+        let mut known = HashMap::new();
+        known.insert("Seq<int>".to_string(), vec!["View".to_string(), "Clone".to_string()]);
+        assert_eq!(names(&known), vec!["lemma_inline"]);
+        known.insert("Key".to_string(), vec!["SpecOrd".to_string()]);
+        assert_eq!(names(&known), vec!["lemma_inline", "lemma_where", "lemma_method"]);
+    }
+
+    const TERMINATION_SAMPLE: &str = r#"
+verus! {
+
+#[verifier::exec_allows_no_decreases_clause]
+fn spin_waived(n: u64) {
+    let mut i = 0;
+    while i < n {
+        i = i + 1;
+    }
+}
+
+fn spin_unwaived(n: u64) {
+    let mut i = 0;
+    while i < n
+        invariant i <= n,
+    {
+        i = i + 1;
+    }
+}
+
+fn spin_measured(n: u64) {
+    let mut i = 0;
+    while i < n
+        invariant i <= n,
+        decreases n - i,
+    {
+        i = i + 1;
+    }
+    #[verifier::exec_allows_no_decreases_clause]
+    loop {
+        break;
+    }
+    for j in 0..n {
+    }
+}
+
+spec fn count(n: nat) -> nat
+    decreases n,
+{
+    if n == 0 { 0 } else { 1 + count((n - 1) as nat) }
+}
+
+proof fn lemma_forever(n: nat) {
+    lemma_forever(n);
+}
+
+}
+"#;
+
+    #[test]
+    fn test_extract_loops_termination_waivers() {
+        let loops = extract_loops_core(TERMINATION_SAMPLE).unwrap();
+        let summary: Vec<(&str, &str, bool, bool)> = loops
+            .iter()
+            .map(|l| (l.function.as_str(), l.kind.as_str(), l.has_decreases, l.termination_waived))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("spin_waived", "while", false, true),
+                ("spin_unwaived", "while", false, false),
+                ("spin_measured", "while", true, false),
+                ("spin_measured", "loop", false, true),
+                ("spin_measured", "for", false, false),
+            ]
+        );
+        assert_eq!(loops[3].attributes, vec!["verifier::exec_allows_no_decreases_clause"]);
+        assert_eq!(loops[1].line_number, Some(14));
+    }
+
+    #[test]
+    fn test_termination_report_only_unwaived() {
+        let issues = termination_report_core(TERMINATION_SAMPLE).unwrap();
+        let reported: Vec<(&str, &str)> = issues.iter().map(|i| (i.function.as_str(), i.kind.as_str())).collect();
+        assert_eq!(reported, vec![("spin_unwaived", "while"), ("lemma_forever", "recursion")]);
+    }
+
+    const MARKDOWN_SAMPLE: &str = r#"pub proof fn lemma_pos(x: int)
+    requires x > 0,
+    ensures x * x > 0,
+{
+}
+
+mod arith {
+    spec fn double(x: int) -> int {
+        2 * x
+    }
+
+    fn add_one(x: u32) -> (r: u32)
+        requires x < 100,
+        ensures r == x + 1,
+    {
+        x + 1
+    }
+}
+"#;
+
+    #[test]
+    fn test_render_markdown_snapshot() {
+        let files = vec![(String::new(), MARKDOWN_SAMPLE.to_string())];
+        let report = render_markdown_core(&files, &MarkdownOptions::default());
+        let expected = r#"# Verus spec report
+
+| Functions | proof | spec | exec | requires | ensures | decreases |
+|---:|---:|---:|---:|---:|---:|---:|
+| 3 | 1 | 1 | 1 | 2 | 2 | 0 |
+
+## `(root)`
+
+| Name | Mode | Lines | requires | ensures | decreases |
+|---|---|---|---:|---:|---:|
+| `lemma_pos` | proof | 1–5 | 1 | 1 | 0 |
+
+<details>
+<summary><code>lemma_pos</code></summary>
+
+```verus
+requires
+    x > 0,
+ensures
+    x * x > 0,
+```
+
+</details>
+
+## `arith`
+
+| Name | Mode | Lines | requires | ensures | decreases |
+|---|---|---|---:|---:|---:|
+| `double` | spec | 8–10 | 0 | 0 | 0 |
+| `add_one` | exec | 12–17 | 1 | 1 | 0 |
+
+<details>
+<summary><code>add_one</code></summary>
+
+```verus
+requires
+    x < 100,
+ensures
+    r == x + 1,
+```
+
+</details>
+"#;
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn test_render_markdown_options() {
+        let files = vec![("a.rs".to_string(), MARKDOWN_SAMPLE.to_string()), ("bad.rs".to_string(), "fn (".to_string())];
+        let options = MarkdownOptions {
+            include_bodies: true,
+            proof_only: true,
+        };
+        let report = render_markdown_core(&files, &options);
+        assert!(report.contains("| 1 | 1 | 0 | 0 | 1 | 1 | 0 |"));
+        assert!(report.contains("## `a.rs — (root)`"));
+        assert!(!report.contains("arith"));
+        assert!(report.contains("```verus\npub proof fn lemma_pos(x: int)\n    requires x > 0,\n    ensures x * x > 0,\n{\n}\n```"));
+        assert!(report.contains("## Errors\n\n- `bad.rs`: Parse error:"));
+    }
+
+    #[test]
+    fn test_group_lemmas_by_subject() {
+        let code = r#"
+proof fn lemma_mul_comm(x: int, y: int)
+    ensures x * y == y * x,
+{
+}
+
+proof fn lemma_mod_bound(x: int, m: int)
+    requires m > 0,
+    ensures 0 <= x % m, x % m < m,
+{
+}
+
+proof fn lemma_push_len(s: Seq<int>, v: int)
+    ensures s.push(v).len() == s.len() + 1,
+{
+}
+
+proof fn lemma_push_last(s: Seq<int>, v: int)
+    ensures s.push(v)[s.len() as int] == v,
+{
+}
+
+proof fn lemma_nothing()
+{
+}
+
+fn exec_not_a_lemma(x: u32) -> (r: u32)
+    ensures r == x * 2,
+{
+    x * 2
+}
+"#;
+        let groups = group_lemmas_core(code).unwrap();
+        let names = |key: &str| -> Vec<String> { groups[key].iter().map(|f| f.name.clone()).collect() };
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec!["Seq::push", "modular arithmetic", "multiplication", "unclassified"]
+        );
+        assert_eq!(names("multiplication"), vec!["lemma_mul_comm"]);
+        assert_eq!(names("modular arithmetic"), vec!["lemma_mod_bound"]);
+        assert_eq!(names("Seq::push"), vec!["lemma_push_len", "lemma_push_last"]);
+        assert_eq!(names("unclassified"), vec!["lemma_nothing"]);
+    }
+
+    #[test]
+    fn test_lemma_spec_associations() {
+        let code = r#"
+verus! {
+spec fn pow2(n: nat) -> nat
+    decreases n,
+{
+    if n == 0 { 1 } else { 2 * pow2((n - 1) as nat) }
+}
+
+spec fn pow(b: int, n: nat) -> int {
+    b
+}
+
+proof fn lemma_pow2_adds(e1: nat, e2: nat)
+    ensures pow2(e1) * pow2(e2) == pow2(e1 + e2),
+{
+}
+
+proof fn lemma_pow2_pos(e: nat)
+{
+}
+
+proof fn lemma_mul_comm(x: int, y: int)
+    ensures x * y == y * x,
+{
+}
+}
+"#;
+        let associations = lemma_spec_associations_core(code).unwrap();
+        let pow2 = &associations.by_spec["pow2"];
+        assert_eq!(pow2.len(), 2);
+        assert_eq!(pow2[0].lemma, "lemma_pow2_adds");
+        assert_eq!(pow2[0].reasons, vec!["ensures_mentions", "name_token"]);
+        assert_eq!(pow2[1].lemma, "lemma_pow2_pos");
+        assert_eq!(pow2[1].reasons, vec!["name_token"]);
+        // `pow` is not a whole token of `lemma_pow2_adds`
+        assert!(associations.by_spec["pow"].is_empty());
+
+        // The reverse mapping
+        assert_eq!(associations.by_lemma["lemma_pow2_adds"][0].spec_fn, "pow2");
+        assert!(associations.by_lemma["lemma_mul_comm"].is_empty());
+    }
+
+    #[test]
+    fn test_external_lemma_usage() {
+        let file_a = r#"
+use vstd::prelude::*;
+use vstd::arithmetic::mul::lemma_mul_is_commutative;
+use vstd::arithmetic::div_mod as dm;
+
+verus! {
+
+proof fn lemma_local() {
+}
+
+fn uses_vstd(x: u64, y: u64) {
+    proof {
+        lemma_mul_is_commutative(x as int, y as int);
+        dm::lemma_fundamental_div_mod(x as int, 3);
+        lemma_local();
+    }
+    let r = Some(helper_b(x));
+}
+
+}
+"#;
+        let file_b = r#"
+fn helper_b(a: u64) -> u64 {
+    proof {
+        vstd::arithmetic::mul::lemma_mul_is_commutative(a as int, 2);
+        crate::a::lemma_local();
+    }
+    a
+}
+"#;
+        let files = vec![("a.rs".to_string(), file_a.to_string()), ("b.rs".to_string(), file_b.to_string())];
+        let usage = external_lemma_usage_core(&files).unwrap();
+        assert_eq!(usage.keys().collect::<Vec<_>>(), vec!["vstd"]);
+        let vstd = &usage["vstd"];
+        assert_eq!(
+            vstd.keys().collect::<Vec<_>>(),
+            vec![
+                "vstd::arithmetic::div_mod::lemma_fundamental_div_mod",
+                "vstd::arithmetic::mul::lemma_mul_is_commutative"
+            ]
+        );
+        let commutative = &vstd["vstd::arithmetic::mul::lemma_mul_is_commutative"];
+        assert_eq!(commutative.count, 2);
+        assert_eq!(commutative.callers, vec!["uses_vstd", "helper_b"]);
+        assert_eq!(vstd["vstd::arithmetic::div_mod::lemma_fundamental_div_mod"].count, 1);
+    }
+
+    #[test]
+    fn test_wrapper_lemmas() {
+        let code = r#"
+use vstd::prelude::*;
+use vstd::arithmetic::mul::lemma_mul_is_commutative;
+
+verus! {
+
+proof fn lemma_mul_comm(x: int, y: int)
+    ensures x * y == y * x,
+{
+    lemma_mul_is_commutative(x, y);
+}
+
+proof fn lemma_two_calls(x: int, y: int)
+    ensures x * y == y * x,
+{
+    lemma_mul_is_commutative(x, y);
+    lemma_mul_is_commutative(y, x);
+}
+
+proof fn lemma_add_zero(a: int)
+    ensures a + 0 == a,
+{
+}
+
+proof fn lemma_zero_add(b: int)
+    ensures b + 0 == b,
+{
+    proof {
+        lemma_add_zero(b);
+    }
+}
+
+proof fn lemma_stronger(b: int)
+    ensures b + 0 == b, b <= b,
+{
+    lemma_add_zero(b);
+}
+
+}
+"#;
+        let files = vec![("lib.rs".to_string(), code.to_string())];
+        let wrappers = wrapper_lemmas_core(&files).unwrap();
+        let names: Vec<&str> = wrappers.iter().map(|w| w.function.as_str()).collect();
+        assert_eq!(names, vec!["lemma_mul_comm", "lemma_zero_add", "lemma_stronger"]);
+
+        let vstd = &wrappers[0];
+        assert_eq!(vstd.callee, "vstd::arithmetic::mul::lemma_mul_is_commutative");
+        assert_eq!((vstd.file_path.as_str(), vstd.line_number), ("lib.rs", 7));
+        assert!(vstd.is_external && vstd.forwards_params);
+        // The contract of a vstd lemma is not in the files
+        assert_eq!(vstd.contract_equivalent, None);
+
+        // A local callee inside a proof block, its contract compared after substitution
+        assert_eq!(wrappers[1].callee, "lemma_add_zero");
+        assert!(!wrappers[1].is_external);
+        assert_eq!(wrappers[1].contract_equivalent, Some(true));
+        assert_eq!(wrappers[2].contract_equivalent, Some(false));
+    }
+
+    #[test]
+    fn test_save_load_specs_round_trip() {
+        let mut specs = parse_verus_file_core(SAMPLE_VERUS).unwrap();
+        specs.push(FunctionSpecs {
+            name: "lemma_full".to_string(),
+            file_path: "src/lemmas.rs".into(),
+            line_number: Some(3),
+            end_line: Some(9),
+            decreases: vec!["n".to_string()],
+            module_path: "arith::mul".to_string(),
+            proof_blocks: vec![ProofBlock {
+                line_number: Some(5),
+                end_line: Some(7),
+                calls: vec!["lemma_step".to_string()],
+            }],
+            bounds: vec![("T".to_string(), vec!["View".to_string()])],
+            parse_error: Some("Parse error: unexpected token".to_string()),
+            error_kind: Some("parse_error".to_string()),
+            ..Default::default()
+        });
+        specs.push(FunctionSpecs::default());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("specs.bin");
+        std::fs::write(&path, encode_specs(&specs).unwrap()).unwrap();
+        let loaded = decode_specs(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(loaded, specs);
+    }
+
+    #[test]
+    fn test_load_specs_rejects_bad_header() {
+        let bytes = encode_specs(&parse_verus_file_core(SAMPLE_VERUS).unwrap()).unwrap();
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] ^= 0xff;
+        assert!(decode_specs(&corrupted).unwrap_err().contains("bad header"));
+        assert!(decode_specs(&bytes[..4]).unwrap_err().contains("bad header"));
+
+        let mut old = bytes.clone();
+        old[SPECS_MAGIC.len()..SPECS_MAGIC.len() + 4].copy_from_slice(&(SPECS_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(decode_specs(&old).unwrap_err().contains("format version"));
+    }
+
+    #[test]
+    fn test_spec_evaluation_attributes() {
+        let code = r#"
+verus! {
+
+#[verifier::memoize]
+spec fn fib(n: nat) -> nat
+    decreases n
+{
+    if n < 2 { n } else { fib((n - 1) as nat) + fib((n - 2) as nat) }
+}
+
+#[verifier::inline]
+spec fn double(x: int) -> int {
+    2 * x
+}
+
+#[verifier::memoize]
+#[verifier(inline)]
+spec fn both(x: int) -> int {
+    x
+}
+
+spec fn neither(x: int) -> int {
+    x
+}
+
+struct Counter {}
+
+impl Counter {
+    #[verifier::inline]
+    #[verifier::opaque]
+    spec fn limit(&self) -> int {
+        10
+    }
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let flags = |name: &str| {
+            let f = funcs.iter().find(|f| f.name == name).unwrap();
+            (f.is_memoized, f.is_inlined_spec)
+        };
+        assert_eq!(flags("fib"), (true, false));
+        assert_eq!(flags("double"), (false, true));
+        assert_eq!(flags("both"), (true, true));
+        assert_eq!(flags("neither"), (false, false));
+        assert_eq!(flags("limit"), (false, true));
+        let limit = funcs.iter().find(|f| f.name == "limit").unwrap();
+        assert_eq!(limit.attributes, vec!["verifier::inline", "verifier::opaque"]);
+
+        let stats = compute_stats(&funcs);
+        assert_eq!(stats.memoized_functions, 2);
+        assert_eq!(stats.inlined_spec_functions, 3);
+        assert_eq!(stats.attributes["verifier::memoize"], 2);
+        assert_eq!(stats.attributes["verifier::inline"], 3);
+        assert_eq!(stats.attributes["verifier::opaque"], 1);
+    }
+
+    #[test]
+    fn test_fuzzy_find() {
+        let code = r#"
+verus! {
+
+proof fn lemma_mod_basics(n: int) {}
+proof fn lemma_mod_multiples_basic(x: int, m: int) {}
+proof fn lemma_mod_multiples_vanish(a: int, b: int, m: int) {}
+proof fn lemma_mul_basics(x: int) {}
+proof fn lemma_div_basics(x: int) {}
+
+mod sub {
+    proof fn lemma_mod_basics(n: int) {}
+}
+
+}
+"#;
+        let files = vec![(String::new(), code.to_string())];
+        let names = |query: &str| -> Vec<(String, String)> {
+            fuzzy_find_core(&files, query, 3)
+                .unwrap()
+                .into_iter()
+                .map(|(f, _)| (f.module_path, f.name))
+                .collect()
+        };
+
+        let best = names("mod mult basic");
+        assert_eq!(best[0].1, "lemma_mod_multiples_basic");
+        assert_eq!(names("lemma_mod_multiples_basic")[0].1, "lemma_mod_multiples_basic");
+        assert_eq!(names("lemma_mod_basics")[0].1, "lemma_mod_basics");
+        assert_eq!(names("sub::lemma_mod_basics")[0], ("sub".to_string(), "lemma_mod_basics".to_string()));
+
+        let scored = fuzzy_find_core(&files, "lemma_mul_basics", 10).unwrap();
+        assert_eq!(scored[0].1, 1.0);
+        assert!(scored[1..].iter().all(|(_, score)| *score < 1.0));
+        assert!(fuzzy_find_core(&files, "qqqq", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clause_ast() {
+        let code = r#"
+verus! {
+
+proof fn lemma_scaled(s: Seq<int>, x: int)
+    requires
+        s.len() == x,
+    ensures
+        x > 0 ==> forall|i: int| #![trigger s[i]] 0 <= i && i < x ==> s[i] * 2 >= i + 1u64,
+{
+}
+
+}
+"#;
+        let ast = clause_ast_core(code, "lemma_scaled", "ensures", 0).unwrap();
+        let json = serde_json::to_value(&ast).unwrap();
+        assert_eq!(json["node"], "binary");
+        assert_eq!(json["op"], "==>");
+        assert_eq!(json["left"]["node"], "binary");
+        assert_eq!(json["left"]["op"], ">");
+        assert_eq!(json["left"]["left"], serde_json::json!({"node": "path", "path": "x"}));
+        assert_eq!(
+            json["left"]["right"],
+            serde_json::json!({"node": "lit", "kind": "int", "value": "0", "suffix": null})
+        );
+
+        let quantifier = &json["right"];
+        assert_eq!(quantifier["node"], "quantifier");
+        assert_eq!(quantifier["quantifier"], "forall");
+        assert_eq!(quantifier["binders"], serde_json::json!([{"name": "i", "ty": "int"}]));
+        let body = &quantifier["body"];
+        assert_eq!(body["op"], "==>");
+        let comparison = &body["right"];
+        assert_eq!(comparison["op"], ">=");
+        assert_eq!(comparison["left"]["op"], "*");
+        assert_eq!(comparison["left"]["left"]["node"], "index");
+        assert_eq!(comparison["right"]["right"]["suffix"], "u64");
+
+        let requires = clause_ast_core(code, "lemma_scaled", "requires", 0).unwrap();
+        let json = serde_json::to_value(&requires).unwrap();
+        assert_eq!(json["left"]["node"], "method_call");
+        assert_eq!(json["left"]["method"], "len");
+
+        assert!(clause_ast_core(code, "lemma_scaled", "ensures", 1).is_err());
+        assert!(clause_ast_core(code, "lemma_scaled", "invariant", 0).is_err());
+        assert!(clause_ast_core(code, "missing", "ensures", 0).is_err());
+    }
+
+    #[test]
+    fn test_spec_visibility_lint() {
+        let code = r#"
+verus! {
+
+spec fn hidden(x: int) -> bool {
+    x > 0
+}
+
+pub open spec fn visible(x: int) -> bool {
+    x > 0
+}
+
+const LIMIT: u64 = 10;
+
+pub proof fn lemma_uses_hidden(x: int)
+    requires
+        x > 1,
+    ensures
+        hidden(x),
+{
+}
+
+pub proof fn lemma_uses_visible(x: int)
+    ensures
+        visible(x),
+        x < LIMIT ==> x < 11,
+{
+}
+
+proof fn lemma_private(x: int)
+    ensures
+        hidden(x),
+{
+}
+
+}
+"#;
+        let (issues, suppressed) = spec_visibility_lint_core(code).unwrap();
+        assert_eq!(suppressed, 0);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].function, "lemma_uses_hidden");
+        assert_eq!(issues[0].clause_kind, "ensures");
+        assert_eq!(issues[0].clause, "hidden (x)");
+        assert_eq!(issues[0].item, "hidden");
+        assert_eq!(issues[0].item_kind, "spec fn");
+        assert_eq!(issues[1].function, "lemma_uses_visible");
+        assert_eq!(issues[1].item, "LIMIT");
+        assert_eq!(issues[1].item_kind, "const");
+    }
+
+    #[test]
+    fn test_rename_function() {
+        let code = r#"
+verus! {
+
+mod arith {
+    pub proof fn lemma_old(x: int)
+        ensures x + 0 == x,
+    {
+    }
+}
+
+proof fn uses_old(a: int, b: int)
+    requires a == b,
+{
+    arith::lemma_old(a);
+    assert(a + 0 == a) by {
+        crate::arith::lemma_old(a);
+    }
+    calc! {
+        (==)
+        a + 0; { arith::lemma_old(a); }
+        a;
+    }
+    let lemma_old_count = 1;
+}
+
+}
+"#;
+        let renamed = rename_function_core(code, "lemma_old", "lemma_new").unwrap();
+        assert!(verus_syn::parse_file(&renamed).is_ok());
+        assert!(!renamed.replace("lemma_old_count", "").contains("lemma_old"));
+        assert_eq!(renamed, code.replace("lemma_old(", "lemma_new("));
+
+        let err = rename_function_core(code, "lemma_old", "uses_old").unwrap_err();
+        assert!(err.contains("already defined"));
+        assert!(rename_function_core(code, "lemma_missing", "lemma_new").is_err());
+    }
+
+    #[test]
+    fn test_rename_function_keeps_same_named_locals() {
+        let code = r#"
+verus! {
+
+spec fn total(x: int) -> int {
+    x
+}
+
+proof fn uses_total(total: int, p: Pair)
+    ensures total(total) == total,
+{
+    let total2 = total;
+    let total = total + 1;
+    let q = Pair { total: total };
+    assert(p.total == p.total);
+}
+
+}
+"#;
+        let renamed = rename_function_core(code, "total", "sum").unwrap();
+        let expected = code
+            .replace("spec fn total(", "spec fn sum(")
+            .replace("ensures total(total)", "ensures sum(total)");
+        assert_eq!(renamed, expected);
+    }
+
+    #[test]
+    fn test_usage_examples() {
+        let lemmas = r#"
+verus! {
+
+pub proof fn lemma_mul_pos(x: int, y: int)
+    requires x > 0, y > 0,
+    ensures x * y > 0,
+{
+}
+
+}
+"#;
+        let callers = r#"use crate::lemmas::lemma_mul_pos as mul_pos;
+
+verus! {
+
+fn area(w: u64, h: u64) -> u64
+    requires w > 0, h > 0, w * h < 100,
+{
+    proof {
+        lemma_mul_pos(w as int, h as int);
+    }
+    w * h
+}
+
+proof fn lemma_square(n: int)
+    requires n > 0,
+    ensures n * n > 0,
+{
+    assert(n * n > 0) by {
+        mul_pos(n, n);
+    }
+}
+
+proof fn lemma_cube(n: int)
+    requires n > 0,
+{
+    crate::lemmas::lemma_mul_pos(n * n, n);
+}
+
+}
+"#;
+        let files = vec![
+            ("lemmas.rs".to_string(), lemmas.to_string()),
+            ("callers.rs".to_string(), callers.to_string()),
+        ];
+        let examples = usage_examples_core(&files, "lemma_mul_pos", 1).unwrap();
+        assert_eq!(examples.len(), 3);
+
+        assert_eq!(examples[0].caller, "area");
+        assert_eq!(examples[0].file_path, "callers.rs");
+        assert_eq!(examples[0].line_number, 9);
+        assert_eq!(examples[0].args, vec!["w as int", "h as int"]);
+        assert_eq!((examples[0].snippet_start, examples[0].snippet_end), (8, 10));
+        assert_eq!(
+            examples[0].snippet,
+            "    proof {\n        lemma_mul_pos(w as int, h as int);\n    }"
+        );
+
+        assert_eq!(examples[1].caller, "lemma_square");
+        assert_eq!(examples[1].args, vec!["n", "n"]);
+        assert_eq!(examples[1].snippet.lines().nth(1), Some("        mul_pos(n, n);"));
+
+        assert_eq!(examples[2].caller, "lemma_cube");
+        assert_eq!(examples[2].args, vec!["n * n", "n"]);
+        let last = usage_examples_core(&files[1..], "lemma_mul_pos", 100).unwrap();
+        assert_eq!((last[2].snippet_start, last[2].snippet_end), (1, callers.lines().count()));
+    }
+
+    #[test]
+    fn test_extract_traits() {
+        let code = r#"
+verus! {
+
+mod containers {
+    pub trait Container<T>: Sized + View {
+        type V: View;
+
+        const CAPACITY: usize;
+
+        spec fn spec_len(&self) -> nat;
+
+        fn push(&mut self, item: T)
+            requires
+                old(self).spec_len() < Self::CAPACITY,
+            ensures
+                self.spec_len() == old(self).spec_len() + 1;
+
+        fn is_full(&self) -> (b: bool)
+            ensures
+                b == (self.spec_len() == Self::CAPACITY),
+        {
+            false
+        }
+    }
+}
+
+}
+"#;
+        let traits = extract_traits_core(code).unwrap();
+        assert_eq!(traits.len(), 1);
+        let t = &traits[0];
+        assert_eq!(t.name, "Container");
+        assert_eq!(t.module_path, "containers");
+        assert_eq!(t.generics, "<T>");
+        assert_eq!(t.supertraits, vec!["Sized", "View"]);
+        assert_eq!(
+            t.associated_types,
+            vec![AssociatedItem {
+                name: "V".to_string(),
+                bounds: vec!["View".to_string()],
+                ty: None,
+                default: None,
+            }]
+        );
+        assert_eq!(t.associated_consts.len(), 1);
+        assert_eq!(t.associated_consts[0].name, "CAPACITY");
+        assert_eq!(t.associated_consts[0].ty.as_deref(), Some("usize"));
+
+        let names: Vec<&str> = t.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["spec_len", "push", "is_full"]);
+        assert_eq!(t.methods[1].requires.len(), 1);
+        assert_eq!(t.methods[1].ensures.len(), 1);
+        assert_eq!(t.methods[2].ensures.len(), 1);
+        assert_eq!(t.methods[1].module_path, "containers");
+        assert_eq!(t.provided_methods, vec!["is_full"]);
+    }
+
+    #[test]
+    fn test_clause_presence() {
+        let code = r#"
+verus! {
+
+proof fn lemma_trivially_true(x: int)
+    ensures
+        true,
+{
+}
+
+proof fn lemma_no_ensures(x: int)
+    requires
+        x > 0,
+{
+}
+
+spec fn count(n: nat) -> nat
+    decreases n,
+{
+    if n == 0 { 0 } else { count((n - 1) as nat) }
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let presence = |name: &str| {
+            let f = funcs.iter().find(|f| f.name == name).unwrap();
+            (f.has_requires, f.has_ensures, f.has_decreases)
+        };
+        assert_eq!(presence("lemma_trivially_true"), (false, true, false));
+        assert_eq!(presence("lemma_no_ensures"), (true, false, false));
+        assert_eq!(presence("count"), (false, false, true));
+        assert_eq!(funcs[0].ensures, vec!["true"]);
+
+        let stats = compute_stats(&funcs);
+        assert_eq!((stats.with_requires, stats.with_ensures, stats.with_decreases), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_refinement_lint() {
+        let code = r#"
+verus! {
+
+trait Bounded {
+    fn clamp(&self, x: u64) -> (r: u64)
+        ensures
+            r <= x,
+            r <= 100;
+}
+
+struct Exact {}
+
+impl Bounded for Exact {
+    fn clamp(&self, y: u64) -> (out: u64)
+        ensures
+            out <= y,
+            out <= 100,
+    {
+        if y < 100 { y } else { 100 }
+    }
+}
+
+struct Weak {}
+
+impl Bounded for Weak {
+    fn clamp(&self, x: u64) -> (r: u64)
+        requires
+            x > 0,
+        ensures
+            r <= x,
+    {
+        if x < 100 { x } else { 100 }
+    }
+}
+
+}
+"#;
+        let (issues, _) = refinement_lint_core(code).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.impl_type == "Weak" && i.method == "clamp" && i.trait_name == "Bounded"));
+        assert_eq!(issues[0].kind, "syntactic_missing_ensures");
+        assert_eq!(issues[0].clause, "r <= 100");
+        assert_eq!(issues[1].kind, "syntactic_added_requires");
+        assert_eq!(issues[1].clause, "x > 0");
+    }
+
+    #[test]
+    fn test_functions_touched_by_line_ranges() {
+        let code = r#"verus! {
+
+proof fn lemma_a(x: int)
+    requires
+        x > 0,
+    ensures
+        x >= 1,
+{
+}
+
+spec fn b(x: int) -> int {
+    x + 1
+}
+
+}
+"#;
+        let names = |ranges: &[(usize, usize)]| -> Vec<String> {
+            functions_touched_core(code, ranges).unwrap().into_iter().map(|f| f.name).collect()
+        };
+        // Only the ensures clause lines of lemma_a
+        assert_eq!(names(&[(6, 7)]), vec!["lemma_a"]);
+        // Clipping the end of lemma_a and the start of b
+        assert_eq!(names(&[(9, 11)]), vec!["lemma_a", "b"]);
+        assert_eq!(names(&[(1, 2)]), Vec::<String>::new());
+        assert_eq!(names(&[(1, 3), (13, 20)]), vec!["lemma_a", "b"]);
+        assert_eq!(names(&[(10, 10)]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_to_scip_index() {
+        let code = r#"
+verus! {
+
+mod arith {
+    impl Scalar {
+        proof fn lemma_add_zero(x: int)
+            ensures
+                x + 0 == x,
+        {
+        }
+    }
+
+    impl View for Scalar {
+        spec fn view(&self) -> int { 0 }
+    }
+
+    impl Deep for Scalar {
+        spec fn view(&self) -> int { 1 }
+    }
+}
+
+}
+"#;
+        let files = vec![("src/lib.rs".to_string(), code.to_string())];
+        let (bytes, symbols) = scip_index_core(&files, "curve").unwrap();
+        assert_eq!(symbols, 3);
+
+        let read: scip::Index = prost::Message::decode(bytes.as_slice()).unwrap();
+        let document = &read.documents[0];
+        assert_eq!(document.relative_path, "src/lib.rs");
+        let symbol = &document.symbols[0];
+        assert_eq!(symbol.symbol, "verus-lemma-finder cargo curve . arith/Scalar#lemma_add_zero().");
+        let signature = symbol.signature_documentation.as_ref().unwrap();
+        assert!(signature.text.starts_with("proof fn lemma_add_zero(x: int)"));
+        assert!(signature.text.contains("ensures\n        x + 0 == x,"));
+
+        let occurrences: Vec<(&str, &[i32], &[i32])> = document
+            .occurrences
+            .iter()
+            .map(|o| {
+                let symbol = o.symbol.strip_prefix("verus-lemma-finder cargo curve . ").unwrap();
+                (symbol, o.range.as_slice(), o.enclosing_range.as_slice())
+            })
+            .collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                ("arith/Scalar#lemma_add_zero().", &[5, 17, 31][..], &[5, 0, 9, 9][..]),
+                ("arith/impl#[Scalar][View]view().", &[13, 16, 20][..], &[13, 0, 13, 40][..]),
+                ("arith/impl#[Scalar][Deep]view().", &[17, 16, 20][..], &[17, 0, 17, 40][..]),
+            ]
+        );
+        assert!(document.occurrences.iter().all(|o| o.symbol_roles == 1));
+    }
+
+    #[test]
+    fn test_compute_proofs() {
+        let code = r#"
+verus! {
+
+proof fn lemma_pow_table() {
+    assert(pow2(10) == 1024) by (compute);
+    assert(1 + 1 == 2);
+    assert(0u8 < 1u8) by (compute_only);
+    assert(2 * 3 == 6) by (nonlinear_arith);
+}
+
+}
+"#;
+        let proofs = compute_proofs_core(code).unwrap();
+        let found: Vec<(&str, Option<usize>, &str, &str)> = proofs
+            .iter()
+            .map(|p| (p.function.as_str(), p.line_number, p.expr.as_str(), p.prover.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("lemma_pow_table", Some(5), "pow2 (10) == 1024", "compute"),
+                ("lemma_pow_table", Some(7), "0u8 < 1u8", "compute_only"),
+            ]
+        );
+
+        let functions = parse_verus_file_core(code).unwrap();
+        assert_eq!(functions[0].asserts.len(), 4);
+        assert_eq!(functions[0].asserts[1].prover, None);
+        assert_eq!(compute_stats(&functions).compute_proofs, 2);
+    }
+
+    #[test]
+    fn test_suggest_lemma_name() {
+        let order: Vec<String> = ["subject", "relation", "property"].iter().map(|p| p.to_string()).collect();
+        let suggest = |ensures: &str, existing: &[&str]| {
+            let existing: Vec<String> = existing.iter().map(|e| e.to_string()).collect();
+            suggest_lemma_name_core(ensures, &existing, "lemma_", &order, 3).unwrap()
+        };
+
+        let names = suggest("a * (b + c) == a * b + a * c", &[]);
+        assert_eq!(names, vec!["lemma_mul_distrib", "lemma_mul_add_distrib", "lemma_mul"]);
+        assert!(names.iter().all(|n| n.contains("mul")));
+        assert!(names[0].contains("distrib"));
+
+        let names = suggest("a * (b + c) == a * b + a * c", &["lemma_mul_distrib", "lemma_mul_distrib_2"]);
+        assert_eq!(names[0], "lemma_mul_distrib_3");
+
+        assert_eq!(suggest("x <= y ==> x * z <= y * z", &[])[0], "lemma_mul_le_mono");
+        assert_eq!(suggest("forall|i: int| a + b == b + a", &[])[0], "lemma_add_comm");
+        assert_eq!(suggest("pow(b, e) > 0", &[])[0], "lemma_pow_gt");
+
+        let custom: Vec<String> = vec!["property".to_string(), "subject".to_string()];
+        let names = suggest_lemma_name_core("x * y == y * x", &[], "proof_", &custom, 1).unwrap();
+        assert_eq!(names, vec!["proof_comm_mul"]);
+        assert!(suggest_lemma_name_core("x", &[], "lemma_", &["verb".to_string()], 1).is_err());
+    }
+
+    #[test]
+    fn test_nontrivial_proofs() {
+        let code = r#"
+verus! {
+
+proof fn lemma_auto(x: int)
+    ensures x + 0 == x,
+{
+}
+
+proof fn lemma_one_assert(x: int)
+    ensures x * 1 == x,
+{
+    assert(x * 1 == x);
+}
+
+proof fn lemma_long(x: int, y: int)
+    requires y > 0,
+    ensures x % y < y,
+{
+    let q = x / y;
+    assert(q * y <= x);
+    if x > 0 {
+        lemma_one_assert(x);
+        assert(x % y >= 0);
+    }
+}
+
+fn exec_fn() {}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let facts: Vec<(&str, Option<usize>, bool)> =
+            funcs.iter().map(|f| (f.name.as_str(), f.body_stmt_count, f.is_trivial_proof)).collect();
+        assert_eq!(
+            facts,
+            vec![
+                ("lemma_auto", Some(0), true),
+                ("lemma_one_assert", Some(1), false),
+                ("lemma_long", Some(5), false),
+                ("exec_fn", Some(0), false),
+            ]
+        );
+
+        let names: Vec<String> = nontrivial_proofs_core(code).unwrap().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["lemma_long", "lemma_one_assert"]);
+    }
+
+    #[test]
+    fn test_context_for_error() {
+        let defs = r#"
+verus! {
+
+pub open spec fn is_even(n: int) -> bool {
+    n % 2 == 0
+}
+
+pub open spec fn double(n: int) -> int {
+    2 * n
+}
+
+proof fn lemma_double_even(n: int)
+    ensures is_even(double(n)),
+{
+}
+
+proof fn lemma_unrelated(x: int)
+    ensures x + 0 == x,
+{
+}
+
+}
+"#;
+        let user = r#"
+verus! {
+
+proof fn caller(k: int)
+    requires k > 0,
+    ensures
+        k >= 1,
+        is_even(double(k)),
+{
+    assert(k != 0);
+}
+
+}
+"#;
+        let files = vec![("src/defs.rs".to_string(), defs.to_string()), ("src/user.rs".to_string(), user.to_string())];
+
+        // Failing ensures on line 8
+        let ctx = context_for_error_core(&files, "caller", 8).unwrap();
+        assert_eq!(&*ctx.function.file_path, "src/user.rs");
+        assert_eq!(ctx.clause_kind.as_deref(), Some("ensures"));
+        assert!(ctx.clause.as_deref().unwrap().contains("is_even"));
+        let specs: Vec<&str> = ctx.spec_functions.iter().map(|d| d.specs.name.as_str()).collect();
+        assert_eq!(specs, vec!["is_even", "double"]);
+        assert!(ctx.spec_functions[0].source.contains("n % 2 == 0"));
+        let lemmas: Vec<&str> = ctx.related_lemmas.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(lemmas, vec!["lemma_double_even"]);
+
+        // Failing assert: no spec functions involved
+        let ctx = context_for_error_core(&files, "caller", 10).unwrap();
+        assert_eq!(ctx.clause_kind.as_deref(), Some("assert"));
+        assert!(ctx.spec_functions.is_empty() && ctx.related_lemmas.is_empty());
+
+        // A line outside any clause still resolves the function
+        let ctx = context_for_error_core(&files, "caller", 4).unwrap();
+        assert_eq!(ctx.clause, None);
+        assert!(context_for_error_core(&files, "missing", 1).is_err());
+    }
+
+    #[test]
+    fn test_truncation_report() {
+        let code = r#"
+verus! {
+
+fn low_byte(x: u64) -> u8 {
+    #[verifier::truncate] (x as u8)
+}
+
+fn widen(x: u32) -> u64 {
+    let y: u16 = 7;
+    (x as u64) + (y as u64) + (x as usize as u64)
+}
+
+proof fn ghost_cast(x: int) {
+    let y = x as u8;
+}
+
+}
+"#;
+        let files = vec![("src/lib.rs".to_string(), code.to_string())];
+        let report = truncation_report_core(&files).unwrap();
+        let casts: Vec<String> = report["src/lib.rs"]
+            .iter()
+            .map(|c| {
+                let from = c.cast.from_ty.as_deref().unwrap_or("?");
+                format!("{}:{} {} -> {} {}", c.function, c.cast.line_number.unwrap(), from, c.cast.to_ty, c.cast.kind)
+            })
+            .collect();
+        assert_eq!(
+            casts,
+            vec![
+                "low_byte:5 u64 -> u8 truncate",
+                "widen:10 u32 -> u64 widening",
+                "widen:10 u16 -> u64 widening",
+                "widen:10 usize -> u64 widening",
+                "widen:10 u32 -> usize widening",
+            ]
+        );
+        assert_eq!(cast_kind(Some("u64"), "usize"), "narrowing");
+        assert_eq!(cast_kind(Some("i32"), "u64"), "narrowing");
+        assert_eq!(cast_kind(None, "u8"), "unknown");
+    }
+
+    #[test]
+    fn test_lemma_fan_in() {
+        let lemmas = r#"
+verus! {
+
+pub proof fn lemma_popular(x: int)
+    ensures x == x,
+{
+}
+
+pub proof fn lemma_rare(x: int)
+    ensures x + 0 == x,
+{
+    lemma_popular(x);
+}
+
+pub broadcast proof fn lemma_broadcast(x: int)
+    ensures #[trigger] (x * 1) == x,
+{
+}
+
+}
+"#;
+        let users = r#"
+use crate::lemmas::lemma_popular as popular;
+
+verus! {
+
+proof fn first(x: int) {
+    popular(x);
+    popular(x + 1);
+}
+
+proof fn second(x: int) {
+    crate::lemmas::lemma_popular(x);
+    lemma_rare(x);
+}
+
+}
+"#;
+        let files = vec![("src/lemmas.rs".to_string(), lemmas.to_string()), ("src/users.rs".to_string(), users.to_string())];
+        let ranked = lemma_fan_in_core(&files).unwrap();
+        let counts: Vec<(&str, usize)> = ranked.iter().map(|(f, count)| (f.name.as_str(), *count)).collect();
+        assert_eq!(
+            counts,
+            vec![("lemma_popular", 3), ("lemma_rare", 1), ("first", 0), ("lemma_broadcast", 0), ("second", 0)]
+        );
+        assert!(ranked.iter().all(|(f, _)| f.is_broadcast == (f.name == "lemma_broadcast")));
+    }
+
+    #[test]
+    fn test_duplicate_definitions() {
+        let code = r#"
+fn twice() {}
+
+#[cfg(feature = "alloc")]
+fn gated() {}
+
+#[cfg(not(feature = "alloc"))]
+fn gated() {}
+
+mod a {
+    fn same() {}
+}
+
+mod b {
+    fn same() {}
+}
+
+verus! {
+
+proof fn twice() {}
+
+struct S {}
+
+impl S {
+    fn same(&self) {}
+}
+
+}
+"#;
+        let groups = duplicate_definitions_core(code).unwrap();
+        let lines: Vec<Vec<Option<usize>>> =
+            groups.iter().map(|group| group.iter().map(|f| f.line_number).collect()).collect();
+        assert_eq!(lines, vec![vec![Some(2), Some(20)]]);
+        assert_eq!(groups[0][0].name, "twice");
+        assert_eq!(groups[0][0].cfgs, Vec::<String>::new());
+
+        let functions = parse_verus_file_core(code).unwrap();
+        assert_eq!(functions[2].cfgs, vec!["not(feature = \"alloc\")".to_string()]);
+    }
+
+    #[test]
+    fn test_spec_literals() {
+        let code = r#"
+verus! {
+
+pub fn high_bit(b: u8) -> (r: bool)
+    ensures r == (b >= 0x80u8),
+{
+    b >= 0x80
+}
+
+proof fn lemma_bound(x: int)
+    requires 0 <= x < 128,
+    ensures x / 2 < 64, x <= 1_000int,
+{
+}
+
+}
+"#;
+        let files = vec![("src/lib.rs".to_string(), code.to_string())];
+        let literals = spec_literals_core(&files, false).unwrap();
+        let keys: Vec<&str> = literals.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["0", "0x80", "1000", "128", "2", "64"]);
+        let hex = &literals["0x80"][0];
+        assert_eq!(
+            (hex.function.as_str(), hex.clause_kind.as_str(), hex.text.as_str()),
+            ("high_bit", "ensures", "0x80u8")
+        );
+        assert_eq!(hex.line_number, 5);
+        assert_eq!(literals["1000"][0].text, "1_000int");
+
+        let normalized = spec_literals_core(&files, true).unwrap();
+        let uses: Vec<&str> = normalized["128"].iter().map(|u| u.text.as_str()).collect();
+        assert_eq!(uses, vec!["0x80u8", "128"]);
+    }
+
+    #[test]
+    fn test_specs_outside_verus() {
+        let names = |funcs: Vec<FunctionSpecs>| funcs.into_iter().map(|f| f.name).collect::<Vec<_>>();
+        assert_eq!(names(specs_outside_verus_core(SAMPLE_VERUS).unwrap().0), vec!["lemma_mul_inequality", "exec_add"]);
+
+        let wrapped = format!("verus! {{\n{}\n}}\n\nfn plain() {{}}\n", SAMPLE_VERUS);
+        assert!(specs_outside_verus_core(&wrapped).unwrap().0.is_empty());
+        let funcs = parse_verus_file_core(&wrapped).unwrap();
+        assert_eq!(funcs.iter().map(|f| f.in_verus_block).collect::<Vec<_>>(), vec![true, true, false]);
+
+        // The parallel path keeps the flag for items split out of the verus! body
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        let funcs = parse_limited(&wrapped, None, &parallel).unwrap();
+        assert_eq!(funcs.iter().map(|f| f.in_verus_block).collect::<Vec<_>>(), vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_module_tree() {
+        let code = r#"
+//! Root docs
+fn top() {}
+
+mod arith {
+    //! Arithmetic
+    pub struct Scalar;
+
+    impl Scalar {
+        verus! {
+            pub proof fn lemma_a()
+                ensures true,
+            {
+            }
+
+            pub open spec fn double(x: int) -> int { 2 * x }
+        }
+
+        fn plain(&self) {
+            fn nested() {}
+        }
+    }
+
+    pub fn helper() {}
+
+    mod empty {}
+}
+
+verus! {
+mod inner {
+    proof fn lemma_b() {}
+}
+}
+"#;
+        let tree = module_tree_core(code).unwrap();
+        let names = |fs: &[FunctionSummary]| fs.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(tree.name, "crate");
+        assert_eq!(tree.doc.as_deref(), Some("Root docs"));
+        assert_eq!(names(&tree.functions), vec!["top"]);
+        assert!(tree.impls.is_empty());
+        assert_eq!(tree.modules.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["arith", "inner"]);
+
+        let arith = &tree.modules[0];
+        assert_eq!(arith.doc.as_deref(), Some("Arithmetic"));
+        assert_eq!(names(&arith.functions), vec!["helper"]);
+        assert_eq!(arith.impls.len(), 1);
+        assert_eq!(arith.impls[0].name, "impl Scalar");
+        assert_eq!(names(&arith.impls[0].functions), vec!["lemma_a", "double", "plain", "nested"]);
+        let modes: Vec<&str> = arith.impls[0].functions.iter().map(|f| f.mode.as_str()).collect();
+        assert_eq!(modes, vec!["proof", "spec", "exec", "exec"]);
+        assert_eq!(arith.impls[0].functions[0].line_number, Some(11));
+        assert_eq!(arith.modules.len(), 1);
+        assert_eq!(arith.modules[0].name, "empty");
+        assert!(arith.modules[0].functions.is_empty());
+
+        let inner = &tree.modules[1];
+        assert_eq!(names(&inner.functions), vec!["lemma_b"]);
+        assert_eq!(inner.functions[0].mode, "proof");
+    }
+
+    #[test]
+    fn test_applicable_lemmas() {
+        let code = format!(
+            "{}{}",
+            SAMPLE_VERUS,
+            r#"
+pub proof fn lemma_mul_negative(x: int, y: int, z: int)
+    requires
+        x <= y,
+        z < 0,
+    ensures
+        x * z >= y * z,
+{
+}
+
+pub proof fn lemma_square_nonneg(a: int)
+    ensures
+        a * a >= 0,
+{
+}
+
+pub proof fn lemma_antisym(a: int, b: int)
+    requires
+        a <= b,
+        b <= a,
+    ensures
+        a == b,
+{
+}
+
+pub proof fn lemma_le_refl(a: int, b: int)
+    requires
+        a <= b,
+    ensures
+        b >= a,
+{
+}
+"#
+        );
+        let facts = parse_facts(&["x <= y".to_string(), "z > 0".to_string()]).unwrap();
+        let applicable = applicable_lemmas_core(&code, &facts, false, None).unwrap();
+        let names: Vec<&str> = applicable.iter().map(|(f, _, _)| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_mul_inequality", "lemma_le_refl", "lemma_square_nonneg"]);
+
+        let bindings = &applicable[0].1;
+        let expected: BTreeMap<String, String> =
+            [("x", "x"), ("y", "y"), ("z", "z")].iter().map(|(p, v)| (p.to_string(), v.to_string())).collect();
+        assert_eq!(*bindings, expected);
+        assert_eq!(applicable[1].1.get("a").map(String::as_str), Some("x"));
+        assert!(applicable[2].1.is_empty());
+
+        // Bindings must be consistent across the whole lemma: with a = x and
+        // b = y, `b <= a` needs `y <= x`
+        assert!(!names.contains(&"lemma_antisym"));
+        let facts = parse_facts(&["x <= y".to_string(), "y <= x".to_string()]).unwrap();
+        let applicable = applicable_lemmas_core(&code, &facts, false, None).unwrap();
+        assert!(applicable.iter().any(|(f, _, _)| f.name == "lemma_antisym"));
+    }
+
+    #[test]
+    fn test_ensures_unfolding() {
+        let specs = r#"
+verus! {
+pub open spec fn outer(x: int) -> int {
+    inner(x) * 2
+}
+
+spec fn inner(x: int) -> int {
+    hidden(x) + outer_const()
+}
+
+spec fn outer_const() -> int {
+    1
+}
+
+pub closed spec fn hidden(x: int) -> int {
+    secret(x)
+}
+
+spec fn secret(x: int) -> int {
+    x
+}
+}
+"#;
+        let lemmas = r#"
+verus! {
+proof fn lemma_outer(x: int)
+    requires
+        x >= 0,
+    ensures
+        outer(x) >= vstd::math::abs(x),
+{
+}
+}
+"#;
+        let files = vec![
+            ("src/specs.rs".to_string(), specs.to_string()),
+            ("src/lemmas.rs".to_string(), lemmas.to_string()),
+        ];
+        let unfolding = ensures_unfolding_core(&files, "lemma_outer").unwrap();
+        assert_eq!(unfolding.lemma.name, "lemma_outer");
+        assert_eq!(&*unfolding.lemma.file_path, "src/lemmas.rs");
+        let closure: Vec<(&str, usize, &str)> =
+            unfolding.closure.iter().map(|u| (u.name.as_str(), u.depth, u.opacity.as_str())).collect();
+        // Unfolding stops at the closed fn: `secret` is never reached
+        assert_eq!(
+            closure,
+            vec![("outer", 1, "open"), ("inner", 2, "open"), ("hidden", 3, "closed"), ("outer_const", 3, "open")]
+        );
+        assert_eq!(unfolding.max_depth, 3);
+        assert_eq!(unfolding.closure[0].file_path, "src/specs.rs");
+        assert_eq!(unfolding.closure[0].line_number, 3);
+
+        assert!(ensures_unfolding_core(&files, "lemma_missing").is_err());
+    }
+
+    #[test]
+    fn test_lint_spec_exprs() {
+        let code = r#"
+verus! {
+fn copy_all(v: &Vec<u64>) -> (r: Vec<u64>)
+    requires
+        v.len() < 100,
+    ensures
+        r@ == v@,
+        r == v.clone(),
+        r.len() == vec![1u64].len(),
+        r@ == seq![1u64],
+{
+    v.clone()
+}
+
+fn pop_last(v: &mut Vec<u64>) -> (r: u64)
+    ensures
+        r == last_of(&mut v),
+        (r += 1) > 0,
+{
+    v.pop().unwrap()
+}
+}
+"#;
+        let (issues, _) = lint_spec_exprs_core(code).unwrap();
+        let found: Vec<(&str, &str, &str)> =
+            issues.iter().map(|i| (i.function.as_str(), i.kind.as_str(), i.expr.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("copy_all", "clone", "v . clone ()"),
+                ("copy_all", "macro", "vec ! [1u64]"),
+                ("pop_last", "mut_borrow", "& mut v"),
+                ("pop_last", "assignment", "r += 1"),
+            ]
+        );
+        assert_eq!(issues[0].clause_kind, "ensures");
+        assert_eq!(issues[0].clause, "r == v . clone ()");
+        assert_eq!((issues[0].line_number, issues[0].column), (8, 13));
+    }
+
+    #[test]
+    fn test_draft_lemma_from_assert() {
+        let code = r#"
+verus! {
+fn sum_bounded(a: u64, b: &u64, c: u64, v: &mut Vec<u64>)
+    requires
+        a < 100,
+        *b < 100,
+        c > 0,
+        a + c < 200,
+        old(v).len() > 0,
+{
+    let t = a + *b;
+    assert(a + *b < 200 && t >= 0);
+}
+}
+"#;
+        let draft = draft_lemma_from_assert_core(code, "sum_bounded", "assert(a + *b < 200 && t >= 0)").unwrap();
+        assert_eq!(
+            draft,
+            "// NOTE: type of `t` unknown, assumed int\n\
+             proof fn lemma_add(a: u64, b: &u64, t: int)\n    \
+             requires\n        a < 100,\n        * b < 100,\n    \
+             ensures\n        a + * b < 200 && t >= 0,\n{\n}\n"
+        );
+
+        let draft = draft_lemma_from_assert_core(code, "sum_bounded", "a * c > 0").unwrap();
+        assert_eq!(
+            draft,
+            "proof fn lemma_mul_gt(a: u64, c: u64)\n    \
+             requires\n        a < 100,\n        c > 0,\n        a + c < 200,\n    \
+             ensures\n        a * c > 0,\n{\n}\n"
+        );
+        assert!(draft_lemma_from_assert_core(code, "missing", "a > 0").is_err());
+    }
+
+    #[test]
+    fn test_invariant_usage() {
+        let code = r#"
+verus! {
+    fn bump(inv: &LocalInvariant<(), u32, Pred>, outer: &vstd::invariant::AtomicInvariant<(), u8, Pred>) {
+        open_local_invariant!(inv => inner => {
+            open_atomic_invariant!(outer => o => {
+                inner = inner + 1;
+            });
+        });
+    }
+
+    fn make(x: u32) -> (r: Tracked<LocalInvariant<(), u32, Pred>>) {
+        let i = LocalInvariant::new((), x, 0);
+        Tracked(i)
+    }
+
+    fn unrelated(v: Vec<u32>) {}
+}
+"#;
+        let usages = invariant_usage_core(code).unwrap();
+        let summary: Vec<(&str, &str, bool, usize, usize)> = usages
+            .iter()
+            .map(|u| (u.function.as_str(), u.kind.as_str(), u.atomic, u.line_number, u.end_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("bump", "signature", false, 3, 3),
+                ("bump", "signature", true, 3, 3),
+                ("bump", "open", false, 4, 8),
+                ("bump", "open", true, 5, 7),
+                ("make", "signature", false, 11, 11),
+                ("make", "create", false, 12, 12),
+            ]
+        );
+        assert_eq!(usages[1].expr, "vstd::invariant::AtomicInvariant<(), u8, Pred>");
+        assert_eq!(usages[2].expr, "inv");
+        assert_eq!(usages[2].binding.as_deref(), Some("inner"));
+        assert_eq!(usages[3].binding.as_deref(), Some("o"));
+    }
+
+    #[test]
+    fn test_lint_trivial_lemmas() {
+        let code = r#"
+verus! {
+    proof fn lemma_tautology(s: Seq<int>, n: int)
+        requires
+            n > 0 && forall|i: int| 0 <= i < s.len() ==> s[i] >= n,
+        ensures
+            true,
+            1 + 1 == 2,
+            forall|j: int| 0 <= j < s.len() ==> s[j] >= n,
+    {
+    }
+
+    proof fn lemma_positive(s: Seq<int>, n: int)
+        requires
+            n > 0 && forall|i: int| 0 <= i < s.len() ==> s[i] >= n,
+        ensures
+            forall|j: int| 0 <= j < s.len() ==> s[j] > 0,
+    {
+    }
+
+    proof fn lemma_no_ensures() {}
+
+    fn exec_true() ensures true, {}
+}
+"#;
+        let names: Vec<String> = lint_trivial_lemmas_core(code).unwrap().0.into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["lemma_tautology"]);
+
+        // Renaming is positional: a swapped pair of binders is a different statement
+        let swapped = "verus! { proof fn l(f: spec_fn(int, int) -> bool) \
+            requires forall|a: int, b: int| f(a, b), ensures forall|b: int, a: int| f(a, b), {} }";
+        assert!(lint_trivial_lemmas_core(swapped).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let caps = capabilities_core();
+        assert_eq!(caps.version, env!("CARGO_PKG_VERSION"));
+        assert!(!caps.verus_syn_version.is_empty());
+        assert_ne!(caps.verus_syn_version, "unknown");
+        assert_eq!(caps.specs_format_version, SPECS_FORMAT_VERSION);
+        assert_eq!(caps.features.len(), GRAMMAR_PROBES.len());
+        assert!(GRAMMAR_PROBES.iter().all(|(name, _)| caps.features.contains_key(*name)));
+        assert!(caps.features["spec_fn"]);
+        assert!(caps.features["named_return"]);
+        assert!(caps.features["quantifiers"]);
+    }
+
+    #[test]
+    fn test_siblings_same_impl_block() {
+        let code = r#"
+verus! {
+    impl Scalar {
+        pub fn add(&self, other: &Scalar) -> Scalar { *self }
+        proof fn lemma_add_comm(a: Scalar, b: Scalar) ensures a.add(&b) == b.add(&a) {}
+    }
+
+    impl Scalar {
+        pub fn mul(&self, other: &Scalar) -> Scalar { *self }
+        proof fn lemma_mul_comm(a: Scalar, b: Scalar) ensures a.mul(&b) == b.mul(&a) {}
+    }
+
+    fn free_a() {}
+    fn free_b() {}
+
+    mod inner {
+        fn free_c() {}
+    }
+}
+"#;
+        let names = |name: &str| -> Vec<String> {
+            siblings_core(code, name).unwrap().into_iter().map(|f| f.name).collect()
+        };
+        assert_eq!(names("add"), vec!["lemma_add_comm"]);
+        assert_eq!(names("lemma_mul_comm"), vec!["mul"]);
+        assert_eq!(names("free_a"), vec!["free_b"]);
+        assert!(names("free_c").is_empty());
+        assert!(siblings_core(code, "missing").unwrap_err().contains("not found"));
+    }
+
+    #[test]
+    fn test_unknown_verifier_attributes_preserved() {
+        let code = r#"
+verus! {
+    #[verifier::hypothetical(42, "x  y")]
+    #[verifier::rlimit(20)]
+    #[verifier(external_body)]
+    #[verifier::opaque]
+    #[inline]
+    spec fn future() -> int { 0 }
+}
+"#;
+        let f = &parse_verus_file_core(code).unwrap()[0];
+        assert_eq!(
+            f.verifier_attributes,
+            vec![
+                ("verifier::hypothetical".to_string(), Some("42, \"x  y\"".to_string())),
+                ("verifier::rlimit".to_string(), Some("20".to_string())),
+                ("verifier::external_body".to_string(), None),
+                ("verifier::opaque".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            f.attributes,
+            vec![
+                "verifier::hypothetical(42, \"x  y\")",
+                "verifier::rlimit(20)",
+                "verifier::external_body",
+                "verifier::opaque",
+            ]
+        );
+
+        let bytes = encode_specs(std::slice::from_ref(f)).unwrap();
+        assert_eq!(decode_specs(&bytes).unwrap()[0].verifier_attributes, f.verifier_attributes);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let code = r#"
+verus! {
+    proof fn lemma_mul_inequality(x: int, y: int, z: int)
+        requires
+            x <= y,
+            z > 0,
+        ensures
+            x * z <= y * z,
+    {
+    }
+
+    proof fn lemma_add_inequality(x: int, y: int, z: int)
+        requires
+            x <= y,
+        ensures
+            x + z <= y + z,
+    {
+    }
+
+    proof fn lemma_forall(s: Seq<int>, c: int)
+        ensures
+            forall|i: int| 0 <= i < s.len() ==> s[i] * c <= s[i] * c,
+    {
+    }
+}
+"#;
+        let found = find_pattern_core(code, "?a * ?c <= ?b * ?c", false).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].function, "lemma_mul_inequality");
+        assert_eq!(found[0].clause_kind, "ensures");
+        let expected: BTreeMap<String, String> =
+            [("a", "x"), ("b", "y"), ("c", "z")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert_eq!(found[0].bindings, expected);
+        // Found inside the quantifier's body
+        assert_eq!(found[1].function, "lemma_forall");
+        assert_eq!(found[1].expr, "s [i] * c <= s [i] * c");
+
+        // Holes bind consistently: `?c` cannot be both z and a different expression
+        assert!(find_pattern_core(code, "?a * ?a <= _", false).unwrap().is_empty());
+        let anonymous = find_pattern_core(code, "_ * _ <= _ * _", false).unwrap();
+        assert_eq!(anonymous.len(), 2);
+        assert!(anonymous[0].bindings.is_empty());
+        assert!(anonymous.iter().all(|m| m.function != "lemma_add_inequality"));
+        // Whole requires clauses match too
+        let requires = find_pattern_core(code, "?lo <= ?hi", false).unwrap();
+        assert!(requires.iter().any(|m| m.function == "lemma_add_inequality" && m.clause_kind == "requires"));
+
+        assert!(find_pattern_core(code, "_ * ", false).unwrap_err().starts_with("Invalid pattern"));
+    }
+
+    #[test]
+    fn test_spec_diff_modified_clause() {
+        let old = r#"
+verus! {
+    proof fn lemma_bound(x: int, y: int)
+        requires
+            x >= 0,
+        ensures
+            x * y <= x * y + x,
+            y == y,
+    {
+    }
+
+    proof fn lemma_gone() ensures true, {}
+}
+"#;
+        let new = r#"
+verus! {
+    proof fn lemma_bound(x: int, y: int)
+        requires
+            x >= 0,
+        ensures
+            x * y < x * y + x,
+            y == y,
+    {
+    }
+
+    proof fn lemma_new(x: int) requires x > 0, ensures x * x > 0, {}
+}
+"#;
+        let diffs = spec_diff_core(old, new).unwrap();
+        assert_eq!(diffs.iter().map(|d| (d.function.as_str(), d.status.as_str())).collect::<Vec<_>>(), vec![
+            ("lemma_bound", "modified"),
+            ("lemma_new", "added"),
+            ("lemma_gone", "removed"),
+        ]);
+        let changes = &diffs[0].changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change, "modified");
+        assert_eq!(changes[0].clause_kind, "ensures");
+        let edited: Vec<(&str, &str)> =
+            changes[0].tokens.iter().filter(|t| t.op != "equal").map(|t| (t.op.as_str(), t.text.as_str())).collect();
+        assert_eq!(edited, vec![("removed", "<="), ("added", "<")]);
+        assert_eq!(changes[0].snippet, "x * y [-<=-] {+<+} x * y + x");
+        assert_eq!(diffs[1].changes.iter().map(|c| c.change.as_str()).collect::<Vec<_>>(), vec!["added", "added"]);
+
+        // Unrelated clauses are not paired up
+        let swapped = spec_diff_core(
+            "verus! { proof fn l(a: int) ensures a == a, {} }",
+            "verus! { proof fn l(a: int) ensures forall|s: Seq<int>| s.len() >= 0, {} }",
+        )
+        .unwrap();
+        let kinds: Vec<&str> = swapped[0].changes.iter().map(|c| c.change.as_str()).collect();
+        assert_eq!(kinds, vec!["removed", "added"]);
+        assert!(spec_diff_core(old, old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_directories() {
+        let (old_dir, new_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for root in [old_dir.path(), new_dir.path()] {
+            std::fs::create_dir_all(root.join("src")).unwrap();
+            std::fs::write(root.join("src/same.rs"), "verus! { proof fn lemma_same() ensures true, {} }").unwrap();
+        }
+        std::fs::write(
+            old_dir.path().join("src/lib.rs"),
+            r#"
+verus! {
+    proof fn lemma_old_name(x: int) requires x > 0, ensures x * x > 0, {}
+    proof fn lemma_bound(x: int) ensures x <= x + 1, {}
+    proof fn lemma_dropped() ensures true, {}
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            new_dir.path().join("src/lib.rs"),
+            r#"
+verus! {
+    proof fn lemma_bound(x: int) ensures x < x + 1, {}
+    proof fn lemma_new_name(x: int) requires x > 0, ensures x * x > 0, {}
+    proof fn lemma_added(y: int) ensures y == y, {}
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(old_dir.path().join("src/gone.rs"), "verus! { proof fn lemma_gone() {} }").unwrap();
+
+        let old = read_verus_tree(old_dir.path()).unwrap();
+        assert_eq!(old.keys().collect::<Vec<_>>(), vec!["src/gone.rs", "src/lib.rs", "src/same.rs"]);
+        let diff = diff_directories_core(&old, &read_verus_tree(new_dir.path()).unwrap()).unwrap();
+        assert_eq!(diff.removed_files, vec!["src/gone.rs"]);
+        assert!(diff.added_files.is_empty());
+        let files: Vec<(&str, &str)> = diff.files.iter().map(|f| (f.path.as_str(), f.status.as_str())).collect();
+        assert_eq!(files, vec![("src/gone.rs", "removed"), ("src/lib.rs", "modified")]);
+        assert_eq!(diff.files[0].removed, vec!["lemma_gone"]);
+
+        let lib = &diff.files[1];
+        assert_eq!(lib.renamed, vec![("lemma_old_name".to_string(), "lemma_new_name".to_string())]);
+        assert_eq!(lib.added, vec!["lemma_added"]);
+        assert_eq!(lib.removed, vec!["lemma_dropped"]);
+        assert_eq!(lib.modified.len(), 1);
+        assert_eq!(lib.modified[0].function, "lemma_bound");
+        assert_eq!(lib.modified[0].changes[0].snippet, "x [-<=-] {+<+} x + 1");
+
+        let summary = DirectoryDiffSummary {
+            files_removed: 1,
+            files_modified: 1,
+            functions_added: 1,
+            functions_removed: 2,
+            functions_renamed: 1,
+            functions_modified: 1,
+            ..Default::default()
+        };
+        assert_eq!(diff.summary, summary);
+    }
+
+    #[test]
+    fn test_file_attributes() {
+        let code = r#"
+#![allow(unused_imports)]
+#![cfg_attr(verus_keep_ghost, verifier::exec_allows_no_decreases_clause)]
+#![no_std]
+
+verus! {
+global size_of usize == 8;
+
+spec fn f(x: int) -> int { x }
+
+#[verifier::external_body]
+global layout S is size == 8, align == 4;
+}
+"#;
+        let attributes = file_attributes_core(code).unwrap();
+        assert_eq!(
+            attributes,
+            vec![
+                "#![allow(unused_imports)]",
+                "#![cfg_attr(verus_keep_ghost, verifier::exec_allows_no_decreases_clause)]",
+                "#![no_std]",
+                "global size_of usize == 8;",
+                "global layout S is size == 8, align == 4;",
+            ]
+        );
+        // `verus_macro_names` is a parse option only
+        assert!(file_attributes_core("my_verus! { global size_of usize == 8; }").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lemmas_constraining_field() {
+        let code = r#"
+struct Scalar {}
+
+impl Scalar {
+    verus! {
+        pub fn from_bytes_mod_order(bytes: [u8; 32]) -> (result: Scalar)
+            ensures 
+                result.bytes[31] & 0x80 == 0,
+        {
+            Scalar {}
+        }
+
+        proof fn lemma_len(&self, v: Vec<u8>)
+            requires old(self).bytes.len() == 32,
+            ensures self.len() == v.view().len(), forall|v: Scalar| v.bytes[0] == 0,
+        {
+        }
+    }
+}
+"#;
+        let files = vec![(String::new(), code.to_string())];
+        let found = lemmas_constraining_field_core(&files, "Scalar", "bytes").unwrap();
+        let names: Vec<&str> = found.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["from_bytes_mod_order"]);
+        assert!(lemmas_constraining_field_core(&files, "Scalar", "len").unwrap().is_empty());
+
+        let paths = clause_field_paths_core(code, "from_bytes_mod_order").unwrap();
+        assert_eq!(paths[0].fields, vec!["result.bytes"]);
+        let paths = clause_field_paths_core(code, "lemma_len").unwrap();
+        assert_eq!(paths[0].clause_kind, "requires");
+        assert_eq!(paths[0].fields, vec!["self.bytes"]);
+        assert_eq!(paths[0].method_calls, vec!["self.bytes.len()"]);
+        // Method calls are not fields, and the quantified `v` shadows the parameter
+        assert!(paths[1].fields.is_empty());
+        assert_eq!(paths[1].method_calls, vec!["self.len()", "v.view().len()"]);
+        assert!(paths[2].fields.is_empty());
+    }
+
+    #[test]
+    fn test_extract_doc_examples() {
+        let code = r#"
+verus! {
+/// Multiplication by a positive number is monotonic.
+///
+/// ```rust
+/// # use vstd::prelude::*;
+/// proof fn use_it(a: int, b: int) requires a <= b {
+///     lemma_mul_le(a, b, 3);
+/// }
+/// ```
+///
+/// ```
+/// lemma_mul_le(a, b
+/// ```
+///
+/// ```text
+/// not code
+/// ```
+proof fn lemma_mul_le(a: int, b: int, c: nat)
+    requires a <= b,
+    ensures a * c <= b * c,
+{
+}
+}
+"#;
+        let examples = extract_doc_examples_core(code).unwrap();
+        assert_eq!(examples.len(), 2);
+        assert!(examples.iter().all(|e| e.function == "lemma_mul_le"));
+        assert!(examples[0].parsed);
+        assert!(examples[0].error.is_none());
+        assert!(examples[0].code.starts_with("use vstd::prelude::*;\nproof fn use_it"));
+        assert_eq!(examples[0].calls, vec!["lemma_mul_le"]);
+        assert_eq!(examples[0].line_number, 6);
+        assert!(!examples[1].parsed);
+        assert!(examples[1].error.as_deref().is_some_and(|e| e.starts_with("Parse error")));
+        assert_eq!(examples[1].code, "lemma_mul_le(a, b");
+        assert_eq!(examples[1].line_number, 13);
+
+        // Statements parse as a function body
+        let statements = "/// ```\n/// assert(x == x) by { lemma(x); }\n/// ```\nfn f() {}\n";
+        let examples = extract_doc_examples_core(statements).unwrap();
+        assert!(examples[0].parsed);
+        assert_eq!(examples[0].calls, vec!["lemma"]);
+    }
+
+    #[test]
+    fn test_lint_suppressions() {
+        let code = r#"
+verus! {
+proof fn lemma_used() ensures true {}
+
+// verus-lemma-finder: allow(unused_lemma)
+/// Kept for downstream crates.
+proof fn lemma_kept(x: int) ensures x + 0 == x {}
+
+proof fn lemma_dead(x: int) ensures x * 1 == x {}
+
+#[cfg_attr(any(), allow(trivial_lemma, unused_lemma, not_a_lint))]
+proof fn lemma_silenced() ensures 1 + 1 == 2 {}
+
+fn main() {
+    proof { lemma_used(); }
+}
+}
+"#;
+        let files = vec![(String::new(), code.to_string())];
+        let (unused, suppressed) = find_unused_lemmas_core(&files).unwrap();
+        let names: Vec<&str> = unused.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_dead"]);
+        assert_eq!(suppressed, 2);
+
+        let (trivial, suppressed) = lint_trivial_lemmas_core(code).unwrap();
+        let names: Vec<&str> = trivial.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_used"]);
+        assert_eq!(suppressed, 1);
+
+        let functions = parse_verus_file_core(code).unwrap();
+        let suppressed = |name: &str| functions.iter().find(|f| f.name == name).unwrap().suppressed.clone();
+        assert_eq!(suppressed("lemma_kept"), vec!["unused_lemma"]);
+        assert_eq!(suppressed("lemma_silenced"), vec!["trivial_lemma", "unused_lemma"]);
+        assert!(suppressed("lemma_dead").is_empty());
+        assert!(Lint::ALL.iter().all(|lint| Lint::from_name(lint.name()) == Some(*lint)));
+
+        let counts = suppressed_findings_core(&files).unwrap();
+        assert_eq!(counts.len(), Lint::ALL.len());
+        assert_eq!((counts["unused_lemma"], counts["trivial_lemma"], counts["refinement"]), (2, 1, 0));
+    }
+
+    #[test]
+    fn test_establishes_predicate() {
+        let code = r#"
+verus! {
+spec fn inv(s: State) -> bool { true }
+
+impl State {
+    fn new() -> (result: State)
+        ensures inv(result),
+    { State {} }
+
+    fn step(&mut self, n: u64)
+        requires inv(*old(self)), n > 0,
+        ensures self.wf(), old(self).counter < n,
+    { }
+
+    fn check(&self)
+        requires inv(self),
+        ensures true,
+    { }
+
+    fn copy(&self) -> (r: State)
+        ensures r == *self, Self::inv(r) && r.wf(),
+    { *self }
+}
+}
+"#;
+        let files = vec![(String::new(), code.to_string())];
+        let names = |predicate: &str| -> Vec<String> {
+            establishes_predicate_core(&files, predicate).unwrap().into_iter().map(|f| f.name).collect()
+        };
+        assert_eq!(names("inv"), vec!["new", "copy"]);
+        assert_eq!(names("Self::inv"), vec!["copy"]);
+        assert_eq!(names("wf"), vec!["step", "copy"]);
+
+        let calls = predicate_calls_core(code, "step").unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].clause_kind, "requires");
+        assert_eq!(calls[0].args, vec![("complex".to_string(), "*old(self)".to_string())]);
+        assert!(calls[1].is_method);
+        assert_eq!(calls[1].args, vec![("param".to_string(), "self".to_string())]);
+        let calls = predicate_calls_core(code, "copy").unwrap();
+        let callees: Vec<&str> = calls.iter().map(|c| c.callee.as_str()).collect();
+        assert_eq!(callees, vec!["Self::inv", "wf"]);
+        assert_eq!(calls[0].args[0].0, "result");
+    }
+
+    #[test]
+    fn test_explain_bundle() {
+        let specs = r#"
+verus! {
+pub open spec fn is_even(n: int) -> bool {
+    n % 2 == 0
+}
+
+pub proof fn lemma_helper(n: int) {
+}
+
+/// Doubling gives an even number.
+pub proof fn lemma_double_even(n: int)
+    ensures is_even(2 * n),
+{
+    lemma_helper(n);
+}
+}
+"#;
+        let user = r#"
+verus! {
+proof fn use_it(k: int) {
+    crate::specs::lemma_double_even(k);
+}
+}
+"#;
+        let files = vec![("specs.rs".to_string(), specs.to_string()), ("user.rs".to_string(), user.to_string())];
+        let bundles = explain_core(&files, "lemma_double_even").unwrap();
+        assert_eq!(bundles.len(), 1);
+        let bundle = &bundles[0];
+        assert_eq!(bundle.doc, "Doubling gives an even number.");
+        assert!(bundle.source.contains("lemma_helper(n);"));
+        assert_eq!(bundle.callers.len(), 1);
+        assert_eq!((bundle.callers[0].caller.as_str(), bundle.callers[0].file_path.as_str()), ("use_it", "user.rs"));
+        assert_eq!(bundle.callees, vec!["lemma_helper"]);
+        assert_eq!(bundle.siblings, vec!["is_even", "lemma_helper"]);
+        assert_eq!(bundle.spec_functions.len(), 1);
+        assert_eq!(bundle.spec_functions[0].specs.name, "is_even");
+        assert!(bundle.spec_functions[0].source.contains("n % 2 == 0"));
+        assert!(explain_core(&files, "lemma_missing").unwrap().is_empty());
+
+        // A file that does not parse is left out
+        let mut with_broken = files.clone();
+        with_broken.insert(0, ("broken.rs".to_string(), "fn broken( {".to_string()));
+        assert_eq!(explain_core(&with_broken, "lemma_double_even").unwrap(), bundles);
+        assert!(explain_core(&with_broken[..1], "lemma_double_even").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_todo_markers_attribution() {
+        let content = r#"
+fn outside(x: u64) -> u64 {
+    // FIXME: overflow
+    x
+}
+
+// TODO(proof): add a lemma for the inverse
+
+verus! {
+proof fn lemma_in_verus(x: int)
+    ensures x + 0 == x,
+{
+    /* TODO(proof): strengthen
+       ensures */
+    let s = "TODO inside a string";
+}
+}
+"#;
+        let todos = proof_todos_core(&[("a.rs".to_string(), content.to_string())], None).unwrap();
+        let found: Vec<(usize, Option<&str>)> = todos.iter().map(|t| (t.line_number, t.function.as_deref())).collect();
+        assert_eq!(found, vec![(3, Some("outside")), (7, None), (13, Some("lemma_in_verus"))]);
+        assert_eq!(todos[1].text, "TODO(proof): add a lemma for the inverse");
+
+        for parallel_threshold in [None, Some(0)] {
+            let options = ParseOptions { parallel_threshold, ..Default::default() };
+            let (functions, _) = parse_guarded_with_diagnostics(content, None, &options).unwrap();
+            let lemma = functions.iter().find(|f| f.name == "lemma_in_verus").unwrap();
+            assert_eq!(lemma.todo_markers, vec![(13, "TODO(proof): strengthen".to_string())]);
+        }
+        let options = ParseOptions { todo_markers: Some(vec!["overflow".to_string()]), ..Default::default() };
+        let (functions, _) = parse_guarded_with_diagnostics(content, None, &options).unwrap();
+        assert_eq!(functions[0].todo_markers, vec![(3, "FIXME: overflow".to_string())]);
+        assert!(functions[1].todo_markers.is_empty());
+    }
+
+    #[test]
+    fn test_call_context() {
+        let lemmas = r#"
+verus! {
+pub proof fn lemma_sum_bound(a: int, b: int, bound: int)
+    requires a <= bound, b <= bound,
+    ensures a + b <= 2 * bound,
+{
+}
+}
+"#;
+        let user = r#"
+use vstd::arithmetic::mul::lemma_mul_inequality;
+
+verus! {
+proof fn caller(x: int, y: int)
+    requires x <= 10, y <= 10,
+    ensures x + y <= 20,
+{
+    crate::lemmas::lemma_sum_bound(x, y + 0, 10);
+    lemma_mul_inequality(x, 10, y);
+}
+}
+"#;
+        let files = vec![("lemmas.rs".to_string(), lemmas.to_string()), ("user.rs".to_string(), user.to_string())];
+        let context = call_context_core(&files, "user.rs", 9).unwrap();
+        assert_eq!(context.caller.name, "caller");
+        assert!(context.resolved);
+        assert_eq!(context.callee.name, "lemma_sum_bound");
+        assert_eq!(context.callee.requires, vec!["a <= bound", "b <= bound"]);
+        assert_eq!(context.args, vec!["x", "y + 0", "10"]);
+
+        let external = call_context_core(&files, "user.rs", 11).unwrap();
+        assert_eq!(external.line_number, 10);
+        assert!(!external.resolved);
+        assert_eq!(external.callee_path, "lemma_mul_inequality");
+        assert_eq!(external.callee, FunctionSpecs::default());
+        assert!(call_context_core(&files, "other.rs", 1).is_err());
+    }
+
+    #[test]
+    fn test_type_alias_expansion() {
+        let code = r#"
+type Word = Limb;
+type Loop = Loop2;
+type Loop2 = Loop;
+
+mod limbs {
+    pub type Pair<T> = (T, T);
+}
+
+verus! {
+pub type Limb = u64;
+
+proof fn lemma_word_bound(w: Word, l: Loop, p: limbs::Pair<u8>)
+    requires w < Word::MAX,
+    ensures (w as int) + 1 <= Word::MAX as int,
+{
+}
+}
+"#;
+        let aliases = extract_type_aliases_core(code).unwrap();
+        let names: Vec<(&str, &str)> = aliases.iter().map(|a| (a.name.as_str(), a.target.as_str())).collect();
+        assert_eq!(
+            names,
+            vec![("Word", "Limb"), ("Loop", "Loop2"), ("Loop2", "Loop"), ("Pair", "(T, T)"), ("Limb", "u64")]
+        );
+        assert_eq!((aliases[3].generics.as_str(), aliases[3].module_path.as_str()), ("<T>", "limbs"));
+
+        assert!(find_pattern_core(code, "?x <= u64::MAX as int", false).unwrap().is_empty());
+        let found = find_pattern_core(code, "?x <= u64::MAX as int", true).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].function, "lemma_word_bound");
+        assert_eq!(found[0].line_number, 15);
+
+        let facts = vec![verus_syn::parse_str::<Expr>("n < u64::MAX").unwrap()];
+        assert!(filter_by_requires_core(code, &facts, None, false, None).unwrap().is_empty());
+        assert_eq!(filter_by_requires_core(code, &facts, None, true, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_spec_exprs() {
+        let exprs: Vec<String> = ["x+1>x", "x + ", "forall|i: int| 0 <= i < n ==> is_even(crate::MAX + i)", "a b", "(x"]
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        for parallel in [false, true] {
+            let results = validate_spec_exprs_core(&exprs, parallel);
+            assert_eq!(results.len(), 5);
+            assert!(results[0].valid);
+            assert_eq!(results[0].normalized.as_deref(), Some("x + 1 > x"));
+            assert_eq!(results[0].identifiers, vec!["x"]);
+            assert_eq!((results[0].error.as_ref(), results[0].error_column), (None, None));
+
+            assert!(!results[1].valid);
+            assert!(results[1].error.is_some() && results[1].normalized.is_none());
+            assert_eq!(results[1].error_column, Some(3));
+
+            assert!(results[2].valid);
+            assert_eq!(results[2].identifiers, vec!["n", "is_even", "crate::MAX"]);
+
+            assert!(!results[3].valid);
+            assert_eq!(results[3].error_column, Some(2));
+            assert_eq!((results[4].valid, results[4].error_column), (false, None));
+        }
+    }
+
+    #[test]
+    fn test_assert_labels() {
+        let code = r#"
+verus! {
+fn check(x: u64, y: u64) {
+    assert!(x > 0, "x positive");
+    assert(y >= 0); // y nonnegative
+    assert(x + 0 == x) by (compute); /* identity */
+    assert(x == x);
+    assert!(x < y, "{} below {}", x, y);
+}
+}
+"#;
+        let functions = parse_verus_file_core(code).unwrap();
+        let asserts = &functions[0].asserts;
+        assert_eq!(asserts.len(), 5);
+        assert_eq!((asserts[0].expr.as_str(), asserts[0].labels.clone()), ("x > 0", vec!["x positive".to_string()]));
+        assert_eq!(asserts[1].comment.as_deref(), Some("y nonnegative"));
+        assert_eq!((asserts[2].prover.as_deref(), asserts[2].comment.as_deref()), (Some("compute"), Some("identity")));
+        // Without a label, only the expression
+        assert_eq!(
+            (asserts[3].expr.as_str(), asserts[3].labels.is_empty(), asserts[3].comment.clone()),
+            ("x == x", true, None)
+        );
+        // Format arguments are neither labels nor part of the expression
+        assert_eq!((asserts[4].expr.as_str(), asserts[4].labels.clone()), ("x < y", vec!["{} below {}".to_string()]));
+
+        let files = vec![("src/check.rs".to_string(), code.to_string())];
+        let found = |label: &str| -> Vec<(String, Option<usize>)> {
+            let asserts = find_assert_by_label_core(&files, label).unwrap();
+            asserts.into_iter().map(|a| (a.function, a.line_number)).collect()
+        };
+        assert_eq!(found("x positive"), vec![("check".to_string(), Some(4))]);
+        assert_eq!(found(" y nonnegative "), vec![("check".to_string(), Some(5))]);
+        assert!(found("x == x").is_empty());
+    }
+
+    #[test]
+    fn test_unicode_spans() {
+        let code = r#"use vstd::prelude::*;
+
+verus! {
+
+// 引理：平方非负
+    proof fn lemma_σ(α: int) requires α >= 0, ensures α * α >= 0, { assert(α * α >= 0); // 检查
+    }
+
+    spec fn λ_sum(β: int, γ: int) -> int { β + γ }
+
+    fn ω(s: &str) -> (r: bool) ensures r == true, { let t = "ñandú"; lemma_σ(1); true }
+}
+"#;
+        let bom = format!("\u{feff}{}", code);
+        let positions = |content: &str, parallel: bool| {
+            let parallel_threshold = parallel.then_some(0);
+            let options = ParseOptions { header_source: true, parallel_threshold, ..Default::default() };
+            let functions = parse_limited(content, None, &options).unwrap();
+            let position = |f: FunctionSpecs| {
+                (f.name, f.line_number, f.end_line, f.sig_end_line, f.sig_end_column, f.header_source.unwrap())
+            };
+            functions.into_iter().map(position).collect::<Vec<_>>()
+        };
+        // Columns count characters: the header of lemma_σ ends at byte 70 but character 65
+        let expected = vec![
+            (
+                "lemma_σ".to_string(),
+                Some(6),
+                Some(7),
+                Some(6),
+                Some(65),
+                "proof fn lemma_σ(α: int) requires α >= 0, ensures α * α >= 0,".to_string(),
+            ),
+            (
+                "λ_sum".to_string(),
+                Some(9),
+                Some(9),
+                Some(9),
+                Some(40),
+                "spec fn λ_sum(β: int, γ: int) -> int".to_string(),
+            ),
+            (
+                "ω".to_string(),
+                Some(11),
+                Some(11),
+                Some(11),
+                Some(49),
+                "fn ω(s: &str) -> (r: bool) ensures r == true,".to_string(),
+            ),
+        ];
+        // The batches of the parallel path start mid-line, after the indentation
+        for (content, parallel) in [(code, false), (code, true), (bom.as_str(), false), (bom.as_str(), true)] {
+            assert_eq!(positions(content, parallel), expected, "parallel: {}", parallel);
+        }
+        let functions = parse_verus_file_core(code).unwrap();
+        assert_eq!(functions[0].ensures, vec!["α * α >= 0"]);
+        assert_eq!(functions[0].asserts[0].comment.as_deref(), Some("检查"));
+
+        // Spans into a file with a byte order mark are offsets past it
+        let context = call_context_core(&[("a.rs".to_string(), bom.clone())], "a.rs", 11).unwrap();
+        assert_eq!((context.caller.name.as_str(), context.args), ("ω", vec!["1".to_string()]));
+        assert!(erase_verus_core(&bom, &[]).unwrap().starts_with("\u{feff}use vstd::prelude::*;"));
+
+        // The second error comes after an item with multi-byte characters that
+        // recovery blanks out; its column is still that of the original line
+        let broken = "fn ω() -> { \"ππ\" } fn bad() -> { }\n";
+        let (_, diagnostics) = parse_recovering(broken, &ParseOptions::default()).unwrap();
+        let columns: Vec<_> = diagnostics.iter().map(|d| (d.kind.as_str(), d.line_number, d.column)).collect();
+        assert_eq!(columns, vec![("parse_error", Some(1), Some(10)), ("parse_error", Some(1), Some(31))]);
+        let validation = validate_spec_exprs_core(&["α + + β".to_string()], false);
+        assert_eq!(validation[0].error_column, Some(4));
+
+        let index = LineIndex::new(&bom);
+        let offset = bom.find("lemma_σ(α").unwrap() + "lemma_σ(".len();
+        assert_eq!(index.position(offset), (6, 21));
+        assert_eq!(index.offset(6, 21), offset);
+        assert_eq!(index.position(bom.find("use").unwrap()), (1, 0));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let code = r#"
+verus! {
+
+use vstd::arithmetic::mul::lemma_mul_is_commutative;
+
+spec fn double(x: int) -> int { x + x }
+
+spec fn quad(x: int) -> int { double(double(x)) }
+
+proof fn lemma_double(x: int) ensures double(x) == 2 * x {
+    lemma_mul_is_commutative(x, 2);
+}
+
+pub broadcast proof fn lemma_quad(x: int) ensures #[trigger] quad(x) == 4 * x {
+    lemma_double(x);
+    lemma_double(double(x));
+}
+
+mod far {
+    proof fn lemma_far(x: int) { super::lemma_quad(x); }
+    proof fn lemma_farther(x: int) { lemma_far(x); }
+}
+
+fn exec_unrelated() {}
+
+}
+"#;
+        let files = vec![("a.rs".to_string(), code.to_string())];
+        let lines = |dot: &str, edges: bool| -> Vec<String> {
+            dot.lines()
+                .map(str::trim)
+                .filter(|line| line.starts_with('"') && line.contains(" -> ") == edges)
+                .map(str::to_string)
+                .collect()
+        };
+
+        let dot = to_dot_core("call", &files, false, None, 2).unwrap();
+        assert!(dot.starts_with("digraph calls {"));
+        assert_eq!(lines(&dot, false).len(), 8);
+        assert!(dot.contains("\"vstd::arithmetic::mul::lemma_mul_is_commutative\" [shape=box, fillcolor=lightgray];"));
+        assert!(dot.contains("\"lemma_quad\" [fillcolor=lightblue, style=\"filled,dashed\"];"));
+        assert!(dot.contains("\"quad\" [fillcolor=lightyellow];"));
+        let edges = lines(&dot, true);
+        assert_eq!(edges.len(), 6);
+        assert!(edges.contains(&"\"lemma_quad\" -> \"lemma_double\" [label=\"2\"];".to_string()));
+        assert!(edges.contains(&"\"quad\" -> \"double\" [label=\"2\"];".to_string()));
+
+        let focused = to_dot_core("call", &files, false, Some("lemma_double"), 1).unwrap();
+        let nodes = lines(&focused, false);
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes.iter().all(|node| !node.starts_with("\"lemma_far")));
+        assert_eq!(lines(&focused, true).len(), 2);
+        let two_hops = to_dot_core("call", &files, false, Some("lemma_double"), 2).unwrap();
+        assert_eq!(lines(&two_hops, false).len(), 5);
+        assert!(two_hops.contains("\"lemma_far\""));
+        assert!(!two_hops.contains("\"lemma_farther\""));
+
+        let spec = to_dot_core("spec", &files, false, None, 2).unwrap();
+        let edges = lines(&spec, true);
+        assert_eq!(
+            edges,
+            vec![
+                "\"lemma_double\" -> \"double\" [label=\"1\"];",
+                "\"lemma_quad\" -> \"quad\" [label=\"1\"];",
+                "\"quad\" -> \"double\" [label=\"2\"];",
+            ]
+        );
+
+        let clustered = to_dot_core("call", &files, true, None, 2).unwrap();
+        assert!(clustered.contains("subgraph \"cluster_far\" {\n        label=\"far\";\n        \"lemma_far\""));
+        assert!(to_dot_core("call", &files, false, Some("missing"), 2).is_err());
+        assert!(to_dot_core("types", &files, false, None, 2).is_err());
+    }
+
+    #[test]
+    fn test_untriggered_quantifiers() {
+        let code = r#"
+verus! {
+
+spec fn f(i: int) -> bool;
+
+spec fn g(i: int, j: int) -> bool;
+
+proof fn lemma_q(s: Seq<int>)
+    requires
+        forall|i: int| 0 <= i < s.len() ==> #[trigger] s[i] > 0,
+        forall|i: int| #![trigger f(i)] f(i),
+    ensures
+        forall|i: int, j: int| f(i) ==> g(i, j),
+{
+}
+
+spec fn all_nested(s: Seq<int>) -> bool {
+    forall|i: int| #[trigger] f(i) ==> exists|j: int| g(i, j)
+}
+
+spec fn auto(s: Seq<int>) -> bool {
+    forall|i| #![auto] f(i)
+}
+
+}
+"#;
+        let files = vec![("a.rs".to_string(), code.to_string())];
+        let found = untriggered_quantifiers_core(&files).unwrap();
+        let summary: Vec<(&str, &str, &str, usize, usize, bool)> = found
+            .iter()
+            .map(|q| {
+                (q.function.as_str(), q.clause_kind.as_str(), q.quantifier.as_str(), q.line_number, q.column, q.is_auto)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("lemma_q", "ensures", "forall", 13, 8, false),
+                ("all_nested", "body", "exists", 18, 39, false),
+                ("auto", "body", "forall", 22, 4, true),
+            ]
+        );
+        let binders = |name: &str, ty: Option<&str>| (name.to_string(), ty.map(String::from));
+        assert_eq!(found[0].binders, vec![binders("i", Some("int")), binders("j", Some("int"))]);
+        assert_eq!(found[0].text, "forall|i: int, j: int| f(i) ==> g(i, j)");
+        assert_eq!(found[0].file_path, "a.rs");
+        assert_eq!(found[2].binders, vec![binders("i", None)]);
+    }
+
+    #[test]
+    fn test_method_call_resolution() {
+        let code = r#"
+verus! {
+
+struct Counter { n: u64 }
+
+impl Counter {
+    proof fn lemma_base(self) {}
+
+    proof fn lemma_step(self, x: Seq<int>) {
+        self.lemma_base();
+        Self::lemma_base(self);
+        let n = x.len();
+    }
+
+    proof fn len(self) {}
+}
+
+}
+"#;
+        let file = verus_syn::parse_file(code).unwrap();
+        let mut finder = CallFinder::default();
+        finder.visit_file(&file);
+        let calls: Vec<(&str, &str, Option<&str>, usize)> = finder
+            .calls
+            .iter()
+            .map(|c| (c.caller.as_str(), c.callee.as_str(), c.receiver_type.as_deref(), c.line))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("lemma_step", "Counter::lemma_base", Some("Counter"), 10),
+                ("lemma_step", "Counter::lemma_base", Some("Counter"), 11),
+                ("lemma_step", "len", Some("unknown"), 12),
+            ]
+        );
+        assert!(finder.calls[2].is_unresolved_method());
+
+        let files = vec![("a.rs".to_string(), code.to_string())];
+        let fan_in: Vec<(String, usize)> =
+            lemma_fan_in_core(&files).unwrap().into_iter().map(|(f, count)| (f.name, count)).collect();
+        let expected = [("lemma_base", 1), ("lemma_step", 0), ("len", 0)];
+        assert_eq!(fan_in, expected.map(|(name, count)| (name.to_string(), count)));
+        // `x.len()` is not taken for a call to `Counter::len`
+        let (unused, _) = find_unused_lemmas_core(&files).unwrap();
+        assert_eq!(unused.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["lemma_step", "len"]);
+        assert!(usage_examples_core(&files, "len", 0).unwrap().is_empty());
+        assert_eq!(explain_core(&files, "lemma_step").unwrap()[0].callees, vec!["lemma_base"]);
+        assert!(external_lemma_usage_core(&files).unwrap().is_empty());
+        let dot = to_dot_core("call", &files, false, None, 2).unwrap();
+        assert!(dot.contains("\"lemma_step\" -> \"lemma_base\" [label=\"2\"];"));
+        assert!(!dot.contains("-> \"len\""));
+    }
+
+    #[test]
+    fn test_fact_types_int_coercion() {
+        let code = r#"
+verus! {
+    proof fn lemma_nat_pos(n: nat)
+        requires n > 0,
+        ensures n >= 1,
+    {}
+
+    proof fn lemma_usize_pos(n: usize)
+        requires n > 0,
+        ensures n >= 1,
+    {}
+}
+"#;
+        let facts = parse_facts(&["x > 0".to_string()]).unwrap();
+        let names = |types: Option<&FactTypes>| -> Vec<String> {
+            filter_by_requires_core(code, &facts, None, false, types).unwrap().into_iter().map(|f| f.name).collect()
+        };
+        assert_eq!(names(None), vec!["lemma_nat_pos", "lemma_usize_pos"]);
+
+        let mut types =
+            FactTypes { types: HashMap::from([("x".to_string(), "usize".to_string())]), ..Default::default() };
+        assert_eq!(names(Some(&types)), vec!["lemma_usize_pos"]);
+        types.coerce_int_types = true;
+        assert_eq!(names(Some(&types)), vec!["lemma_nat_pos", "lemma_usize_pos"]);
+
+        // The exact match ranks first; the coercion is recorded
+        let applicable = applicable_lemmas_core(code, &facts, false, Some(&types)).unwrap();
+        assert_eq!(applicable[0].0.name, "lemma_usize_pos");
+        assert!(applicable[0].2.is_empty());
+        assert_eq!(applicable[1].0.name, "lemma_nat_pos");
+        let coercion = ("n".to_string(), "usize".to_string(), "nat".to_string());
+        assert_eq!(applicable[1].2, vec![coercion]);
+
+        // A signed machine integer never stands for a nat
+        types.types.insert("x".to_string(), "i64".to_string());
+        assert!(names(Some(&types)).is_empty());
+
+        // Suffixed literals and casts carry their own types
+        let facts = parse_facts(&["5u8 > 0".to_string()]).unwrap();
+        let found = filter_by_requires_core(code, &facts, None, false, Some(&types)).unwrap();
+        assert_eq!(found.into_iter().map(|f| f.name).collect::<Vec<_>>(), vec!["lemma_nat_pos"]);
+        let facts = parse_facts(&["(y as usize) > 0".to_string()]).unwrap();
+        let found = filter_by_requires_core(code, &facts, None, false, Some(&types)).unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn test_public_surface_and_reachability() {
+        let lib = r#"
+pub mod api;
+mod internal;
+verus! {
+    pub mod arith {
+        pub proof fn lemma_add() {}
+        proof fn lemma_private() {}
+    }
+    pub use crate::arith::lemma_add as add;
+}
+"#;
+        let surface = public_surface_core(lib).unwrap();
+        assert_eq!(surface.modules, vec!["api", "arith"]);
+        let reexport = Reexport {
+            module_path: String::new(),
+            path: "crate::arith::lemma_add".to_string(),
+            alias: Some("add".to_string()),
+        };
+        assert_eq!(surface.reexports, vec![reexport]);
+        assert_eq!(surface.functions, vec!["arith::lemma_add"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("internal")).unwrap();
+        std::fs::write(src.join("lib.rs"), lib).unwrap();
+        std::fs::write(src.join("api.rs"), "verus! { pub proof fn lemma_api() {} }\nmod missing;\n").unwrap();
+        std::fs::write(
+            src.join("internal/mod.rs"),
+            "pub mod nested;\npub struct S;\nimpl S { pub proof fn lemma_s() {} }\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("internal/nested.rs"), "verus! { pub proof fn lemma_hidden() {} }").unwrap();
+
+        let reachability = |lib: &str, options: &ParseOptions| {
+            std::fs::write(src.join("lib.rs"), lib).unwrap();
+            let (functions, diagnostics) = parse_verus_crate_core(&src.join("lib.rs"), options).unwrap();
+            let api = src.join("api.rs").to_string_lossy().to_string();
+            assert_eq!(diagnostics[&api][0].kind, "module_not_found");
+            functions
+                .into_iter()
+                .map(|f| (f.qualified_name(), f.is_externally_reachable))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let reachable = reachability(lib, &ParseOptions::default());
+        assert!(reachable["api::lemma_api"]);
+        assert!(reachable["arith::lemma_add"]);
+        assert!(!reachable["arith::lemma_private"]);
+        // Pub, but inside a private module
+        assert!(!reachable["internal::nested::lemma_hidden"]);
+        assert!(!reachable["internal::S::lemma_s"]);
+
+        let reexported = format!("{}pub use internal::nested::*;\npub use self::internal::S;\n", lib);
+        let reachable = reachability(&reexported, &ParseOptions::default());
+        assert!(reachable["internal::nested::lemma_hidden"]);
+        assert!(reachable["internal::S::lemma_s"]);
+        assert!(!reachable["arith::lemma_private"]);
+
+        // Modules and re-exports inside a project macro wrapping `verus!`
+        let options = ParseOptions { verus_macro_names: vec!["my_verus".to_string()], ..Default::default() };
+        let reachable = reachability(&lib.replace("verus! {", "my_verus! {"), &options);
+        assert!(reachable["arith::lemma_add"]);
+        assert!(!reachable["arith::lemma_private"]);
+    }
+}
@@ -0,0 +1,3785 @@
+//! The extraction pipeline: parse options, guarded and recovering parses,
+//! parallel and incremental extraction, and multi-file, directory and crate
+//! parses
+
+use crate::model::*;
+use crate::visitor::*;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use verus_syn::spanned::Spanned;
+use verus_syn::visit::Visit;
+use verus_syn::{Expr, ImplItemFn, ItemFn, ItemMacro, TraitItemFn};
+
+/// The line starts of a source text, for moving between byte offsets (what
+/// slicing the text takes) and positions
+///
+/// Positions are as in `proc_macro2::LineColumn`: 1-indexed lines and
+/// 0-indexed columns counted in characters, not bytes, with a leading byte
+/// order mark not counted (`verus_syn::parse_file` drops it). Every column
+/// this module reports follows that convention, so a column indexes the
+/// line as a Python string.
+pub(crate) struct LineIndex<'a> {
+    pub(crate) text: &'a str,
+    /// Byte offset of the start of each line (of the first, after any byte order mark)
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        let first = if text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        let starts = std::iter::once(first).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        LineIndex { text, starts }
+    }
+
+    /// Line and column of byte `offset`
+    pub(crate) fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset).max(1);
+        let start = self.starts[line - 1];
+        (line, self.text[start.min(offset)..offset].chars().count())
+    }
+
+    /// Byte offset of a line and column; past the end of its line, the end
+    /// of the line, and past the last line, the end of the text
+    pub(crate) fn offset(&self, line: usize, column: usize) -> usize {
+        let Some(&start) = self.starts.get(line.saturating_sub(1)) else {
+            return self.text.len();
+        };
+        let end = self.starts.get(line).map_or(self.text.len(), |next| next - 1);
+        self.text[start..end].char_indices().nth(column).map_or(end, |(i, _)| start + i)
+    }
+}
+
+/// The part of `content` that `verus_syn::parse_file` parses, after any
+/// byte order mark and `#!` line; the byte ranges of the spans of the file
+/// it returns are offsets into this, not into `content`
+pub(crate) fn parsed_source(content: &str) -> &str {
+    let text = content.strip_prefix('\u{feff}').unwrap_or(content);
+    match text.strip_prefix("#!") {
+        Some(rest) if !rest.trim_start().starts_with('[') => &text[text.find('\n').unwrap_or(text.len())..],
+        _ => text,
+    }
+}
+
+/// Split an expression into its `&&` conjuncts. Chains nest to the left, so
+/// the walk keeps its own stack rather than recursing once per operator.
+pub(crate) fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    let mut parts = Vec::new();
+    let mut pending = vec![expr];
+    while let Some(expr) = pending.pop() {
+        match strip_parens(expr) {
+            Expr::Binary(bin) if bin_op_text(&bin.op) == "&&" => {
+                pending.push(&bin.right);
+                pending.push(&bin.left);
+            }
+            other => parts.push(other),
+        }
+    }
+    parts
+}
+
+pub(crate) fn bin_op_text(op: &verus_syn::BinOp) -> String {
+    quote::quote!(#op).to_string().replace(' ', "")
+}
+
+/// Replace the "function_excluded" diagnostics (one per function) with one
+/// "functions_excluded" diagnostic per reason, at the first such function,
+/// with the count and names
+fn summarize_exclusions(diagnostics: &mut Vec<Diagnostic>) {
+    let (excluded, rest): (Vec<Diagnostic>, Vec<Diagnostic>) =
+        std::mem::take(diagnostics).into_iter().partition(|d| d.kind == "function_excluded");
+    *diagnostics = rest;
+    let mut by_reason: Vec<(String, Option<usize>, Vec<String>)> = Vec::new();
+    for d in excluded {
+        let name = d.function.unwrap_or_default();
+        match by_reason.iter_mut().find(|(reason, _, _)| *reason == d.detail) {
+            Some((_, _, names)) => names.push(name),
+            None => by_reason.push((d.detail, d.line_number, vec![name])),
+        }
+    }
+    diagnostics.extend(by_reason.into_iter().map(|(reason, line_number, names)| Diagnostic {
+        kind: "functions_excluded".to_string(),
+        line_number,
+        detail: format!("{} excluded by {}: {}", names.len(), reason, names.join(", ")),
+        ..Default::default()
+    }));
+}
+
+/// Marker of a suppression comment: `// verus-lemma-finder: allow(unused_lemma)`
+const SUPPRESSION_MARKER: &str = "verus-lemma-finder:";
+
+/// Lints suppressed by `// verus-lemma-finder: allow(...)` comments in the
+/// run of comment and attribute lines just above `line` (1-indexed) of `lines`
+pub(crate) fn comment_suppressions(lines: &[&str], line: usize) -> Vec<String> {
+    let mut lints = Vec::new();
+    for text in lines[..line.saturating_sub(1).min(lines.len())].iter().rev() {
+        let text = text.trim();
+        if let Some(comment) = text.strip_prefix("//") {
+            let directive = comment.trim_start_matches('/').trim();
+            if let Some(allow) = directive.strip_prefix(SUPPRESSION_MARKER) {
+                if let Ok(tokens) = allow.parse::<TokenStream>() {
+                    allowed_lints(tokens, &mut lints);
+                }
+            }
+        } else if !text.starts_with("#[") {
+            break;
+        }
+    }
+    lints
+}
+
+/// Add the comment suppressions of each function in `content` to `suppressed`
+fn add_comment_suppressions(functions: &mut [FunctionSpecs], content: &str) {
+    let lines: Vec<&str> = content.lines().collect();
+    for f in functions {
+        for lint in comment_suppressions(&lines, f.line_number.unwrap_or_default()) {
+            if !f.suppressed.contains(&lint) {
+                f.suppressed.push(lint);
+            }
+        }
+    }
+}
+
+/// Markers of proof debt looked for in comments when none are configured
+const DEFAULT_TODO_MARKERS: &[&str] = &["TODO", "FIXME", "XXX", "admit"];
+
+/// The comment lines of `content` containing one of `markers` (the default
+/// ones if None) as (line, text without the comment delimiters); a block
+/// comment is checked line by line
+fn todo_comments(content: &str, markers: Option<&[String]>) -> Vec<(usize, String)> {
+    let defaults: Vec<String> = DEFAULT_TODO_MARKERS.iter().map(|m| m.to_string()).collect();
+    let markers = markers.unwrap_or(&defaults);
+    let bytes = content.as_bytes();
+    let mut found = Vec::new();
+    let (mut i, mut line) = (0, 1);
+    while i < bytes.len() {
+        let Some(next) = skip_literal(bytes, i, bytes.len()) else {
+            line += usize::from(bytes[i] == b'\n');
+            i += 1;
+            continue;
+        };
+        let text = &content[i..next];
+        if text.starts_with("//") || text.starts_with("/*") {
+            let body = text.strip_prefix("/*").map_or(text, |t| t.strip_suffix("*/").unwrap_or(t));
+            for (offset, comment) in body.lines().enumerate() {
+                let comment = comment.trim().trim_start_matches(['/', '*', '!']).trim();
+                if markers.iter().any(|m| comment.contains(m.as_str())) {
+                    found.push((line + offset, comment.to_string()));
+                }
+            }
+        }
+        line += text.matches('\n').count();
+        i = next;
+    }
+    found
+}
+
+/// Attribute the marker comments of `content` to the innermost function whose
+/// lines contain them, as `todo_markers`; returns those outside any function
+pub(crate) fn add_todo_markers(
+    functions: &mut [FunctionSpecs],
+    content: &str,
+    markers: Option<&[String]>,
+) -> Vec<(usize, String)> {
+    let mut unattributed = Vec::new();
+    for (line, text) in todo_comments(content, markers) {
+        let contains = |f: &FunctionSpecs| {
+            matches!((f.line_number, f.end_line), (Some(start), Some(end)) if (start..=end).contains(&line))
+        };
+        let innermost = functions
+            .iter_mut()
+            .filter(|f| contains(f))
+            .min_by_key(|f| f.end_line.unwrap_or_default() - f.line_number.unwrap_or_default());
+        match innermost {
+            Some(f) => f.todo_markers.push((line, text)),
+            None => unattributed.push((line, text)),
+        }
+    }
+    unattributed
+}
+
+/// The comments of `content` that follow code on their line, by line, as
+/// text without the comment delimiters (a block comment only up to the end of
+/// its first line)
+fn trailing_comments(content: &str) -> HashMap<usize, String> {
+    let bytes = content.as_bytes();
+    let mut comments = HashMap::new();
+    let (mut i, mut line, mut code_on_line) = (0, 1, false);
+    while i < bytes.len() {
+        let Some(next) = skip_literal(bytes, i, bytes.len()) else {
+            match bytes[i] {
+                b'\n' => (line, code_on_line) = (line + 1, false),
+                byte => code_on_line |= !byte.is_ascii_whitespace(),
+            }
+            i += 1;
+            continue;
+        };
+        let text = &content[i..next];
+        if code_on_line && (text.starts_with("//") || text.starts_with("/*")) {
+            let first = text.lines().next().unwrap_or_default();
+            let comment = first.trim_start_matches(['/', '*', '!']).trim_end().trim_end_matches("*/").trim();
+            comments.entry(line).or_insert_with(|| comment.to_string());
+        }
+        // A string literal is code; a comment leaves the line as it was
+        code_on_line |= text.starts_with(['"', '\'']);
+        let newlines = text.matches('\n').count();
+        if newlines > 0 {
+            (line, code_on_line) = (line + newlines, false);
+        }
+        i = next;
+    }
+    comments
+}
+
+/// Fill in the `comment` of each assert of `functions` from the comment after
+/// it on its last line
+fn add_assert_comments(functions: &mut [FunctionSpecs], content: &str) {
+    if functions.iter().all(|f| f.asserts.is_empty()) {
+        return;
+    }
+    let comments = trailing_comments(content);
+    for assert in functions.iter_mut().flat_map(|f| f.asserts.iter_mut()) {
+        assert.comment = assert.end_line.and_then(|line| comments.get(&line).cloned());
+    }
+}
+
+/// Whether an item is visible outside its crate (`pub`, not `pub(crate)` etc.)
+pub(crate) fn is_pub(vis: &verus_syn::Visibility) -> bool {
+    matches!(vis, verus_syn::Visibility::Public(_))
+}
+
+/// The text of the inner (`//!` or `#![doc = ...]`) doc attributes, one line
+/// each, with the space after `//!` removed; None if there are none
+fn inner_doc(attrs: &[verus_syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| matches!(attr.style, verus_syn::AttrStyle::Inner(_)) && attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            verus_syn::Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(lit) => match &lit.lit {
+                    verus_syn::Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Collects the inner docs of the file and its modules, keyed by module path
+#[derive(Default)]
+pub(crate) struct ModuleDocFinder {
+    pub(crate) docs: BTreeMap<String, String>,
+    /// Paths of all modules in source order
+    pub(crate) modules: Vec<String>,
+    /// Names of the enclosing modules
+    module_stack: Vec<String>,
+    /// Project macros wrapping `verus!` whose bodies are searched too
+    verus_macro_names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for ModuleDocFinder {
+    fn visit_file(&mut self, node: &'ast verus_syn::File) {
+        if let Some(doc) = inner_doc(&node.attrs) {
+            self.docs.insert(String::new(), doc);
+        }
+        verus_syn::visit::visit_file(self, node);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.module_stack.push(node.ident.to_string());
+        self.modules.push(self.module_stack.join("::"));
+        if let Some(doc) = inner_doc(&node.attrs) {
+            self.docs.insert(self.module_stack.join("::"), doc);
+        }
+        verus_syn::visit::visit_item_mod(self, node);
+        self.module_stack.pop();
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &self.verus_macro_names) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// Map each documented module path (`""` for the file root) to its inner
+/// docs, looking into `verus!` blocks and those of `verus_macro_names`
+pub(crate) fn collect_module_docs(file: &verus_syn::File, verus_macro_names: &[String]) -> BTreeMap<String, String> {
+    let mut finder = ModuleDocFinder { verus_macro_names: verus_macro_names.to_vec(), ..Default::default() };
+    finder.visit_file(file);
+    finder.docs
+}
+
+/// A module declared in a file, as found by `SurfaceFinder`
+pub(crate) struct ModuleDecl {
+    /// Path within the file, e.g. "arith::mul"
+    pub(crate) path: String,
+    pub(crate) is_pub: bool,
+    /// Whether it is declared without a body (`mod name;`)
+    outline: bool,
+    /// Value of its `#[path = "..."]` attribute
+    path_attr: Option<String>,
+    line: usize,
+}
+
+/// A public function, as found by `SurfaceFinder`
+pub(crate) struct SurfaceFn {
+    pub(crate) module_path: String,
+    /// Self type of the impl or name of the trait the function belongs to
+    pub(crate) owner: Option<String>,
+    pub(crate) name: String,
+    line: usize,
+}
+
+/// Collects the module declarations, `pub use` declarations and public
+/// functions of a file, including those inside `verus!` blocks
+#[derive(Default)]
+pub(crate) struct SurfaceFinder {
+    pub(crate) modules: Vec<ModuleDecl>,
+    pub(crate) reexports: Vec<Reexport>,
+    pub(crate) functions: Vec<SurfaceFn>,
+    /// Names of the enclosing modules
+    module_stack: Vec<String>,
+    /// Self type of the enclosing impl or name of the enclosing trait, and
+    /// whether its methods are public without `pub` (trait impls, `pub` traits)
+    owner: Option<(String, bool)>,
+    /// Project macros wrapping `verus!` whose bodies are searched too
+    pub(crate) verus_macro_names: Vec<String>,
+}
+
+impl SurfaceFinder {
+    fn add_fn(&mut self, vis: &verus_syn::Visibility, name: &verus_syn::Ident, line: usize) {
+        let inherited = self.owner.as_ref().is_some_and(|(_, public)| *public);
+        if inherited || is_pub(vis) {
+            self.functions.push(SurfaceFn {
+                module_path: self.module_stack.join("::"),
+                owner: self.owner.as_ref().map(|(owner, _)| owner.clone()),
+                name: name.to_string(),
+                line,
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for SurfaceFinder {
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.module_stack.push(node.ident.to_string());
+        let path_attr = node.attrs.iter().find(|attr| attr.path().is_ident("path")).and_then(|attr| match &attr.meta {
+            verus_syn::Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(lit) => match &lit.lit {
+                    verus_syn::Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        });
+        self.modules.push(ModuleDecl {
+            path: self.module_stack.join("::"),
+            is_pub: is_pub(&node.vis),
+            outline: node.content.is_none(),
+            path_attr,
+            line: node.span().start().line,
+        });
+        verus_syn::visit::visit_item_mod(self, node);
+        self.module_stack.pop();
+    }
+
+    fn visit_item_use(&mut self, node: &'ast verus_syn::ItemUse) {
+        if is_pub(&node.vis) {
+            let mut paths = Vec::new();
+            use_tree_paths(&node.tree, &mut Vec::new(), &mut paths);
+            let module_path = self.module_stack.join("::");
+            self.reexports.extend(
+                paths.into_iter().map(|(path, alias)| Reexport { module_path: module_path.clone(), path, alias }),
+            );
+        }
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.add_fn(&node.vis, &node.sig.ident, node.span().start().line);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.add_fn(&node.vis, &node.sig.ident, node.span().start().line);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.add_fn(&verus_syn::Visibility::Inherited, &node.sig.ident, node.span().start().line);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        let outer = self.owner.replace((node.ident.to_string(), is_pub(&node.vis)));
+        verus_syn::visit::visit_item_trait(self, node);
+        self.owner = outer;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        let self_ty = &node.self_ty;
+        let outer = self.owner.replace((render_tokens(quote::quote!(#self_ty)), node.trait_.is_some()));
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                for impl_item in verus_impl_macro_items(&mac.mac, &self.verus_macro_names) {
+                    self.visit_impl_item(&impl_item);
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+        self.owner = outer;
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        for item in verus_macro_items(&node.mac, &self.verus_macro_names) {
+            self.visit_item(&item);
+        }
+    }
+}
+
+/// Flatten a `use` tree into (path, rename) entries, a glob as a path ending in `*`
+fn use_tree_paths(tree: &verus_syn::UseTree, prefix: &mut Vec<String>, paths: &mut Vec<(String, Option<String>)>) {
+    let joined = |prefix: &[String], last: &str| match prefix.is_empty() {
+        true => last.to_string(),
+        false => format!("{}::{}", prefix.join("::"), last),
+    };
+    match tree {
+        verus_syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            use_tree_paths(&path.tree, prefix, paths);
+            prefix.pop();
+        }
+        verus_syn::UseTree::Name(name) if name.ident == "self" => paths.push((prefix.join("::"), None)),
+        verus_syn::UseTree::Name(name) => paths.push((joined(prefix, &name.ident.to_string()), None)),
+        verus_syn::UseTree::Rename(rename) => {
+            let path = match rename.ident == "self" {
+                true => prefix.join("::"),
+                false => joined(prefix, &rename.ident.to_string()),
+            };
+            paths.push((path, Some(rename.rename.to_string())));
+        }
+        verus_syn::UseTree::Group(group) => {
+            for item in &group.items {
+                use_tree_paths(item, prefix, paths);
+            }
+        }
+        verus_syn::UseTree::Glob(_) => paths.push((joined(prefix, "*"), None)),
+    }
+}
+
+/// The crate-relative path a `use` path written in `module` refers to, if it
+/// is inside the crate: `crate::`, `self::` and `super::` paths, and paths
+/// starting with a module of `modules` (a child of `module` or a top-level one)
+fn resolve_use_path(path: &str, module: &str, modules: &BTreeSet<&str>) -> Option<String> {
+    let mut base: Vec<&str> = if module.is_empty() { Vec::new() } else { module.split("::").collect() };
+    let mut segments = path.split("::").peekable();
+    match segments.peek().copied() {
+        Some("crate") => {
+            base.clear();
+            segments.next();
+        }
+        Some("self") => {
+            segments.next();
+        }
+        Some("super") => {
+            while segments.next_if_eq(&"super").is_some() {
+                base.pop()?;
+            }
+        }
+        Some(first) => {
+            let child = base.iter().copied().chain([first]).collect::<Vec<_>>().join("::");
+            if !modules.contains(child.as_str()) {
+                if !modules.contains(first) {
+                    return None;
+                }
+                base.clear();
+            }
+        }
+        None => return None,
+    }
+    Some(base.into_iter().chain(segments).collect::<Vec<_>>().join("::"))
+}
+
+/// Which of `functions` can be named from outside the crate, given the
+/// crate's modules and re-exports (all with crate-relative module paths)
+///
+/// The crate root is exposed, and so is a `pub` module of an exposed module,
+/// and a module an exposed module re-exports or glob-imports publicly. A
+/// public function is reachable if its module is exposed, or if it (or the
+/// type of its impl) is re-exported by name from an exposed module.
+fn externally_reachable(modules: &[ModuleDecl], reexports: &[Reexport], functions: &[SurfaceFn]) -> Vec<bool> {
+    let known: BTreeSet<&str> = modules.iter().map(|m| m.path.as_str()).collect();
+    let parent = |path: &str| path.rsplit_once("::").map_or(String::new(), |(parent, _)| parent.to_string());
+    let mut exposed: BTreeSet<String> = BTreeSet::from([String::new()]);
+    let mut items: BTreeSet<String> = BTreeSet::new();
+    loop {
+        let before = (exposed.len(), items.len());
+        let public: Vec<String> = modules
+            .iter()
+            .filter(|m| m.is_pub && exposed.contains(&parent(&m.path)))
+            .map(|m| m.path.clone())
+            .collect();
+        exposed.extend(public);
+        let targets: Vec<(String, bool)> = reexports
+            .iter()
+            .filter(|r| exposed.contains(&r.module_path))
+            .filter_map(|r| {
+                let (path, glob) = match r.path.strip_suffix("::*") {
+                    Some(path) => (path, true),
+                    None => (r.path.as_str(), false),
+                };
+                resolve_use_path(path, &r.module_path, &known).map(|target| (target, glob))
+            })
+            .collect();
+        for (target, glob) in targets {
+            if glob || known.contains(target.as_str()) {
+                exposed.insert(target);
+            } else {
+                items.insert(target);
+            }
+        }
+        if (exposed.len(), items.len()) == before {
+            break;
+        }
+    }
+    functions
+        .iter()
+        .map(|f| {
+            // An impl's self type is re-exported without its generic arguments
+            let name = f.owner.as_deref().map_or(f.name.as_str(), |owner| owner.split('<').next().unwrap_or(owner));
+            let item = if f.module_path.is_empty() { name.to_string() } else { format!("{}::{}", f.module_path, name) };
+            exposed.contains(&f.module_path) || items.contains(&item)
+        })
+        .collect()
+}
+
+/// Set the file path of `functions`, sharing one allocation between them
+pub fn set_file_path(functions: &mut [FunctionSpecs], path: &str) {
+    let path: Arc<str> = Arc::from(path);
+    for f in functions {
+        f.file_path = Arc::clone(&path);
+    }
+}
+
+/// Parse Verus source and collect all function specs, or the parse error message
+pub fn parse_verus_file_core(content: &str) -> Result<Vec<FunctionSpecs>, String> {
+    parse_guarded(content, None, &ParseOptions::default()).map_err(|e| e.message)
+}
+
+/// Options for the parsing entry points: opt-in extraction modes and guards for
+/// untrusted input
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Also parse the bodies of non-`verus!` item macros as items
+    pub item_macros: bool,
+    /// Attach the `//!` docs of each function's module as `module_doc`
+    pub module_doc: bool,
+    /// Reject input larger than this many bytes before parsing
+    pub max_bytes: Option<usize>,
+    /// Stop after visiting this many items (functions, impls, impl/trait members, ...)
+    pub max_items: Option<usize>,
+    /// Reject input whose brackets, braces and parentheses nest deeper than this
+    pub max_depth: Option<usize>,
+    /// Abandon the parse after this many milliseconds
+    pub timeout_ms: Option<u64>,
+    /// Extract in parallel when the input is at least this many bytes
+    /// (ignored together with `max_items` and `module_doc`, which need the whole file)
+    pub parallel_threshold: Option<usize>,
+    /// Leave out test-only functions (those with `in_test` set)
+    pub skip_tests: bool,
+    /// Treat modules named `tests` as test-only even without `#[cfg(test)]`
+    pub tests_module_heuristic: bool,
+    /// Split top-level `&&&`/`&&` conjunctions in requires and ensures clauses
+    pub split_conjunctions: bool,
+    /// Fill in `requires_offsets`/`ensures_offsets` (after any splitting)
+    pub clause_offsets: bool,
+    /// Keep only functions of this mode: "proof", "exec" (including functions
+    /// without a mode keyword) or "spec" (including `spec(checked)`)
+    pub mode: Option<String>,
+    /// Names of project macros wrapping `verus!` whose bodies are parsed the same way
+    pub verus_macro_names: Vec<String>,
+    /// Replace `Self` in method signatures and clauses with the impl's self type
+    pub resolve_self: bool,
+    /// Also read `#[requires(...)]`/`#[ensures(...)]` attributes as clauses
+    pub attribute_specs: bool,
+    /// Fill in `header_source`
+    pub header_source: bool,
+    /// Skip the `not_rust_source` pre-check and always run the full parse
+    pub strict: bool,
+    /// Comment markers reported in `todo_markers` (`DEFAULT_TODO_MARKERS` if None)
+    pub todo_markers: Option<Vec<String>>,
+    /// Leave out functions with (or inside an item with) an attribute whose
+    /// path matches one of these globs, e.g. "automatically_derived"
+    pub exclude_attributes: Vec<String>,
+    /// Leave out functions whose name matches one of these globs, e.g. "__*"
+    pub exclude_name_patterns: Vec<String>,
+    /// `file_path` of every function, for content taken from a larger
+    /// document (notebook cell, Markdown block, patch hunk); ignored by the
+    /// multi-file parses, which set the path of each file
+    pub file_path: Option<String>,
+    /// Lines of that document before the content, added to every reported
+    /// line; ignored by the multi-file parses
+    pub line_offset: usize,
+}
+
+impl ParseOptions {
+    /// The options without `file_path` and `line_offset`, for results in the
+    /// coordinates of the content itself (e.g. each file of a multi-file parse)
+    fn per_file(&self) -> ParseOptions {
+        ParseOptions { file_path: None, line_offset: 0, ..self.clone() }
+    }
+}
+
+/// Why a guarded parse failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    /// Value for `FunctionSpecs::error_kind`
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl ParseFailure {
+    pub(crate) fn new(kind: &'static str, message: String) -> Self {
+        Self { kind, message }
+    }
+
+    /// The error entry returned to Python in place of results
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn into_specs(self, name: &str) -> FunctionSpecs {
+        FunctionSpecs {
+            name: name.to_string(),
+            parse_error: Some(self.message),
+            error_kind: Some(self.kind.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// `into_specs` for a parse of content taken from a larger document,
+    /// with the `file_path` of `options`
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn into_located_specs(self, name: &str, options: &ParseOptions) -> FunctionSpecs {
+        let mut specs = [self.into_specs(name)];
+        relocate(&mut specs, &mut [], options);
+        let [specs] = specs;
+        specs
+    }
+
+    /// The `delimiter_diagnostics` of `content` for a failed parse error,
+    /// relocated as results are (none for other failures)
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn diagnostics(&self, content: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+        if self.kind != "parse_error" {
+            return Vec::new();
+        }
+        let mut diagnostics = delimiter_diagnostics(content, &options.verus_macro_names);
+        relocate(&mut [], &mut diagnostics, options);
+        diagnostics
+    }
+}
+
+/// Largest input the pre-check lets through to the parser
+const MAX_SOURCE_BYTES: usize = 64 << 20;
+
+/// Keywords of which any Rust file worth parsing contains at least one
+const RUST_MARKERS: [&str; 4] = ["fn", "verus", "impl", "mod"];
+
+/// Why `bytes` is evidently not Rust source (not UTF-8, binary, implausibly
+/// large, or without any `fn`/`verus!`/`impl`/`mod`), from a single cheap scan;
+/// None if it may be. Whitespace-only input passes: it parses, to nothing.
+fn not_rust_source(bytes: &[u8]) -> Option<String> {
+    if bytes.len() > MAX_SOURCE_BYTES {
+        return Some(format!("Input is {} bytes, too large for a source file", bytes.len()));
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return Some("Input is not valid UTF-8".to_string());
+    };
+    if let Some(pos) = bytes.iter().position(|&b| b < b' ' && !b"\t\n\r\x0c".contains(&b)) {
+        return Some(format!("Input contains control byte {:#04x} at offset {}, likely binary", bytes[pos], pos));
+    }
+    let has_marker = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .any(|word| RUST_MARKERS.contains(&word));
+    if has_marker || text.trim().is_empty() {
+        None
+    } else {
+        Some("Input has no fn, verus!, impl or mod, likely not Rust".to_string())
+    }
+}
+
+/// Whether any group in `tokens` is nested deeper than `max_depth`
+fn nesting_exceeds(tokens: TokenStream, max_depth: usize) -> bool {
+    // Explicit stack, so pathological input cannot overflow the call stack
+    let mut stack = vec![tokens.into_iter()];
+    while let Some(iter) = stack.last_mut() {
+        match iter.next() {
+            Some(TokenTree::Group(group)) => {
+                if stack.len() > max_depth {
+                    return true;
+                }
+                stack.push(group.stream().into_iter());
+            }
+            Some(_) => {}
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    false
+}
+
+/// Parse `content` and collect the specs of all functions (or only those named
+/// `target_name`), enforcing the limits in `options`
+///
+/// With a timeout the parse runs on a worker thread, which is abandoned (left
+/// to finish in the background) when the timeout expires.
+pub fn parse_guarded(
+    content: &str,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<Vec<FunctionSpecs>, ParseFailure> {
+    run_guarded(content, target_name, options, parse_limited)
+}
+
+/// `parse_guarded`, also returning the places where extraction was lossy
+pub fn parse_guarded_with_diagnostics(
+    content: &str,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    run_guarded(content, target_name, options, parse_limited_with_diagnostics)
+}
+
+/// Run `parse` on the arguments, on a worker thread if `options` has a timeout;
+/// a panic in the parser is reported as a "panic" failure
+fn run_guarded<T: Send + 'static>(
+    content: &str,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+    parse: fn(&str, Option<&str>, &ParseOptions) -> Result<T, ParseFailure>,
+) -> Result<T, ParseFailure> {
+    let parse = move |content: &str, target_name: Option<&str>, options: &ParseOptions| {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parse(content, target_name, options)))
+            .unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|m| m.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Err(ParseFailure::new("panic", format!("Parser panicked: {}", message)))
+            })
+    };
+    let Some(timeout_ms) = options.timeout_ms else {
+        return parse(content, target_name, options);
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let content = content.to_string();
+    let target_name = target_name.map(str::to_string);
+    let options = options.clone();
+    std::thread::spawn(move || {
+        // The receiver is gone if the parse timed out
+        let _ = sender.send(parse(&content, target_name.as_deref(), &options));
+    });
+    receiver
+        .recv_timeout(std::time::Duration::from_millis(timeout_ms))
+        .unwrap_or_else(|_| Err(ParseFailure::new("timeout", format!("Parsing took longer than {} ms", timeout_ms))))
+}
+
+/// `parse_guarded` without the timeout
+pub(crate) fn parse_limited(
+    content: &str,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<Vec<FunctionSpecs>, ParseFailure> {
+    parse_limited_with_diagnostics(content, target_name, options).map(|(functions, _)| functions)
+}
+
+/// `parse_guarded_with_diagnostics` without the timeout
+fn parse_limited_with_diagnostics(
+    content: &str,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    if let Some(max) = options.max_bytes {
+        if content.len() > max {
+            return Err(ParseFailure::new(
+                "max_bytes",
+                format!("Input is {} bytes, more than the limit of {}", content.len(), max),
+            ));
+        }
+    }
+    if !options.strict {
+        if let Some(reason) = not_rust_source(content.as_bytes()) {
+            return Err(ParseFailure::new("not_rust_source", reason));
+        }
+    }
+    if let Some(max) = options.max_depth {
+        // Input that does not lex is reported by the parser below
+        if let Ok(tokens) = content.parse::<TokenStream>() {
+            if nesting_exceeds(tokens, max) {
+                return Err(ParseFailure::new("max_depth", format!("Nesting deeper than {} levels", max)));
+            }
+        }
+    }
+    let parallel = options.parallel_threshold.is_some_and(|threshold| content.len() >= threshold);
+    if parallel && options.max_items.is_none() && !options.module_doc {
+        if let Ok(tokens) = content.parse::<TokenStream>() {
+            let (mut functions, mut diagnostics) = parse_parallel(content, tokens, target_name, options)?;
+            add_comment_suppressions(&mut functions, content);
+            add_todo_markers(&mut functions, content, options.todo_markers.as_deref());
+            add_assert_comments(&mut functions, content);
+            apply_result_options(&mut functions, options);
+            summarize_exclusions(&mut diagnostics);
+            relocate(&mut functions, &mut diagnostics, options);
+            return Ok((functions, diagnostics));
+        }
+    }
+    let file = verus_syn::parse_file(content)
+        .map_err(|e| ParseFailure::new("parse_error", format!("Parse error: {}", e)))?;
+    let mut finder = FunctionFinder::new(target_name.map(str::to_string));
+    finder.keep_signatures = false;
+    finder.max_items = options.max_items;
+    finder.item_macros = options.item_macros;
+    finder.verus_macro_names = options.verus_macro_names.clone();
+    finder.resolve_self = options.resolve_self;
+    finder.attribute_specs = options.attribute_specs;
+    finder.keep_header_ranges = options.header_source;
+    finder.tests_module_heuristic = options.tests_module_heuristic;
+    finder.exclude_attributes = options.exclude_attributes.clone();
+    finder.exclude_name_patterns = options.exclude_name_patterns.clone();
+    finder.visit_file(&file);
+    if let Some(failure) = finder.limit_error {
+        return Err(failure);
+    }
+    let mut functions = finder.functions;
+    set_header_source(&mut functions, &finder.header_ranges, content, content.len() - parsed_source(content).len());
+    add_comment_suppressions(&mut functions, content);
+    add_todo_markers(&mut functions, content, options.todo_markers.as_deref());
+    add_assert_comments(&mut functions, content);
+    apply_result_options(&mut functions, options);
+    if options.module_doc {
+        let docs = collect_module_docs(&file, &options.verus_macro_names);
+        for f in &mut functions {
+            f.module_doc = docs.get(&f.module_path).cloned();
+        }
+    }
+    let mut diagnostics = finder.diagnostics;
+    summarize_exclusions(&mut diagnostics);
+    relocate(&mut functions, &mut diagnostics, options);
+    Ok((functions, diagnostics))
+}
+
+/// Report results in the coordinates of the document the content was taken
+/// from (`file_path` and `line_offset` of `options`). Runs last, after the
+/// per-batch line moves of a parallel parse.
+fn relocate(functions: &mut [FunctionSpecs], diagnostics: &mut [Diagnostic], options: &ParseOptions) {
+    if let Some(path) = &options.file_path {
+        set_file_path(functions, path);
+    }
+    if options.line_offset == 0 {
+        return;
+    }
+    for f in functions.iter_mut() {
+        shift_lines(f, options.line_offset as isize);
+    }
+    for diagnostic in diagnostics {
+        if let Some(line) = &mut diagnostic.line_number {
+            *line += options.line_offset;
+        }
+    }
+}
+
+/// Set `header_source` from header byte ranges (relative to `base` in
+/// `content`), if any were recorded
+fn set_header_source(functions: &mut [FunctionSpecs], ranges: &[Range<usize>], content: &str, base: usize) {
+    for (f, range) in functions.iter_mut().zip(ranges) {
+        let text = content.get(base + range.start..base + range.end);
+        f.header_source = text.map(|text| text.trim_end().to_string());
+    }
+}
+
+/// Most syntax errors `parse_recovering` reports; later ones are mostly
+/// cascades of earlier ones
+const MAX_PARSE_ERRORS: usize = 10;
+
+/// Keywords that can start an item (with `#`, `}` and comments)
+const ITEM_START_WORDS: &[&str] = &[
+    "fn", "pub", "proof", "spec", "exec", "open", "closed", "tracked", "ghost", "broadcast", "axiom", "impl",
+    "struct", "enum", "union", "mod", "use", "trait", "const", "static", "type", "extern", "unsafe", "global",
+    "verus",
+];
+
+/// If a comment, string or char literal starts at byte `i` of `bytes[..end]`,
+/// the byte just after it
+fn skip_literal(bytes: &[u8], i: usize, end: usize) -> Option<usize> {
+    let at = |j: usize| if j < end { bytes[j] } else { 0 };
+    match (at(i), at(i + 1)) {
+        (b'/', b'/') => Some((i..end).find(|&j| bytes[j] == b'\n').unwrap_or(end)),
+        (b'/', b'*') => Some((i + 2..end).find(|&j| bytes[j] == b'*' && at(j + 1) == b'/').map_or(end, |j| j + 2)),
+        (b'"', _) => {
+            let mut j = i + 1;
+            while j < end && bytes[j] != b'"' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some((j + 1).min(end))
+        }
+        (b'\'', b'\\') => Some((i + 2..end).find(|&j| bytes[j] == b'\'').map_or(end, |j| j + 1)),
+        // `'a'` is a char, `'a` a lifetime
+        (b'\'', _) if at(i + 2) == b'\'' => Some(i + 3),
+        _ => None,
+    }
+}
+
+/// Whether an item plausibly starts at the beginning of `rest`
+fn starts_item(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with(['#', '}']) || rest.starts_with("//") || rest.starts_with("/*") {
+        return true;
+    }
+    let word: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    ITEM_START_WORDS.contains(&word.as_str())
+}
+
+/// Byte ranges of the items of `text[range]`, found on the text so that
+/// unbalanced delimiters (which the tokenizer rejects) only spoil their own
+/// item: an item ends at a `;` outside any delimiters or at the brace closing
+/// its body (when an item follows, even if parentheses are left open)
+fn item_chunks(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut chunks = Vec::new();
+    let (mut braces, mut others) = (0usize, 0usize);
+    let mut start = range.start;
+    let mut i = range.start;
+    while i < range.end {
+        if let Some(next) = skip_literal(bytes, i, range.end) {
+            i = next;
+            continue;
+        }
+        let ends = match bytes[i] {
+            b'{' => {
+                braces += 1;
+                false
+            }
+            b'}' => {
+                braces = braces.saturating_sub(1);
+                braces == 0 && (others == 0 || starts_item(&text[i + 1..range.end]))
+            }
+            b'(' | b'[' => {
+                others += 1;
+                false
+            }
+            b')' | b']' => {
+                others = others.saturating_sub(1);
+                false
+            }
+            b';' => braces == 0 && others == 0,
+            _ => false,
+        };
+        i += 1;
+        if ends {
+            // The `;` of `const C: S = S { .. };` belongs to the item
+            let rest = &text[i..range.end];
+            if bytes[i - 1] == b'}' && rest.trim_start().starts_with(';') {
+                i += rest.len() - rest.trim_start().len() + 1;
+            }
+            if !text[start..i].trim().is_empty() {
+                chunks.push(start..i);
+            }
+            start = i;
+            others = 0;
+        }
+    }
+    if !text[start..range.end].trim().is_empty() {
+        chunks.push(start..range.end);
+    }
+    chunks
+}
+
+/// The byte range of the body of an item chunk holding items: a `verus!`
+/// block, impl, module or trait
+fn container_body(text: &str, chunk: Range<usize>, verus_macro_names: &[String]) -> Option<Range<usize>> {
+    let (header, open) = chunk_header(text, chunk.clone())?;
+    let is_verus = header.trim_end().strip_suffix('!').is_some_and(|path| is_verus_name(path, verus_macro_names));
+    if !is_verus && !matches!(header_kind(&header), Some("impl" | "mod" | "trait")) {
+        return None;
+    }
+    let close = open + text[open..chunk.end].rfind('}')?;
+    Some(open + 1..close)
+}
+
+/// The text of an item chunk before its first `{` outside parentheses and
+/// brackets (comments and literals replaced by a space), and that brace's position
+fn chunk_header(text: &str, chunk: Range<usize>) -> Option<(String, usize)> {
+    let bytes = text.as_bytes();
+    let mut header = String::new();
+    let mut others = 0usize;
+    let mut i = chunk.start;
+    loop {
+        if i >= chunk.end {
+            return None;
+        }
+        if let Some(next) = skip_literal(bytes, i, chunk.end) {
+            header.push(' ');
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'{' if others == 0 => return Some((header, i)),
+            b'(' | b'[' => others += 1,
+            b')' | b']' => others = others.saturating_sub(1),
+            _ => {}
+        }
+        header.push(bytes[i] as char);
+        i += 1;
+    }
+}
+
+/// The first item keyword of a chunk header ("fn", "impl", "mod", ...)
+fn header_kind(header: &str) -> Option<&str> {
+    const KINDS: &[&str] = &["fn", "impl", "mod", "trait", "struct", "enum", "union", "const", "static", "type"];
+    header.split(|c: char| !(c.is_alphanumeric() || c == '_')).find(|word| KINDS.contains(word))
+}
+
+/// Whether a macro path as written (`verus`, `vstd::prelude::verus`) names
+/// `verus!` or one of the configured macros wrapping it
+fn is_verus_name(path: &str, verus_macro_names: &[String]) -> bool {
+    let name = path.rsplit("::").next().unwrap_or(path).trim();
+    name == "verus" || verus_macro_names.iter().any(|n| n == name)
+}
+
+/// The innermost item chunk of `text[range]` at byte `offset`: the last one
+/// starting at or before it, descending into bodies that hold items
+fn innermost_chunk(text: &str, range: Range<usize>, offset: usize, names: &[String]) -> Option<Range<usize>> {
+    let chunk = item_chunks(text, range).into_iter().rev().find(|chunk| chunk.start <= offset)?;
+    match container_body(text, chunk.clone(), names) {
+        Some(body) if offset >= body.start => innermost_chunk(text, body, offset, names).or(Some(chunk)),
+        _ => Some(chunk),
+    }
+}
+
+/// The innermost item chunk of `text[range]` that does not tokenize
+fn unlexable_chunk(text: &str, range: Range<usize>, names: &[String]) -> Option<Range<usize>> {
+    let chunk = item_chunks(text, range).into_iter().find(|chunk| text[chunk.clone()].parse::<TokenStream>().is_err())?;
+    container_body(text, chunk.clone(), names)
+        .and_then(|body| unlexable_chunk(text, body, names))
+        .or(Some(chunk))
+}
+
+/// Collects the errors of `verus!` bodies that do not parse
+struct VerusBodyErrors<'a> {
+    verus_macro_names: &'a [String],
+    errors: Vec<verus_syn::Error>,
+}
+
+impl<'ast> Visit<'ast> for VerusBodyErrors<'_> {
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        if is_verus_macro(&node.mac.path, self.verus_macro_names) {
+            match verus_syn::parse2::<VerusMacroBody>(node.mac.tokens.clone()) {
+                Ok(body) => body.items.iter().for_each(|item| self.visit_item(item)),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                if is_verus_macro(&mac.mac.path, self.verus_macro_names) {
+                    if let Err(e) = verus_syn::parse2::<VerusImplMacroBody>(mac.mac.tokens.clone()) {
+                        self.errors.push(e);
+                    }
+                }
+            }
+        }
+        verus_syn::visit::visit_item_impl(self, node);
+    }
+}
+
+/// The first syntax error of `text` as (message, byte offset, innermost item
+/// chunk around it), None if it parses (`verus!` bodies included)
+fn locate_syntax_error(text: &str, names: &[String]) -> Option<(String, usize, Option<Range<usize>>)> {
+    let whole = 0..text.len();
+    if let Err(e) = text.parse::<TokenStream>() {
+        let chunk = unlexable_chunk(text, whole, names);
+        let offset = chunk.as_ref().map_or(0, |chunk| {
+            let chunk_text = &text[chunk.clone()];
+            chunk.start + chunk_text.len() - chunk_text.trim_start().len()
+        });
+        return Some((format!("Parse error: {}", e), offset, chunk));
+    }
+    let error = match verus_syn::parse_file(text) {
+        Err(e) => e,
+        Ok(file) => {
+            let mut bodies = VerusBodyErrors { verus_macro_names: names, errors: Vec::new() };
+            bodies.visit_file(&file);
+            bodies.errors.into_iter().min_by_key(|e| (e.span().start().line, e.span().start().column))?
+        }
+    };
+    let start = error.span().start();
+    let offset = LineIndex::new(text).offset(start.line.max(1), start.column);
+    let chunk = (start.line > 0).then(|| innermost_chunk(text, whole, offset, names)).flatten();
+    Some((format!("Parse error: {}", error), offset, chunk))
+}
+
+/// Line (1-indexed) of byte `offset` of `text`
+fn line_at(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+/// Position of the delimiter closing the one opening at byte `open` of
+/// `text[..end]`, counting all three kinds alike; None if it is never closed
+fn matching_close(text: &str, open: usize, end: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open;
+    while i < end {
+        if let Some(next) = skip_literal(bytes, i, end) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// If an item chunk is a macro invocation (`path! { ... }`, after any
+/// attributes and comments), its path as written without spaces, the
+/// position where the path starts, the opening delimiter's position and the
+/// closing one's (None if it is never closed)
+fn macro_invocation(text: &str, chunk: Range<usize>) -> Option<(String, usize, usize, Option<usize>)> {
+    let bytes = text.as_bytes();
+    let mut i = chunk.start;
+    loop {
+        while i < chunk.end && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if let Some(next) = skip_literal(bytes, i, chunk.end) {
+            i = next;
+        } else if bytes.get(i) == Some(&b'#') {
+            let open = text[i..chunk.end].find('[')? + i;
+            i = matching_close(text, open, chunk.end)? + 1;
+        } else {
+            break;
+        }
+    }
+    let start = i;
+    while i < chunk.end && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b':') {
+        i += 1;
+    }
+    let path = text[start..i].to_string();
+    let rest = &text[i..chunk.end];
+    let after_bang = rest.trim_start().strip_prefix('!')?.trim_start();
+    let open = chunk.end - after_bang.len();
+    if path.is_empty() || !after_bang.starts_with(['{', '(', '[']) {
+        return None;
+    }
+    Some((path, start, open, matching_close(text, open, chunk.end)))
+}
+
+/// Delimiter problems in `text[range]`, found on the text (as tokenizing
+/// fails on them): the first closing delimiter of the wrong kind, the first
+/// one with nothing to close, the deepest delimiter left open at the end,
+/// and (for a missing delimiter balanced out further on) the first opening
+/// one whose closing delimiter starts a line indented less than its own
+fn delimiter_issues(text: &str, range: Range<usize>) -> Vec<Diagnostic> {
+    let bytes = text.as_bytes();
+    let line_start = |pos: usize| text[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let indent = |pos: usize| {
+        let start = line_start(pos);
+        text[start..].bytes().take_while(|b| *b == b' ' || *b == b'\t').count()
+    };
+    let issue = |line: usize, detail: String| Diagnostic::new("unbalanced_delimiter", line, None, detail);
+    let mut stack: Vec<(char, usize, usize)> = Vec::new();
+    let (mut mismatch, mut negative, mut suspect) = (None, None, None);
+    let mut line = line_at(text, range.start);
+    let mut i = range.start;
+    while i < range.end {
+        if let Some(next) = skip_literal(bytes, i, range.end) {
+            line += text[i..next].matches('\n').count();
+            i = next;
+            continue;
+        }
+        let c = bytes[i] as char;
+        match c {
+            '\n' => line += 1,
+            '{' | '(' | '[' => stack.push((c, i, line)),
+            '}' | ')' | ']' => match stack.pop() {
+                None => {
+                    negative.get_or_insert_with(|| issue(line, format!("`{}` has no opening delimiter", c)));
+                }
+                Some((open, _, open_line)) if !matches!((open, c), ('{', '}') | ('(', ')') | ('[', ']')) => {
+                    let detail = format!("`{}` closes the `{}` opened on line {}", c, open, open_line);
+                    mismatch.get_or_insert_with(|| issue(line, detail));
+                }
+                Some((open, open_pos, open_line)) => {
+                    let starts_line = text[line_start(i)..i].trim().is_empty();
+                    if open_line != line && starts_line && indent(i) < indent(open_pos) && suspect.is_none() {
+                        suspect = Some(issue(
+                            open_line,
+                            format!(
+                                "`{}` may be missing its `{}`: the one closing it, on line {}, is indented less",
+                                open, c, line
+                            ),
+                        ));
+                    }
+                }
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    let unclosed = stack.last().map(|(open, _, open_line)| {
+        let others = match stack.len() {
+            1 => String::new(),
+            n => format!(" ({} delimiters left open in all)", n),
+        };
+        issue(*open_line, format!("`{}` is never closed{}", open, others))
+    });
+    let mut issues: Vec<Diagnostic> = [suspect, mismatch, negative, unclosed].into_iter().flatten().collect();
+    issues.sort_by_key(|d| d.line_number);
+    issues
+}
+
+/// The `verus!` blocks of `text[range]`, found on the text so that blocks
+/// whose bodies do not tokenize are listed too; looks into modules, impls
+/// and traits
+fn collect_verus_blocks(
+    text: &str,
+    range: Range<usize>,
+    names: &[String],
+    in_impl: bool,
+    blocks: &mut Vec<VerusBlock>,
+) {
+    for chunk in item_chunks(text, range) {
+        if let Some((path, start, open, close)) = macro_invocation(text, chunk.clone()) {
+            if is_verus_name(&path, names) {
+                blocks.push(verus_block(text, path, start, open, close.ok_or(chunk.end), in_impl));
+            }
+            continue;
+        }
+        let kind = chunk_header(text, chunk.clone()).and_then(|(header, _)| header_kind(&header).map(str::to_string));
+        if let (Some(kind), Some(body)) = (kind, container_body(text, chunk, names)) {
+            collect_verus_blocks(text, body, names, kind == "impl", blocks);
+        }
+    }
+}
+
+/// Describe one `verus!` invocation; `close` is the closing delimiter's
+/// position, or the end of the text it runs to if it is never closed
+fn verus_block(
+    text: &str,
+    path: String,
+    start: usize,
+    open: usize,
+    close: Result<usize, usize>,
+    in_impl: bool,
+) -> VerusBlock {
+    let delimiter = match text.as_bytes()[open] {
+        b'(' => "()",
+        b'[' => "[]",
+        _ => "{}",
+    };
+    let open_line = line_at(text, open);
+    let mut block = VerusBlock {
+        macro_path: path,
+        delimiter: delimiter.to_string(),
+        line_number: line_at(text, start),
+        end_line: line_at(text, close.unwrap_or_else(|end| end)),
+        in_impl,
+        ..Default::default()
+    };
+    let parsed = match close {
+        Err(_) => Err((format!("`{}` opened on line {} is never closed", &delimiter[..1], open_line), None)),
+        Ok(close) => match text[open + 1..close].parse::<TokenStream>() {
+            Err(e) => Err((format!("Parse error: {}", e), None)),
+            Ok(tokens) => {
+                let parsed = if in_impl {
+                    verus_syn::parse2::<VerusImplMacroBody>(tokens).map(|_| ())
+                } else {
+                    verus_syn::parse2::<VerusMacroBody>(tokens).map(|_| ())
+                };
+                // Spans count lines from the body's first line
+                parsed.map_err(|e| (format!("Parse error: {}", e), Some(open_line + e.span().start().line - 1)))
+            }
+        },
+    };
+    if let Err((error, error_line)) = parsed {
+        block.error = Some(error);
+        block.error_line = error_line.filter(|line| *line >= open_line);
+        block.delimiter_issues = delimiter_issues(text, open + 1..close.unwrap_or_else(|end| end));
+    } else {
+        block.parsed = true;
+    }
+    block
+}
+
+/// The `verus!` blocks of a file in source order
+pub fn verus_block_ranges_core(content: &str, names: &[String]) -> Vec<VerusBlock> {
+    let mut blocks = Vec::new();
+    collect_verus_blocks(content, 0..content.len(), names, false, &mut blocks);
+    blocks
+}
+
+/// "unbalanced_delimiter" diagnostics for a file that does not parse: those
+/// of its `verus!` blocks that do not parse, else (for a file that does not
+/// tokenize) those of the whole file
+pub fn delimiter_diagnostics(content: &str, names: &[String]) -> Vec<Diagnostic> {
+    let mut issues: Vec<Diagnostic> = verus_block_ranges_core(content, names)
+        .into_iter()
+        .flat_map(|block| block.delimiter_issues)
+        .collect();
+    if issues.is_empty() && content.parse::<TokenStream>().is_err() {
+        issues = delimiter_issues(content, 0..content.len());
+    }
+    issues
+}
+
+/// `parse_guarded_with_diagnostics`, recovering from syntax errors
+///
+/// The item around each error (the innermost one, inside `verus!` blocks,
+/// impls, modules and traits) is blanked out and the rest parsed again, so
+/// the functions around it are still extracted. Up to `MAX_PARSE_ERRORS`
+/// errors are reported and the rest only counted, followed by the
+/// `delimiter_diagnostics`. A file whose errors cannot all be placed keeps
+/// the plain result (with those diagnostics if it parses).
+pub fn parse_recovering(
+    content: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    // Syntax errors are placed in `content`, so everything is relocated at the end
+    let local = options.per_file();
+    let (mut functions, mut diagnostics) = recover_syntax_errors(content, &local)?;
+    relocate(&mut functions, &mut diagnostics, options);
+    Ok((functions, diagnostics))
+}
+
+/// `parse_recovering` in the coordinates of `content`
+fn recover_syntax_errors(
+    content: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    let original = parse_guarded_with_diagnostics(content, None, options);
+    let recoverable = match &original {
+        Ok((_, diagnostics)) => diagnostics.iter().any(|d| d.kind == "verus_body_skipped"),
+        Err(failure) => failure.kind == "parse_error",
+    };
+    if !recoverable {
+        return original;
+    }
+    let names = &options.verus_macro_names;
+    // Blanking keeps byte offsets but not columns (a blanked character may
+    // have been several bytes), so positions are taken from the original
+    let index = LineIndex::new(content);
+    let mut text = content.to_string();
+    let mut errors = Vec::new();
+    let mut dropped = 0;
+    while let Some((message, offset, chunk)) = locate_syntax_error(&text, names) {
+        let (line, column) = index.position(offset);
+        if errors.len() < MAX_PARSE_ERRORS {
+            errors.push(Diagnostic {
+                kind: "parse_error".to_string(),
+                line_number: Some(line),
+                column: Some(column),
+                context: content.lines().nth(line.saturating_sub(1)).map(|l| l.trim().to_string()),
+                function: None,
+                detail: message,
+            });
+        } else {
+            dropped += 1;
+        }
+        let Some(chunk) = chunk else { break };
+        let blank: String = text[chunk.clone()].bytes().map(|b| if b == b'\n' { '\n' } else { ' ' }).collect();
+        text.replace_range(chunk, &blank);
+    }
+    let with_delimiter_issues = |(functions, mut diagnostics): (Vec<FunctionSpecs>, Vec<Diagnostic>)| {
+        diagnostics.extend(delimiter_diagnostics(content, names));
+        (functions, diagnostics)
+    };
+    if errors.is_empty() {
+        return original.map(with_delimiter_issues);
+    }
+    // Tokenizer errors are found first, wherever they are
+    errors.sort_by_key(|d| (d.line_number, d.column));
+    if dropped > 0 {
+        errors.push(Diagnostic {
+            kind: "parse_errors_truncated".to_string(),
+            detail: format!("{} more syntax errors not reported", dropped),
+            ..Default::default()
+        });
+    }
+    match parse_guarded_with_diagnostics(&text, None, options) {
+        Ok((functions, diagnostics)) => {
+            Ok(with_delimiter_issues((functions, errors.into_iter().chain(diagnostics).collect())))
+        }
+        Err(_) => original.map(with_delimiter_issues),
+    }
+}
+
+/// Apply the options that post-process extracted functions
+fn apply_result_options(functions: &mut Vec<FunctionSpecs>, options: &ParseOptions) {
+    if options.skip_tests {
+        functions.retain(|f| !f.in_test);
+    }
+    if let Some(mode) = &options.mode {
+        functions.retain(|f| mode_matches(&f.mode, mode));
+    }
+    if options.split_conjunctions {
+        for f in functions.iter_mut() {
+            // Mentions follow the split clauses; the locals are the names any clause used as such
+            let locals: Vec<String> =
+                f.requires_mentions.iter().chain(&f.ensures_mentions).flat_map(|m| m.params.clone()).collect();
+            let remention = |clause: &String| {
+                verus_syn::parse_str::<Expr>(clause).map(|e| clause_mentions(&e, &locals)).unwrap_or_default()
+            };
+            let (requires, requires_parent) = split_conjunctions(&f.requires);
+            f.requires_raw = std::mem::replace(&mut f.requires, requires);
+            f.requires_parent = requires_parent;
+            let (ensures, ensures_parent) = split_conjunctions(&f.ensures);
+            f.ensures_raw = std::mem::replace(&mut f.ensures, ensures);
+            f.ensures_parent = ensures_parent;
+            f.requires_mentions = f.requires.iter().map(remention).collect();
+            f.ensures_mentions = f.ensures.iter().map(remention).collect();
+        }
+    }
+    if options.clause_offsets {
+        for f in functions.iter_mut() {
+            f.requires_offsets = f.requires.iter().map(|clause| clause_offsets(clause)).collect();
+            f.ensures_offsets = f.ensures.iter().map(|clause| clause_offsets(clause)).collect();
+        }
+    }
+}
+
+/// A token of a rendered clause with its character range in the rendering
+struct RenderedToken {
+    tt: TokenTree,
+    range: Range<usize>,
+    /// Tokens inside a group
+    children: Vec<RenderedToken>,
+}
+
+/// Render tokens as `TokenStream::to_string` does, recording where each token lands
+fn render_tracked(tokens: TokenStream, out: &mut String, pos: &mut usize) -> Vec<RenderedToken> {
+    let write = |out: &mut String, pos: &mut usize, text: &str| {
+        out.push_str(text);
+        *pos += text.chars().count();
+    };
+    let mut rendered = Vec::new();
+    let mut joint = false;
+    for (i, tt) in tokens.into_iter().enumerate() {
+        if i != 0 && !joint {
+            write(out, pos, " ");
+        }
+        joint = matches!(&tt, TokenTree::Punct(p) if p.spacing() == Spacing::Joint);
+        let start = *pos;
+        let children = match &tt {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{ ", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                write(out, pos, open);
+                let children = render_tracked(group.stream(), out, pos);
+                if group.delimiter() == Delimiter::Brace && !children.is_empty() {
+                    write(out, pos, " ");
+                }
+                write(out, pos, close);
+                children
+            }
+            _ => {
+                write(out, pos, &tt.to_string());
+                Vec::new()
+            }
+        };
+        rendered.push(RenderedToken { range: start..*pos, tt, children });
+    }
+    rendered
+}
+
+/// Character ranges of the quantifier bodies, trigger terms and top-level
+/// relation sides of a clause rendered by `TokenStream::to_string` (ranges are
+/// left out if the clause is not in that form)
+fn clause_offsets(clause: &str) -> ClauseOffsets {
+    fn count(tokens: &[RenderedToken]) -> usize {
+        tokens.iter().map(|t| if t.children.is_empty() { 1 } else { count(&t.children) }).sum()
+    }
+    let Ok(tokens) = clause.parse::<TokenStream>() else {
+        return ClauseOffsets::default();
+    };
+    let mut rendered = String::new();
+    let tokens = render_tracked(tokens, &mut rendered, &mut 0);
+    let mut offsets = ClauseOffsets { token_count: count(&tokens), ranges: Vec::new() };
+    if rendered == clause {
+        collect_clause_ranges(&tokens, &mut offsets.ranges);
+        if let Some((lhs, rhs)) = relation_sides(&tokens) {
+            offsets.ranges.push(ClauseRange { kind: "lhs".to_string(), start: lhs.start, end: lhs.end });
+            offsets.ranges.push(ClauseRange { kind: "rhs".to_string(), start: rhs.start, end: rhs.end });
+        }
+        offsets.ranges.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+    }
+    offsets
+}
+
+/// The range from the first to the last of `tokens`
+fn tokens_range(tokens: &[RenderedToken]) -> Option<Range<usize>> {
+    Some(tokens.first()?.range.start..tokens.last()?.range.end)
+}
+
+/// Whether a rendered token is the punctuation character `c`
+fn is_punct(token: Option<&RenderedToken>, c: char) -> bool {
+    matches!(token.map(|t| &t.tt), Some(TokenTree::Punct(p)) if p.as_char() == c)
+}
+
+/// Collect quantifier bodies and trigger terms, at any depth
+fn collect_clause_ranges(tokens: &[RenderedToken], ranges: &mut Vec<ClauseRange>) {
+    let push = |ranges: &mut Vec<ClauseRange>, kind: &str, range: Option<Range<usize>>| {
+        if let Some(range) = range {
+            ranges.push(ClauseRange { kind: kind.to_string(), start: range.start, end: range.end });
+        }
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.tt {
+            // forall|x: int| body: the body runs to the end of the enclosing tokens
+            TokenTree::Ident(ident)
+                if ["forall", "exists", "choose"].contains(&ident.to_string().as_str())
+                    && is_punct(tokens.get(i + 1), '|') =>
+            {
+                if let Some(close) = (i + 2..tokens.len()).find(|&j| is_punct(tokens.get(j), '|')) {
+                    push(ranges, "quantifier_body", tokens_range(&tokens[close + 1..]));
+                }
+            }
+            // #[trigger] term, or #![trigger term, ...]
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let inner = is_punct(tokens.get(i + 1), '!');
+                let Some(attr) = tokens.get(i + 1 + inner as usize) else {
+                    continue;
+                };
+                let TokenTree::Group(group) = &attr.tt else {
+                    continue;
+                };
+                let is_trigger = matches!(attr.children.first().map(|t| &t.tt), Some(TokenTree::Ident(id)) if id == "trigger");
+                if group.delimiter() != Delimiter::Bracket || !is_trigger {
+                    continue;
+                }
+                if inner {
+                    for term in attr.children[1..].split(|t| is_punct(Some(t), ',')) {
+                        push(ranges, "trigger", tokens_range(term));
+                    }
+                } else {
+                    let rest = &tokens[i + 2..];
+                    push(ranges, "trigger", tokens_range(&rest[..postfix_term_len(rest)]));
+                }
+            }
+            _ => {}
+        }
+        collect_clause_ranges(&token.children, ranges);
+    }
+}
+
+/// Number of tokens of the primary expression at the start of `tokens`: a
+/// group, literal or path, followed by calls, indexing, fields and method calls
+fn postfix_term_len(tokens: &[RenderedToken]) -> usize {
+    let mut i = match tokens.first().map(|t| &t.tt) {
+        Some(TokenTree::Group(_) | TokenTree::Literal(_) | TokenTree::Ident(_)) => 1,
+        _ => return 0,
+    };
+    if matches!(tokens[0].tt, TokenTree::Ident(_)) {
+        while is_punct(tokens.get(i), ':') && is_punct(tokens.get(i + 1), ':') {
+            i += 2;
+            if matches!(tokens.get(i).map(|t| &t.tt), Some(TokenTree::Ident(_))) {
+                i += 1;
+            }
+        }
+    }
+    loop {
+        match tokens.get(i).map(|t| &t.tt) {
+            Some(TokenTree::Group(group)) if group.delimiter() != Delimiter::Brace => i += 1,
+            Some(TokenTree::Punct(p)) if p.as_char() == '.' => {
+                if !matches!(tokens.get(i + 1).map(|t| &t.tt), Some(TokenTree::Ident(_) | TokenTree::Literal(_))) {
+                    return i;
+                }
+                i += 2;
+            }
+            _ => return i,
+        }
+    }
+}
+
+/// The sides of a clause that is a single comparison at its top level (no
+/// lower-precedence operator, quantifier or binding next to it)
+fn relation_sides(tokens: &[RenderedToken]) -> Option<(Range<usize>, Range<usize>)> {
+    const RELATIONS: &[&str] = &["==", "!=", "<", "<=", ">", ">=", "===", "!==", "=~=", "=~~="];
+    const LOOSER: &[&str] = &["&&", "||", "&&&", "|||", "==>", "<==", "<==>", "|", "=", "=>"];
+    let mut relation = None;
+    let mut generic_depth = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i].tt {
+            TokenTree::Ident(ident) => {
+                if ["forall", "exists", "choose", "if", "match", "let", "matches"].contains(&ident.to_string().as_str()) {
+                    return None;
+                }
+                i += 1;
+            }
+            TokenTree::Punct(_) => {
+                // Multi-character operators are runs of Joint punctuation
+                let start = i;
+                let mut op = String::new();
+                while let Some(TokenTree::Punct(p)) = tokens.get(i).map(|t| &t.tt) {
+                    op.push(p.as_char());
+                    i += 1;
+                    if p.spacing() != Spacing::Joint {
+                        break;
+                    }
+                }
+                if op == "::<" || (op == "<" && start > 0 && is_punct(tokens.get(start - 1), ':')) {
+                    generic_depth += 1;
+                } else if op == ">" && generic_depth > 0 {
+                    generic_depth -= 1;
+                } else if LOOSER.contains(&op.as_str()) {
+                    return None;
+                } else if RELATIONS.contains(&op.as_str()) && generic_depth == 0 {
+                    if relation.is_some() {
+                        return None;
+                    }
+                    relation = Some(start..i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    let op = relation?;
+    Some((tokens_range(&tokens[..op.start])?, tokens_range(&tokens[op.end..])?))
+}
+
+/// Whether a function `mode` (as in `FunctionSpecs::mode`) falls under a mode
+/// filter; "spec" covers `spec(checked)` too
+fn mode_matches(mode: &str, filter: &str) -> bool {
+    match filter {
+        "spec" => mode == "spec" || mode == "spec(checked)",
+        _ => mode == filter,
+    }
+}
+
+/// Split each clause into its top-level conjuncts, returning the conjuncts and,
+/// for each, the index of the clause it came from
+fn split_conjunctions(clauses: &[String]) -> (Vec<String>, Vec<usize>) {
+    let mut split = Vec::new();
+    let mut parents = Vec::new();
+    for (i, clause) in clauses.iter().enumerate() {
+        for conjunct in clause_conjuncts(clause) {
+            split.push(conjunct);
+            parents.push(i);
+        }
+    }
+    (split, parents)
+}
+
+/// The top-level conjuncts of a clause joined by `&&&` (including a leading
+/// one) or `&&`. `&&&` binds most loosely, so it always splits; a `&&` chain
+/// is kept whole if its top level has something the split must not cross: an
+/// operator binding more loosely than `&&` (`||`, `==>`, `<==`, `<==>`), a
+/// quantifier or closure, or a `matches`/`let` binding.
+fn clause_conjuncts(clause: &str) -> Vec<String> {
+    let Ok(tokens) = clause.parse::<TokenStream>() else {
+        return vec![clause.to_string()];
+    };
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let parts: Vec<String> = split_on_and(&tts, 3)
+        .iter()
+        .flat_map(|part| {
+            let conjuncts = split_on_and(part, 2);
+            if conjuncts.len() > 1 && conjuncts.iter().all(|c| can_split_around(c)) {
+                conjuncts
+            } else {
+                vec![part.clone()]
+            }
+        })
+        .map(|part| part.into_iter().collect::<TokenStream>().to_string())
+        .collect();
+    if parts.len() > 1 {
+        parts
+    } else {
+        vec![clause.to_string()]
+    }
+}
+
+/// Split tokens at `&&&` (`width` 3) or `&&` (`width` 2) operators, dropping empty parts
+fn split_on_and(tts: &[TokenTree], width: usize) -> Vec<Vec<TokenTree>> {
+    let is_amp = |i: usize, joint: bool| {
+        matches!(tts.get(i), Some(TokenTree::Punct(p))
+            if p.as_char() == '&' && (!joint || p.spacing() == Spacing::Joint))
+    };
+    let mut parts: Vec<Vec<TokenTree>> = vec![Vec::new()];
+    let mut i = 0;
+    while i < tts.len() {
+        // `&&&` lexes as Joint, Joint, Alone and `&&` as Joint, Alone
+        let op_len = if is_amp(i, true) && is_amp(i + 1, true) && is_amp(i + 2, false) {
+            3
+        } else if is_amp(i, true) && is_amp(i + 1, false) {
+            2
+        } else {
+            0
+        };
+        if op_len == width {
+            parts.push(Vec::new());
+            i += width;
+        } else {
+            let len = op_len.max(1);
+            parts.last_mut().unwrap().extend(tts[i..i + len].iter().cloned());
+            i += len;
+        }
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Whether a `&&` operand has none of the constructs a `&&` split must not cross
+fn can_split_around(tts: &[TokenTree]) -> bool {
+    let punct = |i: usize| match tts.get(i) {
+        Some(TokenTree::Punct(p)) => Some((p.as_char(), p.spacing())),
+        _ => None,
+    };
+    (0..tts.len()).all(|i| match &tts[i] {
+        TokenTree::Ident(ident) => {
+            !["forall", "exists", "choose", "matches", "if", "match", "let"].contains(&ident.to_string().as_str())
+        }
+        TokenTree::Punct(_) => !matches!(
+            (punct(i), punct(i + 1), punct(i + 2)),
+            (Some(('|', _)), _, _)
+                | (Some(('=', Spacing::Joint)), Some(('>', _)), _)
+                | (Some(('<', Spacing::Joint)), Some(('=', Spacing::Joint)), Some(('=', _)))
+        ),
+        _ => true,
+    })
+}
+
+/// A contiguous run of items extracted by one parallel worker, with the
+/// context the sequential visitor would have at that point
+#[derive(Clone)]
+struct ItemBatch {
+    /// Byte range of the items in the file
+    range: Range<usize>,
+    /// Line of the first item (1-indexed)
+    line: usize,
+    scope: BatchScope,
+}
+
+/// Traversal state at the start of a batch
+#[derive(Clone, Default, PartialEq)]
+struct BatchScope {
+    module_stack: Vec<String>,
+    context: Vec<String>,
+    /// Line of the macro path if the items come from a `verus!` body, where
+    /// (as in the sequential visitor) a body that does not parse is skipped
+    /// rather than an error, and reported at that line
+    macro_line: Option<usize>,
+    /// Whether an enclosing module is test-only
+    in_test: bool,
+    /// Conditions of the cfg attributes of the enclosing modules and macro invocations
+    cfgs: Vec<String>,
+    /// Why the enclosing modules and macro invocations exclude their
+    /// functions (see `ParseOptions::exclude_attributes`)
+    exclusions: Vec<String>,
+}
+
+/// Split a token stream into items: an item ends with a `;` or, unless it is a
+/// const, static, use or type item, with a brace group; an inner attribute is
+/// an item of its own
+fn split_items(tokens: TokenStream) -> Vec<Vec<TokenTree>> {
+    fn is_punct(tt: Option<&TokenTree>, ch: char) -> bool {
+        matches!(tt, Some(TokenTree::Punct(p)) if p.as_char() == ch)
+    }
+    fn ends_with_semicolon(item: &[TokenTree]) -> bool {
+        const KEYWORDS: &[&str] = &["fn", "struct", "enum", "union", "impl", "trait", "mod", "const", "static", "use", "type"];
+        let mut idents = item.iter().filter_map(|tt| match tt {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            _ => None,
+        });
+        match idents.find(|ident| KEYWORDS.contains(&ident.as_str())).as_deref() {
+            // `const fn` (possibly `const unsafe fn`) is a function
+            Some("const") => idents.find(|ident| !["unsafe", "async", "extern"].contains(&ident.as_str())).as_deref() != Some("fn"),
+            Some("static" | "use" | "type") => true,
+            _ => false,
+        }
+    }
+
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut items = Vec::new();
+    let mut current: Vec<TokenTree> = Vec::new();
+    let mut i = 0;
+    while i < tts.len() {
+        let inner_attr = current.is_empty()
+            && is_punct(tts.get(i), '#')
+            && is_punct(tts.get(i + 1), '!')
+            && matches!(tts.get(i + 2), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket);
+        if inner_attr {
+            items.push(tts[i..i + 3].to_vec());
+            i += 3;
+            continue;
+        }
+        current.push(tts[i].clone());
+        let ends = match &tts[i] {
+            TokenTree::Punct(p) => p.as_char() == ';',
+            TokenTree::Group(g) => g.delimiter() == Delimiter::Brace && !ends_with_semicolon(&current),
+            _ => false,
+        };
+        if ends {
+            items.push(std::mem::take(&mut current));
+        }
+        i += 1;
+    }
+    if !current.is_empty() {
+        items.push(current);
+    }
+    items
+}
+
+/// An item whose body `collect_batches` may descend into
+struct ItemContainer {
+    /// Breadcrumb the item adds
+    label: String,
+    /// Module name, for modules
+    module: Option<String>,
+    /// Tokens of the outer attributes
+    attrs: Vec<TokenStream>,
+    body: TokenStream,
+    /// Line of the macro path or `mod` keyword
+    line: usize,
+}
+
+/// The item as a container if it is a `verus!` block (or one of
+/// `verus_macro_names`) or an inline module
+fn item_container(item: &[TokenTree], verus_macro_names: &[String]) -> Option<ItemContainer> {
+    // Skip outer attributes and visibility
+    let mut rest = item;
+    let mut attrs = Vec::new();
+    loop {
+        match rest {
+            [TokenTree::Punct(p), TokenTree::Group(g), tail @ ..]
+                if p.as_char() == '#' && g.delimiter() == Delimiter::Bracket =>
+            {
+                attrs.push(g.stream());
+                rest = tail
+            }
+            [TokenTree::Ident(vis), TokenTree::Group(g), tail @ ..]
+                if vis == "pub" && g.delimiter() == Delimiter::Parenthesis =>
+            {
+                rest = tail
+            }
+            [TokenTree::Ident(vis), tail @ ..] if vis == "pub" => rest = tail,
+            _ => break,
+        }
+    }
+    let line = rest.first()?.span().start().line;
+    // Skip the path of a qualified invocation (`vstd::prelude::verus! { ... }`)
+    let mut path = Vec::new();
+    while let [TokenTree::Ident(segment), TokenTree::Punct(c1), TokenTree::Punct(c2), tail @ ..] = rest {
+        if c1.as_char() != ':' || c2.as_char() != ':' {
+            break;
+        }
+        path.push(segment.to_string());
+        rest = tail;
+    }
+    match rest {
+        [TokenTree::Ident(name), TokenTree::Punct(bang), TokenTree::Group(body)]
+            if (name == "verus" || verus_macro_names.iter().any(|n| name == n))
+                && bang.as_char() == '!'
+                && body.delimiter() == Delimiter::Brace =>
+        {
+            path.push(name.to_string());
+            let label = format!("{}!", path.join("::"));
+            Some(ItemContainer { label, module: None, attrs, body: body.stream(), line })
+        }
+        [TokenTree::Ident(kw), TokenTree::Ident(name), TokenTree::Group(body)]
+            if kw == "mod" && body.delimiter() == Delimiter::Brace =>
+        {
+            let module = Some(name.to_string());
+            Some(ItemContainer { label: format!("mod {}", name), module, attrs, body: body.stream(), line })
+        }
+        _ => None,
+    }
+}
+
+/// Group the items of `tokens` into batches of about `target` bytes, descending
+/// into `verus!` blocks and inline modules larger than that
+fn collect_batches(
+    tokens: TokenStream,
+    scope: &BatchScope,
+    options: &ParseOptions,
+    target: usize,
+    batches: &mut Vec<ItemBatch>,
+) {
+    // Whether the last batch belongs to this level and may be extended
+    let mut open = false;
+    for item in split_items(tokens) {
+        let (Some(first), Some(last)) = (item.first(), item.last()) else {
+            continue;
+        };
+        let range = first.span().byte_range().start..last.span().byte_range().end;
+        if range.len() > target {
+            if let Some(ItemContainer { label, module, attrs, body, line }) =
+                item_container(&item, &options.verus_macro_names)
+            {
+                let mut inner = scope.clone();
+                inner.context.push(label);
+                inner.in_test |= attrs.iter().any(|attr| cfg_requires_test(attr.clone()));
+                let exclusion =
+                    attrs.iter().find_map(|attr| excluding_attribute(&options.exclude_attributes, attr.clone()));
+                inner.exclusions.extend(exclusion);
+                inner.cfgs.extend(attrs.into_iter().filter_map(cfg_condition));
+                match module {
+                    Some(module) => {
+                        inner.in_test |= options.tests_module_heuristic && module == "tests";
+                        inner.module_stack.push(module);
+                    }
+                    None => inner.macro_line = Some(line),
+                }
+                collect_batches(body, &inner, options, target, batches);
+                open = false;
+                continue;
+            }
+        }
+        match batches.last_mut() {
+            Some(batch) if open && batch.range.len() < target => batch.range.end = range.end,
+            _ => batches.push(ItemBatch { range, line: first.span().start().line, scope: scope.clone() }),
+        }
+        open = true;
+    }
+}
+
+/// Move the line numbers of a function (and of its proof blocks, asserts,
+/// casts and todo markers) by `delta`
+fn shift_lines(f: &mut FunctionSpecs, delta: isize) {
+    let shift = |line: &mut Option<usize>| {
+        if let Some(line) = line {
+            *line = line.saturating_add_signed(delta);
+        }
+    };
+    shift(&mut f.line_number);
+    shift(&mut f.end_line);
+    shift(&mut f.sig_end_line);
+    for block in &mut f.proof_blocks {
+        shift(&mut block.line_number);
+        shift(&mut block.end_line);
+    }
+    for assert in &mut f.asserts {
+        shift(&mut assert.line_number);
+        shift(&mut assert.end_line);
+    }
+    for cast in &mut f.casts {
+        shift(&mut cast.line_number);
+    }
+    for (line, _) in &mut f.todo_markers {
+        *line = line.saturating_add_signed(delta);
+    }
+}
+
+/// Run a FunctionFinder over one batch; the batch is parsed on its own and
+/// line numbers are then moved to where the batch is in the file
+fn extract_batch(
+    content: &str,
+    batch: &ItemBatch,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    let file = match verus_syn::parse_file(&content[batch.range.clone()]) {
+        Ok(file) => file,
+        Err(e) => match batch.scope.macro_line {
+            Some(line) => {
+                return Ok((Vec::new(), vec![Diagnostic::new("verus_body_skipped", line, None, e.to_string())]));
+            }
+            None => return Err(ParseFailure::new("parse_error", format!("Parse error: {}", e))),
+        },
+    };
+    let mut finder = FunctionFinder::new(target_name.map(str::to_string));
+    finder.keep_signatures = false;
+    finder.item_macros = options.item_macros;
+    finder.verus_macro_names = options.verus_macro_names.clone();
+    finder.resolve_self = options.resolve_self;
+    finder.attribute_specs = options.attribute_specs;
+    finder.keep_header_ranges = options.header_source;
+    finder.tests_module_heuristic = options.tests_module_heuristic;
+    finder.module_stack = batch.scope.module_stack.clone();
+    finder.context = batch.scope.context.clone();
+    finder.in_test = batch.scope.in_test;
+    finder.enclosing_cfgs = batch.scope.cfgs.clone();
+    finder.exclude_attributes = options.exclude_attributes.clone();
+    finder.exclude_name_patterns = options.exclude_name_patterns.clone();
+    finder.enclosing_exclusions = batch.scope.exclusions.clone();
+    finder.in_verus = batch.scope.macro_line.is_some();
+    finder.verus_entry = batch.scope.macro_line.map(|_| "verus_macro");
+    finder.visit_file(&file);
+    let mut functions = finder.functions;
+    // The batch was parsed on its own, so its offsets start at the batch, and
+    // so do the columns of its first line
+    set_header_source(&mut functions, &finder.header_ranges, content, batch.range.start);
+    let bom = content.len() - content.strip_prefix('\u{feff}').unwrap_or(content).len();
+    let line_start = content[..batch.range.start].rfind('\n').map_or(bom, |i| i + 1);
+    let first_column = content[line_start..batch.range.start].chars().count();
+    for f in &mut functions {
+        if f.sig_end_line == Some(1) {
+            f.sig_end_column = f.sig_end_column.map(|column| column + first_column);
+        }
+        shift_lines(f, batch.line as isize - 1);
+    }
+    let mut diagnostics = finder.diagnostics;
+    for d in &mut diagnostics {
+        d.line_number = d.line_number.map(|line| line + batch.line - 1);
+    }
+    Ok((functions, diagnostics))
+}
+
+/// A parsed file that can be updated after an edit by `reparse_incremental`
+///
+/// Keeps the source and, per item (items of `verus!` blocks and inline
+/// modules counted separately), the functions extracted from it.
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+pub struct ParsedVerusFile {
+    pub content: String,
+    /// Items in source order with their functions
+    items: Vec<(ItemBatch, Vec<FunctionSpecs>)>,
+}
+
+impl ParsedVerusFile {
+    /// The items of `content`, or None if they cannot be delimited (the source does not lex)
+    fn split(content: &str) -> Option<Vec<ItemBatch>> {
+        let tokens = content.parse::<TokenStream>().ok()?;
+        let mut items = Vec::new();
+        collect_batches(tokens, &BatchScope::default(), &ParseOptions::default(), 0, &mut items);
+        Some(items)
+    }
+
+    /// The functions of `item`, with the todo markers of its text
+    fn extract(content: &str, item: &ItemBatch) -> Result<Vec<FunctionSpecs>, String> {
+        let (mut functions, _) = extract_batch(content, item, None, &ParseOptions::default()).map_err(|e| e.message)?;
+        // Markers are found in the item's text alone, so its lines count from 1 meanwhile
+        let before = item.line as isize - 1;
+        for f in &mut functions {
+            shift_lines(f, -before);
+        }
+        add_todo_markers(&mut functions, &content[item.range.clone()], None);
+        for f in &mut functions {
+            shift_lines(f, before);
+        }
+        Ok(functions)
+    }
+
+    /// Parse a whole file
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let Some(items) = Self::split(content) else {
+            // Report the parser's error
+            verus_syn::parse_file(content).map_err(|e| format!("Parse error: {}", e))?;
+            return Err("Parse error: unbalanced delimiters".to_string());
+        };
+        let items = items
+            .into_iter()
+            .map(|item| {
+                let functions = Self::extract(content, &item)?;
+                Ok((item, functions))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            content: content.to_string(),
+            items,
+        })
+    }
+
+    /// Update for `new_content`, where lines `changed.0..=changed.1` (of the
+    /// new content) were edited. Items before the edit are kept as they are,
+    /// items after it are kept with their lines moved by the change in line
+    /// count, and only the items overlapping the edit are parsed again. An
+    /// item is only kept if its text is unchanged; if the items cannot be
+    /// delimited any more, the whole file is parsed.
+    pub fn reparse(&self, new_content: &str, changed: (usize, usize)) -> Result<Self, String> {
+        let Some(new_items) = Self::split(new_content) else {
+            return Self::parse(new_content);
+        };
+        let delta = new_content.matches('\n').count() as isize - self.content.matches('\n').count() as isize;
+        let old_by_line: HashMap<usize, usize> =
+            self.items.iter().enumerate().map(|(i, (item, _))| (item.line, i)).collect();
+
+        let mut items = Vec::with_capacity(new_items.len());
+        for item in new_items {
+            let text = &new_content[item.range.clone()];
+            let end_line = item.line + text.matches('\n').count();
+            // Where the item was before the edit, if it lies outside the edit
+            let old_line = if end_line < changed.0 {
+                Some(item.line)
+            } else if item.line > changed.1 {
+                item.line.checked_add_signed(-delta)
+            } else {
+                None
+            };
+            let reused = old_line
+                .and_then(|line| old_by_line.get(&line))
+                .map(|&i| &self.items[i])
+                .filter(|(old, _)| &self.content[old.range.clone()] == text && old.scope == item.scope);
+            let functions = match reused {
+                Some((old, functions)) => {
+                    let mut functions = functions.clone();
+                    for f in &mut functions {
+                        shift_lines(f, item.line as isize - old.line as isize);
+                    }
+                    functions
+                }
+                None => Self::extract(new_content, &item)?,
+            };
+            items.push((item, functions));
+        }
+        Ok(Self {
+            content: new_content.to_string(),
+            items,
+        })
+    }
+
+    pub fn all_functions(&self) -> Vec<FunctionSpecs> {
+        self.items.iter().flat_map(|(_, functions)| functions.iter().cloned()).collect()
+    }
+}
+
+/// `parse_limited` for large files: split the items (including those of
+/// `verus!` blocks and modules) into batches and extract them on the rayon
+/// pool. Syntax trees cannot move between threads, so each worker parses its
+/// own batch; results are concatenated in source order, as the sequential
+/// visitor would produce them.
+fn parse_parallel(
+    content: &str,
+    tokens: TokenStream,
+    target_name: Option<&str>,
+    options: &ParseOptions,
+) -> Result<(Vec<FunctionSpecs>, Vec<Diagnostic>), ParseFailure> {
+    let target = (content.len() / (rayon::current_num_threads() * 4)).max(1);
+    let mut batches = Vec::new();
+    collect_batches(tokens, &BatchScope::default(), options, target, &mut batches);
+    let results: Vec<_> = batches
+        .par_iter()
+        .map(|batch| extract_batch(content, batch, target_name, options))
+        .collect();
+    let mut functions = Vec::new();
+    let mut diagnostics = Vec::new();
+    for result in results {
+        let (batch_functions, batch_diagnostics) = result?;
+        functions.extend(batch_functions);
+        diagnostics.extend(batch_diagnostics);
+    }
+    Ok((functions, diagnostics))
+}
+
+/// Diagnostics per file path
+pub type FileDiagnostics = BTreeMap<String, Vec<Diagnostic>>;
+
+/// Parse in-memory files (path to content) in parallel
+///
+/// Returns the functions of all files, with `file_path` set and sorted by path
+/// and line, and the diagnostics of each file that has any; a file that fails
+/// to parse contributes no functions and a single diagnostic of its error kind.
+pub fn parse_verus_contents_core(
+    files: &HashMap<String, String>,
+    options: &ParseOptions,
+) -> (Vec<FunctionSpecs>, FileDiagnostics) {
+    let options = &options.per_file();
+    let results: Vec<_> =
+        files.par_iter().map(|(path, content)| (path.clone(), parse_file_entry(path, content, options))).collect();
+    merge_file_results(results)
+}
+
+/// The functions of one file of a multi-file parse, with `file_path` set,
+/// and its diagnostics; a file that fails to parse has no functions and a
+/// single diagnostic of its error kind
+fn parse_file_entry(path: &str, content: &str, options: &ParseOptions) -> (Vec<FunctionSpecs>, Vec<Diagnostic>) {
+    match parse_guarded_with_diagnostics(content, None, options) {
+        Ok((mut functions, diagnostics)) => {
+            set_file_path(&mut functions, path);
+            (functions, diagnostics)
+        }
+        Err(failure) => {
+            let diagnostic =
+                Diagnostic { kind: failure.kind.to_string(), detail: failure.message, ..Default::default() };
+            (Vec::new(), vec![diagnostic])
+        }
+    }
+}
+
+/// Combine per-file results: all functions sorted by path and line, and the
+/// diagnostics of the files that have any
+fn merge_file_results(
+    results: impl IntoIterator<Item = (String, (Vec<FunctionSpecs>, Vec<Diagnostic>))>,
+) -> (Vec<FunctionSpecs>, FileDiagnostics) {
+    let mut functions = Vec::new();
+    let mut diagnostics = BTreeMap::new();
+    for (path, (file_functions, file_diagnostics)) in results {
+        functions.extend(file_functions);
+        if !file_diagnostics.is_empty() {
+            diagnostics.insert(path, file_diagnostics);
+        }
+    }
+    functions.sort_by(|a, b| (&a.file_path, a.line_number).cmp(&(&b.file_path, b.line_number)));
+    (functions, diagnostics)
+}
+
+/// Whether `text` matches the glob `pattern`, both `/`-separated: `*` matches
+/// within a path segment, `?` one character and a `**` segment any number of
+/// segments
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    fn segment_match(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('*', rest)) => (0..=text.len()).any(|skip| segment_match(rest, &text[skip..])),
+            Some(('?', rest)) => !text.is_empty() && segment_match(rest, &text[1..]),
+            Some((c, rest)) => text.first() == Some(c) && segment_match(rest, &text[1..]),
+        }
+    }
+    fn segments_match(pattern: &[&str], text: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some((&"**", rest)) => (0..=text.len()).any(|skip| segments_match(rest, &text[skip..])),
+            Some((segment, rest)) => {
+                let segment: Vec<char> = segment.chars().collect();
+                text.split_first().is_some_and(|(first, text_rest)| {
+                    segment_match(&segment, &first.chars().collect::<Vec<_>>()) && segments_match(rest, text_rest)
+                })
+            }
+        }
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let text: Vec<&str> = text.split('/').collect();
+    segments_match(&pattern, &text)
+}
+
+/// An exclusion pattern with gitignore-style semantics: a trailing `/`
+/// matches directories only; a pattern containing another `/` is anchored at
+/// the directory of the rule, one without matches a name at any depth below it
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory the rule applies within, relative to the walk root ("" for the root)
+    base: String,
+    pattern: String,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /// Parse one pattern (a `.gitignore` line); None for blank lines, comments
+    /// and negations (`!pattern`), which are not supported
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            return None;
+        }
+        let (line, dir_only) = match line.strip_suffix('/') {
+            Some(dir) => (dir, true),
+            None => (line, false),
+        };
+        let anchored = line.contains('/');
+        Some(IgnoreRule {
+            base: base.to_string(),
+            pattern: line.trim_start_matches('/').to_string(),
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule excludes `rel` (relative to the walk root)
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let rest = if self.base.is_empty() {
+            rel
+        } else {
+            match rel.strip_prefix(&self.base).and_then(|rest| rest.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => return false,
+            }
+        };
+        if self.anchored {
+            glob_match(&self.pattern, rest)
+        } else {
+            glob_match(&self.pattern, rest.rsplit('/').next().unwrap_or(rest))
+        }
+    }
+}
+
+/// The `.rs` files under `root`, sorted, skipping those matched by `exclude`
+/// (glob patterns relative to `root`, gitignore-style as in `IgnoreRule`)
+///
+/// With `default_excludes`, `target/` directories, hidden files and
+/// directories, and the simple patterns of every `.gitignore` in the tree
+/// (scoped to its directory; negations are not supported) are skipped too.
+/// Excluded directories are not descended into, and symlinks are not followed.
+pub fn verus_files_core(root: &Path, exclude: &[String], default_excludes: bool) -> std::io::Result<Vec<PathBuf>> {
+    fn walk(
+        root: &Path,
+        rel_dir: &str,
+        rules: &mut Vec<IgnoreRule>,
+        default_excludes: bool,
+        files: &mut Vec<PathBuf>,
+    ) -> std::io::Result<()> {
+        let dir = root.join(rel_dir);
+        let outer_rules = rules.len();
+        if default_excludes {
+            if let Ok(gitignore) = std::fs::read_to_string(dir.join(".gitignore")) {
+                rules.extend(gitignore.lines().filter_map(|line| IgnoreRule::parse(rel_dir, line)));
+            }
+        }
+        let mut entries = std::fs::read_dir(&dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let rel = if rel_dir.is_empty() { name.clone() } else { format!("{}/{}", rel_dir, name) };
+            let file_type = entry.file_type()?;
+            if rules.iter().any(|rule| rule.matches(&rel, file_type.is_dir())) {
+                continue;
+            }
+            if file_type.is_dir() {
+                walk(root, &rel, rules, default_excludes, files)?;
+            } else if file_type.is_file() && name.ends_with(".rs") {
+                files.push(root.join(&rel));
+            }
+        }
+        rules.truncate(outer_rules);
+        Ok(())
+    }
+
+    let mut rules: Vec<IgnoreRule> = exclude.iter().filter_map(|pattern| IgnoreRule::parse("", pattern)).collect();
+    if default_excludes {
+        rules.extend(["target/", ".*"].iter().filter_map(|pattern| IgnoreRule::parse("", pattern)));
+    }
+    let mut files = Vec::new();
+    walk(root, "", &mut rules, default_excludes, &mut files)?;
+    Ok(files)
+}
+
+/// Parse the `.rs` files under `root` (as listed by `verus_files_core`) in
+/// parallel, as `parse_verus_contents_core` does; a file that is not UTF-8
+/// contributes a single "not_rust_source" diagnostic ("read_error" with `strict`)
+pub fn parse_verus_directory_core(
+    root: &Path,
+    exclude: &[String],
+    default_excludes: bool,
+    options: &ParseOptions,
+) -> std::io::Result<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    parse_verus_directory_with_progress_core(root, exclude, default_excludes, options, usize::MAX, |_, _, _| Ok(()))
+}
+
+/// Read and parse one file of a directory parse
+fn parse_directory_file(path: &Path, options: &ParseOptions) -> std::io::Result<(Vec<FunctionSpecs>, Vec<Diagnostic>)> {
+    let display = path.to_string_lossy();
+    let bytes = std::fs::read(path)?;
+    // Non-UTF-8 content never reaches the parser; without `strict` it is
+    // reported like any other input failing the pre-check
+    let rejected = match (options.strict, String::from_utf8(bytes)) {
+        (_, Ok(content)) => return Ok(parse_file_entry(&display, &content, options)),
+        (false, Err(e)) => ("not_rust_source", not_rust_source(e.as_bytes()).unwrap_or_default()),
+        (true, Err(e)) => ("read_error", e.utf8_error().to_string()),
+    };
+    let diagnostic = Diagnostic { kind: rejected.0.to_string(), detail: rejected.1, ..Default::default() };
+    Ok((Vec::new(), vec![diagnostic]))
+}
+
+/// Parse a crate from its root file (e.g. `src/lib.rs`), following each
+/// `mod name;` to `name.rs` or `name/mod.rs` (or its `#[path]`)
+///
+/// Functions are returned as by `parse_verus_contents_core`, with
+/// `module_path` relative to the crate root and `is_externally_reachable`
+/// set (see `externally_reachable`). A module whose file is missing gets a
+/// "module_not_found" diagnostic in the declaring file; each file is parsed
+/// once however many times it is declared.
+pub fn parse_verus_crate_core(
+    root: &Path,
+    options: &ParseOptions,
+) -> std::io::Result<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    let options = &options.per_file();
+    let mut results = Vec::new();
+    let (mut modules, mut reexports, mut surface_fns) = (Vec::new(), Vec::new(), Vec::new());
+    // Files to parse: (path, crate-relative module path, directory of its outline submodules)
+    let root_dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut queue = std::collections::VecDeque::from([(root.to_path_buf(), String::new(), root_dir)]);
+    let mut seen = BTreeSet::new();
+    while let Some((path, prefix, dir)) = queue.pop_front() {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let display = path.to_string_lossy().to_string();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            // Reported as in a directory parse
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                results.push((display, parse_directory_file(&path, options)?));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let qualify = |module: &str| match (prefix.is_empty(), module.is_empty()) {
+            (_, true) => prefix.clone(),
+            (true, false) => module.to_string(),
+            (false, false) => format!("{}::{}", prefix, module),
+        };
+        let (mut functions, mut diagnostics) = parse_file_entry(&display, &content, options);
+        for f in &mut functions {
+            f.module_path = qualify(&f.module_path);
+        }
+        let mut finder = SurfaceFinder { verus_macro_names: options.verus_macro_names.clone(), ..Default::default() };
+        if let Ok(file) = verus_syn::parse_file(&content) {
+            finder.visit_file(&file);
+        }
+        for module in finder.modules {
+            if module.outline {
+                let segments: Vec<&str> = module.path.split("::").collect();
+                let (name, inline) = segments.split_last().expect("module path is not empty");
+                let base = inline.iter().fold(dir.clone(), |dir, segment| dir.join(segment));
+                let candidates = match &module.path_attr {
+                    // Relative to the file's own directory outside inline modules
+                    Some(attr) if inline.is_empty() => vec![path.parent().unwrap_or(Path::new("")).join(attr)],
+                    Some(attr) => vec![base.join(attr)],
+                    None => vec![base.join(format!("{}.rs", name)), base.join(name).join("mod.rs")],
+                };
+                match candidates.iter().find(|candidate| candidate.is_file()) {
+                    Some(file) => {
+                        let own_dir = module.path_attr.is_some() || file.ends_with("mod.rs");
+                        let child_dir = match own_dir {
+                            true => file.parent().unwrap_or(Path::new("")).to_path_buf(),
+                            false => base.join(name),
+                        };
+                        queue.push_back((file.clone(), qualify(&module.path), child_dir));
+                    }
+                    None => {
+                        let tried: Vec<String> = candidates.iter().map(|c| c.to_string_lossy().to_string()).collect();
+                        let detail = format!("mod {}: none of {} exists", name, tried.join(", "));
+                        diagnostics.push(Diagnostic::new("module_not_found", module.line, None, detail));
+                    }
+                }
+            }
+            modules.push(ModuleDecl { path: qualify(&module.path), ..module });
+        }
+        reexports.extend(finder.reexports.into_iter().map(|r| Reexport { module_path: qualify(&r.module_path), ..r }));
+        surface_fns.extend(finder.functions.into_iter().map(|f| {
+            (display.clone(), SurfaceFn { module_path: qualify(&f.module_path), ..f })
+        }));
+        results.push((display, (functions, diagnostics)));
+    }
+
+    let (paths, surface_fns): (Vec<String>, Vec<SurfaceFn>) = surface_fns.into_iter().unzip();
+    let reachable: BTreeSet<(&str, usize)> = externally_reachable(&modules, &reexports, &surface_fns)
+        .into_iter()
+        .zip(paths.iter().zip(&surface_fns))
+        .filter(|(reachable, _)| *reachable)
+        .map(|(_, (path, f))| (path.as_str(), f.line))
+        .collect();
+    let (mut functions, diagnostics) = merge_file_results(results);
+    for f in &mut functions {
+        f.is_externally_reachable = f.line_number.is_some_and(|line| reachable.contains(&(&*f.file_path, line)));
+    }
+    Ok((functions, diagnostics))
+}
+
+/// `parse_verus_directory_core`, calling `progress(files_done, files_total,
+/// current_path)` on the calling thread after every `every` parsed files
+/// (and after the last) while worker threads parse the rest
+///
+/// An error from `progress` stops the workers from starting on further files
+/// and is returned once those already started finish.
+pub fn parse_verus_directory_with_progress_core<E: From<std::io::Error>>(
+    root: &Path,
+    exclude: &[String],
+    default_excludes: bool,
+    options: &ParseOptions,
+    every: usize,
+    mut progress: impl FnMut(usize, usize, &str) -> Result<(), E>,
+) -> Result<(Vec<FunctionSpecs>, FileDiagnostics), E> {
+    let options = &options.per_file();
+    let paths = verus_files_core(root, exclude, default_excludes)?;
+    let total = paths.len();
+    let every = every.max(1);
+    let cancelled = std::sync::atomic::AtomicBool::new(false);
+    let (sender, receiver) = std::sync::mpsc::channel::<String>();
+    let (results, failure) = std::thread::scope(|scope| {
+        let workers = scope.spawn(|| {
+            paths
+                .par_iter()
+                .map_with(sender, |sender, path| {
+                    if cancelled.load(std::sync::atomic::Ordering::Relaxed) {
+                        return None;
+                    }
+                    let display = path.to_string_lossy().into_owned();
+                    let result = parse_directory_file(path, options);
+                    // The receiver only goes away once the workers are done
+                    let _ = sender.send(display.clone());
+                    Some((display, result))
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut failure = None;
+        for (done, current) in receiver.iter().enumerate().map(|(i, current)| (i + 1, current)) {
+            if failure.is_none() && (done % every == 0 || done == total) {
+                if let Err(e) = progress(done, total, &current) {
+                    cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+                    failure = Some(e);
+                }
+            }
+        }
+        let results = workers.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        (results, failure)
+    });
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    let results = results
+        .into_iter()
+        .flatten()
+        .map(|(display, result)| result.map(|result| (display, result)))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(merge_file_results(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SAMPLE_VERUS;
+    use verus_syn::Item;
+
+    #[test]
+    fn test_parse_limits() {
+        let unlimited = ParseOptions::default();
+        let functions = parse_guarded(SAMPLE_VERUS, None, &unlimited).unwrap();
+        assert_eq!(functions.len(), 2);
+
+        let small = ParseOptions {
+            max_bytes: Some(SAMPLE_VERUS.len() - 1),
+            ..Default::default()
+        };
+        assert_eq!(parse_guarded(SAMPLE_VERUS, None, &small).unwrap_err().kind, "max_bytes");
+
+        let few_items = ParseOptions {
+            max_items: Some(1),
+            ..Default::default()
+        };
+        let failure = parse_guarded(SAMPLE_VERUS, None, &few_items).unwrap_err();
+        assert_eq!(failure.kind, "max_items");
+        let specs = failure.into_specs("");
+        assert_eq!(specs.error_kind.as_deref(), Some("max_items"));
+        assert_eq!(specs.parse_error.as_deref(), Some("More than 1 items"));
+        let enough_items = ParseOptions {
+            max_items: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(parse_guarded(SAMPLE_VERUS, None, &enough_items).unwrap().len(), 2);
+
+        let nested = format!("fn deep() {{ {}0{} }}", "(".repeat(10), ")".repeat(10));
+        let shallow = ParseOptions {
+            max_depth: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(parse_guarded(&nested, None, &shallow).unwrap_err().kind, "max_depth");
+
+        let invalid = parse_guarded("fn broken(", None, &unlimited).unwrap_err();
+        assert_eq!(invalid.kind, "parse_error");
+    }
+
+    #[test]
+    fn test_functions_in_item_macros() {
+        let code = r#"
+macro_rules! define_lemma {
+    ($($body:tt)*) => { $($body)* };
+}
+
+define_lemma! {
+    pub proof fn lemma_from_macro(x: int)
+        requires x > 0,
+        ensures x * x > 0,
+    {
+    }
+}
+
+println!("not items {}", 42);
+
+verus! {
+
+proof fn lemma_plain() {}
+
+}
+"#;
+        let default = parse_guarded(code, None, &ParseOptions::default()).unwrap();
+        let names: Vec<&str> = default.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_plain"]);
+
+        let options = ParseOptions {
+            item_macros: true,
+            ..Default::default()
+        };
+        let funcs = parse_guarded(code, None, &options).unwrap();
+        let names: Vec<&str> = funcs.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_from_macro", "lemma_plain"]);
+        assert_eq!(funcs[0].from_macro.as_deref(), Some("define_lemma"));
+        assert_eq!(funcs[0].requires, vec!["x > 0"]);
+        assert_eq!(funcs[0].provenance, "macro:define_lemma");
+        assert_eq!(funcs[1].from_macro, None);
+        assert_eq!(funcs[1].provenance, "verus_macro");
+    }
+
+    #[test]
+    fn test_module_docs() {
+        let code = r#"//! Arithmetic lemmas.
+//! Start here.
+
+verus! {
+
+mod mul {
+    //! Multiplication facts.
+
+    pub proof fn lemma_mul_zero(x: int)
+        ensures x * 0 == 0,
+    {
+    }
+
+    mod nonlinear {
+        //! Nonlinear arithmetic.
+        pub proof fn lemma_mul_pos(x: int, y: int) {}
+    }
+
+    mod undocumented {
+        pub proof fn lemma_plain() {}
+    }
+}
+
+proof fn lemma_root() {}
+
+}
+"#;
+        let file = verus_syn::parse_file(code).unwrap();
+        let docs = collect_module_docs(&file, &[]);
+        let expected: BTreeMap<String, String> = [
+            ("", "Arithmetic lemmas.\nStart here."),
+            ("mul", "Multiplication facts."),
+            ("mul::nonlinear", "Nonlinear arithmetic."),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(docs, expected);
+
+        let options = ParseOptions {
+            module_doc: true,
+            ..Default::default()
+        };
+        let funcs = parse_guarded(code, None, &options).unwrap();
+        let doc = |name: &str| funcs.iter().find(|f| f.name == name).unwrap().module_doc.clone();
+        assert_eq!(doc("lemma_mul_zero").as_deref(), Some("Multiplication facts."));
+        assert_eq!(doc("lemma_mul_pos").as_deref(), Some("Nonlinear arithmetic."));
+        assert_eq!(doc("lemma_plain"), None);
+        assert_eq!(doc("lemma_root").as_deref(), Some("Arithmetic lemmas.\nStart here."));
+        assert!(parse_verus_file_core(code).unwrap().iter().all(|f| f.module_doc.is_none()));
+    }
+
+    #[test]
+    fn test_parallel_extraction_matches_sequential() {
+        let mut code = String::from("#![allow(unused)]\nuse vstd::prelude::*;\n\n");
+        for i in 0..200 {
+            code.push_str(&format!("fn exec_{i}(x: u32) -> u32 {{ x }}\n"));
+            code.push_str(&format!("const C_{i}: S = S {{ a: {i} }};\n"));
+        }
+        code.push_str("verus! {\n");
+        for m in 0..20 {
+            code.push_str(&format!("pub mod m{m} {{\n    use super::*;\n"));
+            for i in 0..25 {
+                code.push_str(&format!(
+                    "    proof fn lemma_{m}_{i}(x: int)\n        requires x > {i},\n        ensures x >= {i},\n        decreases x,\n    {{\n        proof {{ lemma_{m}_0(x); }}\n    }}\n"
+                ));
+                code.push_str(&format!("    pub const fn cf_{m}_{i}() -> u8 {{ {i} }}\n"));
+            }
+            code.push_str("}\n");
+        }
+        code.push_str("impl Scalar {\n    spec fn view(&self) -> int { 0 }\n}\n}\n");
+        code.push_str("impl Other {\n    verus! {\n        fn inner(&self) {}\n    }\n}\n");
+
+        let sequential = parse_limited(&code, None, &ParseOptions::default()).unwrap();
+        let options = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        let parallel = parse_limited(&code, None, &options).unwrap();
+        assert_eq!(sequential.len(), 200 + 20 * 25 * 2 + 2);
+        assert_eq!(parallel, sequential);
+
+        let one = parse_limited(&code, Some("lemma_7_3"), &options).unwrap();
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].path, vec!["crate", "verus!", "mod m7", "fn lemma_7_3"]);
+    }
+
+    #[test]
+    fn test_file_path_and_line_offset() {
+        let code = r#"fn helper() {}
+verus! {
+proof fn lemma_a(x: int)
+    ensures x + 0 == x,
+{
+    assert(x == x);
+}
+}
+"#;
+        let options = ParseOptions { file_path: Some("notes.md".to_string()), line_offset: 100, ..Default::default() };
+        let functions = parse_limited(code, None, &options).unwrap();
+        assert_eq!(functions[0].line_number, Some(101));
+        let lemma = &functions[1];
+        assert_eq!((lemma.line_number, lemma.end_line), (Some(103), Some(107)));
+        assert_eq!(lemma.asserts[0].line_number, Some(106));
+        assert!(functions.iter().all(|f| &*f.file_path == "notes.md"));
+
+        // The offset applies once, after the per-batch moves of a parallel parse
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..options.clone() };
+        assert_eq!(parse_limited(code, None, &parallel).unwrap(), functions);
+
+        // Recovered syntax errors are reported in the same coordinates
+        let broken = format!("{}fn broken( {{}}\nfn after() {{}}\n", code);
+        let (functions, diagnostics) = parse_recovering(&broken, &options).unwrap();
+        assert_eq!(functions.last().unwrap().line_number, Some(110));
+        assert_eq!(diagnostics[0].kind, "parse_error");
+        assert_eq!(diagnostics[0].line_number, Some(109));
+
+        // So are a failed parse and its delimiter diagnostics
+        let unbalanced = format!("{}verus! {{\nproof fn lemma_b() {{\n}}\n", code);
+        let failure = parse_guarded(&unbalanced, None, &options).unwrap_err();
+        let diagnostics = failure.diagnostics(&unbalanced, &options);
+        assert_eq!((diagnostics[0].kind.as_str(), diagnostics[0].line_number), ("unbalanced_delimiter", Some(109)));
+        assert_eq!(&*failure.into_located_specs("", &options).file_path, "notes.md");
+
+        // Multi-file parses set each file's path and keep its own lines
+        let files = HashMap::from([("a.rs".to_string(), code.to_string())]);
+        let (functions, _) = parse_verus_contents_core(&files, &options);
+        assert_eq!((&*functions[1].file_path, functions[1].line_number), ("a.rs", Some(3)));
+    }
+
+    #[test]
+    fn test_test_only_functions() {
+        let code = r#"
+verus! {
+
+proof fn lemma_prod(x: int) ensures x == x {}
+
+#[cfg(test)]
+mod checks {
+    proof fn lemma_in_cfg_test(x: int) ensures x == x {}
+}
+
+#[cfg(not(test))]
+proof fn lemma_not_test() {}
+
+#[cfg(all(test, feature = "slow"))]
+fn slow_check() {}
+
+mod tests {
+    fn by_name() {}
+}
+
+}
+"#;
+        let in_test = |options: &ParseOptions| -> Vec<(String, bool)> {
+            parse_limited(code, None, options).unwrap().into_iter().map(|f| (f.name, f.in_test)).collect()
+        };
+        let expected = vec![
+            ("lemma_prod".to_string(), false),
+            ("lemma_in_cfg_test".to_string(), true),
+            ("lemma_not_test".to_string(), false),
+            ("slow_check".to_string(), true),
+            ("by_name".to_string(), false),
+        ];
+        assert_eq!(in_test(&ParseOptions::default()), expected);
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        assert_eq!(in_test(&parallel), expected);
+
+        let heuristic = ParseOptions { tests_module_heuristic: true, ..Default::default() };
+        assert!(in_test(&heuristic).contains(&("by_name".to_string(), true)));
+
+        let skip = ParseOptions { skip_tests: true, tests_module_heuristic: true, ..Default::default() };
+        let names: Vec<String> = in_test(&skip).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["lemma_prod", "lemma_not_test"]);
+    }
+
+    #[test]
+    fn test_split_conjunctions() {
+        let code = r#"
+verus! {
+
+proof fn lemma_bounds(x: int, s: Seq<int>)
+    requires
+        x > 0 && x < 10,
+        x > 0 ==> x >= 1 && x != 0,
+    ensures
+        &&& x >= 1
+        &&& x <= 9
+        &&& forall|i: int| 0 <= i && i < s.len() ==> s[i] == s[i],
+{
+}
+
+}
+"#;
+        let unsplit = parse_limited(code, None, &ParseOptions::default()).unwrap();
+        assert_eq!(unsplit[0].ensures.len(), 1);
+        assert!(unsplit[0].ensures_raw.is_empty());
+
+        let options = ParseOptions { split_conjunctions: true, ..Default::default() };
+        let f = &parse_limited(code, None, &options).unwrap()[0];
+        assert_eq!(f.ensures.len(), 3);
+        assert_eq!(f.ensures[0], "x >= 1");
+        assert_eq!(f.ensures[1], "x <= 9");
+        assert!(f.ensures[2].starts_with("forall"));
+        assert_eq!(f.ensures_parent, vec![0, 0, 0]);
+        assert_eq!(f.ensures_raw, unsplit[0].ensures);
+
+        // The implication is not split
+        assert_eq!(f.requires, vec!["x > 0", "x < 10", "x > 0 ==> x >= 1 && x != 0"]);
+        assert_eq!(f.requires_parent, vec![0, 0, 1]);
+        assert_eq!(f.requires_raw, unsplit[0].requires);
+    }
+
+    #[test]
+    fn test_reparse_incremental() {
+        let code = r#"verus! {
+
+proof fn lemma_a(x: int)
+    ensures
+        x + 0 == x,
+{
+}
+
+proof fn lemma_b(x: int)
+    ensures
+        x * 1 == x,
+{
+}
+
+}
+
+fn after() {}
+"#;
+        let parsed = ParsedVerusFile::parse(code).unwrap();
+        assert_eq!(parsed.all_functions(), parse_verus_file_core(code).unwrap());
+
+        // Replace lemma_a's ensures clause with two lines
+        let edited = code.replace("        x + 0 == x,\n", "        x + 0 == x,\n        0 + x == x,\n");
+        let reparsed = parsed.reparse(&edited, (5, 6)).unwrap();
+        let functions = reparsed.all_functions();
+        assert_eq!(functions, parse_verus_file_core(&edited).unwrap());
+
+        let before = parsed.all_functions();
+        assert_eq!(functions[0].ensures, vec!["x + 0 == x", "0 + x == x"]);
+        assert_eq!(functions[0].end_line, Some(8));
+        for (old, new) in before.iter().zip(&functions).skip(1) {
+            assert_eq!(old.name, new.name);
+            assert_eq!(old.ensures, new.ensures);
+            assert_eq!(new.line_number, old.line_number.map(|l| l + 1));
+        }
+
+        // An unbalanced brace falls back to a full parse, which fails
+        let broken = edited.replace("fn after() {}", "fn after() {");
+        assert!(reparsed.reparse(&broken, (18, 18)).is_err());
+    }
+
+    #[test]
+    fn test_todo_marker_lines() {
+        let code = "verus! {\n\nproof fn lemma_a() {\n}\n\nproof fn lemma_b() {\n    // TODO: prove\n}\n\n}\n";
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        let functions = parse_limited(code, None, &parallel).unwrap();
+        assert_eq!(functions[1].todo_markers, vec![(7, "TODO: prove".to_string())]);
+
+        // A reparse keeps lemma_b, moving its marker with it
+        let parsed = ParsedVerusFile::parse(code).unwrap();
+        assert_eq!(parsed.all_functions(), functions);
+        let edited = code.replace("proof fn lemma_a() {\n", "proof fn lemma_a() {\n    assert(true);\n");
+        let functions = parsed.reparse(&edited, (3, 4)).unwrap().all_functions();
+        assert_eq!(functions[1].todo_markers, vec![(8, "TODO: prove".to_string())]);
+        assert_eq!(functions, parse_verus_file_core(&edited).unwrap());
+    }
+
+    #[test]
+    fn test_clause_offsets() {
+        let code = r#"
+verus! {
+
+spec fn f(x: int) -> int;
+spec fn g(x: int) -> int;
+
+proof fn lemma_pos(s: Seq<int>, n: int)
+    ensures
+        forall|i: int| 0 <= i && i < s.len() ==> #[trigger] s[i] > f(i),
+        forall|x: int| #![trigger f(x), g(x)] f(x) == g(x),
+        f(n) + 1 >= g(n),
+        n == n && n == n,
+{
+}
+
+}
+"#;
+        let options = ParseOptions { clause_offsets: true, ..Default::default() };
+        let functions = parse_limited(code, None, &options).unwrap();
+        let f = functions.iter().find(|f| f.name == "lemma_pos").unwrap();
+        let slices = |index: usize| -> Vec<(String, String)> {
+            let clause: Vec<char> = f.ensures[index].chars().collect();
+            f.ensures_offsets[index]
+                .ranges
+                .iter()
+                .map(|r| (r.kind.clone(), clause[r.start..r.end].iter().collect()))
+                .collect()
+        };
+        let pairs = |expected: &[(&str, &str)]| -> Vec<(String, String)> {
+            expected.iter().map(|(k, t)| (k.to_string(), t.to_string())).collect()
+        };
+
+        assert_eq!(
+            slices(0),
+            pairs(&[("quantifier_body", "0 <= i && i < s . len () ==> # [trigger] s [i] > f (i)"), ("trigger", "s [i]")])
+        );
+        assert_eq!(
+            slices(1),
+            pairs(&[
+                ("quantifier_body", "#! [trigger f (x) , g (x)] f (x) == g (x)"),
+                ("trigger", "f (x)"),
+                ("trigger", "g (x)"),
+            ])
+        );
+        assert_eq!(slices(2), pairs(&[("lhs", "f (n) + 1"), ("rhs", "g (n)")]));
+        assert_eq!(slices(3), Vec::new());
+        assert_eq!(f.ensures_offsets[2].token_count, 8);
+        assert!(f.requires_offsets.is_empty());
+    }
+
+    #[test]
+    fn test_modules_inside_verus_macro() {
+        let code = r#"
+use vstd::prelude::*;
+
+verus! {
+
+mod seq_lemmas {
+    proof fn lemma_x(s: Seq<int>)
+        ensures s.len() >= 0,
+    {
+    }
+}
+
+pub mod arith {
+    pub mod nested {
+        pub proof fn lemma_y() {}
+    }
+
+    proof fn lemma_z() {}
+}
+
+fn top() {}
+
+}
+"#;
+        let expected = vec![
+            ("seq_lemmas::lemma_x".to_string(), Some(7), Some(10)),
+            ("arith::nested::lemma_y".to_string(), Some(15), Some(15)),
+            ("arith::lemma_z".to_string(), Some(18), Some(18)),
+            ("top".to_string(), Some(21), Some(21)),
+        ];
+        let qualified = |functions: Vec<FunctionSpecs>| -> Vec<(String, Option<usize>, Option<usize>)> {
+            functions.into_iter().map(|f| (f.qualified_name(), f.line_number, f.end_line)).collect()
+        };
+        assert_eq!(qualified(parse_verus_file_core(code).unwrap()), expected);
+
+        let options = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        assert_eq!(qualified(parse_limited(code, None, &options).unwrap()), expected);
+        assert_eq!(qualified(ParsedVerusFile::parse(code).unwrap().all_functions()), expected);
+    }
+
+    #[test]
+    fn test_mode_filter() {
+        let code = r#"
+verus! {
+
+spec fn s1(x: int) -> int { x }
+spec(checked) fn s2(x: int) -> int recommends x > 0 { x }
+proof fn p1() {}
+exec fn e1() {}
+fn e2() {}
+
+trait T {
+    fn e3(&self);
+    proof fn p2(&self);
+}
+
+}
+"#;
+        let count = |mode: Option<&str>| {
+            let options = ParseOptions { mode: mode.map(str::to_string), ..Default::default() };
+            parse_limited(code, None, &options).unwrap().len()
+        };
+        assert_eq!((count(Some("proof")), count(Some("exec")), count(Some("spec"))), (2, 3, 2));
+        assert_eq!(count(Some("proof")) + count(Some("exec")) + count(Some("spec")), count(None));
+    }
+
+    #[test]
+    fn test_verus_macro_names() {
+        let code = r#"
+macro_rules! my_verus { ($($t:tt)*) => { verus! { $($t)* } } }
+
+my_verus! {
+
+proof fn lemma_wrapped(x: int)
+    ensures x == x,
+{
+}
+
+}
+
+vstd::prelude::verus! {
+
+proof fn lemma_qualified() {}
+
+}
+"#;
+        let names = |options: &ParseOptions| -> Vec<(String, Vec<String>)> {
+            parse_limited(code, None, options).unwrap().into_iter().map(|f| (f.name, f.path)).collect()
+        };
+        let qualified = (
+            "lemma_qualified".to_string(),
+            vec!["crate".to_string(), "vstd::prelude::verus!".to_string(), "fn lemma_qualified".to_string()],
+        );
+        assert_eq!(names(&ParseOptions::default()), vec![qualified.clone()]);
+
+        let wrapped = (
+            "lemma_wrapped".to_string(),
+            vec!["crate".to_string(), "my_verus!".to_string(), "fn lemma_wrapped".to_string()],
+        );
+        let options = ParseOptions { verus_macro_names: vec!["my_verus".to_string()], ..Default::default() };
+        assert_eq!(names(&options), vec![wrapped.clone(), qualified.clone()]);
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..options };
+        assert_eq!(names(&parallel), vec![wrapped, qualified]);
+        assert!(parse_limited(code, None, &parallel).unwrap()[0].from_macro.is_none());
+
+        let documented = "my_verus! {\nmod arith {\n    //! Arithmetic.\n    proof fn lemma_a() {}\n}\n}\n";
+        let options = ParseOptions { module_doc: true, parallel_threshold: None, ..parallel };
+        let functions = parse_limited(documented, None, &options).unwrap();
+        assert_eq!(functions[0].module_doc.as_deref(), Some("Arithmetic."));
+    }
+
+    #[test]
+    fn test_file_path_interning() {
+        let code: String = (0..200).map(|i| format!("proof fn lemma_{}() ensures true {{}}\n", i)).collect();
+        let mut functions = parse_verus_file_core(&code).unwrap();
+        let path = "src/very/deeply/nested/module/of/the/project/lemmas.rs";
+
+        set_file_path(&mut functions, path);
+        assert!(functions.iter().all(|f| Arc::ptr_eq(&f.file_path, &functions[0].file_path)));
+
+        // Serialized like the String it replaces
+        let json = serde_json::to_value(&functions[0]).unwrap();
+        assert_eq!(json["file_path"], path);
+        assert_eq!(bincode::serialize(&functions[0].file_path).unwrap(), bincode::serialize(path).unwrap());
+        let bytes = bincode::serialize(&functions).unwrap();
+        assert_eq!(bincode::deserialize::<Vec<FunctionSpecs>>(&bytes).unwrap(), functions);
+    }
+
+    #[test]
+    fn test_resolve_self() {
+        let code = r#"
+verus! {
+
+impl Scalar {
+    pub const ZERO: u64 = 0;
+
+    pub fn zero() -> (result: Self)
+        ensures
+            result == Self::ZERO,
+    {
+        Scalar
+    }
+
+    pub fn same(&self, other: &Self) -> bool { true }
+
+    pub fn plain(x: u64) -> u64 { x }
+}
+
+impl<T> Wrapper<T> {
+    pub fn wrap(value: T) -> (result: Self)
+        ensures
+            result == Self::new(value),
+    {
+        Wrapper(value)
+    }
+}
+
+}
+"#;
+        let options = ParseOptions { resolve_self: true, ..Default::default() };
+        let funcs = parse_limited(code, None, &options).unwrap();
+        let func = |name: &str| funcs.iter().find(|f| f.name == name).unwrap();
+
+        let zero = func("zero");
+        assert_eq!(zero.ensures, vec!["result == Scalar :: ZERO"]);
+        assert_eq!(zero.returns.as_ref().unwrap().ty, "Scalar");
+        assert_eq!(zero.ensures_mentions[0].externals, vec!["Scalar::ZERO"]);
+        let unresolved = zero.unresolved.as_ref().unwrap();
+        assert_eq!(unresolved.self_type, "Scalar");
+        assert_eq!(unresolved.ensures, vec!["result == Self :: ZERO"]);
+        assert_eq!(unresolved.returns.as_ref().unwrap().ty, "Self");
+        // Substituted tokens take the spans of the `Self` they replace
+        assert_eq!(zero.line_number, Some(7));
+
+        let same = func("same");
+        assert_eq!(same.params[1].ty, "Scalar");
+        assert_eq!(same.params[1].reference, "&");
+        assert!(same.signature.contains("other: &Scalar"), "{}", same.signature);
+        assert!(func("plain").unresolved.is_none());
+
+        let wrap = func("wrap");
+        assert_eq!(wrap.ensures, vec!["result == Wrapper :: < T > :: new (value)"]);
+        assert_eq!(wrap.returns.as_ref().unwrap().ty, "Wrapper<T>");
+
+        // Off by default
+        let funcs = parse_verus_file_core(code).unwrap();
+        assert_eq!(funcs[0].ensures, vec!["result == Self :: ZERO"]);
+        assert!(funcs.iter().all(|f| f.unresolved.is_none()));
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let code = r#"
+verus! {
+
+#[cfg_attr(verus_keep_ghost, verifier::opaque)]
+pub open spec fn hidden(x: int) -> int { x }
+
+}
+
+verus! {
+
+proof fn lemma_lost(x: int) -> {}
+
+}
+"#;
+        let (funcs, diagnostics) = parse_limited_with_diagnostics(code, None, &ParseOptions::default()).unwrap();
+        assert_eq!(funcs.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["hidden"]);
+        let summary: Vec<(&str, Option<usize>, Option<&str>)> =
+            diagnostics.iter().map(|d| (d.kind.as_str(), d.line_number, d.function.as_deref())).collect();
+        assert_eq!(
+            summary,
+            vec![("cfg_attr_ignored", Some(4), Some("hidden")), ("verus_body_skipped", Some(9), None)]
+        );
+        assert_eq!(diagnostics[0].detail, "cfg_attr(verus_keep_ghost, verifier::opaque)");
+        assert!(!diagnostics[1].detail.is_empty());
+
+        // The parallel path reports the same kinds, at the lines of the file
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        let (_, diagnostics) = parse_limited_with_diagnostics(code, None, &parallel).unwrap();
+        let kinds: Vec<(&str, Option<usize>)> = diagnostics.iter().map(|d| (d.kind.as_str(), d.line_number)).collect();
+        assert_eq!(kinds, vec![("cfg_attr_ignored", Some(4)), ("verus_body_skipped", Some(9))]);
+    }
+
+    #[test]
+    fn test_sig_end_line() {
+        let code = r#"
+verus! {
+
+proof fn lemma_long(x: int, y: int)
+    requires
+        x < y,
+    ensures
+        x + 1 <= y,
+        x - 1 < y,
+        x < y + 1,
+{
+    assert(x < y);
+}
+
+fn short(x: u8) -> u8 { x }
+
+trait Shape {
+    spec fn area(&self) -> int
+        ensures
+            true;
+
+    fn sides(&self) -> u8 { 0 }
+}
+
+}
+"#;
+        for options in [ParseOptions::default(), ParseOptions { parallel_threshold: Some(0), ..Default::default() }] {
+            let funcs = parse_limited(code, None, &options).unwrap();
+            let func = |name: &str| funcs.iter().find(|f| f.name == name).unwrap();
+            let long = func("lemma_long");
+            assert_eq!((long.sig_end_line, long.end_line), (Some(10), Some(13)));
+            assert_eq!(long.sig_end_column, Some(18));
+            let short = func("short");
+            assert_eq!((short.line_number, short.sig_end_line, short.sig_end_column), (Some(15), Some(15), Some(21)));
+            // A declaration without a body ends with its header
+            let area = func("area");
+            assert_eq!(area.sig_end_line, area.end_line);
+            assert_eq!(area.sig_end_line, Some(20));
+            assert_eq!(func("sides").sig_end_line, Some(22));
+        }
+    }
+
+    #[test]
+    fn test_parse_verus_contents() {
+        let mut files = HashMap::new();
+        files.insert("src/b.rs".to_string(), "verus! {\nproof fn lemma_b2() {}\n}\nfn b1() {}\n".to_string());
+        files.insert("src/a.rs".to_string(), SAMPLE_VERUS.to_string());
+        files.insert("src/broken.rs".to_string(), "fn broken( {".to_string());
+
+        let (functions, diagnostics) = parse_verus_contents_core(&files, &ParseOptions::default());
+        let found: Vec<String> = functions
+            .iter()
+            .map(|f| format!("{}:{}:{}", f.file_path, f.line_number.unwrap(), f.name))
+            .collect();
+        assert_eq!(
+            found,
+            vec!["src/a.rs:2:lemma_mul_inequality", "src/a.rs:11:exec_add", "src/b.rs:2:lemma_b2", "src/b.rs:4:b1"]
+        );
+        assert_eq!(diagnostics.keys().collect::<Vec<_>>(), vec!["src/broken.rs"]);
+        let broken = &diagnostics["src/broken.rs"];
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].kind, "parse_error");
+        assert!(broken[0].detail.starts_with("Parse error"), "{}", broken[0].detail);
+    }
+
+    #[test]
+    fn test_effective_cfgs() {
+        let code = r#"
+verus! {
+
+#[cfg(feature = "alloc")]
+mod alloc_lemmas {
+    #[cfg(not(feature = "slow"))]
+    proof fn lemma_alloc() ensures true {}
+
+    #[cfg(verus_keep_ghost)]
+    impl Buffer {
+        proof fn lemma_method() {}
+    }
+}
+
+proof fn lemma_plain() {}
+
+}
+
+#[cfg(feature = "extra")]
+verus! {
+proof fn lemma_extra() {}
+}
+"#;
+        for options in [ParseOptions::default(), ParseOptions { parallel_threshold: Some(0), ..Default::default() }] {
+            let funcs = parse_limited(code, None, &options).unwrap();
+            let func = |name: &str| funcs.iter().find(|f| f.name == name).unwrap();
+            let alloc = func("lemma_alloc");
+            assert_eq!(alloc.cfgs, vec!["not(feature = \"slow\")"]);
+            assert_eq!(alloc.effective_cfgs, vec!["feature = \"alloc\"", "not(feature = \"slow\")"]);
+            assert_eq!(func("lemma_method").effective_cfgs, vec!["feature = \"alloc\"", "verus_keep_ghost"]);
+            assert!(func("lemma_method").cfgs.is_empty());
+            assert!(func("lemma_plain").effective_cfgs.is_empty());
+            assert_eq!(func("lemma_extra").effective_cfgs, vec!["feature = \"extra\""]);
+        }
+    }
+
+    #[test]
+    fn test_long_conjunction_clause() {
+        let chain = |n: usize, op: &str| {
+            (0..n).map(|i| format!("s[{}] + {} < t.len()", i, i)).collect::<Vec<_>>().join(op)
+        };
+        let source = |clauses: &str| {
+            format!("verus! {{\nproof fn big(s: Seq<int>, t: Seq<int>)\n    ensures\n        {},\n{{\n}}\n}}\n", clauses)
+        };
+
+        // On a small sample the clause text matches rendering each clause through quote
+        let small = source(&format!("{}, &&& {}", chain(20, " && "), chain(20, " &&& ")));
+        let file = verus_syn::parse_file(&small).unwrap();
+        let Item::Macro(mac) = &file.items[0] else { panic!("expected verus! block") };
+        let body = verus_syn::parse2::<VerusMacroBody>(mac.mac.tokens.clone()).unwrap();
+        let Item::Fn(f) = &body.items[0] else { panic!("expected a function") };
+        let slow: Vec<String> =
+            f.sig.spec.ensures.as_ref().unwrap().exprs.exprs.iter().map(|e| quote::quote!(#e).to_string()).collect();
+        let parsed = parse_verus_file_core(&small).unwrap();
+        assert_eq!(parsed[0].ensures, slow);
+        assert_eq!(conjuncts(&f.sig.spec.ensures.as_ref().unwrap().exprs.exprs[0]).len(), 20);
+
+        let split = parse_limited(&small, None, &ParseOptions { split_conjunctions: true, ..Default::default() }).unwrap();
+        let expected: Vec<String> = (0..20)
+            .map(|i| {
+                let e: Expr = verus_syn::parse_str(&format!("s[{}] + {} < t.len()", i, i)).unwrap();
+                quote::quote!(#e).to_string()
+            })
+            .collect();
+        assert_eq!(split[0].ensures.len(), 40);
+        assert_eq!(split[0].ensures[..20], expected[..]);
+        assert_eq!(split[0].ensures[20..], expected[..]);
+
+        // A 5k-conjunct chain is handled in bounded time and split into its conjuncts
+        let big = source(&format!("&&& {}", chain(5000, " &&& ")));
+        let start = std::time::Instant::now();
+        let split = parse_limited(&big, None, &ParseOptions { split_conjunctions: true, ..Default::default() }).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(60), "took {:?}", start.elapsed());
+        assert_eq!(split[0].ensures.len(), 5000);
+        assert_eq!(split[0].ensures[4999], "s [4999] + 4999 < t . len ()");
+        assert!(split[0].ensures_parent.iter().all(|&parent| parent == 0));
+    }
+
+    #[test]
+    fn test_provenance_trait_and_parallel() {
+        let code = r#"
+trait Shape {
+    fn area(&self) -> (r: u64)
+        ensures
+            r > 0;
+}
+
+verus! {
+trait Measured {
+    spec fn size(&self) -> nat;
+}
+
+impl Measured for u64 {
+    spec fn size(&self) -> nat { 1 }
+}
+}
+
+impl Shape for u64 {
+    verus! {
+        fn area(&self) -> (r: u64) { 1 }
+    }
+}
+"#;
+        let provenance = |funcs: &[FunctionSpecs]| -> Vec<(String, String)> {
+            funcs.iter().map(|f| (f.name.clone(), f.provenance.clone())).collect()
+        };
+        let expected = vec![
+            ("area".to_string(), "trait".to_string()),
+            ("size".to_string(), "verus_macro".to_string()),
+            ("size".to_string(), "verus_macro".to_string()),
+            ("area".to_string(), "verus_macro_in_impl".to_string()),
+        ];
+        assert_eq!(provenance(&parse_verus_file_core(code).unwrap()), expected);
+        let options = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        assert_eq!(provenance(&parse_limited(code, None, &options).unwrap()), expected);
+    }
+
+    #[test]
+    fn test_attribute_specs() {
+        let code = r#"
+#[requires(x > 0)]
+#[ensures(result > x)]
+fn attribute_only(x: u32) -> u32 {
+    x + 1
+}
+
+fn clause_only(x: u32) -> (r: u32)
+    requires
+        x > 0,
+    ensures
+        r > x,
+{
+    x + 1
+}
+
+#[prusti_contracts::requires(x < 100)]
+#[ensures(r >= x)]
+fn mixed(x: u32) -> (r: u32)
+    requires
+        x > 0,
+{
+    x + 1
+}
+
+#[requires(=> nonsense)]
+fn broken(x: u32) {}
+"#;
+        let options = ParseOptions { attribute_specs: true, ..Default::default() };
+        let (funcs, diagnostics) = parse_limited_with_diagnostics(code, None, &options).unwrap();
+        let summary: Vec<(&str, &str, Vec<String>, Vec<String>)> = funcs
+            .iter()
+            .map(|f| (f.name.as_str(), f.spec_style.as_str(), f.requires.clone(), f.ensures.clone()))
+            .collect();
+        let strings = |clauses: &[&str]| clauses.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("attribute_only", "attribute", strings(&["x > 0"]), strings(&["result > x"])),
+                ("clause_only", "verus", strings(&["x > 0"]), strings(&["r > x"])),
+                ("mixed", "mixed", strings(&["x < 100", "x > 0"]), strings(&["r >= x"])),
+                ("broken", "verus", vec![], vec![]),
+            ]
+        );
+        // `result` is the return value, not a free name
+        assert!(funcs[0].is_self_contained);
+        assert!(funcs[0].has_requires && funcs[0].has_ensures);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, "spec_attribute_unparsed");
+        assert_eq!(diagnostics[0].function.as_deref(), Some("broken"));
+
+        // Off by default: attributes are left alone
+        let funcs = parse_verus_file_core(code).unwrap();
+        assert!(funcs[0].requires.is_empty());
+        assert_eq!(funcs[2].requires, vec!["x > 0"]);
+        assert!(funcs.iter().all(|f| f.spec_style == "verus"));
+
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..options };
+        assert_eq!(parse_limited(code, None, &parallel).unwrap()[2].requires, vec!["x < 100", "x > 0"]);
+    }
+
+    #[test]
+    fn test_parse_verus_directory_excludes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["src/snapshots", "target/debug", ".cache", "vendor/dep"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        let lemma = |name: &str| format!("verus! {{ proof fn {}() ensures true, {{}} }}", name);
+        std::fs::write(root.join("src/lib.rs"), lemma("lemma_lib")).unwrap();
+        std::fs::write(root.join("src/snapshots/snap.rs"), lemma("lemma_snap")).unwrap();
+        std::fs::write(root.join("src/generated.rs"), lemma("lemma_generated")).unwrap();
+        std::fs::write(root.join("src/.gitignore"), "# generated code\ngenerated.rs\n").unwrap();
+        std::fs::write(root.join("target/debug/build.rs"), "fn broken(").unwrap();
+        std::fs::write(root.join(".cache/cached.rs"), lemma("lemma_cached")).unwrap();
+        std::fs::write(root.join("vendor/dep/dep.rs"), lemma("lemma_dep")).unwrap();
+        std::fs::write(root.join("README.md"), "not rust").unwrap();
+
+        let relative = |files: Vec<PathBuf>| -> Vec<String> {
+            files.iter().map(|f| f.strip_prefix(root).unwrap().to_string_lossy().into_owned()).collect()
+        };
+        let exclude = vec!["vendor/".to_string(), "src/snapshots/**".to_string()];
+        assert_eq!(relative(verus_files_core(root, &exclude, true).unwrap()), vec!["src/lib.rs"]);
+        assert_eq!(
+            relative(verus_files_core(root, &[], false).unwrap()),
+            vec![
+                ".cache/cached.rs",
+                "src/generated.rs",
+                "src/lib.rs",
+                "src/snapshots/snap.rs",
+                "target/debug/build.rs",
+                "vendor/dep/dep.rs",
+            ]
+        );
+
+        // The broken file under target/ is skipped, not reported as an error
+        let (funcs, diagnostics) = parse_verus_directory_core(root, &exclude, true, &ParseOptions::default()).unwrap();
+        assert_eq!(funcs.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["lemma_lib"]);
+        assert!(diagnostics.is_empty());
+
+        let (funcs, diagnostics) = parse_verus_directory_core(root, &exclude, false, &ParseOptions::default()).unwrap();
+        assert_eq!(funcs.len(), 3);
+        let broken = root.join("target/debug/build.rs").to_string_lossy().into_owned();
+        assert_eq!(diagnostics.keys().collect::<Vec<_>>(), vec![&broken]);
+        assert_eq!(diagnostics[&broken][0].kind, "parse_error");
+
+        assert!(glob_match("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(glob_match("**/gen?.rs", "gen1.rs"));
+        assert!(!glob_match("src/*.rs", "src/a/b.rs"));
+    }
+
+    #[test]
+    fn test_not_rust_source_precheck() {
+        let png: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0";
+        assert_eq!(not_rust_source(png).unwrap(), "Input is not valid UTF-8");
+        // The same header decoded as Latin-1 is valid UTF-8 but binary
+        let latin1: String = png.iter().map(|&b| b as char).collect();
+        let failure = parse_guarded(&latin1, None, &ParseOptions::default()).unwrap_err();
+        assert_eq!(failure.kind, "not_rust_source");
+        assert!(failure.message.contains("control byte 0x1a"), "{}", failure.message);
+
+        let markdown = "# Verus lemma finder\n\nSearch the *module* index for lemmas.\n\n- [docs](docs/)\n";
+        assert_eq!(parse_guarded(markdown, None, &ParseOptions::default()).unwrap_err().kind, "not_rust_source");
+        // Strict callers get the parser's own answer
+        let strict = ParseOptions { strict: true, ..Default::default() };
+        assert_eq!(parse_guarded(markdown, None, &strict).unwrap_err().kind, "parse_error");
+
+        let verus = "verus! { proof fn lemma_a(x: int) ensures x + 0 == x, {} }";
+        assert_eq!(parse_guarded(verus, None, &ParseOptions::default()).unwrap()[0].name, "lemma_a");
+        assert!(parse_guarded("\n", None, &ParseOptions::default()).unwrap().is_empty());
+
+        fn panicking(_: &str, _: Option<&str>, _: &ParseOptions) -> Result<Vec<FunctionSpecs>, ParseFailure> {
+            panic!("boom")
+        }
+        let failure = run_guarded(verus, None, &ParseOptions::default(), panicking).unwrap_err();
+        assert_eq!(failure, ParseFailure::new("panic", "Parser panicked: boom".to_string()));
+        let timed = ParseOptions { timeout_ms: Some(10_000), ..Default::default() };
+        assert_eq!(run_guarded(verus, None, &timed, panicking).unwrap_err().kind, "panic");
+    }
+
+    #[test]
+    fn test_header_source() {
+        let code = r#"use vstd::prelude::*;
+
+verus! {
+    pub proof fn lemma_bounds(x: int)
+        requires
+            0 <= x,
+        ensures
+            x + 1 > 0,
+            // the square is non-negative too
+            x * x >= 0,
+    {
+    }
+
+    trait T {
+        spec fn f(&self) -> int;
+    }
+}
+"#;
+        let options = ParseOptions { header_source: true, ..Default::default() };
+        let funcs = parse_limited(code, None, &options).unwrap();
+        let header = funcs[0].header_source.as_deref().unwrap();
+        assert!(header.starts_with("proof fn lemma_bounds(x: int)"), "{}", header);
+        assert!(header.contains("x + 1 > 0,\n            // the square is non-negative too\n            x * x >= 0,"));
+        assert!(header.ends_with("x * x >= 0,"));
+        assert_eq!(funcs[1].header_source.as_deref(), Some("spec fn f(&self) -> int"));
+
+        // Batches of the parallel path are offset back into the file
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..options };
+        let parallel_funcs = parse_limited(code, None, &parallel).unwrap();
+        assert_eq!(parallel_funcs[0].header_source, funcs[0].header_source);
+        assert_eq!(parallel_funcs[1].header_source, funcs[1].header_source);
+
+        assert!(parse_verus_file_core(code).unwrap()[0].header_source.is_none());
+    }
+
+    #[test]
+    fn test_parse_recovering_reports_each_syntax_error() {
+        let content = r#"
+verus! {
+fn broken_one(x: u64) -> u64 {
+    x + + ;
+}
+
+proof fn lemma_valid(x: int)
+    ensures x + 0 == x,
+{
+}
+
+fn broken_two(x: u64 {
+    x
+}
+}
+"#;
+        let (functions, diagnostics) = parse_recovering(content, &ParseOptions::default()).unwrap();
+        let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lemma_valid"]);
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.kind == "parse_error").collect();
+        assert_eq!(errors.len(), 2, "{:?}", diagnostics);
+        assert_eq!(errors[0].line_number, Some(4));
+        assert_eq!(errors[0].context.as_deref(), Some("x + + ;"));
+        assert_eq!(errors[1].line_number, Some(12));
+        assert!(errors.iter().all(|d| d.column.is_some()));
+    }
+
+    #[test]
+    fn test_verus_block_ranges_unbalanced_brace() {
+        let code = r#"
+use vstd::prelude::*;
+
+vstd::prelude::verus!(
+fn c() {}
+);
+
+verus! {
+
+fn a(x: u64) -> u64 {
+    if x > 0 {
+        return x;
+    0
+}
+
+fn b() {
+}
+
+} // verus!
+"#;
+        let blocks = verus_block_ranges_core(code, &[]);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            (blocks[0].macro_path.as_str(), blocks[0].delimiter.as_str(), blocks[0].parsed),
+            ("vstd::prelude::verus", "()", true)
+        );
+        assert_eq!(
+            (blocks[1].macro_path.as_str(), blocks[1].delimiter.as_str(), blocks[1].line_number),
+            ("verus", "{}", 8)
+        );
+        // The missing `}` leaves the block open to the end of the file
+        assert_eq!((blocks[1].parsed, blocks[1].end_line), (false, 20));
+        let at_if = |d: &Diagnostic| d.kind == "unbalanced_delimiter" && d.line_number == Some(11);
+        assert!(blocks[1].delimiter_issues.iter().any(at_if));
+
+        let (_, diagnostics) = parse_recovering(code, &ParseOptions::default()).unwrap();
+        assert!(diagnostics.iter().any(at_if));
+    }
+
+    #[test]
+    fn test_exclusion_options() {
+        let code = r#"
+verus! {
+
+#[automatically_derived]
+impl Clone for Point {
+    fn clone(&self) -> Self { *self }
+}
+
+#[verifier::external_body]
+fn __helper() {}
+
+fn __internal() {}
+
+proof fn lemma_kept(x: int) ensures x == x {}
+
+#[automatically_derived]
+fn derived_eq() {}
+
+}
+"#;
+        let options = ParseOptions {
+            exclude_attributes: vec!["automatically_derived".to_string()],
+            exclude_name_patterns: vec!["__*".to_string()],
+            ..Default::default()
+        };
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..options.clone() };
+        for options in [options, parallel] {
+            let (functions, diagnostics) = parse_limited_with_diagnostics(code, None, &options).unwrap();
+            let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(names, vec!["lemma_kept"]);
+            let excluded: Vec<(Option<usize>, &str)> = diagnostics
+                .iter()
+                .filter(|d| d.kind == "functions_excluded")
+                .map(|d| (d.line_number, d.detail.as_str()))
+                .collect();
+            assert_eq!(
+                excluded,
+                vec![
+                    (Some(6), "2 excluded by attribute `automatically_derived`: clone, derived_eq"),
+                    (Some(10), "2 excluded by name pattern `__*`: __helper, __internal"),
+                ]
+            );
+        }
+
+        let by_path = ParseOptions { exclude_attributes: vec!["verifier::*".to_string()], ..Default::default() };
+        let (functions, _) = parse_limited_with_diagnostics(code, None, &by_path).unwrap();
+        assert!(functions.iter().all(|f| f.name != "__helper"));
+        assert_eq!(functions.len(), 4);
+
+        let (functions, diagnostics) = parse_limited_with_diagnostics(code, None, &ParseOptions::default()).unwrap();
+        assert_eq!(functions.len(), 5);
+        assert!(diagnostics.iter().all(|d| d.kind != "functions_excluded"));
+    }
+
+    #[test]
+    fn test_impl_qualified_names() {
+        let code = r#"
+verus! {
+
+pub trait MyTrait {
+    spec fn measure(&self) -> nat;
+}
+
+impl<T> MyTrait for Vec<T> {
+    spec fn measure(&self) -> nat { self.len() as nat }
+}
+
+impl MyTrait for &u32 {
+    spec fn measure(&self) -> nat { **self as nat }
+}
+
+impl MyTrait for (u32, u32) {
+    spec fn measure(&self) -> nat { self.0 as nat }
+}
+
+mod slices {
+    impl<'a, T> crate::MyTrait for &'a mut [T] {
+        spec fn measure(&self) -> nat { self.len() as nat }
+    }
+
+    impl<T> super::Wrapper<T> {
+        proof fn lemma_wrap(self) {}
+    }
+
+    impl MyTrait for Slot {
+        spec fn measure(&self) -> nat { 0 }
+    }
+
+    impl self::Slot {
+        proof fn lemma_slot(self) {}
+    }
+}
+
+impl<T: View> MyTrait for T {
+    spec fn measure(&self) -> nat { 1 }
+}
+
+}
+"#;
+        let owned = |name: &str, impl_type: &str, trait_path: Option<&str>| {
+            (name.to_string(), Some(impl_type.to_string()), trait_path.map(String::from))
+        };
+        let expected = vec![
+            owned("<Vec<T> as MyTrait>::measure", "Vec<T>", Some("MyTrait")),
+            owned("<&u32 as MyTrait>::measure", "&u32", Some("MyTrait")),
+            owned("<(u32, u32) as MyTrait>::measure", "(u32, u32)", Some("MyTrait")),
+            owned("<&'a mut [T] as crate::MyTrait>::measure", "&'a mut [T]", Some("crate::MyTrait")),
+            owned("<Wrapper<T>>::lemma_wrap", "super::Wrapper<T>", None),
+            owned("<slices::Slot as MyTrait>::measure", "Slot", Some("MyTrait")),
+            owned("slices::Slot::lemma_slot", "self::Slot", None),
+            owned("<T as MyTrait>::measure", "T", Some("MyTrait")),
+        ];
+        let names = |functions: &[FunctionSpecs]| -> Vec<(String, Option<String>, Option<String>)> {
+            functions.iter().map(|f| (f.qualified_name(), f.impl_type.clone(), f.trait_path.clone())).collect()
+        };
+        let functions = parse_verus_file_core(code).unwrap();
+        assert_eq!(functions[0].qualified_name(), "measure");
+        assert_eq!(functions[1].path, vec!["crate", "verus!", "impl MyTrait for Vec<T>", "fn measure"]);
+        assert_eq!(functions[4].path[3], "impl crate::MyTrait for &'a mut [T]");
+        assert_eq!(names(&functions[1..]), expected);
+
+        let parallel = ParseOptions { parallel_threshold: Some(0), ..Default::default() };
+        assert_eq!(names(&parse_limited(code, None, &parallel).unwrap()[1..]), expected);
+        let resolved = ParseOptions { resolve_self: true, ..Default::default() };
+        assert_eq!(names(&parse_limited(code, None, &resolved).unwrap()[1..]), expected);
+    }
+
+    #[test]
+    fn test_directory_parse_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        for (i, rel) in ["src/a.rs", "src/b.rs", "src/nested/c.rs", "src/nested/d.rs", "e.rs"].iter().enumerate() {
+            std::fs::write(root.join(rel), format!("verus! {{ proof fn lemma_{}() {{}} }}", i)).unwrap();
+        }
+        std::fs::write(root.join("src/broken.rs"), "fn broken(").unwrap();
+        let options = ParseOptions::default();
+
+        let mut calls: Vec<(usize, usize, String)> = Vec::new();
+        let parsed = parse_verus_directory_with_progress_core(root, &[], true, &options, 2, |done, total, current| {
+            calls.push((done, total, current.to_string()));
+            Ok::<(), std::io::Error>(())
+        })
+        .unwrap();
+        let counts: Vec<(usize, usize)> = calls.iter().map(|(done, total, _)| (*done, *total)).collect();
+        assert_eq!(counts, vec![(2, 6), (4, 6), (6, 6)]);
+        assert!(calls.iter().all(|(_, _, current)| current.starts_with(&*root.to_string_lossy())));
+        assert_eq!(parsed.0.len(), 5);
+        assert_eq!(parsed.1.len(), 1);
+        assert_eq!(parsed, parse_verus_directory_core(root, &[], true, &options).unwrap());
+
+        let mut called = 0;
+        let cancelled = parse_verus_directory_with_progress_core(root, &[], true, &options, 1, |_, _, _| {
+            called += 1;
+            Err(std::io::Error::other("stop"))
+        });
+        assert_eq!(cancelled.unwrap_err().to_string(), "stop");
+        assert_eq!(called, 1);
+    }
+}
@@ -0,0 +1,46 @@
+//! Source fixtures shared by the unit tests of several modules
+
+pub(crate) const SAMPLE_VERUS: &str = r#"
+pub proof fn lemma_mul_inequality(x: int, y: int, z: int)
+    requires
+        x <= y,
+        z > 0,
+    ensures
+        x * z <= y * z,
+{
+}
+
+pub fn exec_add(a: u32, b: u32) -> (result: u32)
+    requires
+        a + b <= u32::MAX,
+    ensures
+        result == a + b,
+{
+    a + b
+}
+"#;
+
+pub(crate) const GENERIC_LEMMAS: &str = r#"
+trait SpecOrd {}
+
+proof fn lemma_inline<T: View + Clone>(x: T)
+    requires x == x,
+{
+}
+
+proof fn lemma_where<T, U>(x: T, y: U)
+    where
+        T: SpecOrd,
+        U: vstd::view::View,
+{
+}
+
+struct Wrapper<A> { a: A }
+
+impl<A: View> Wrapper<A> {
+    proof fn lemma_method<B>(&self, b: B)
+        where B: SpecOrd
+    {
+    }
+}
+"#;
@@ -0,0 +1,1292 @@
+//! Result types returned by the parser and its analyses
+
+use crate::extract::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use verus_syn::Expr;
+
+/// Extracted specification from a Verus function
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct FunctionSpecs {
+    /// Function name
+    pub name: String,
+    /// File path (if provided); one allocation shared by the functions of a file,
+    /// serialized as a plain string
+    pub file_path: Arc<str>,
+    /// Line number of function definition (1-indexed)
+    pub line_number: Option<usize>,
+    /// End line number of function
+    pub end_line: Option<usize>,
+    /// Line where the header (signature and spec clauses) ends, just before the
+    /// body; equals `end_line` for a declaration without a body
+    pub sig_end_line: Option<usize>,
+    /// Column just past the header's last token (0-indexed, in characters)
+    pub sig_end_column: Option<usize>,
+    /// List of requires clauses
+    pub requires: Vec<String>,
+    /// List of ensures clauses
+    pub ensures: Vec<String>,
+    /// List of decreases clauses
+    pub decreases: Vec<String>,
+    /// Shape of the termination measure (None without a decreases clause)
+    pub decreases_measure: Option<DecreasesMeasure>,
+    /// List of recommends clauses (only checked for `spec(checked)` functions)
+    pub recommends: Vec<String>,
+    /// Requires clauses before conjunction splitting (only filled in when splitting)
+    pub requires_raw: Vec<String>,
+    /// Ensures clauses before conjunction splitting (only filled in when splitting)
+    pub ensures_raw: Vec<String>,
+    /// For each requires entry, the index of the `requires_raw` clause it was split from
+    pub requires_parent: Vec<usize>,
+    /// For each ensures entry, the index of the `ensures_raw` clause it was split from
+    pub ensures_parent: Vec<usize>,
+    /// Highlighting ranges within each `requires` entry (only filled in on request)
+    pub requires_offsets: Vec<ClauseOffsets>,
+    /// Highlighting ranges within each `ensures` entry (only filled in on request)
+    pub ensures_offsets: Vec<ClauseOffsets>,
+    /// Parameters and free names each `requires` entry refers to
+    pub requires_mentions: Vec<ClauseMentions>,
+    /// Parameters and free names each `ensures` entry refers to
+    pub ensures_mentions: Vec<ClauseMentions>,
+    /// Whether no requires or ensures clause refers to anything but the
+    /// parameters, the return value and spec functions applied to them
+    pub is_self_contained: bool,
+    /// Whether a `requires` block is present (even if it holds no expressions)
+    pub has_requires: bool,
+    /// Whether an `ensures` block is present
+    pub has_ensures: bool,
+    /// Whether a `decreases` clause is present
+    pub has_decreases: bool,
+    /// Where the requires/ensures come from: "verus" (clauses), "attribute"
+    /// (`#[requires(...)]`/`#[ensures(...)]`, with `attribute_specs`) or
+    /// "mixed" (both, attributes first)
+    pub spec_style: String,
+    /// Function signature as `mode fn name<generics>(params) -> (binding: Type)`
+    /// (plus any where clause) with canonical spacing and no spec clauses
+    pub signature: String,
+    /// Signature as rendered token by token (spacing and whether spec clauses
+    /// are included depend on the verus_syn version)
+    pub signature_raw: String,
+    /// Source text from the start of the signature to the body's opening
+    /// brace (or the `;` of a declaration), formatting and comments intact
+    /// (only filled in on request)
+    pub header_source: Option<String>,
+    /// Parameters in order, receiver included
+    pub params: Vec<ParamInfo>,
+    /// Return value binding and type (None when the function returns `()`)
+    pub returns: Option<ReturnInfo>,
+    /// Whether the function is a proof function
+    pub is_proof: bool,
+    /// Function mode: "spec", "spec(checked)", "proof" or "exec" (also used
+    /// for functions without a mode keyword)
+    pub mode: String,
+    /// Whether the function is declared `broadcast` (its uses through
+    /// `broadcast use` are implicit and not visible as calls)
+    pub is_broadcast: bool,
+    /// Whether the function has `#[verifier::memoize]`
+    pub is_memoized: bool,
+    /// Whether the function has `#[verifier::inline]` (inlined spec function)
+    pub is_inlined_spec: bool,
+    /// Verifier attributes on the function, e.g. "verifier::opaque" or
+    /// "verifier::rlimit(20)"; every `verifier` attribute is kept, known or not
+    pub attributes: Vec<String>,
+    /// The same attributes as (name, argument text) pairs, e.g.
+    /// ("verifier::rlimit", Some("20")); None for an attribute without arguments
+    pub verifier_attributes: Vec<(String, Option<String>)>,
+    /// Built-in lints suppressed for the function (`unused_lemma`, ...), by
+    /// `#[cfg_attr(any(), allow(...))]` or, in the parse entry points, a
+    /// `// verus-lemma-finder: allow(...)` comment on the lines above it
+    pub suppressed: Vec<String>,
+    /// Comment lines inside the function holding a proof-debt marker
+    /// (`TODO`, `FIXME`, ... or the configured `todo_markers`) as (line,
+    /// comment text); filled in by the parse entry points
+    pub todo_markers: Vec<(usize, String)>,
+    /// Conditions of the function's own `#[cfg(...)]` attributes, e.g. `feature = "alloc"`
+    pub cfgs: Vec<String>,
+    /// Conditions of the `#[cfg(...)]` attributes of the enclosing modules,
+    /// impls, traits, macro invocations and functions (outermost first),
+    /// followed by `cfgs`
+    pub effective_cfgs: Vec<String>,
+    /// Path of the enclosing module within the file (`::`-separated, empty at top level)
+    pub module_path: String,
+    /// For a method of an impl, the impl's self type as written, e.g. `Vec<T>`,
+    /// `&u32` or `(u32, u32)`
+    pub impl_type: Option<String>,
+    /// For a method of a trait impl, the implemented trait as written, e.g. `View`
+    /// or `crate::traits::Bounded<T>`
+    pub trait_path: Option<String>,
+    /// Breadcrumbs of the enclosing modules, impls, traits, macro invocations
+    /// and functions, e.g. `["crate", "mod arith", "impl Scalar", "verus!", "fn add"]`
+    pub path: Vec<String>,
+    /// Whether the function is test-only: marked `#[cfg(test)]` or inside such
+    /// an item or module (or, on request, a module named `tests`)
+    pub in_test: bool,
+    /// Name of the (non-`verus!`) macro invocation the function was found in
+    pub from_macro: Option<String>,
+    /// Whether the function is inside a `verus!` block (or a configured macro wrapping it)
+    pub in_verus_block: bool,
+    /// Which traversal path found the function: "top_level", "impl", "trait",
+    /// "verus_macro", "verus_macro_in_impl" (a `verus!` inside an impl body)
+    /// or "macro:<name>" (another item macro)
+    pub provenance: String,
+    /// `//!` docs of the enclosing module (only filled in on request)
+    pub module_doc: Option<String>,
+    /// Whether the function can be named from outside its crate, through
+    /// `pub` modules or `pub use` re-exports (only filled in by `parse_verus_crate`)
+    pub is_externally_reachable: bool,
+    /// For a method whose `Self` was replaced by the impl's self type (only on
+    /// request), the signature and clauses as written
+    pub unresolved: Option<UnresolvedSpecs>,
+    /// `proof { ... }` blocks in the function body
+    pub proof_blocks: Vec<ProofBlock>,
+    /// `assert(...)` statements in the function body
+    pub asserts: Vec<AssertInfo>,
+    /// `as` casts to fixed-width integer types in the body (exec functions only)
+    pub casts: Vec<CastInfo>,
+    /// Number of statements in the body, counting those in nested blocks
+    /// (None for a function without a body)
+    pub body_stmt_count: Option<usize>,
+    /// Whether this is a proof function with an empty body, proved by the solver alone
+    pub is_trivial_proof: bool,
+    /// Trait bounds per generic parameter, merged from the parameter list, the
+    /// where clause and (for methods) the enclosing impl or trait
+    pub bounds: Vec<(String, Vec<String>)>,
+    /// Stable SHA-256 hex digest of the signature and spec clauses
+    /// (independent of formatting, comments, position and body)
+    pub spec_fingerprint: String,
+    /// Any parse errors encountered
+    pub parse_error: Option<String>,
+    /// Category of `parse_error`: "parse_error", "not_found", "not_rust_source"
+    /// (rejected by the pre-check without parsing), "panic" (the parser
+    /// crashed on the input), or the name of the exceeded limit ("max_bytes",
+    /// "max_items", "max_depth", "timeout")
+    pub error_kind: Option<String>,
+}
+
+/// `path` as written in module `module_path` (`::`-separated, empty at the
+/// top level), relative to the crate root
+fn in_module(module_path: &str, path: &str) -> String {
+    if path.starts_with("crate::") || path.starts_with("::") {
+        return path.to_string();
+    }
+    let mut segments: Vec<&str> = module_path.split("::").filter(|segment| !segment.is_empty()).collect();
+    let mut rest = path;
+    loop {
+        if let Some(tail) = rest.strip_prefix("self::") {
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("super::") {
+            segments.pop();
+            rest = tail;
+        } else {
+            break;
+        }
+    }
+    segments.push(rest);
+    segments.join("::")
+}
+
+impl FunctionSpecs {
+    /// Name prefixed with the enclosing module path and, for an impl method,
+    /// the impl's self type, as a Rust path: e.g. "seq_lemmas::lemma_x",
+    /// "arith::Scalar::add", "<arith::Wrapper<T>>::get" or
+    /// "<arith::Scalar as View>::view"
+    ///
+    /// A self type that is a path is taken relative to the module (`self::`
+    /// and `super::` applied, `crate::` kept); other self types (references,
+    /// tuples, ...), bounded generic parameters and the trait stay as written.
+    pub fn qualified_name(&self) -> String {
+        let Some(impl_type) = &self.impl_type else {
+            return in_module(&self.module_path, &self.name);
+        };
+        let is_path = impl_type.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == ':');
+        let is_generic = self.bounds.iter().any(|(param, _)| param == impl_type);
+        let self_type = match is_path && !is_generic {
+            true => in_module(&self.module_path, impl_type),
+            false => impl_type.clone(),
+        };
+        match &self.trait_path {
+            Some(trait_path) => format!("<{} as {}>::{}", self_type, trait_path, self.name),
+            None if is_path && !self_type.contains('<') => format!("{}::{}", self_type, self.name),
+            None => format!("<{}>::{}", self_type, self.name),
+        }
+    }
+
+    /// Render the function as a Verus item from its name, mode, params,
+    /// returns and clauses, with an empty body (or `unimplemented!()` when
+    /// `include_body` is set)
+    ///
+    /// The output is parsed back and must yield the same name and clauses;
+    /// otherwise (e.g. a clause that is not an expression) the error says why.
+    pub fn to_verus_source(&self, include_body: bool) -> Result<String, String> {
+        let keyword = match self.mode.as_str() {
+            "" if self.is_proof => "proof fn",
+            "" | "exec" => "fn",
+            "proof" => "proof fn",
+            "spec" => "spec fn",
+            "spec(checked)" => "spec(checked) fn",
+            other => return Err(format!("Unknown mode '{}'", other)),
+        };
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| {
+                let reference = match (p.reference.as_str(), &p.lifetime) {
+                    ("&", Some(lifetime)) => format!("&{} ", lifetime),
+                    ("&mut", Some(lifetime)) => format!("&{} mut ", lifetime),
+                    ("&", None) => "&".to_string(),
+                    ("&mut", None) => "&mut ".to_string(),
+                    _ => String::new(),
+                };
+                if p.name == "self" {
+                    format!("{}self", reference)
+                } else {
+                    format!("{}: {}{}", p.name, reference, p.ty)
+                }
+            })
+            .collect();
+        let mut out = format!("{} {}({})", keyword, self.name, params.join(", "));
+        match &self.returns {
+            Some(ReturnInfo { binding: Some(binding), ty, .. }) => out.push_str(&format!(" -> ({}: {})", binding, ty)),
+            Some(ReturnInfo { ty, .. }) => out.push_str(&format!(" -> {}", ty)),
+            None => {}
+        }
+        out.push('\n');
+        let clauses = [
+            ("recommends", &self.recommends),
+            ("requires", &self.requires),
+            ("ensures", &self.ensures),
+            ("decreases", &self.decreases),
+        ];
+        for (keyword, exprs) in clauses {
+            if exprs.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("    {}\n", keyword));
+            for expr in exprs {
+                out.push_str(&format!("        {},\n", expr));
+            }
+        }
+        out.push_str(if include_body { "{\n    unimplemented!()\n}\n" } else { "{\n}\n" });
+
+        // Round trip: the clauses must come back as written (up to token spacing)
+        let parsed = parse_verus_file_core(&out).map_err(|e| format!("Rendered source does not parse: {}", e))?;
+        let [parsed] = parsed.as_slice() else {
+            return Err(format!("Rendered source has {} functions instead of one", parsed.len()));
+        };
+        for (keyword, exprs) in clauses {
+            let expected: Vec<String> = exprs
+                .iter()
+                .map(|expr| {
+                    verus_syn::parse_str::<Expr>(expr)
+                        .map(|e| quote::quote!(#e).to_string())
+                        .map_err(|e| format!("Invalid {} clause `{}`: {}", keyword, expr, e))
+                })
+                .collect::<Result<_, _>>()?;
+            let found = match keyword {
+                "recommends" => &parsed.recommends,
+                "requires" => &parsed.requires,
+                "ensures" => &parsed.ensures,
+                _ => &parsed.decreases,
+            };
+            if *found != expected {
+                return Err(format!("Rendered {} clauses do not round-trip: {:?} != {:?}", keyword, found, expected));
+            }
+        }
+        if parsed.name != self.name {
+            return Err(format!("Rendered name does not round-trip: {} != {}", parsed.name, self.name));
+        }
+        Ok(out)
+    }
+}
+
+/// Token count of a clause and the character ranges of its notable
+/// sub-expressions within the clause string
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClauseOffsets {
+    /// Number of tokens in the clause (identifiers, punctuation and literals)
+    pub token_count: usize,
+    /// Sub-expression ranges in source order of their start
+    pub ranges: Vec<ClauseRange>,
+}
+
+/// A sub-expression of a clause as a half-open range of characters (not bytes)
+/// of the clause string, so `clause[start:end]` in Python is its text
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClauseRange {
+    /// "quantifier_body", "trigger", "lhs" or "rhs" (sides of a top-level relation)
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// What a spec clause refers to
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClauseMentions {
+    /// Parameters (and the return value binding) used, in order of first use
+    pub params: Vec<String>,
+    /// Other free names: constants, statics and spec functions that are not
+    /// applied to parameters or bound variables, as written (`crate::MAX`)
+    pub externals: Vec<String>,
+}
+
+/// Classification of a `decreases` measure, e.g. for choosing an induction scheme
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DecreasesMeasure {
+    /// "single_var" (`decreases n`), "call_on_param" (`decreases s.len()`,
+    /// `decreases height(t)`), "lexicographic" (`decreases a, b`) or "other"
+    pub kind: String,
+    /// Parameters used by the measure, in order of first use
+    pub params: Vec<String>,
+    /// Number of comma-separated components (more than one only when lexicographic)
+    pub components: usize,
+}
+
+/// The parts of a method's specs that mention `Self`, as written, kept when
+/// `Self` is resolved to the impl's self type
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UnresolvedSpecs {
+    /// The self type `Self` was replaced with, e.g. "Scalar<T>"
+    pub self_type: String,
+    /// Signature with `Self`
+    pub signature: String,
+    /// Requires clauses with `Self`
+    pub requires: Vec<String>,
+    /// Ensures clauses with `Self`
+    pub ensures: Vec<String>,
+    /// Decreases clauses with `Self`
+    pub decreases: Vec<String>,
+    /// Recommends clauses with `Self`
+    pub recommends: Vec<String>,
+    /// Parameters with `Self` in their types
+    pub params: Vec<ParamInfo>,
+    /// Return value with `Self` in its type
+    pub returns: Option<ReturnInfo>,
+}
+
+/// A `proof { ... }` block embedded in a function body
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ProofBlock {
+    /// Line number of the `proof` keyword (1-indexed)
+    pub line_number: Option<usize>,
+    /// Line number of the closing brace
+    pub end_line: Option<usize>,
+    /// Paths of the functions called inside the block, in source order
+    pub calls: Vec<String>,
+}
+
+/// A function parameter, with how it is passed
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ParamInfo {
+    /// Parameter name (`self` for the receiver)
+    pub name: String,
+    /// Type as written, without the reference (`Self` for a receiver)
+    pub ty: String,
+    /// "by_value", "&" or "&mut"
+    pub reference: String,
+    /// Explicit lifetime of the reference, e.g. "'a"
+    pub lifetime: Option<String>,
+    /// Whether the referenced (or passed) type is a slice `[T]`
+    pub is_slice: bool,
+    /// Whether it is an array `[T; N]`
+    pub is_array: bool,
+    /// `N` of an array whose length is a literal
+    pub array_len: Option<usize>,
+}
+
+/// The return value of a function
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ReturnInfo {
+    /// Name bound to the return value (`r` in `-> (r: T)`), if any
+    pub binding: Option<String>,
+    /// Return type
+    pub ty: String,
+    /// Element types when the return type is a tuple, in `r.0`, `r.1`, ... order
+    pub elements: Vec<TupleElement>,
+}
+
+/// An element of a tuple return type
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct TupleElement {
+    /// Position in the tuple
+    pub index: usize,
+    /// Element type
+    pub ty: String,
+}
+
+/// An `assert(...)` statement (or `assert!(...)` macro call) in a function body
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AssertInfo {
+    /// Line number of the `assert` keyword (1-indexed)
+    pub line_number: Option<usize>,
+    /// Line of the closing parenthesis of the arguments, or of the prover's
+    pub end_line: Option<usize>,
+    /// The asserted expression (the first argument)
+    pub expr: String,
+    /// Prover named in `by (...)`, e.g. "compute" or "nonlinear_arith"; None for
+    /// a plain assert or one proved by a `by { ... }` block
+    pub prover: Option<String>,
+    /// Values of the string literal arguments after it, e.g. the message of
+    /// `assert!(x > 0, "x positive")`
+    pub labels: Vec<String>,
+    /// Comment after the assert on its last line (`end_line`), without the
+    /// comment delimiters; filled in by the parse entry points
+    pub comment: Option<String>,
+}
+
+/// An `as` cast to a fixed-width integer type in an exec function body
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CastInfo {
+    /// Line number of the cast (1-indexed)
+    pub line_number: Option<usize>,
+    /// The cast expression, e.g. "x as u8"
+    pub expr: String,
+    /// Type of the operand, when it can be told from the source
+    pub from_ty: Option<String>,
+    /// Target type
+    pub to_ty: String,
+    /// "truncate" (under `#[verifier::truncate]`), "widening", "narrowing" or
+    /// "unknown" (source type not determinable)
+    pub kind: String,
+}
+
+/// An integer cast in a file, for `truncation_report`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct FunctionCast {
+    /// Name of the enclosing function
+    pub function: String,
+    /// The cast itself
+    pub cast: CastInfo,
+}
+
+/// An assert proved by evaluation (`by (compute)` or `by (compute_only)`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ComputeProof {
+    /// Name of the enclosing function
+    pub function: String,
+    /// Line number of the `assert` keyword (1-indexed)
+    pub line_number: Option<usize>,
+    /// The asserted expression
+    pub expr: String,
+    /// "compute" or "compute_only"
+    pub prover: String,
+}
+
+/// An assert whose string literal argument or trailing comment matches a label
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LabeledAssert {
+    /// File containing the assert
+    pub file_path: String,
+    /// Qualified name of the enclosing function
+    pub function: String,
+    /// Line number of the `assert` keyword (1-indexed)
+    pub line_number: Option<usize>,
+    /// The asserted expression
+    pub expr: String,
+    /// String literal arguments of the assert
+    pub labels: Vec<String>,
+    /// Comment after the assert on its last line
+    pub comment: Option<String>,
+}
+
+/// A loop found in a function body
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LoopInfo {
+    /// Name of the enclosing function
+    pub function: String,
+    /// Loop kind: "while", "loop" or "for"
+    pub kind: String,
+    /// Line number of the loop (1-indexed)
+    pub line_number: Option<usize>,
+    /// End line number of the loop
+    pub end_line: Option<usize>,
+    /// Whether the loop has a decreases clause
+    pub has_decreases: bool,
+    /// Whether termination checking is waived for this loop, by a loop-level
+    /// or function-level `#[verifier::exec_allows_no_decreases_clause]`
+    pub termination_waived: bool,
+    /// Verifier attributes on the loop itself
+    pub attributes: Vec<String>,
+}
+
+/// A loop or recursive function with neither a decreases clause nor a waiver
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TerminationIssue {
+    /// Name of the function
+    pub function: String,
+    /// "while" or "loop" for loops, "recursion" for recursive functions
+    pub kind: String,
+    /// Line number of the loop or function (1-indexed)
+    pub line_number: Option<usize>,
+    /// End line number of the loop or function
+    pub end_line: Option<usize>,
+}
+
+/// A call site of a lemma, with surrounding source
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UsageExample {
+    /// Name of the calling function
+    pub caller: String,
+    /// File containing the call
+    pub file_path: String,
+    /// Line number of the call (1-indexed)
+    pub line_number: usize,
+    /// Source text of each argument expression
+    pub args: Vec<String>,
+    /// Source lines around the call
+    pub snippet: String,
+    /// First line of the snippet (1-indexed)
+    pub snippet_start: usize,
+    /// Last line of the snippet
+    pub snippet_end: usize,
+}
+
+/// An associated type or const declared in a trait
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AssociatedItem {
+    /// Name of the type or const
+    pub name: String,
+    /// Bounds of an associated type (`type V: View;` gives ["View"])
+    pub bounds: Vec<String>,
+    /// Type of an associated const
+    pub ty: Option<String>,
+    /// Default type or value, if provided
+    pub default: Option<String>,
+}
+
+/// The full surface of a trait declaration
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct TraitInfo {
+    /// Trait name
+    pub name: String,
+    /// Path of the enclosing module within the file
+    pub module_path: String,
+    /// Line number of the trait (1-indexed)
+    pub line_number: Option<usize>,
+    /// End line number of the trait
+    pub end_line: Option<usize>,
+    /// Generic parameters, e.g. "<T: View>" (empty if none)
+    pub generics: String,
+    /// Supertrait bounds
+    pub supertraits: Vec<String>,
+    /// Associated types
+    pub associated_types: Vec<AssociatedItem>,
+    /// Associated consts
+    pub associated_consts: Vec<AssociatedItem>,
+    /// Specs of the trait's methods
+    pub methods: Vec<FunctionSpecs>,
+    /// Names of the methods with a provided (default) body
+    pub provided_methods: Vec<String>,
+}
+
+/// A trait method implementation whose clauses differ from the trait's
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RefinementIssue {
+    /// Name of the trait
+    pub trait_name: String,
+    /// Self type of the impl
+    pub impl_type: String,
+    /// Name of the method
+    pub method: String,
+    /// Line number of the impl method (1-indexed)
+    pub line_number: Option<usize>,
+    /// "syntactic_missing_ensures" or "syntactic_added_requires"; the
+    /// comparison is textual, not a semantic entailment check
+    pub kind: String,
+    /// The trait's ensures clause missing from the impl, or the impl's extra
+    /// requires clause, with parameters named as in the trait
+    pub clause: String,
+}
+
+/// A clause of a public function that references a non-public item of the same file
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct VisibilityIssue {
+    /// Name of the public function
+    pub function: String,
+    /// Line number of the function (1-indexed)
+    pub line_number: Option<usize>,
+    /// "requires" or "ensures"
+    pub clause_kind: String,
+    /// The offending clause
+    pub clause: String,
+    /// Name of the non-public item referenced
+    pub item: String,
+    /// "spec fn" or "const"
+    pub item_kind: String,
+}
+
+/// A sub-expression of a spec clause that only makes sense in exec code
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpecExprIssue {
+    /// Name of the function
+    pub function: String,
+    /// "requires", "ensures" or "decreases"
+    pub clause_kind: String,
+    /// The clause containing the sub-expression
+    pub clause: String,
+    /// "mut_borrow", "assignment", "control_flow", "macro" or "clone"
+    pub kind: String,
+    /// The offending sub-expression
+    pub expr: String,
+    /// Line number of the sub-expression (1-indexed)
+    pub line_number: usize,
+    /// Column of the sub-expression (0-indexed, in characters)
+    pub column: usize,
+}
+
+/// The outcome of parsing one candidate clause with `validate_spec_exprs`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpecExprValidation {
+    /// Whether the candidate parses as a single Verus expression
+    pub valid: bool,
+    /// The parse error, for an invalid candidate
+    pub error: Option<String>,
+    /// Where the parse error is, in characters from the start of the
+    /// candidate (0-indexed, lines included); None for unbalanced delimiters
+    pub error_column: Option<usize>,
+    /// The expression rendered as clauses are in `FunctionSpecs` (canonical
+    /// token spacing), for a valid candidate
+    pub normalized: Option<String>,
+    /// Free names the expression refers to as written (variables, constants
+    /// and functions, `crate::MAX` whole), in order of first use; quantifier
+    /// variables, method and field names are left out
+    pub identifiers: Vec<String>,
+}
+
+/// Where a function opens, mentions or creates a `LocalInvariant`/`AtomicInvariant`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct InvariantUsage {
+    /// Name of the function
+    pub function: String,
+    /// "open" for `open_local_invariant!`/`open_atomic_invariant!` blocks,
+    /// "signature" for an invariant type among the parameter or return types,
+    /// "create" for a `LocalInvariant::new`/`AtomicInvariant::new` call
+    pub kind: String,
+    /// Whether the invariant is atomic rather than local
+    pub atomic: bool,
+    /// The opened invariant expression, the mentioned type, or the `new` call
+    pub expr: String,
+    /// Name the opened block binds the inner value to
+    pub binding: Option<String>,
+    /// Line number of the block, type or call (1-indexed)
+    pub line_number: usize,
+    /// End line number of the block, type or call
+    pub end_line: usize,
+}
+
+/// A clause sub-expression matched by a `find_pattern` pattern
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PatternMatch {
+    /// Name of the function
+    pub function: String,
+    /// "requires" or "ensures"
+    pub clause_kind: String,
+    /// The clause containing the match
+    pub clause: String,
+    /// The matched sub-expression (the whole clause or part of it)
+    pub expr: String,
+    /// Named hole (without the `?`) -> the text it matched
+    pub bindings: BTreeMap<String, String>,
+    /// Line number of the matched sub-expression (1-indexed)
+    pub line_number: usize,
+}
+
+/// A run of tokens in a clause's token-level diff
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct TokenEdit {
+    /// "equal", "removed" or "added"
+    pub op: String,
+    /// The tokens of the run, space-separated
+    pub text: String,
+}
+
+/// A spec clause that differs between two versions of a function
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClauseChange {
+    /// "requires", "ensures" or "decreases"
+    pub clause_kind: String,
+    /// "added", "removed" or "modified"
+    pub change: String,
+    /// The clause in the old version (None if added)
+    pub old: Option<String>,
+    /// The clause in the new version (None if removed)
+    pub new: Option<String>,
+    /// For a modified clause, the token runs from old to new
+    pub tokens: Vec<TokenEdit>,
+    /// For a modified clause, the new clause with removed runs as `[-...-]`
+    /// and added runs as `{+...+}`
+    pub snippet: String,
+}
+
+/// How a function's spec differs between two versions of a file
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpecDiff {
+    /// Qualified name of the function
+    pub function: String,
+    /// "added", "removed" or "modified"
+    pub status: String,
+    /// Clause changes; for an added or removed function, all its clauses
+    pub changes: Vec<ClauseChange>,
+}
+
+/// How the specs of one file differ between two directory trees
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct FileSpecDiff {
+    /// Path relative to the roots, `/`-separated
+    pub path: String,
+    /// "added" or "removed" (the file is on one side only) or "modified"
+    pub status: String,
+    /// Qualified names of the functions only in the new version
+    pub added: Vec<String>,
+    /// Qualified names of the functions only in the old version
+    pub removed: Vec<String>,
+    /// (old, new) qualified names of functions whose signature and clauses
+    /// are unchanged under another name or in another impl or trait
+    pub renamed: Vec<(String, String)>,
+    /// Functions in both versions whose clauses differ
+    pub modified: Vec<SpecDiff>,
+}
+
+/// Counts over all files of a `DirectoryDiff`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DirectoryDiffSummary {
+    /// Files only in the new tree
+    pub files_added: usize,
+    /// Files only in the old tree
+    pub files_removed: usize,
+    /// Files on both sides with any function added, removed, renamed or modified
+    pub files_modified: usize,
+    /// Functions only in the new version of their file (all of an added file's)
+    pub functions_added: usize,
+    /// Functions only in the old version of their file (all of a removed file's)
+    pub functions_removed: usize,
+    /// Renamed functions
+    pub functions_renamed: usize,
+    /// Functions whose clauses differ
+    pub functions_modified: usize,
+}
+
+/// How the specs of two directory trees differ, file by file
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DirectoryDiff {
+    /// Counts over all files
+    pub summary: DirectoryDiffSummary,
+    /// Relative paths of the files only in the new tree
+    pub added_files: Vec<String>,
+    /// Relative paths of the files only in the old tree
+    pub removed_files: Vec<String>,
+    /// The files with any spec change, added and removed files included, by path
+    pub files: Vec<FileSpecDiff>,
+}
+
+/// The field accesses and method calls on parameters in one spec clause
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ClauseFieldPaths {
+    /// "requires" or "ensures"
+    pub clause_kind: String,
+    /// The clause
+    pub clause: String,
+    /// Field-access paths rooted at a parameter, `self` or the return value
+    /// binding (`self.bytes`, `result.limbs.0`), sorted
+    pub fields: Vec<String>,
+    /// Method-call chains on such a root, with the fields they go through
+    /// (`self.bytes.len()`, `v.view().len()`), sorted
+    pub method_calls: Vec<String>,
+}
+
+/// A spec clause conjunct that is a single call, read as a predicate
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PredicateCall {
+    /// "requires" or "ensures"
+    pub clause_kind: String,
+    /// The conjunct
+    pub conjunct: String,
+    /// Called path as written (`inv`, `Self::wf`), or the method name of a
+    /// method call
+    pub callee: String,
+    /// Whether it is a method call (`self.wf()`), the receiver being the
+    /// first argument
+    pub is_method: bool,
+    /// The arguments as (kind, text) pairs, kind one of "param" (a bare
+    /// parameter, `self` included), "old" (`old(param)`), "result" (the return
+    /// value binding) or "complex"
+    pub args: Vec<(String, String)>,
+}
+
+/// A comment marking proof debt, as listed by `proof_todos`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ProofTodo {
+    /// File containing the comment
+    pub file_path: String,
+    /// Line of the comment (1-indexed)
+    pub line_number: usize,
+    /// Comment text without the comment delimiters
+    pub text: String,
+    /// Name of the innermost function containing it (None between functions)
+    pub function: Option<String>,
+    /// Mode of that function (see `FunctionSpecs::mode`)
+    pub mode: Option<String>,
+}
+
+/// A quantifier without an explicit trigger, as listed by `untriggered_quantifiers`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UntriggeredQuantifier {
+    /// File containing the quantifier
+    pub file_path: String,
+    /// Name of the function whose clause or body contains it
+    pub function: String,
+    /// "requires", "recommends", "ensures" or "body" (of a spec function)
+    pub clause_kind: String,
+    /// "forall", "exists" or "choose"
+    pub quantifier: String,
+    /// Source text of the quantifier, from its keyword through the end of its body
+    pub text: String,
+    /// Bound variables as (name, type as written, if any)
+    pub binders: Vec<(String, Option<String>)>,
+    /// Line of the quantifier keyword (1-indexed)
+    pub line_number: usize,
+    /// Column of the quantifier keyword (0-indexed, in characters)
+    pub column: usize,
+    /// Whether the body asks for automatic triggers with `#![auto]`
+    pub is_auto: bool,
+}
+
+/// A `type` alias item
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct TypeAlias {
+    /// Alias name
+    pub name: String,
+    /// Generic parameters as written, e.g. "<T>" (empty if none)
+    pub generics: String,
+    /// The aliased type
+    pub target: String,
+    /// Path of the enclosing module (`::`-separated, empty at top level)
+    pub module_path: String,
+    /// Line number of the alias (1-indexed)
+    pub line_number: usize,
+}
+
+/// A fenced code example in a function's doc comment
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DocExample {
+    /// Name of the function whose doc comment holds the example
+    pub function: String,
+    /// Line of the first line of code (1-indexed)
+    pub line_number: usize,
+    /// The code, with rustdoc's hidden-line `# ` prefixes removed
+    pub code: String,
+    /// Whether the code parses as Verus items or as statements
+    pub parsed: bool,
+    /// The parse error, if it does not parse
+    pub error: Option<String>,
+    /// Paths called in the example, in order of first call (constructors like
+    /// `Some` left out)
+    pub calls: Vec<String>,
+}
+
+/// How often an external path is called, and from which functions
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ExternalUsage {
+    /// Number of call sites
+    pub count: usize,
+    /// Names of the calling functions, in order of first call
+    pub callers: Vec<String>,
+}
+
+/// A proof function that only calls another lemma, as listed by `wrapper_lemmas`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct WrapperLemma {
+    /// Name of the wrapper
+    pub function: String,
+    /// File containing the wrapper
+    pub file_path: String,
+    /// Line of the wrapper's name
+    pub line_number: usize,
+    /// Path of the wrapped lemma with `use` aliases expanded, e.g.
+    /// "vstd::arithmetic::mul::lemma_mul_is_commutative"
+    pub callee: String,
+    /// Whether the wrapped lemma is defined outside the files (vstd or
+    /// another crate)
+    pub is_external: bool,
+    /// Whether the call passes the wrapper's parameters through unchanged and
+    /// in order
+    pub forwards_params: bool,
+    /// Whether the requires and ensures of the wrapped lemma, with the call's
+    /// arguments put in for its parameters, match the wrapper's (up to
+    /// parentheses and bound variable names); None when the wrapped lemma is
+    /// not defined in the files
+    pub contract_equivalent: Option<bool>,
+}
+
+/// A use of a literal in a spec clause
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LiteralUse {
+    /// Name of the function whose clause uses the literal
+    pub function: String,
+    /// File containing the function
+    pub file_path: String,
+    /// "requires" or "ensures"
+    pub clause_kind: String,
+    /// Line number of the literal (1-indexed)
+    pub line_number: usize,
+    /// The literal as written, suffix included (`0x80u8`)
+    pub text: String,
+}
+
+/// A spec function referenced by a failing clause, with its source text
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpecDefinition {
+    /// Specs of the spec function
+    pub specs: FunctionSpecs,
+    /// Source lines of its definition
+    pub source: String,
+}
+
+/// The specs relevant to a verification failure at a line of a function
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ErrorContext {
+    /// Specs of the failing function
+    pub function: FunctionSpecs,
+    /// Kind of the clause containing the line: "requires", "ensures" or "assert"
+    pub clause_kind: Option<String>,
+    /// Text of the clause containing the line
+    pub clause: Option<String>,
+    /// Spec functions the clause refers to
+    pub spec_functions: Vec<SpecDefinition>,
+    /// Proof functions whose ensures refer to any of those spec functions
+    pub related_lemmas: Vec<FunctionSpecs>,
+}
+
+/// Both contracts around a call site, as found by `call_context`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CallContext {
+    /// Specs of the function making the call
+    pub caller: FunctionSpecs,
+    /// Called path as written
+    pub callee_path: String,
+    /// Specs of the called function (empty when it is not defined in the files)
+    pub callee: FunctionSpecs,
+    /// Whether the callee was found in the files
+    pub resolved: bool,
+    /// Source text of each argument expression
+    pub args: Vec<String>,
+    /// Line of the call (1-indexed)
+    pub line_number: usize,
+}
+
+/// A spec function reached by unfolding a lemma's ensures
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UnfoldedSpec {
+    /// Name of the spec function
+    pub name: String,
+    /// Unfoldings needed to reach it: 1 if the ensures mention it directly
+    pub depth: usize,
+    /// How its definition is exposed: "open" (unfolded further), "closed",
+    /// "opaque" or "uninterp" (where unfolding stops)
+    pub opacity: String,
+    /// File defining it
+    pub file_path: String,
+    /// Line number of its definition (1-indexed)
+    pub line_number: usize,
+}
+
+/// The spec functions a lemma's ensures unfold to, transitively through open bodies
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct EnsuresUnfolding {
+    /// Specs of the lemma
+    pub lemma: FunctionSpecs,
+    /// Reached spec functions, by depth and then in order of first mention
+    pub closure: Vec<UnfoldedSpec>,
+    /// Largest depth in the closure (0 if the ensures use no spec function)
+    pub max_depth: usize,
+}
+
+/// Everything about one function definition, as assembled by `explain`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Explanation {
+    /// Specs of the function
+    pub function: FunctionSpecs,
+    /// Source lines of its definition, body included
+    pub source: String,
+    /// Its doc comment (empty if it has none)
+    pub doc: String,
+    /// Calls to it from other functions
+    pub callers: Vec<UsageExample>,
+    /// Functions of the files it calls, in order of first call
+    pub callees: Vec<String>,
+    /// Names of the other functions of its impl, trait or module (for free functions)
+    pub siblings: Vec<String>,
+    /// Spec functions its requires, ensures and decreases clauses refer to
+    pub spec_functions: Vec<SpecDefinition>,
+}
+
+/// A function as listed in a `ModuleNode` or `ImplNode`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct FunctionSummary {
+    /// Function name
+    pub name: String,
+    /// Function mode (see `FunctionSpecs::mode`)
+    pub mode: String,
+    /// Line number of the function (1-indexed)
+    pub line_number: Option<usize>,
+}
+
+/// An impl or trait block in the module tree
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ImplNode {
+    /// Label of the block, e.g. "impl Scalar", "impl View for Scalar" or "trait T"
+    pub name: String,
+    /// Functions of the block in source order
+    pub functions: Vec<FunctionSummary>,
+}
+
+/// A module of a file with its submodules, impls and free functions
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ModuleNode {
+    /// Module name ("crate" for the file root)
+    pub name: String,
+    /// `//!` docs of the module
+    pub doc: Option<String>,
+    /// Submodules in source order
+    pub modules: Vec<ModuleNode>,
+    /// Impl and trait blocks in source order
+    pub impls: Vec<ImplNode>,
+    /// Functions outside impls and traits in source order
+    pub functions: Vec<FunctionSummary>,
+}
+
+impl ModuleNode {
+    /// The submodule with the given name, added if not there yet
+    pub(crate) fn submodule(&mut self, name: &str) -> &mut ModuleNode {
+        let index = match self.modules.iter().position(|m| m.name == name) {
+            Some(index) => index,
+            None => {
+                self.modules.push(ModuleNode { name: name.to_string(), ..Default::default() });
+                self.modules.len() - 1
+            }
+        };
+        &mut self.modules[index]
+    }
+
+    /// The impl or trait block with the given label, added if not there yet
+    pub(crate) fn impl_block(&mut self, name: &str) -> &mut ImplNode {
+        let index = match self.impls.iter().position(|i| i.name == name) {
+            Some(index) => index,
+            None => {
+                self.impls.push(ImplNode { name: name.to_string(), functions: Vec::new() });
+                self.impls.len() - 1
+            }
+        };
+        &mut self.impls[index]
+    }
+}
+
+/// A `pub use` declaration of a file
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Reexport {
+    /// Module the declaration is in (`::`-separated, empty at top level)
+    pub module_path: String,
+    /// Path re-exported, as written, e.g. "crate::arith::lemma_x" or "inner::*"
+    pub path: String,
+    /// Name it is re-exported under when renamed (`pub use a::f as g;`)
+    pub alias: Option<String>,
+}
+
+/// What a file declares public: modules, re-exports and functions
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PublicSurface {
+    /// Paths of the modules declared `pub`, e.g. "arith" or "arith::mul"
+    pub modules: Vec<String>,
+    /// `pub use` declarations in source order
+    pub reexports: Vec<Reexport>,
+    /// Qualified names of the public functions, e.g. "arith::lemma_x" or
+    /// "arith::Scalar::add" (methods of trait impls and of public traits count
+    /// as public)
+    pub functions: Vec<String>,
+}
+
+/// A lemma paired with a spec function it is about, as listed by
+/// `lemma_spec_associations`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LemmaAssociation {
+    /// The spec function
+    pub spec_fn: String,
+    /// The lemma (a proof function)
+    pub lemma: String,
+    /// Line of the lemma
+    pub line_number: Option<usize>,
+    /// Why they are paired: "ensures_mentions" (the lemma's ensures refer to
+    /// the spec function) and/or "name_token" (the lemma name contains the
+    /// spec function name as whole `_`-separated tokens, as `lemma_pow2_adds`
+    /// does for `pow2`)
+    pub reasons: Vec<String>,
+}
+
+/// Spec functions and lemmas of a file paired both ways
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LemmaSpecAssociations {
+    /// Spec function -> its lemmas in source order; every spec function has
+    /// an entry
+    pub by_spec: BTreeMap<String, Vec<LemmaAssociation>>,
+    /// Lemma -> the spec functions it is about; every lemma has an entry
+    pub by_lemma: BTreeMap<String, Vec<LemmaAssociation>>,
+}
+
+/// A place where extraction was lossy instead of failing
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Diagnostic {
+    /// "verus_body_skipped" (a `verus!` body that does not parse; its
+    /// functions are missing), "cfg_attr_ignored" (a function's
+    /// `#[cfg_attr(...)]`, whose cfgs and attributes are not reported),
+    /// "self_unresolved" (`resolve_self` could not rewrite a method, which
+    /// keeps `Self`) or "spec_attribute_unparsed" (with `attribute_specs`, a
+    /// `#[requires(...)]`/`#[ensures(...)]` whose argument is not an
+    /// expression and is left out); for a file that could not be parsed at all, its
+    /// `error_kind` ("parse_error", "timeout", ...). `parse_verus_file_with_diagnostics`
+    /// also reports each syntax error it recovered from as "parse_error" (the
+    /// item containing it is missing), "parse_errors_truncated" with the
+    /// count of those beyond `MAX_PARSE_ERRORS`, and "unbalanced_delimiter"
+    /// for where a brace, bracket or parenthesis seems to go missing. With
+    /// `exclude_attributes`/`exclude_name_patterns`, "functions_excluded"
+    /// gives, per matching rule, the count and names of the functions left
+    /// out (at the line of the first). `parse_verus_crate` reports
+    /// "module_not_found" for a `mod name;` whose file does not exist
+    pub kind: String,
+    /// Line the diagnostic is about (1-indexed)
+    pub line_number: Option<usize>,
+    /// Column of a syntax error (0-indexed, in characters)
+    pub column: Option<usize>,
+    /// Source line of a syntax error, trimmed
+    pub context: Option<String>,
+    /// Name of the function concerned, if any
+    pub function: Option<String>,
+    /// What was lost, e.g. the parse error
+    pub detail: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(kind: &str, line_number: usize, function: Option<String>, detail: String) -> Self {
+        Diagnostic { kind: kind.to_string(), line_number: Some(line_number), function, detail, ..Default::default() }
+    }
+}
+
+/// A `verus!` macro invocation (or one of a configured macro wrapping it)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct VerusBlock {
+    /// Macro path as written, e.g. "verus" or "vstd::prelude::verus"
+    pub macro_path: String,
+    /// Delimiters of the invocation: "{}", "()" or "[]"
+    pub delimiter: String,
+    /// Line of the macro path (1-indexed)
+    pub line_number: usize,
+    /// Line of the closing delimiter (the last line if it is never closed)
+    pub end_line: usize,
+    /// Whether the block is in an impl body (holding impl items)
+    pub in_impl: bool,
+    /// Whether the body parses
+    pub parsed: bool,
+    /// The parse error, if it does not
+    pub error: Option<String>,
+    /// Line of the parse error, if it has one
+    pub error_line: Option<usize>,
+    /// For a body that does not parse, "unbalanced_delimiter" diagnostics
+    /// pointing into it
+    pub delimiter_issues: Vec<Diagnostic>,
+}
+
+/// Summary counts over a set of extracted functions
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpecStats {
+    /// Number of functions
+    pub functions: usize,
+    /// Number of proof functions
+    pub proof_functions: usize,
+    /// Number of spec functions (including spec(checked))
+    pub spec_functions: usize,
+    /// Number of exec functions
+    pub exec_functions: usize,
+    /// Total number of requires clauses
+    pub requires_clauses: usize,
+    /// Total number of ensures clauses
+    pub ensures_clauses: usize,
+    /// Total number of decreases clauses
+    pub decreases_clauses: usize,
+    /// Number of functions with a `requires` block
+    pub with_requires: usize,
+    /// Number of functions with an `ensures` block
+    pub with_ensures: usize,
+    /// Number of functions with a `decreases` clause
+    pub with_decreases: usize,
+    /// Number of memoized functions
+    pub memoized_functions: usize,
+    /// Number of inlined spec functions
+    pub inlined_spec_functions: usize,
+    /// Number of functions carrying each verifier attribute
+    pub attributes: BTreeMap<String, usize>,
+    /// Number of asserts proved by `compute` or `compute_only`
+    pub compute_proofs: usize,
+}
+
+/// Build and grammar information for gating optional fields on the Python side
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Capabilities {
+    /// Version of verus_parser
+    pub version: String,
+    /// verus_syn version locked when the module was built ("unknown" if the
+    /// lock file was unavailable)
+    pub verus_syn_version: String,
+    /// Version of the `save_specs` file format
+    pub specs_format_version: u32,
+    /// Whether this is a debug build
+    pub debug_build: bool,
+    /// Syntax feature name -> whether the parser accepts it, from `GRAMMAR_PROBES`
+    pub features: BTreeMap<String, bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_verus_source() {
+        let param = |name: &str, ty: &str, reference: &str| ParamInfo {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            reference: reference.to_string(),
+            ..Default::default()
+        };
+        let lemma = FunctionSpecs {
+            name: "lemma_scaled".to_string(),
+            mode: "proof".to_string(),
+            params: vec![param("x", "int", "by_value"), param("s", "Seq<int>", "&")],
+            returns: Some(ReturnInfo { binding: Some("r".to_string()), ty: "int".to_string(), elements: Vec::new() }),
+            requires: vec!["0 <= x".to_string(), "s.len() > 0 && x < s.len()".to_string()],
+            ensures: vec!["r == s[x] * 2".to_string()],
+            decreases: vec!["x".to_string()],
+            ..Default::default()
+        };
+        let source = lemma.to_verus_source(false).unwrap();
+        assert_eq!(
+            source,
+            "proof fn lemma_scaled(x: int, s: &Seq<int>) -> (r: int)\n    requires\n        0 <= x,\n        \
+             s.len() > 0 && x < s.len(),\n    ensures\n        r == s[x] * 2,\n    decreases\n        x,\n{\n}\n"
+        );
+
+        let parsed = parse_verus_file_core(&source).unwrap();
+        assert_eq!(parsed[0].mode, "proof");
+        assert_eq!(parsed[0].requires.len(), 2);
+        assert_eq!(parsed[0].params.iter().map(|p| p.reference.as_str()).collect::<Vec<_>>(), vec!["by_value", "&"]);
+        assert_eq!(parsed[0].returns.as_ref().unwrap().binding.as_deref(), Some("r"));
+
+        let exec = FunctionSpecs {
+            name: "len".to_string(),
+            params: vec![param("self", "Self", "&")],
+            returns: Some(ReturnInfo { binding: None, ty: "usize".to_string(), elements: Vec::new() }),
+            ..Default::default()
+        };
+        assert_eq!(exec.to_verus_source(true).unwrap(), "fn len(&self) -> usize\n{\n    unimplemented!()\n}\n");
+
+        let broken = FunctionSpecs { name: "lemma_bad".to_string(), ensures: vec!["x <".to_string()], ..lemma };
+        assert!(broken.to_verus_source(false).is_err());
+    }
+}
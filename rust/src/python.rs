@@ -0,0 +1,3387 @@
+//! Python bindings: conversions of the result types and the `verus_parser` module
+
+// Suppress false positive from PyO3 macro expansion
+#![allow(clippy::useless_conversion)]
+
+use crate::analysis::*;
+use crate::extract::*;
+use crate::model::*;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+impl IntoPy<PyObject> for FunctionSpecs {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        // Interned, so the dicts of a file share one Python string
+        dict.set_item("file_path", PyString::intern_bound(py, &self.file_path)).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("sig_end_line", self.sig_end_line).unwrap();
+        dict.set_item("sig_end_column", self.sig_end_column).unwrap();
+        dict.set_item("requires", &self.requires).unwrap();
+        dict.set_item("ensures", &self.ensures).unwrap();
+        dict.set_item("decreases", &self.decreases).unwrap();
+        dict.set_item("decreases_measure", self.decreases_measure.clone().into_py(py)).unwrap();
+        dict.set_item("recommends", &self.recommends).unwrap();
+        dict.set_item("requires_raw", &self.requires_raw).unwrap();
+        dict.set_item("ensures_raw", &self.ensures_raw).unwrap();
+        dict.set_item("requires_parent", &self.requires_parent).unwrap();
+        dict.set_item("ensures_parent", &self.ensures_parent).unwrap();
+        dict.set_item("requires_offsets", self.requires_offsets.clone().into_py(py)).unwrap();
+        dict.set_item("ensures_offsets", self.ensures_offsets.clone().into_py(py)).unwrap();
+        dict.set_item("requires_mentions", self.requires_mentions.clone().into_py(py)).unwrap();
+        dict.set_item("ensures_mentions", self.ensures_mentions.clone().into_py(py)).unwrap();
+        dict.set_item("is_self_contained", self.is_self_contained).unwrap();
+        dict.set_item("has_requires", self.has_requires).unwrap();
+        dict.set_item("has_ensures", self.has_ensures).unwrap();
+        dict.set_item("has_decreases", self.has_decreases).unwrap();
+        dict.set_item("spec_style", &self.spec_style).unwrap();
+        dict.set_item("signature", &self.signature).unwrap();
+        dict.set_item("signature_raw", &self.signature_raw).unwrap();
+        dict.set_item("header_source", &self.header_source).unwrap();
+        dict.set_item("params", self.params.clone().into_py(py)).unwrap();
+        dict.set_item("returns", self.returns.clone().into_py(py)).unwrap();
+        dict.set_item("is_proof", self.is_proof).unwrap();
+        dict.set_item("mode", &self.mode).unwrap();
+        dict.set_item("is_broadcast", self.is_broadcast).unwrap();
+        dict.set_item("is_memoized", self.is_memoized).unwrap();
+        dict.set_item("is_inlined_spec", self.is_inlined_spec).unwrap();
+        dict.set_item("attributes", &self.attributes).unwrap();
+        dict.set_item("verifier_attributes", &self.verifier_attributes).unwrap();
+        dict.set_item("suppressed", &self.suppressed).unwrap();
+        dict.set_item("todo_markers", &self.todo_markers).unwrap();
+        dict.set_item("cfgs", &self.cfgs).unwrap();
+        dict.set_item("effective_cfgs", &self.effective_cfgs).unwrap();
+        dict.set_item("module_path", &self.module_path).unwrap();
+        dict.set_item("impl_type", &self.impl_type).unwrap();
+        dict.set_item("trait_path", &self.trait_path).unwrap();
+        dict.set_item("qualified_name", self.qualified_name()).unwrap();
+        dict.set_item("path", &self.path).unwrap();
+        dict.set_item("in_test", self.in_test).unwrap();
+        dict.set_item("from_macro", &self.from_macro).unwrap();
+        dict.set_item("in_verus_block", self.in_verus_block).unwrap();
+        dict.set_item("provenance", &self.provenance).unwrap();
+        dict.set_item("module_doc", &self.module_doc).unwrap();
+        dict.set_item("is_externally_reachable", self.is_externally_reachable).unwrap();
+        dict.set_item("unresolved", self.unresolved.clone().into_py(py)).unwrap();
+        dict.set_item("proof_blocks", self.proof_blocks.clone().into_py(py)).unwrap();
+        dict.set_item("asserts", self.asserts.clone().into_py(py)).unwrap();
+        dict.set_item("casts", self.casts.clone().into_py(py)).unwrap();
+        dict.set_item("body_stmt_count", self.body_stmt_count).unwrap();
+        dict.set_item("is_trivial_proof", self.is_trivial_proof).unwrap();
+        dict.set_item("bounds", &self.bounds).unwrap();
+        dict.set_item("spec_fingerprint", &self.spec_fingerprint).unwrap();
+        dict.set_item("parse_error", &self.parse_error).unwrap();
+        dict.set_item("error_kind", &self.error_kind).unwrap();
+        dict.into()
+    }
+}
+
+/// Read `key` from a result dict, falling back to the default when it is missing or None
+fn dict_field<'py, T: FromPyObject<'py> + Default>(dict: &Bound<'py, PyDict>, key: &str) -> PyResult<T> {
+    match dict.get_item(key)? {
+        Some(value) if !value.is_none() => value.extract(),
+        _ => Ok(T::default()),
+    }
+}
+
+impl<'py> FromPyObject<'py> for FunctionSpecs {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(FunctionSpecs {
+            name: dict_field(dict, "name")?,
+            file_path: dict_field::<String>(dict, "file_path")?.into(),
+            line_number: dict_field(dict, "line_number")?,
+            end_line: dict_field(dict, "end_line")?,
+            sig_end_line: dict_field(dict, "sig_end_line")?,
+            sig_end_column: dict_field(dict, "sig_end_column")?,
+            requires: dict_field(dict, "requires")?,
+            ensures: dict_field(dict, "ensures")?,
+            decreases: dict_field(dict, "decreases")?,
+            decreases_measure: dict_field(dict, "decreases_measure")?,
+            recommends: dict_field(dict, "recommends")?,
+            requires_raw: dict_field(dict, "requires_raw")?,
+            ensures_raw: dict_field(dict, "ensures_raw")?,
+            requires_parent: dict_field(dict, "requires_parent")?,
+            ensures_parent: dict_field(dict, "ensures_parent")?,
+            requires_offsets: dict_field(dict, "requires_offsets")?,
+            ensures_offsets: dict_field(dict, "ensures_offsets")?,
+            requires_mentions: dict_field(dict, "requires_mentions")?,
+            ensures_mentions: dict_field(dict, "ensures_mentions")?,
+            is_self_contained: dict_field(dict, "is_self_contained")?,
+            has_requires: dict_field(dict, "has_requires")?,
+            has_ensures: dict_field(dict, "has_ensures")?,
+            has_decreases: dict_field(dict, "has_decreases")?,
+            spec_style: dict_field(dict, "spec_style")?,
+            signature: dict_field(dict, "signature")?,
+            signature_raw: dict_field(dict, "signature_raw")?,
+            header_source: dict_field(dict, "header_source")?,
+            params: dict_field(dict, "params")?,
+            returns: dict_field(dict, "returns")?,
+            is_proof: dict_field(dict, "is_proof")?,
+            mode: dict_field(dict, "mode")?,
+            is_broadcast: dict_field(dict, "is_broadcast")?,
+            is_memoized: dict_field(dict, "is_memoized")?,
+            is_inlined_spec: dict_field(dict, "is_inlined_spec")?,
+            attributes: dict_field(dict, "attributes")?,
+            verifier_attributes: dict_field(dict, "verifier_attributes")?,
+            suppressed: dict_field(dict, "suppressed")?,
+            todo_markers: dict_field(dict, "todo_markers")?,
+            cfgs: dict_field(dict, "cfgs")?,
+            effective_cfgs: dict_field(dict, "effective_cfgs")?,
+            module_path: dict_field(dict, "module_path")?,
+            impl_type: dict_field(dict, "impl_type")?,
+            trait_path: dict_field(dict, "trait_path")?,
+            path: dict_field(dict, "path")?,
+            in_test: dict_field(dict, "in_test")?,
+            from_macro: dict_field(dict, "from_macro")?,
+            in_verus_block: dict_field(dict, "in_verus_block")?,
+            provenance: dict_field(dict, "provenance")?,
+            module_doc: dict_field(dict, "module_doc")?,
+            is_externally_reachable: dict_field(dict, "is_externally_reachable")?,
+            unresolved: dict_field(dict, "unresolved")?,
+            proof_blocks: dict_field(dict, "proof_blocks")?,
+            asserts: dict_field(dict, "asserts")?,
+            casts: dict_field(dict, "casts")?,
+            body_stmt_count: dict_field(dict, "body_stmt_count")?,
+            is_trivial_proof: dict_field(dict, "is_trivial_proof")?,
+            bounds: dict_field(dict, "bounds")?,
+            spec_fingerprint: dict_field(dict, "spec_fingerprint")?,
+            parse_error: dict_field(dict, "parse_error")?,
+            error_kind: dict_field(dict, "error_kind")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ClauseOffsets {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("token_count", self.token_count).unwrap();
+        let ranges: Vec<(String, usize, usize)> = self.ranges.into_iter().map(|r| (r.kind, r.start, r.end)).collect();
+        dict.set_item("ranges", ranges).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for ClauseOffsets {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        let ranges: Vec<(String, usize, usize)> = dict_field(dict, "ranges")?;
+        Ok(ClauseOffsets {
+            token_count: dict_field(dict, "token_count")?,
+            ranges: ranges.into_iter().map(|(kind, start, end)| ClauseRange { kind, start, end }).collect(),
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ClauseMentions {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("params", &self.params).unwrap();
+        dict.set_item("externals", &self.externals).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for ClauseMentions {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(ClauseMentions {
+            params: dict_field(dict, "params")?,
+            externals: dict_field(dict, "externals")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for DecreasesMeasure {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("params", &self.params).unwrap();
+        dict.set_item("components", self.components).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for DecreasesMeasure {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(DecreasesMeasure {
+            kind: dict_field(dict, "kind")?,
+            params: dict_field(dict, "params")?,
+            components: dict_field(dict, "components")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for UnresolvedSpecs {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("self_type", &self.self_type).unwrap();
+        dict.set_item("signature", &self.signature).unwrap();
+        dict.set_item("requires", &self.requires).unwrap();
+        dict.set_item("ensures", &self.ensures).unwrap();
+        dict.set_item("decreases", &self.decreases).unwrap();
+        dict.set_item("recommends", &self.recommends).unwrap();
+        dict.set_item("params", self.params.into_py(py)).unwrap();
+        dict.set_item("returns", self.returns.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for UnresolvedSpecs {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(UnresolvedSpecs {
+            self_type: dict_field(dict, "self_type")?,
+            signature: dict_field(dict, "signature")?,
+            requires: dict_field(dict, "requires")?,
+            ensures: dict_field(dict, "ensures")?,
+            decreases: dict_field(dict, "decreases")?,
+            recommends: dict_field(dict, "recommends")?,
+            params: dict_field(dict, "params")?,
+            returns: dict_field(dict, "returns")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ProofBlock {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("calls", &self.calls).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for ProofBlock {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(ProofBlock {
+            line_number: dict_field(dict, "line_number")?,
+            end_line: dict_field(dict, "end_line")?,
+            calls: dict_field(dict, "calls")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ParamInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("ty", &self.ty).unwrap();
+        dict.set_item("reference", &self.reference).unwrap();
+        dict.set_item("lifetime", &self.lifetime).unwrap();
+        dict.set_item("is_slice", self.is_slice).unwrap();
+        dict.set_item("is_array", self.is_array).unwrap();
+        dict.set_item("array_len", self.array_len).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for ParamInfo {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(ParamInfo {
+            name: dict_field(dict, "name")?,
+            ty: dict_field(dict, "ty")?,
+            reference: dict_field(dict, "reference")?,
+            lifetime: dict_field(dict, "lifetime")?,
+            is_slice: dict_field(dict, "is_slice")?,
+            is_array: dict_field(dict, "is_array")?,
+            array_len: dict_field(dict, "array_len")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for ReturnInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("binding", &self.binding).unwrap();
+        dict.set_item("ty", &self.ty).unwrap();
+        let elements: Vec<PyObject> = self
+            .elements
+            .into_iter()
+            .map(|e| {
+                let element = PyDict::new_bound(py);
+                element.set_item("index", e.index).unwrap();
+                element.set_item("ty", &e.ty).unwrap();
+                element.into()
+            })
+            .collect();
+        dict.set_item("elements", elements).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for ReturnInfo {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        let elements: Vec<Bound<'py, PyDict>> = dict_field(dict, "elements")?;
+        Ok(ReturnInfo {
+            binding: dict_field(dict, "binding")?,
+            ty: dict_field(dict, "ty")?,
+            elements: elements
+                .iter()
+                .map(|e| {
+                    Ok(TupleElement {
+                        index: dict_field(e, "index")?,
+                        ty: dict_field(e, "ty")?,
+                    })
+                })
+                .collect::<PyResult<_>>()?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for AssertInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("prover", &self.prover).unwrap();
+        dict.set_item("labels", &self.labels).unwrap();
+        dict.set_item("comment", &self.comment).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for AssertInfo {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(AssertInfo {
+            line_number: dict_field(dict, "line_number")?,
+            end_line: dict_field(dict, "end_line")?,
+            expr: dict_field(dict, "expr")?,
+            prover: dict_field(dict, "prover")?,
+            labels: dict_field(dict, "labels")?,
+            comment: dict_field(dict, "comment")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for CastInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("from_ty", &self.from_ty).unwrap();
+        dict.set_item("to_ty", &self.to_ty).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.into()
+    }
+}
+
+impl<'py> FromPyObject<'py> for CastInfo {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let dict = ob.downcast::<PyDict>()?;
+        Ok(CastInfo {
+            line_number: dict_field(dict, "line_number")?,
+            expr: dict_field(dict, "expr")?,
+            from_ty: dict_field(dict, "from_ty")?,
+            to_ty: dict_field(dict, "to_ty")?,
+            kind: dict_field(dict, "kind")?,
+        })
+    }
+}
+
+impl IntoPy<PyObject> for FunctionCast {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("line_number", self.cast.line_number).unwrap();
+        dict.set_item("expr", &self.cast.expr).unwrap();
+        dict.set_item("from_ty", &self.cast.from_ty).unwrap();
+        dict.set_item("to_ty", &self.cast.to_ty).unwrap();
+        dict.set_item("kind", &self.cast.kind).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ComputeProof {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("prover", &self.prover).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for LabeledAssert {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("labels", &self.labels).unwrap();
+        dict.set_item("comment", &self.comment).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for LoopInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("has_decreases", self.has_decreases).unwrap();
+        dict.set_item("termination_waived", self.termination_waived).unwrap();
+        dict.set_item("attributes", &self.attributes).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for TerminationIssue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for UsageExample {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("caller", &self.caller).unwrap();
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("args", &self.args).unwrap();
+        dict.set_item("snippet", &self.snippet).unwrap();
+        dict.set_item("snippet_start", self.snippet_start).unwrap();
+        dict.set_item("snippet_end", self.snippet_end).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for AssociatedItem {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("bounds", &self.bounds).unwrap();
+        dict.set_item("ty", &self.ty).unwrap();
+        dict.set_item("default", &self.default).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for TraitInfo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("module_path", &self.module_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("generics", &self.generics).unwrap();
+        dict.set_item("supertraits", &self.supertraits).unwrap();
+        dict.set_item("associated_types", self.associated_types.into_py(py)).unwrap();
+        dict.set_item("associated_consts", self.associated_consts.into_py(py)).unwrap();
+        dict.set_item("methods", self.methods.into_py(py)).unwrap();
+        dict.set_item("provided_methods", &self.provided_methods).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for RefinementIssue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("trait_name", &self.trait_name).unwrap();
+        dict.set_item("impl_type", &self.impl_type).unwrap();
+        dict.set_item("method", &self.method).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for VisibilityIssue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.set_item("item", &self.item).unwrap();
+        dict.set_item("item_kind", &self.item_kind).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for SpecExprIssue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("column", self.column).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for SpecExprValidation {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("valid", self.valid).unwrap();
+        dict.set_item("error", &self.error).unwrap();
+        dict.set_item("error_column", self.error_column).unwrap();
+        dict.set_item("normalized", &self.normalized).unwrap();
+        dict.set_item("identifiers", &self.identifiers).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for InvariantUsage {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("atomic", self.atomic).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("binding", &self.binding).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for PatternMatch {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.set_item("expr", &self.expr).unwrap();
+        dict.set_item("bindings", &self.bindings).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for TokenEdit {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("op", &self.op).unwrap();
+        dict.set_item("text", &self.text).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ClauseChange {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("change", &self.change).unwrap();
+        dict.set_item("old", &self.old).unwrap();
+        dict.set_item("new", &self.new).unwrap();
+        dict.set_item("tokens", self.tokens.into_py(py)).unwrap();
+        dict.set_item("snippet", &self.snippet).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for SpecDiff {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("status", &self.status).unwrap();
+        dict.set_item("changes", self.changes.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for FileSpecDiff {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("path", &self.path).unwrap();
+        dict.set_item("status", &self.status).unwrap();
+        dict.set_item("added", &self.added).unwrap();
+        dict.set_item("removed", &self.removed).unwrap();
+        dict.set_item("renamed", &self.renamed).unwrap();
+        dict.set_item("modified", self.modified.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for DirectoryDiffSummary {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("files_added", self.files_added).unwrap();
+        dict.set_item("files_removed", self.files_removed).unwrap();
+        dict.set_item("files_modified", self.files_modified).unwrap();
+        dict.set_item("functions_added", self.functions_added).unwrap();
+        dict.set_item("functions_removed", self.functions_removed).unwrap();
+        dict.set_item("functions_renamed", self.functions_renamed).unwrap();
+        dict.set_item("functions_modified", self.functions_modified).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for DirectoryDiff {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("summary", self.summary.into_py(py)).unwrap();
+        dict.set_item("added_files", &self.added_files).unwrap();
+        dict.set_item("removed_files", &self.removed_files).unwrap();
+        dict.set_item("files", self.files.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ClauseFieldPaths {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.set_item("fields", &self.fields).unwrap();
+        dict.set_item("method_calls", &self.method_calls).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for PredicateCall {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("conjunct", &self.conjunct).unwrap();
+        dict.set_item("callee", &self.callee).unwrap();
+        dict.set_item("is_method", self.is_method).unwrap();
+        dict.set_item("args", &self.args).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ProofTodo {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("text", &self.text).unwrap();
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("mode", &self.mode).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for UntriggeredQuantifier {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("quantifier", &self.quantifier).unwrap();
+        dict.set_item("text", &self.text).unwrap();
+        dict.set_item("binders", &self.binders).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("column", self.column).unwrap();
+        dict.set_item("is_auto", self.is_auto).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for TypeAlias {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("generics", &self.generics).unwrap();
+        dict.set_item("target", &self.target).unwrap();
+        dict.set_item("module_path", &self.module_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for DocExample {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("code", &self.code).unwrap();
+        dict.set_item("parsed", self.parsed).unwrap();
+        dict.set_item("error", &self.error).unwrap();
+        dict.set_item("calls", &self.calls).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ExternalUsage {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("count", self.count).unwrap();
+        dict.set_item("callers", &self.callers).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for WrapperLemma {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("callee", &self.callee).unwrap();
+        dict.set_item("is_external", self.is_external).unwrap();
+        dict.set_item("forwards_params", self.forwards_params).unwrap();
+        dict.set_item("contract_equivalent", self.contract_equivalent).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for LiteralUse {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("text", &self.text).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for SpecDefinition {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("specs", self.specs.into_py(py)).unwrap();
+        dict.set_item("source", &self.source).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ErrorContext {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", self.function.into_py(py)).unwrap();
+        dict.set_item("clause_kind", &self.clause_kind).unwrap();
+        dict.set_item("clause", &self.clause).unwrap();
+        dict.set_item("spec_functions", self.spec_functions.into_py(py)).unwrap();
+        dict.set_item("related_lemmas", self.related_lemmas.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for CallContext {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("caller", self.caller.into_py(py)).unwrap();
+        dict.set_item("callee_path", &self.callee_path).unwrap();
+        dict.set_item("callee", self.callee.into_py(py)).unwrap();
+        dict.set_item("resolved", self.resolved).unwrap();
+        dict.set_item("args", &self.args).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for UnfoldedSpec {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("depth", self.depth).unwrap();
+        dict.set_item("opacity", &self.opacity).unwrap();
+        dict.set_item("file_path", &self.file_path).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for EnsuresUnfolding {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("lemma", self.lemma.into_py(py)).unwrap();
+        dict.set_item("closure", self.closure.into_py(py)).unwrap();
+        dict.set_item("max_depth", self.max_depth).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for Explanation {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("function", self.function.into_py(py)).unwrap();
+        dict.set_item("source", &self.source).unwrap();
+        dict.set_item("doc", &self.doc).unwrap();
+        dict.set_item("callers", self.callers.into_py(py)).unwrap();
+        dict.set_item("callees", &self.callees).unwrap();
+        dict.set_item("siblings", &self.siblings).unwrap();
+        dict.set_item("spec_functions", self.spec_functions.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for FunctionSummary {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("mode", &self.mode).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ImplNode {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("functions", self.functions.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for ModuleNode {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name).unwrap();
+        dict.set_item("doc", &self.doc).unwrap();
+        dict.set_item("modules", self.modules.into_py(py)).unwrap();
+        dict.set_item("impls", self.impls.into_py(py)).unwrap();
+        dict.set_item("functions", self.functions.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for Reexport {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("module_path", &self.module_path).unwrap();
+        dict.set_item("path", &self.path).unwrap();
+        dict.set_item("alias", &self.alias).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for PublicSurface {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("modules", &self.modules).unwrap();
+        dict.set_item("reexports", self.reexports.into_py(py)).unwrap();
+        dict.set_item("functions", &self.functions).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for LemmaAssociation {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("spec_fn", &self.spec_fn).unwrap();
+        dict.set_item("lemma", &self.lemma).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("reasons", &self.reasons).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for LemmaSpecAssociations {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("by_spec", self.by_spec.into_py(py)).unwrap();
+        dict.set_item("by_lemma", self.by_lemma.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for Diagnostic {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("kind", &self.kind).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("column", self.column).unwrap();
+        dict.set_item("context", &self.context).unwrap();
+        dict.set_item("function", &self.function).unwrap();
+        dict.set_item("detail", &self.detail).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for VerusBlock {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("macro_path", &self.macro_path).unwrap();
+        dict.set_item("delimiter", &self.delimiter).unwrap();
+        dict.set_item("line_number", self.line_number).unwrap();
+        dict.set_item("end_line", self.end_line).unwrap();
+        dict.set_item("in_impl", self.in_impl).unwrap();
+        dict.set_item("parsed", self.parsed).unwrap();
+        dict.set_item("error", &self.error).unwrap();
+        dict.set_item("error_line", self.error_line).unwrap();
+        dict.set_item("delimiter_issues", self.delimiter_issues.into_py(py)).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for SpecStats {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("functions", self.functions).unwrap();
+        dict.set_item("proof_functions", self.proof_functions).unwrap();
+        dict.set_item("spec_functions", self.spec_functions).unwrap();
+        dict.set_item("exec_functions", self.exec_functions).unwrap();
+        dict.set_item("requires_clauses", self.requires_clauses).unwrap();
+        dict.set_item("ensures_clauses", self.ensures_clauses).unwrap();
+        dict.set_item("decreases_clauses", self.decreases_clauses).unwrap();
+        dict.set_item("with_requires", self.with_requires).unwrap();
+        dict.set_item("with_ensures", self.with_ensures).unwrap();
+        dict.set_item("with_decreases", self.with_decreases).unwrap();
+        dict.set_item("memoized_functions", self.memoized_functions).unwrap();
+        dict.set_item("inlined_spec_functions", self.inlined_spec_functions).unwrap();
+        dict.set_item("attributes", &self.attributes).unwrap();
+        dict.set_item("compute_proofs", self.compute_proofs).unwrap();
+        dict.into()
+    }
+}
+
+impl IntoPy<PyObject> for Capabilities {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("version", &self.version).unwrap();
+        dict.set_item("verus_syn_version", &self.verus_syn_version).unwrap();
+        dict.set_item("specs_format_version", self.specs_format_version).unwrap();
+        dict.set_item("debug_build", self.debug_build).unwrap();
+        dict.set_item("features", &self.features).unwrap();
+        dict.into()
+    }
+}
+
+/// Render a FunctionSpecs (e.g. a dict built in Python) as Verus source
+///
+/// Uses `name`, `mode` ("proof", "spec", "spec(checked)" or "exec"; default
+/// exec), `params` (dicts with `name`, `ty` and optionally `reference` and
+/// `lifetime`), `returns` (`binding`, `ty`) and the `recommends`, `requires`,
+/// `ensures` and `decreases` clause lists, each clause comma-terminated on its
+/// own line. The result is checked by parsing it back.
+///
+/// # Arguments
+/// * `specs` - The function to render
+/// * `include_body` - Give it an `unimplemented!()` body instead of `{}`
+///
+/// # Returns
+/// The source of the function; raises ValueError if it does not round-trip
+/// (e.g. a clause is not a valid expression)
+#[pyfunction]
+#[pyo3(signature = (specs, include_body=false))]
+fn to_verus_source(specs: FunctionSpecs, include_body: bool) -> PyResult<String> {
+    specs.to_verus_source(include_body).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// `options` built from the keyword arguments of a parsing entry point,
+/// once `mode` is checked
+fn checked_options(options: ParseOptions) -> PyResult<ParseOptions> {
+    if let Some(mode) = options.mode.as_deref().filter(|m| !["proof", "exec", "spec"].contains(m)) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Unknown mode '{}' (expected proof, exec, spec or None)",
+            mode
+        )));
+    }
+    Ok(options)
+}
+
+#[pymethods]
+impl ParsedVerusFile {
+    /// Parse `content`; raises ValueError if it does not parse
+    #[new]
+    fn py_new(content: &str) -> PyResult<Self> {
+        Self::parse(content).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// The source the functions were extracted from
+    #[getter]
+    fn content(&self) -> String {
+        self.content.clone()
+    }
+
+    /// The extracted functions, as `parse_verus_file` returns them
+    #[getter]
+    fn functions(&self) -> Vec<FunctionSpecs> {
+        self.all_functions()
+    }
+}
+
+/// Update a parsed file after an edit, re-parsing only the affected items
+///
+/// Items (top-level items and the items of `verus!` blocks and inline
+/// modules) overlapping the edited lines are parsed again; the functions of
+/// the others are reused, with line numbers after the edit moved by the
+/// change in line count. Falls back to parsing the whole file when the items
+/// cannot be delimited (e.g. an unbalanced brace).
+///
+/// # Arguments
+/// * `prev` - The file before the edit
+/// * `new_content` - The source after the edit
+/// * `changed_range` - First and last edited line in `new_content` (1-indexed, inclusive)
+///
+/// # Returns
+/// A new ParsedVerusFile; raises ValueError if the new content does not parse
+#[pyfunction]
+fn reparse_incremental(
+    prev: PyRef<'_, ParsedVerusFile>,
+    new_content: &str,
+    changed_range: (usize, usize),
+) -> PyResult<ParsedVerusFile> {
+    prev.reparse(new_content, changed_range)
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Parse a Verus source file and extract all function specifications
+///
+/// Handles:
+/// - Top-level functions
+/// - Methods in `impl` blocks  
+/// - Trait methods
+/// - Functions inside `verus!` macros
+/// - Nested modules
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * Keyword-only options:
+///   * `item_macros` - Also look for functions in the bodies of other item
+///     macros (e.g. user `macro_rules!` invocations); these get `from_macro` set
+///   * `module_doc` - Set `module_doc` to the `//!` docs of each function's module
+///   * `max_bytes`, `max_items`, `max_depth`, `timeout_ms` - Optional guards for
+///     untrusted input (input size, number of items, bracket nesting depth and
+///     wall-clock time)
+///   * `header_source` - Set `header_source` to the exact source text of each
+///     function's header, from the signature through its last spec clause
+///   * `strict` - Parse even input the pre-check would reject as
+///     `not_rust_source` (binary, over 64 MiB, or without any `fn`,
+///     `verus!`, `impl` or `mod`)
+///   * `todo_markers` - Strings that make a comment a `todo_markers` entry
+///     (default `TODO`, `FIXME`, `XXX` and `admit`)
+///   * `verus_macro_names` - Project macros wrapping `verus!`, parsed like it
+///     (module docs included); only the parsing entry points and `erase_verus`
+///     take it, so the lints and queries on `content` see functions in
+///     `verus!` blocks alone
+///   * `exclude_attributes`, `exclude_name_patterns` - Globs leaving out the
+///     functions with (or inside an item with) a matching attribute path, e.g.
+///     `automatically_derived`, or with a matching name, e.g. `__*`
+///   * `file_path`, `line_offset` - For content cut from a larger document:
+///     the path to set as `file_path` and the number of lines before the
+///     content, added to every reported line (functions, proof blocks,
+///     asserts, casts, todo markers and diagnostics); a failed parse gets
+///     the path and its diagnostics the offset too
+///
+/// # Returns
+/// A list of FunctionSpecs for all functions found in the file, or a single
+/// entry with `parse_error` and `error_kind` set if parsing fails or a limit
+/// is exceeded
+#[pyfunction]
+#[pyo3(signature = (
+    content, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_file(
+    content: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+    })?;
+    match parse_guarded(content, None, &options) {
+        Ok(functions) => Ok(functions),
+        // Return a single entry with error info
+        Err(failure) => Ok(vec![failure.into_located_specs("", &options)]),
+    }
+}
+
+/// Parse a Verus source file like `parse_verus_file`, also reporting where
+/// extraction was lossy
+///
+/// Diagnostics cover `verus!` bodies skipped because they do not parse,
+/// `#[cfg_attr(...)]` attributes of functions (not interpreted, so missing
+/// from `cfgs` and `attributes`) and methods `resolve_self` could not rewrite.
+/// Syntax errors do not stop extraction: the item around each one is left
+/// out, reported as a "parse_error" diagnostic, and the rest of the file
+/// parsed; past 10 errors, only a "parse_errors_truncated" count is added.
+/// A file or `verus!` body that does not parse also gets
+/// "unbalanced_delimiter" diagnostics (see `verus_block_ranges`) pointing
+/// near a missing or extra brace, rather than only at the block start.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * Keyword-only options as for `parse_verus_file`
+///
+/// # Returns
+/// A tuple of the FunctionSpecs list (as from `parse_verus_file`, including
+/// the single error entry on a failure that could not be recovered from), a
+/// list of dicts with `kind`, `line_number`, `column`, `context`, `function`
+/// and `detail`, and the file attributes and
+/// global items (as from `file_attributes`, empty on failure)
+#[pyfunction]
+#[pyo3(signature = (
+    content, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_file_with_diagnostics(
+    content: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<(Vec<FunctionSpecs>, Vec<Diagnostic>, Vec<String>)> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+    })?;
+    match parse_recovering(content, &options) {
+        Ok((functions, diagnostics)) => {
+            Ok((functions, diagnostics, file_attributes_core(content).unwrap_or_default()))
+        }
+        Err(failure) => {
+            let diagnostics = failure.diagnostics(content, &options);
+            Ok((vec![failure.into_located_specs("", &options)], diagnostics, Vec::new()))
+        }
+    }
+}
+
+/// Parse several in-memory files at once
+///
+/// The files are parsed in parallel with the GIL released, so this is the
+/// entry point for contents that are already loaded (e.g. from a blob store).
+///
+/// # Arguments
+/// * `files` - Dict from file path (used as `file_path`) to source content
+/// * Keyword-only options as for `parse_verus_file` (except `file_path` and
+///   `line_offset`), applied to every file
+///
+/// # Returns
+/// A tuple of the FunctionSpecs of all files, ordered by (file_path,
+/// line_number), and a dict from path to the diagnostics of that file (see
+/// `parse_verus_file_with_diagnostics`) for the files that have any; a file
+/// that does not parse has a single diagnostic with its error kind and message
+#[pyfunction]
+#[pyo3(signature = (
+    files, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None, timeout_ms=None,
+    parallel_threshold=None, skip_tests=false, tests_module_heuristic=false, split_conjunctions=false,
+    clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false, attribute_specs=false,
+    header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new()
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_contents(
+    py: Python<'_>,
+    files: HashMap<String, String>,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+) -> PyResult<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        ..Default::default()
+    })?;
+    Ok(py.allow_threads(|| parse_verus_contents_core(&files, &options)))
+}
+
+/// Parse every `.rs` file under a directory
+///
+/// The files are found by `verus_files` and parsed in parallel with the GIL
+/// released. Excluded files do not appear in the results at all, not even as
+/// diagnostics.
+///
+/// # Arguments
+/// * `path` - Root directory
+/// * `exclude` - Glob patterns to skip, gitignore-style (see `verus_files`)
+/// * `default_excludes` - Also skip `target/`, hidden entries and `.gitignore` patterns
+/// * Keyword-only options as for `parse_verus_file` (except `file_path` and
+///   `line_offset`), applied to every file
+///
+/// # Returns
+/// A tuple of the FunctionSpecs of all files, ordered by (file_path,
+/// line_number), and a dict from path to the diagnostics of the files that
+/// have any (as for `parse_verus_contents`; a file that is not UTF-8 has a
+/// single "not_rust_source" diagnostic, "read_error" with `strict`); raises
+/// OSError if the directory cannot be walked
+#[pyfunction]
+#[pyo3(signature = (
+    path, exclude=Vec::new(), default_excludes=true, *, item_macros=false, module_doc=false, max_bytes=None,
+    max_items=None, max_depth=None, timeout_ms=None, parallel_threshold=None, skip_tests=false,
+    tests_module_heuristic=false, split_conjunctions=false, clause_offsets=false, mode=None,
+    verus_macro_names=Vec::new(), resolve_self=false, attribute_specs=false, header_source=false, strict=false,
+    todo_markers=None, exclude_attributes=Vec::new(), exclude_name_patterns=Vec::new()
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_directory(
+    py: Python<'_>,
+    path: PathBuf,
+    exclude: Vec<String>,
+    default_excludes: bool,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+) -> PyResult<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        ..Default::default()
+    })?;
+    Ok(py.allow_threads(|| parse_verus_directory_core(&path, &exclude, default_excludes, &options))?)
+}
+
+/// Parse every `.rs` file under a directory, reporting progress
+///
+/// As `parse_verus_directory`, but `callback(files_done, files_total,
+/// current_path)` is called (with the GIL taken back briefly) after every
+/// `every` parsed files and after the last one, while parsing continues on
+/// worker threads. An exception raised by the callback stops the walk: no
+/// further files are started and the exception propagates.
+///
+/// # Arguments
+/// * `path` - Root directory
+/// * `callback` - Callable taking (files_done, files_total, current_path)
+/// * `exclude`, `default_excludes` - As for `parse_verus_directory`
+/// * `every` - Number of files between callbacks (default 1)
+/// * Keyword-only options as for `parse_verus_file` (except `file_path` and
+///   `line_offset`), applied to every file
+///
+/// # Returns
+/// The same tuple as `parse_verus_directory`; raises OSError if the directory
+/// cannot be walked, and whatever the callback raised
+#[pyfunction]
+#[pyo3(signature = (
+    path, callback, exclude=Vec::new(), default_excludes=true, every=1, *, item_macros=false, module_doc=false,
+    max_bytes=None, max_items=None, max_depth=None, timeout_ms=None, parallel_threshold=None, skip_tests=false,
+    tests_module_heuristic=false, split_conjunctions=false, clause_offsets=false, mode=None,
+    verus_macro_names=Vec::new(), resolve_self=false, attribute_specs=false, header_source=false, strict=false,
+    todo_markers=None, exclude_attributes=Vec::new(), exclude_name_patterns=Vec::new()
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_directory_with_progress(
+    py: Python<'_>,
+    path: PathBuf,
+    callback: PyObject,
+    exclude: Vec<String>,
+    default_excludes: bool,
+    every: usize,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+) -> PyResult<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        ..Default::default()
+    })?;
+    let report = |done: usize, total: usize, current: &str| {
+        Python::with_gil(|py| callback.call1(py, (done, total, current)).map(drop))
+    };
+    py.allow_threads(|| {
+        parse_verus_directory_with_progress_core(&path, &exclude, default_excludes, &options, every, report)
+    })
+}
+
+/// Parse a crate by following its `mod` declarations from the root file
+///
+/// Starting at the crate root (`src/lib.rs` or `src/main.rs`), each `mod
+/// name;` is followed to `name.rs` or `name/mod.rs` next to (or, for a
+/// non-`mod.rs` file, below) the declaring file, or to its `#[path]`. Each
+/// function's `module_path` is relative to the crate root, and
+/// `is_externally_reachable` tells whether it can be named from outside the
+/// crate: it is public (or a method of a trait impl or `pub` trait) and its
+/// module is reachable through `pub mod`s from the root, or it, its impl's
+/// type or its module is re-exported by a `pub use` (glob or not) of a
+/// reachable module. Re-exports of other crates are not followed.
+///
+/// # Arguments
+/// * `path` - Crate root file
+/// * Keyword-only options as for `parse_verus_file` (except `file_path` and
+///   `line_offset`), applied to every file
+///
+/// # Returns
+/// The same tuple as `parse_verus_directory`, with "module_not_found"
+/// diagnostics for the `mod` declarations whose file is missing; raises
+/// OSError if a file cannot be read
+#[pyfunction]
+#[pyo3(signature = (
+    path, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None, timeout_ms=None,
+    parallel_threshold=None, skip_tests=false, tests_module_heuristic=false, split_conjunctions=false,
+    clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false, attribute_specs=false,
+    header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new()
+))]
+#[allow(clippy::too_many_arguments)]
+fn parse_verus_crate(
+    py: Python<'_>,
+    path: PathBuf,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+) -> PyResult<(Vec<FunctionSpecs>, FileDiagnostics)> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        ..Default::default()
+    })?;
+    Ok(py.allow_threads(|| parse_verus_crate_core(&path, &options))?)
+}
+
+/// List the `.rs` files under a directory, for the directory parse and the
+/// path-based entry points (`to_scip`, `lemma_fan_in`, ...)
+///
+/// Patterns follow `.gitignore` conventions: `vendor/` skips directories
+/// named `vendor` at any depth, `*.gen.rs` files with that suffix anywhere,
+/// and a pattern with an inner `/` (`src/snapshots/**`) is matched against
+/// the whole path relative to `path`. Excluded directories are not entered.
+///
+/// # Arguments
+/// * `path` - Root directory
+/// * `exclude` - Glob patterns to skip (`*`, `?` and `**` segments)
+/// * `default_excludes` - Also skip `target/` directories, hidden files and
+///   directories, and the patterns of the `.gitignore` files in the tree
+///   (each scoped to its directory; `!` negations are not supported)
+///
+/// # Returns
+/// The paths of the files, sorted; raises OSError if the directory cannot be walked
+#[pyfunction]
+#[pyo3(signature = (path, exclude=Vec::new(), default_excludes=true))]
+fn verus_files(path: PathBuf, exclude: Vec<String>, default_excludes: bool) -> PyResult<Vec<PathBuf>> {
+    Ok(verus_files_core(&path, &exclude, default_excludes)?)
+}
+
+/// Extract specifications for a specific function from Verus source
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - The name of the function to find
+/// * Keyword-only options as for `parse_verus_file`
+///
+/// # Returns
+/// FunctionSpecs for the function, or specs with parse_error if not found
+#[pyfunction]
+#[pyo3(signature = (
+    content, function_name, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn extract_function_specs(
+    content: &str,
+    function_name: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<FunctionSpecs> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+    })?;
+    match parse_guarded(content, Some(function_name), &options) {
+        Ok(functions) => {
+            if let Some(specs) = functions.into_iter().next() {
+                Ok(specs)
+            } else {
+                let message = format!("Function '{}' not found", function_name);
+                Ok(ParseFailure::new("not_found", message).into_located_specs(function_name, &options))
+            }
+        }
+        Err(failure) => Ok(failure.into_located_specs(function_name, &options)),
+    }
+}
+
+/// Extract all proof functions from Verus source
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * Keyword-only options as for `parse_verus_file`
+///
+/// # Returns
+/// A list of FunctionSpecs for all proof functions found
+#[pyfunction]
+#[pyo3(signature = (
+    content, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn extract_proof_functions(
+    content: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+    })?;
+    match parse_guarded(content, None, &options) {
+        Ok(functions) => {
+            // Filter to only proof functions
+            let proof_fns: Vec<_> = functions.into_iter().filter(|f| f.is_proof).collect();
+            Ok(proof_fns)
+        }
+        Err(failure) => Ok(vec![failure.into_located_specs("", &options)]),
+    }
+}
+
+/// Extract all exec functions (including those without a mode keyword) from Verus source
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * Keyword-only options as for `parse_verus_file`
+///
+/// # Returns
+/// A list of FunctionSpecs for all exec functions found
+#[pyfunction]
+#[pyo3(signature = (
+    content, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn extract_exec_functions(
+    content: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+        mode: Some("exec".to_string()),
+    })?;
+    match parse_guarded(content, None, &options) {
+        Ok(functions) => Ok(functions),
+        Err(failure) => Ok(vec![failure.into_located_specs("", &options)]),
+    }
+}
+
+/// Report functions defined more than once in the same scope of a file
+///
+/// Functions are compared by their breadcrumbs with macro invocations left
+/// out, so a function inside `verus!` clashes with one of the same name next
+/// to it. Pairs behind mutually exclusive cfgs (`cfg(c)` and `cfg(not(c))`)
+/// are not reported.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of groups (each a list of FunctionSpecs in source order); raises
+/// ValueError if the file does not parse
+#[pyfunction]
+fn duplicate_definitions(content: &str) -> PyResult<Vec<Vec<FunctionSpecs>>> {
+    duplicate_definitions_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Lint for specs written outside `verus!`
+///
+/// Outside the macro, `requires`/`ensures` are not Rust syntax and plain
+/// rustc rejects the file (or Verus never sees the specs), so such a
+/// function usually means a missing `verus! { ... }` wrapper.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of the offending FunctionSpecs in source order, without those
+/// suppressing lint `specs_outside_verus` (see `find_unused_lemmas`); raises
+/// ValueError if the file does not parse
+#[pyfunction]
+fn specs_outside_verus(content: &str) -> PyResult<Vec<FunctionSpecs>> {
+    let (outside, _) = specs_outside_verus_core(content).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(outside)
+}
+
+/// Extract the proof functions that need more than the solver alone
+///
+/// Lemmas with an empty body (`is_trivial_proof`) are left out; the rest are
+/// sorted by `body_stmt_count`, largest first.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of FunctionSpecs; raises ValueError if the file does not parse
+#[pyfunction]
+fn nontrivial_proofs(content: &str) -> PyResult<Vec<FunctionSpecs>> {
+    nontrivial_proofs_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Extract the `spec(checked)` functions from Verus source
+///
+/// Unlike plain spec functions, these have their `recommends` clauses checked
+/// by the verifier; the clauses are in the `recommends` field.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * Keyword-only options as for `parse_verus_file`
+///
+/// # Returns
+/// A list of FunctionSpecs for all spec(checked) functions found
+#[pyfunction]
+#[pyo3(signature = (
+    content, *, item_macros=false, module_doc=false, max_bytes=None, max_items=None, max_depth=None,
+    timeout_ms=None, parallel_threshold=None, skip_tests=false, tests_module_heuristic=false,
+    split_conjunctions=false, clause_offsets=false, mode=None, verus_macro_names=Vec::new(), resolve_self=false,
+    attribute_specs=false, header_source=false, strict=false, todo_markers=None, exclude_attributes=Vec::new(),
+    exclude_name_patterns=Vec::new(), file_path=None, line_offset=0
+))]
+#[allow(clippy::too_many_arguments)]
+fn extract_checked_specs(
+    content: &str,
+    item_macros: bool,
+    module_doc: bool,
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+    timeout_ms: Option<u64>,
+    parallel_threshold: Option<usize>,
+    skip_tests: bool,
+    tests_module_heuristic: bool,
+    split_conjunctions: bool,
+    clause_offsets: bool,
+    mode: Option<String>,
+    verus_macro_names: Vec<String>,
+    resolve_self: bool,
+    attribute_specs: bool,
+    header_source: bool,
+    strict: bool,
+    todo_markers: Option<Vec<String>>,
+    exclude_attributes: Vec<String>,
+    exclude_name_patterns: Vec<String>,
+    file_path: Option<String>,
+    line_offset: usize,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let options = checked_options(ParseOptions {
+        item_macros,
+        module_doc,
+        max_bytes,
+        max_items,
+        max_depth,
+        timeout_ms,
+        parallel_threshold,
+        skip_tests,
+        tests_module_heuristic,
+        split_conjunctions,
+        clause_offsets,
+        mode,
+        verus_macro_names,
+        resolve_self,
+        attribute_specs,
+        header_source,
+        strict,
+        todo_markers,
+        exclude_attributes,
+        exclude_name_patterns,
+        file_path,
+        line_offset,
+    })?;
+    match parse_guarded(content, None, &options) {
+        Ok(functions) => Ok(functions.into_iter().filter(|f| f.mode == "spec(checked)").collect()),
+        Err(failure) => Ok(vec![failure.into_located_specs("", &options)]),
+    }
+}
+
+/// Check if a file can be parsed as valid Verus code
+///
+/// # Arguments
+/// * `content` - The source code content to check
+///
+/// # Returns
+/// True if the file parses successfully, False otherwise
+#[pyfunction]
+fn is_valid_verus(content: &str) -> bool {
+    verus_syn::parse_file(content).is_ok()
+}
+
+/// Check many candidate spec clauses in one call
+///
+/// Each candidate must parse as a single Verus expression. The parsing runs
+/// with the GIL released, so generated batches pay the call overhead once.
+///
+/// # Arguments
+/// * `exprs` - The candidate clauses, e.g. ensures strings
+/// * `parallel` - Parse the candidates in parallel
+///
+/// # Returns
+/// One dict per candidate, in input order, with valid, error and
+/// error_column (for invalid ones, the column counting characters from the
+/// start of the candidate), normalized (canonical rendering of valid ones)
+/// and identifiers (free names as written, quantifier variables left out)
+#[pyfunction]
+#[pyo3(signature = (exprs, parallel=false))]
+fn validate_spec_exprs(py: Python<'_>, exprs: Vec<String>, parallel: bool) -> Vec<SpecExprValidation> {
+    py.allow_threads(|| validate_spec_exprs_core(&exprs, parallel))
+}
+
+/// Compute a digest of all function specs in a file
+///
+/// The digest combines each function's `spec_fingerprint` order-insensitively,
+/// so it is stable under whitespace/comment edits, body changes and moving
+/// functions around, but changes whenever any signature or clause changes.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A SHA-256 hex digest; raises ValueError if the file does not parse
+#[pyfunction]
+fn specs_digest(content: &str) -> PyResult<String> {
+    parse_verus_file_core(content)
+        .map(|functions| combine_fingerprints(&functions))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Strip Verus annotations to produce plain Rust
+///
+/// Removes requires/ensures/decreases (and other spec) clauses, unwraps
+/// `verus! { ... }` blocks, drops proof and spec functions, ghost code in
+/// exec bodies and `#[verifier::...]` attributes. The result is computed with
+/// span-based text edits, so untouched code keeps its original bytes.
+///
+/// # Arguments
+/// * `content` - The source code content to erase
+/// * `verus_macro_names` - Project macros wrapping `verus!`, unwrapped like it
+///
+/// # Returns
+/// The erased source; raises ValueError if the file does not parse
+#[pyfunction]
+#[pyo3(signature = (content, *, verus_macro_names=Vec::new()))]
+fn erase_verus(content: &str, verus_macro_names: Vec<String>) -> PyResult<String> {
+    erase_verus_core(content, &verus_macro_names).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Keep the lemmas whose preconditions are syntactically met by known facts
+///
+/// A requires clause is met when it equals (modulo parentheses and mirrored
+/// comparisons) one of the facts or a conjunct of one. Lemma parameters act as
+/// wildcards and must bind consistently across all of a lemma's requires, so
+/// `requires x > 0` is met by the fact `y > 0`. The check is intentionally
+/// incomplete: it never reasons beyond syntax.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `facts` - Known facts, each a Verus expression
+/// * `known_bounds` - Optional map from goal type to the traits it implements;
+///   generic lemmas whose bounds no known type satisfies are dropped
+/// * `expand_aliases` - Expand the file's non-generic `type` aliases
+///   (transitively) in the lemmas and the facts before matching, so that a
+///   lemma over `Word` (`type Word = u64;`) meets facts about `u64`
+/// * `fact_types` - Optional map from the names in the facts to their types
+///   (e.g. `{"n": "usize"}`); lemmas whose parameters bind to a name, suffixed
+///   literal or cast of another type are dropped
+/// * `coerce_int_types` - With `fact_types`, let machine integers match `int`
+///   parameters and unsigned ones `nat` parameters
+///
+/// # Returns
+/// FunctionSpecs of the proof functions whose requires are all met; raises
+/// ValueError if a fact does not parse
+#[pyfunction]
+#[pyo3(signature = (content, facts, known_bounds=None, expand_aliases=false, fact_types=None, coerce_int_types=false))]
+fn filter_by_requires(
+    content: &str,
+    facts: Vec<String>,
+    known_bounds: Option<HashMap<String, Vec<String>>>,
+    expand_aliases: bool,
+    fact_types: Option<HashMap<String, String>>,
+    coerce_int_types: bool,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let facts = parse_facts(&facts).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let fact_types = fact_types.map(|types| FactTypes { types, coerce_int_types });
+    match filter_by_requires_core(content, &facts, known_bounds.as_ref(), expand_aliases, fact_types.as_ref()) {
+        Ok(functions) => Ok(functions),
+        Err(e) => Ok(vec![ParseFailure::new("parse_error", e).into_specs("")]),
+    }
+}
+
+/// Forward chaining: the lemmas whose requires all follow from the hypotheses
+///
+/// Requires are matched as in `filter_by_requires`, with parameters bound
+/// consistently across the whole lemma. Lemmas without requires apply
+/// anywhere and are listed last; the others are ranked by how specific their
+/// conclusions are (ensures conjuncts whose parameters are all bound by the
+/// hypotheses), then by the number of requires used.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `hypotheses` - Known facts, each a Verus expression
+/// * `expand_aliases` - Expand type aliases first, as for `filter_by_requires`
+/// * `fact_types`, `coerce_int_types` - Check parameter types, as for
+///   `filter_by_requires`; lemmas needing coercions rank after those that do not
+///
+/// # Returns
+/// A list of (FunctionSpecs, bindings, coercions) tuples, bindings mapping
+/// each bound parameter to the hypothesis text it stands for and coercions
+/// listing (parameter, bound type, parameter type) for each integer coercion
+/// used (always empty without `fact_types`). Raises ValueError if a
+/// hypothesis or the file does not parse
+#[pyfunction]
+#[pyo3(signature = (content, hypotheses, expand_aliases=false, fact_types=None, coerce_int_types=false))]
+fn applicable_lemmas(
+    content: &str,
+    hypotheses: Vec<String>,
+    expand_aliases: bool,
+    fact_types: Option<HashMap<String, String>>,
+    coerce_int_types: bool,
+) -> PyResult<Vec<(FunctionSpecs, Bindings, Vec<Coercion>)>> {
+    let facts = parse_facts(&hypotheses).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let fact_types = fact_types.map(|types| FactTypes { types, coerce_int_types });
+    applicable_lemmas_core(content, &facts, expand_aliases, fact_types.as_ref())
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Search spec clauses by structure rather than text
+///
+/// The pattern is a Verus expression in which `_` matches any sub-expression
+/// and `?x` any sub-expression as long as every `?x` matches the same text;
+/// everything else must match literally, up to parentheses and the
+/// direction of comparisons (`_ <= _` also matches `b >= a`). Every
+/// sub-expression of every requires and ensures clause is tried, so a
+/// pattern can match inside a quantifier or an implication.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `pattern` - The pattern, e.g. `"?a * ?c <= ?b * ?c"`
+/// * `expand_aliases` - Expand type aliases in the clauses and the pattern
+///   first, as for `filter_by_requires` (clause texts then show the expansion)
+///
+/// # Returns
+/// A list of dicts (function, clause_kind, clause, expr, bindings,
+/// line_number), bindings mapping each named hole (without `?`) to the text it
+/// matched; raises ValueError if the pattern or the file does not parse
+#[pyfunction]
+#[pyo3(signature = (content, pattern, expand_aliases=false))]
+fn find_pattern(content: &str, pattern: &str, expand_aliases: bool) -> PyResult<Vec<PatternMatch>> {
+    find_pattern_core(content, pattern, expand_aliases).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Extract the loops in all function bodies
+///
+/// Each loop records whether it has a decreases clause and whether termination
+/// checking is waived (`#[verifier::exec_allows_no_decreases_clause]` on the
+/// loop or its function).
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of LoopInfo dicts; raises ValueError if the file does not parse
+#[pyfunction]
+fn extract_loops(content: &str) -> PyResult<Vec<LoopInfo>> {
+    extract_loops_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Report loops and recursive functions that lack both a decreases clause and
+/// a termination waiver
+///
+/// `for` loops over iterators need no decreases and are not reported. Bodies
+/// of `#[verifier::external_body]` functions are skipped.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of issues (function, kind, line_number, end_line), ordered by line;
+/// raises ValueError if the file does not parse
+#[pyfunction]
+fn termination_report(content: &str) -> PyResult<Vec<TerminationIssue>> {
+    termination_report_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Suggest names for a new lemma from its ensures clause
+///
+/// The name is built from the clause's outermost operator or function
+/// (`mul`, `pow`, `len`, ...), its relation (`le`, `lt`, ...; equations go
+/// unnamed when a property is found) and an algebraic property (`comm`,
+/// `assoc`, `distrib`, `mono`), e.g. `lemma_mul_le_mono` for
+/// `x * z <= y * z`. Names already in `existing_names` get a numeric suffix.
+///
+/// # Arguments
+/// * `ensures` - The ensures clause
+/// * `existing_names` - Names in use, to avoid collisions
+/// * `prefix` - Prefix of every name
+/// * `order` - Order of the name parts ("subject", "relation", "property");
+///   parts left out are not used
+/// * `limit` - Maximum number of candidates
+///
+/// # Returns
+/// Candidate names, best first; raises ValueError if the clause does not parse
+/// or `order` names an unknown part
+#[pyfunction]
+#[pyo3(signature = (ensures, existing_names, prefix="lemma_", order=None, limit=3))]
+fn suggest_lemma_name(
+    ensures: &str,
+    existing_names: Vec<String>,
+    prefix: &str,
+    order: Option<Vec<String>>,
+    limit: usize,
+) -> PyResult<Vec<String>> {
+    let order = order.unwrap_or_else(|| vec!["subject".to_string(), "relation".to_string(), "property".to_string()]);
+    suggest_lemma_name_core(ensures, &existing_names, prefix, &order, limit)
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Draft a standalone lemma from a failing assert
+///
+/// The lemma's ensures is the asserted expression, its parameters the
+/// assert's free variables (typed from the enclosing signature; other
+/// variables are assumed `int`, with a `// NOTE` comment) and its requires
+/// the enclosing function's requires that mention only those variables.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - Name of the function containing the assert
+/// * `assert_text` - The asserted expression, with or without `assert(...)`
+///
+/// # Returns
+/// Verus source of the draft, checked by parsing it back; raises ValueError
+/// if the file or assert does not parse or the function is not found
+#[pyfunction]
+fn draft_lemma_from_assert(content: &str, function_name: &str, assert_text: &str) -> PyResult<String> {
+    draft_lemma_from_assert_core(content, function_name, assert_text).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the asserts proved by evaluation
+///
+/// Asserts discharged `by (compute)` or `by (compute_only)` rely on the
+/// values of the constants involved rather than on lemmas.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (function, line_number, expr, prover) in source order;
+/// raises ValueError if the file does not parse
+#[pyfunction]
+fn compute_proofs(content: &str) -> PyResult<Vec<ComputeProof>> {
+    compute_proofs_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Summary counts (functions per mode, clauses per kind, verifier attributes) for a file
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict of counts; raises ValueError if the file does not parse
+#[pyfunction]
+fn spec_stats(content: &str) -> PyResult<SpecStats> {
+    parse_verus_file_core(content)
+        .map(|functions| compute_stats(&functions))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Read a `content_or_paths` argument: a source string (with an empty path) or
+/// a list of file paths
+fn read_content_or_paths(content_or_paths: &Bound<'_, PyAny>) -> PyResult<Vec<(String, String)>> {
+    if let Ok(content) = content_or_paths.extract::<String>() {
+        return Ok(vec![(String::new(), content)]);
+    }
+    let paths: Vec<String> = content_or_paths.extract()?;
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<_, _>>()?;
+    Ok(files)
+}
+
+/// Write a SCIP index of the functions in the given files
+///
+/// Each function gets a definition occurrence on its name (enclosing its
+/// lines) and a symbol whose signature documentation is the signature followed
+/// by its spec clauses. Symbols follow the SCIP grammar with scheme
+/// `verus-lemma-finder`, e.g. `verus-lemma-finder cargo my_crate .
+/// arith/Scalar#lemma_add().` (module namespaces, then the inherent impl's self
+/// type, the trait or `impl#[Type][Trait]` for a trait impl, then the
+/// function). References are not indexed.
+///
+/// # Arguments
+/// * `paths` - Paths of the files to index (stored as given)
+/// * `out_path` - Where to write the index (conventionally `index.scip`)
+/// * `package` - Package name used in the symbols
+///
+/// # Returns
+/// The number of symbols written; raises OSError if a file cannot be read or
+/// written and ValueError if one does not parse
+#[pyfunction]
+#[pyo3(signature = (paths, out_path, package="."))]
+fn to_scip(paths: Vec<String>, out_path: &str, package: &str) -> PyResult<usize> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (index, symbols) = scip_index_core(&files, package).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    std::fs::write(out_path, index)?;
+    Ok(symbols)
+}
+
+/// Render extraction results as a Markdown report
+///
+/// The report starts with a summary table of the stats rollup, followed by one
+/// section per module with a table of its functions (name, mode, lines, clause
+/// counts) and a collapsible Verus code block per function.
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+/// * `include_bodies` - Show each function's full source instead of its clauses
+/// * `proof_only` - Only include proof functions
+///
+/// # Returns
+/// The Markdown document; files that fail to parse are listed under "Errors"
+#[pyfunction]
+#[pyo3(signature = (content_or_paths, *, include_bodies=false, proof_only=false))]
+fn render_markdown(content_or_paths: &Bound<'_, PyAny>, include_bodies: bool, proof_only: bool) -> PyResult<String> {
+    let files = read_content_or_paths(content_or_paths)?;
+    let options = MarkdownOptions {
+        include_bodies,
+        proof_only,
+    };
+    Ok(render_markdown_core(&files, &options))
+}
+
+/// Group lemmas by subject for a browsable catalog
+///
+/// Each proof function is classified by the most frequent outermost symbol
+/// among the operands of its ensures relations: an arithmetic operator category
+/// (`*` is "multiplication", `%` is "modular arithmetic", ...) or a called
+/// function such as "Seq::push". Ties prefer functions over operators, then the
+/// alphabetically first subject. Lemmas without such a symbol (including those
+/// without ensures) are grouped under "unclassified".
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict mapping subject to a list of FunctionSpecs; raises ValueError if the
+/// file does not parse
+#[pyfunction]
+fn group_lemmas(content: &str) -> PyResult<BTreeMap<String, Vec<FunctionSpecs>>> {
+    group_lemmas_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the functions defined next to a given one, for context
+///
+/// The scope is the function's innermost enclosing impl block, trait, module
+/// or (for nested items) function, or the file for a top-level free
+/// function. Scopes are told apart by position, so methods of a second
+/// `impl Scalar` block are not siblings of those in the first; `verus!`
+/// blocks do not form scopes.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - The function whose siblings to list (its first
+///   definition, if there are several)
+///
+/// # Returns
+/// The FunctionSpecs of the other functions in the scope, in source order;
+/// raises ValueError if the file does not parse or the function does not exist
+#[pyfunction]
+fn siblings(content: &str, function_name: &str) -> PyResult<Vec<FunctionSpecs>> {
+    siblings_core(content, function_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Pair lemmas with the spec functions they are about
+///
+/// Following the vstd naming convention (`spec fn pow2` and the
+/// `lemma_pow2_*` lemmas), a proof function is associated with a spec
+/// function when its ensures refer to it ("ensures_mentions", calls and method
+/// calls alike) or when its name contains the spec function name as whole
+/// `_`-separated tokens ("name_token"). Functions are matched by name.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict with "by_spec" (spec function -> list of associations) and
+/// "by_lemma" (lemma -> list of associations), each association a dict
+/// (spec_fn, lemma, line_number, reasons); raises ValueError if the file does
+/// not parse
+#[pyfunction]
+fn lemma_spec_associations(content: &str) -> PyResult<LemmaSpecAssociations> {
+    lemma_spec_associations_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the file-level attributes and Verus global settings of a file
+///
+/// These are the crate/module inner attributes (`#![allow(...)]`,
+/// `#![cfg_attr(verus_keep_ghost, ...)]`, ...) and, for each top-level
+/// `verus!` block, the inner attributes and `global` items (`global size_of
+/// usize == 8;`) written where items go.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// The rendered attributes and global items in source order, file attributes
+/// first (e.g. `"#![allow(unused_imports)]"`); raises ValueError if the file
+/// does not parse
+#[pyfunction]
+fn file_attributes(content: &str) -> PyResult<Vec<String>> {
+    file_attributes_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the `verus!` blocks of a file with how they are invoked and whether
+/// their bodies parse
+///
+/// Blocks are found on the text, so this also works on files that do not
+/// parse, including blocks inside modules and impls. For a body that does
+/// not parse, `delimiter_issues` points at where a delimiter seems to be
+/// missing or extra: the first closing delimiter of the wrong kind or with
+/// nothing to close, the deepest one left open, and the first opening brace
+/// closed by a less indented line (a missing `}` balanced out further on).
+///
+/// # Arguments
+/// * `content` - The source code content to scan
+/// * `verus_macro_names` - Project macros wrapping `verus!`, as for `parse_verus_file`
+///
+/// # Returns
+/// A list of dicts (macro_path, delimiter, line_number, end_line, in_impl,
+/// parsed, error, error_line, delimiter_issues as diagnostic dicts) in source order
+#[pyfunction]
+#[pyo3(signature = (content, verus_macro_names=None))]
+fn verus_block_ranges(content: &str, verus_macro_names: Option<Vec<String>>) -> Vec<VerusBlock> {
+    verus_block_ranges_core(content, &verus_macro_names.unwrap_or_default())
+}
+
+/// Report which external (e.g. vstd) functions a project calls
+///
+/// Collects every path call in the function bodies of the given files, expands
+/// `use` aliases back to full paths, drops calls to functions defined in the
+/// files themselves, and groups the rest by leading crate segment.
+///
+/// # Arguments
+/// * `paths` - Source files making up the project
+///
+/// # Returns
+/// A dict `{crate: {full_path: {"count": n, "callers": [fn names]}}}`; raises
+/// OSError if a file cannot be read and ValueError if one does not parse
+#[pyfunction]
+fn external_lemma_usage(paths: Vec<String>) -> PyResult<BTreeMap<String, BTreeMap<String, ExternalUsage>>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    external_lemma_usage_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find thin wrapper lemmas that could be replaced by the lemma they call
+///
+/// A proof function is a wrapper when its body is exactly one call statement
+/// (possibly inside a single `proof { ... }` block) and its ensures use no
+/// names other than its parameters, its return binding, bound variables and
+/// the segments of the called path. Callees are resolved through `use`
+/// aliases as in `external_lemma_usage`.
+///
+/// # Arguments
+/// * `paths` - Source files making up the project
+///
+/// # Returns
+/// A list of dicts (function, file_path, line_number, callee, is_external,
+/// forwards_params, contract_equivalent) in file and source order,
+/// contract_equivalent being None for a callee not defined in the files;
+/// raises OSError if a file cannot be read and ValueError if one does not parse
+#[pyfunction]
+fn wrapper_lemmas(paths: Vec<String>) -> PyResult<Vec<WrapperLemma>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    wrapper_lemmas_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find functions whose names resemble a half-remembered query
+///
+/// Query and names are compared word by word (snake_case parts), so
+/// "mod mult basic" finds `lemma_mod_multiples_basic`. Words match exactly, as
+/// prefixes, as subsequences or with small typos. Queries containing `::` are
+/// matched against the module-qualified name. Exact matches score 1.0 and
+/// always rank first.
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+/// * `query` - Name, qualified name or space/underscore-separated words
+/// * `limit` - Maximum number of results
+///
+/// # Returns
+/// A list of `(FunctionSpecs, score)` tuples, best first; raises OSError if a
+/// file cannot be read and ValueError if one does not parse
+#[pyfunction]
+#[pyo3(signature = (content_or_paths, query, limit=10))]
+fn fuzzy_find(content_or_paths: &Bound<'_, PyAny>, query: &str, limit: usize) -> PyResult<Vec<(FunctionSpecs, f64)>> {
+    let files = read_content_or_paths(content_or_paths)?;
+    fuzzy_find_core(&files, query, limit).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Compare the specs of two versions of a file
+///
+/// Functions are matched by name and enclosing module, impl or trait. Per
+/// clause kind, clauses present in both versions are left out; of the rest,
+/// old/new pairs whose tokens overlap enough (Dice coefficient of at least
+/// 0.5) are reported as one modified clause, most similar pairs first, with
+/// a token-level diff; the others are reported as removed or added.
+///
+/// # Arguments
+/// * `old_content` - The file before the change
+/// * `new_content` - The file after the change
+///
+/// # Returns
+/// A list of dicts (function, status: "added", "removed" or "modified",
+/// changes), new functions in source order followed by removed ones; each
+/// change has clause_kind, change, old, new, tokens (a list of {op, text}
+/// runs, op "equal", "removed" or "added") and snippet (the clause with
+/// `[-removed-]` and `{+added+}` runs); raises ValueError if either version
+/// does not parse
+#[pyfunction]
+fn spec_diff(old_content: &str, new_content: &str) -> PyResult<Vec<SpecDiff>> {
+    spec_diff_core(old_content, new_content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Compare the specs of two directory trees, e.g. before and after a refactor
+///
+/// The `.rs` files under each root (as listed by `verus_files` with the
+/// default excludes) are matched by relative path, and their functions as in
+/// `spec_diff`. A function on one side only whose signature and clauses
+/// match one on the other side only, apart from its name (or enclosing impl
+/// or trait), is reported as renamed rather than added and removed.
+///
+/// # Arguments
+/// * `old_root` - Root of the tree before the change
+/// * `new_root` - Root of the tree after the change
+///
+/// # Returns
+/// A dict with summary (counts: files_added, files_removed, files_modified,
+/// functions_added, functions_removed, functions_renamed,
+/// functions_modified), added_files and removed_files (relative paths) and
+/// files: per file with any change, a dict (path, status: "added",
+/// "removed" or "modified", added and removed qualified names, renamed
+/// (old, new) pairs, modified: `spec_diff` entries); raises OSError if a
+/// tree cannot be read and ValueError if a file does not parse
+#[pyfunction]
+fn diff_directories(old_root: PathBuf, new_root: PathBuf) -> PyResult<DirectoryDiff> {
+    let (old, new) = (read_verus_tree(&old_root)?, read_verus_tree(&new_root)?);
+    diff_directories_core(&old, &new).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Flag trait impl methods whose contracts look weaker than the trait's
+///
+/// Compares clause text after renaming the impl's parameters and return value
+/// to the trait's: an impl method with an ensures block that lacks one of the
+/// trait's ensures clauses, or with a requires clause the trait does not have,
+/// is reported. This is a syntactic check; logically equivalent clauses written
+/// differently are reported too. Impl methods without an ensures block inherit
+/// the trait's and are not checked for missing ensures.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (trait_name, impl_type, method, line_number, kind, clause),
+/// without the issues suppressed on the impl method (lint name `refinement`,
+/// see `find_unused_lemmas`); raises ValueError if the file does not parse
+#[pyfunction]
+fn refinement_lint(content: &str) -> PyResult<Vec<RefinementIssue>> {
+    let (issues, _) = refinement_lint_core(content).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(issues)
+}
+
+/// Collect the `//!` docs of the file and its modules
+///
+/// Modules inside `verus!` blocks are included.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict mapping module path (`::`-separated, `""` for the file root) to its
+/// doc text, for the modules that have docs; raises ValueError if the file does
+/// not parse
+#[pyfunction]
+fn module_docs(content: &str) -> PyResult<BTreeMap<String, String>> {
+    let file = verus_syn::parse_file(content)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Parse error: {}", e)))?;
+    Ok(collect_module_docs(&file, &[]))
+}
+
+/// List what a file makes public: `pub mod`s, `pub use` re-exports and public functions
+///
+/// Includes declarations inside `verus!` blocks and nested modules. Whether a
+/// function is reachable from outside the crate depends on the enclosing
+/// modules and on re-exports elsewhere; see `parse_verus_crate`.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict with `modules` (paths of the modules declared `pub`), `reexports`
+/// (dicts with `module_path`, `path` as written, ending in `*` for a glob, and
+/// `alias` when renamed) and `functions` (qualified names of the `pub`
+/// functions and methods, plus the methods of trait impls and `pub` traits);
+/// raises ValueError if the file does not parse
+#[pyfunction]
+fn public_surface(content: &str) -> PyResult<PublicSurface> {
+    public_surface_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Build the module tree of a file, e.g. for a crate → module → impl → function outline
+///
+/// Functions inside `verus!` blocks are placed where the block is; functions
+/// nested in other functions go with their outermost enclosing function.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A dict for the file root with keys `name` ("crate"), `doc`, `modules`
+/// (dicts of the same shape), `impls` (dicts with `name` and `functions`) and
+/// `functions` (dicts with `name`, `mode` and `line_number`), each list in
+/// source order; raises ValueError if the file does not parse
+#[pyfunction]
+fn module_tree(content: &str) -> PyResult<ModuleNode> {
+    module_tree_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Extract trait declarations with their full surface
+///
+/// Includes traits declared inside `verus!` blocks and nested modules.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (name, module_path, line_number, end_line, generics,
+/// supertraits, associated_types, associated_consts, methods as FunctionSpecs,
+/// provided_methods naming the methods with default bodies); raises ValueError
+/// if the file does not parse
+#[pyfunction]
+fn extract_traits(content: &str) -> PyResult<Vec<TraitInfo>> {
+    extract_traits_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Extract the `type` aliases of a file
+///
+/// Includes aliases declared inside `verus!` blocks and nested modules.
+/// Matching entry points can expand the non-generic ones (`expand_aliases`).
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (name, generics, target, module_path, line_number) in
+/// source order; raises ValueError if the file does not parse
+#[pyfunction]
+fn extract_type_aliases(content: &str) -> PyResult<Vec<TypeAlias>> {
+    extract_type_aliases_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Show how a lemma is used: each call site with surrounding source
+///
+/// Calls are found in all function bodies, including `proof { }` blocks and
+/// `assert ... by { }` bodies; `use` aliases are expanded before matching.
+/// Method calls on receivers other than `self` are skipped, since their type
+/// is unknown.
+///
+/// # Arguments
+/// * `paths` - Source files to search
+/// * `lemma_name` - Name of the lemma (or a `::`-separated path suffix)
+/// * `context_lines` - Number of lines to include before and after each call
+///
+/// # Returns
+/// A list of dicts (caller, file_path, line_number, args, snippet,
+/// snippet_start, snippet_end) in file and source order; raises OSError if a
+/// file cannot be read and ValueError if one does not parse
+#[pyfunction]
+#[pyo3(signature = (paths, lemma_name, context_lines=3))]
+fn usage_examples(paths: Vec<String>, lemma_name: &str, context_lines: usize) -> PyResult<Vec<UsageExample>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    usage_examples_core(&files, lemma_name, context_lines).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Report the literals (magic numbers, strings) used in spec clauses across a project
+///
+/// Literals are grouped by their text with any type suffix (`u32`, `int`)
+/// and digit separators removed, keeping the radix: `0x80` and `128` are
+/// different keys unless `normalize_radix` is set, which groups integers by
+/// value under their decimal form.
+///
+/// # Arguments
+/// * `paths` - Paths of the project files
+/// * `normalize_radix` - Group integer literals by value
+///
+/// # Returns
+/// A dict from key to a list of uses (function, file_path, clause_kind,
+/// line_number, text as written); raises OSError if a file cannot be read and
+/// ValueError if one does not parse
+#[pyfunction]
+#[pyo3(signature = (paths, normalize_radix=false))]
+fn spec_literals(paths: Vec<String>, normalize_radix: bool) -> PyResult<BTreeMap<String, Vec<LiteralUse>>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    spec_literals_core(&files, normalize_radix).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Rank a project's lemmas by fan-in: the number of distinct functions calling them
+///
+/// Broadcast lemmas (`specs["is_broadcast"]`) are mostly used implicitly
+/// through `broadcast use`, which is not counted, so their fan-in understates
+/// their use. Method calls count only on `self`, where the impl type is known.
+///
+/// # Arguments
+/// * `paths` - Paths of the project files
+///
+/// # Returns
+/// A list of (FunctionSpecs, fan_in) tuples for every proof function, highest
+/// fan-in first and ties by name; raises OSError if a file cannot be read and
+/// ValueError if one does not parse
+#[pyfunction]
+fn lemma_fan_in(paths: Vec<String>) -> PyResult<Vec<(FunctionSpecs, usize)>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    lemma_fan_in_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the lemmas no function calls
+///
+/// A proof function is unused when no function of the given files calls it
+/// (calls matched as for `lemma_fan_in`). Broadcast lemmas are left out,
+/// since they are used through `broadcast use`.
+///
+/// Like every built-in lint, findings can be suppressed at the source: with
+/// `// verus-lemma-finder: allow(unused_lemma)` on a line just above the
+/// function (or its attributes and doc comment), or with
+/// `#[cfg_attr(any(), allow(unused_lemma))]`, which rustc never applies.
+/// The lint names are `unused_lemma`, `trivial_lemma`,
+/// `specs_outside_verus`, `spec_visibility`, `spec_expr` and `refinement`;
+/// several can be listed in one `allow(...)`. Extracted FunctionSpecs carry
+/// them in `suppressed`, and `suppressed_findings` counts what each lint left
+/// out.
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+///
+/// # Returns
+/// A list of the FunctionSpecs of the unused lemmas, in file and source order,
+/// without the suppressed ones; raises OSError if a file cannot be read and
+/// ValueError if one does not parse
+#[pyfunction]
+fn find_unused_lemmas(content_or_paths: &Bound<'_, PyAny>) -> PyResult<Vec<FunctionSpecs>> {
+    let files = read_content_or_paths(content_or_paths)?;
+    let (unused, _) = find_unused_lemmas_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(unused)
+}
+
+/// Count the findings the built-in lints leave out because of a suppression
+///
+/// Runs `find_unused_lemmas` over all the files and the per-file lints over
+/// each of them (see `find_unused_lemmas` for the suppression syntax).
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+///
+/// # Returns
+/// A dict mapping every lint name to the number of its findings suppressed;
+/// raises OSError if a file cannot be read and ValueError if one does not parse
+#[pyfunction]
+fn suppressed_findings(content_or_paths: &Bound<'_, PyAny>) -> PyResult<BTreeMap<String, usize>> {
+    let files = read_content_or_paths(content_or_paths)?;
+    suppressed_findings_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Render the call graph or spec-dependency graph of a project as
+/// Graphviz DOT text
+///
+/// Nodes are filled by mode (proof light blue, spec light yellow, exec
+/// white), broadcast lemmas are dashed and external paths (e.g. vstd lemmas)
+/// are gray boxes; edges are labeled with the number of call sites. Calls
+/// resolve by name as for `lemma_fan_in`.
+///
+/// # Arguments
+/// * `graph_kind` - "call" (calls in function bodies) or "spec" (calls in
+///   requires, ensures and decreases clauses and in spec function bodies)
+/// * `paths` - Paths of the project files
+/// * `cluster_by_module` - Group the functions of each module in a cluster
+/// * `focus` - Only keep the functions within `depth` edges of this one,
+///   following calls in either direction
+/// * `depth` - Number of hops kept around `focus` (default 2)
+///
+/// # Returns
+/// The DOT text; raises OSError if a file cannot be read and ValueError if
+/// one does not parse, the graph kind is unknown or `focus` is not found
+#[pyfunction]
+#[pyo3(signature = (graph_kind, paths, cluster_by_module=false, focus=None, depth=2))]
+fn to_dot(
+    graph_kind: &str,
+    paths: Vec<String>,
+    cluster_by_module: bool,
+    focus: Option<&str>,
+    depth: usize,
+) -> PyResult<String> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    to_dot_core(graph_kind, &files, cluster_by_module, focus, depth).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the quantifiers in specs that have no explicit trigger, whose
+/// automatically chosen triggers are prime suspects for flaky proofs
+///
+/// The requires, recommends and ensures clauses of every function and the
+/// bodies of spec functions are searched. A quantifier counts as triggered
+/// when its body starts with `#![trigger ...]` or marks a term `#[trigger]`
+/// (outside any nested quantifier, which is checked separately); `#![auto]`
+/// quantifiers are listed with `is_auto` set.
+///
+/// # Arguments
+/// * `paths` - Paths of the files to scan
+///
+/// # Returns
+/// A list of dicts with file_path, function, clause_kind, quantifier, text,
+/// binders [(name, type or None)], line_number, column and is_auto, in file
+/// and source order; raises OSError if a file cannot be read and ValueError
+/// if one does not parse
+#[pyfunction]
+fn untriggered_quantifiers(paths: Vec<String>) -> PyResult<Vec<UntriggeredQuantifier>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    untriggered_quantifiers_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the comments marking proof debt, such as `// TODO(proof)` or
+/// `// FIXME: strengthen ensures`
+///
+/// A comment line is listed when it contains one of the markers, and is
+/// attributed to the innermost function whose lines contain it (comments
+/// between functions have none). The parse entry points attach the same
+/// comments to each function as `todo_markers`.
+///
+/// # Arguments
+/// * `paths` - Paths of the files to scan
+/// * `markers` - Strings to look for (default `TODO`, `FIXME`, `XXX` and `admit`)
+///
+/// # Returns
+/// A list of dicts with file_path, line_number, text, function and mode, in
+/// file and line order; raises OSError if a file cannot be read and
+/// ValueError if one does not parse
+#[pyfunction]
+#[pyo3(signature = (paths, markers=None))]
+fn proof_todos(paths: Vec<String>, markers: Option<Vec<String>>) -> PyResult<Vec<ProofTodo>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    proof_todos_core(&files, markers.as_deref()).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the asserts carrying a label, to map a failure log back to source
+///
+/// An assert's labels are its string literal arguments (the message of
+/// `assert!(cond, "...")`) and the comment after it on its last line; the
+/// label must equal one of them, apart from surrounding whitespace.
+///
+/// # Arguments
+/// * `paths` - Paths of the files to search
+/// * `label` - The label, e.g. as printed in a failure log
+///
+/// # Returns
+/// A list of dicts (file_path, function, line_number, expr, labels,
+/// comment) in file and line order; raises OSError if a file cannot be read
+/// and ValueError if one does not parse
+#[pyfunction]
+fn find_assert_by_label(paths: Vec<String>, label: &str) -> PyResult<Vec<LabeledAssert>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    find_assert_by_label_core(&files, label).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Integer casts in exec code, for auditing overflow reasoning
+///
+/// Reports every `as` cast to a fixed-width integer type in exec functions.
+/// Casts under `#[verifier::truncate]` have kind "truncate"; the others are
+/// "widening" or "narrowing" when the operand type can be told (parameter,
+/// typed `let`, suffixed literal or inner cast) and "unknown" otherwise.
+///
+/// # Arguments
+/// * `paths` - Paths of the files to audit
+///
+/// # Returns
+/// A dict from path to a list of dicts (function, line_number, expr, from_ty,
+/// to_ty, kind) in source order; raises OSError if a file cannot be read and
+/// ValueError if one does not parse
+#[pyfunction]
+fn truncation_report(paths: Vec<String>) -> PyResult<BTreeMap<String, Vec<FunctionCast>>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    truncation_report_core(&files).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Gather the specs relevant to a Verus verification failure
+///
+/// Resolves the failing function across the files, finds the requires or
+/// ensures clause (or assert) at the reported line, and adds the spec
+/// functions that clause refers to and the lemmas whose ensures mention them.
+///
+/// # Arguments
+/// * `paths` - Paths of the project files to search
+/// * `function_name` - Name of the failing function from the Verus output
+/// * `line` - Reported line of the failing clause (1-indexed)
+///
+/// # Returns
+/// A dict with function, clause_kind ("requires", "ensures", "assert" or
+/// None), clause, spec_functions (dicts with specs and source) and
+/// related_lemmas; raises OSError if a file cannot be read and ValueError if
+/// one does not parse or the function is not found
+#[pyfunction]
+fn context_for_error(paths: Vec<String>, function_name: &str, line: usize) -> PyResult<ErrorContext> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    context_for_error_core(&files, function_name, line).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Get the contracts on both sides of a call, to judge whether it is justified
+///
+/// Takes the call in `file` nearest to `line` and resolves the callee within
+/// the files when it is defined there; an external callee (e.g. from vstd)
+/// comes back with its path and empty specs.
+///
+/// # Arguments
+/// * `paths` - Paths of the project files
+/// * `file` - The path (one of `paths`) containing the call
+/// * `line` - Line of the call (1-indexed)
+///
+/// # Returns
+/// A dict with caller and callee (FunctionSpecs), callee_path, resolved,
+/// args (argument source texts in order) and line_number; raises OSError if a
+/// file cannot be read and ValueError if one does not parse, `file` is not
+/// among the paths or has no call
+#[pyfunction]
+fn call_context(paths: Vec<String>, file: &str, line: usize) -> PyResult<CallContext> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    call_context_core(&files, file, line).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Report which spec functions a lemma's ensures unfold to
+///
+/// Follows the spec functions named in the ensures through the bodies of
+/// open ones, to estimate how deep the lemma's statement is. Unfolding stops
+/// at closed, opaque and uninterpreted spec functions (still listed) and at
+/// names the files do not define as spec functions.
+///
+/// # Arguments
+/// * `paths` - Paths of the project files
+/// * `lemma` - Name of the lemma
+///
+/// # Returns
+/// A dict with lemma (FunctionSpecs), closure (dicts with name, depth,
+/// opacity, file_path, line_number, by depth) and max_depth; raises OSError
+/// if a file cannot be read and ValueError if one does not parse or the
+/// lemma is not found
+#[pyfunction]
+fn ensures_unfolding(paths: Vec<String>, lemma: &str) -> PyResult<EnsuresUnfolding> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    ensures_unfolding_core(&files, lemma).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Gather everything about a function in one call: its specs and source,
+/// doc comment, callers, callees, siblings and the spec functions its
+/// clauses use
+///
+/// # Arguments
+/// * `paths` - Paths of the project files
+/// * `function_name` - Name of the function
+///
+/// # Returns
+/// A list with one dict per definition of the function (empty if it is not
+/// defined in a file that parses), with function (FunctionSpecs), source,
+/// doc, callers (dicts as from `usage_examples`), callees and siblings (names)
+/// and spec_functions (dicts with specs and source); raises OSError if a file
+/// cannot be read
+#[pyfunction]
+fn explain(paths: Vec<String>, function_name: &str) -> PyResult<Vec<Explanation>> {
+    let files = paths
+        .into_iter()
+        .map(|path| std::fs::read_to_string(&path).map(|content| (path, content)))
+        .collect::<Result<Vec<_>, _>>()?;
+    explain_core(&files, function_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the functions overlapping a line range
+///
+/// A function is included if any of its lines (signature, spec clauses or
+/// body) falls within `start_line..=end_line`; it need not be fully contained.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `start_line` - First line of the range (1-indexed)
+/// * `end_line` - Last line of the range (inclusive)
+///
+/// # Returns
+/// A list of FunctionSpecs; raises ValueError if the file does not parse
+#[pyfunction]
+fn functions_in_range(content: &str, start_line: usize, end_line: usize) -> PyResult<Vec<FunctionSpecs>> {
+    functions_touched_core(content, &[(start_line, end_line)]).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the functions touched by a diff
+///
+/// Like `functions_in_range` for several ranges at once, e.g. the new-file
+/// line ranges of each hunk; each function is reported once.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `ranges` - Inclusive (start_line, end_line) pairs (1-indexed)
+///
+/// # Returns
+/// A list of FunctionSpecs; raises ValueError if the file does not parse
+#[pyfunction]
+fn functions_touched_by_diff(content: &str, ranges: Vec<(usize, usize)>) -> PyResult<Vec<FunctionSpecs>> {
+    functions_touched_core(content, &ranges).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find public functions whose specs reference private items
+///
+/// For each `pub` function (trait methods are skipped), the identifiers in its
+/// requires and ensures clauses are checked against the spec fns and consts
+/// defined in the same file; references to ones that are not `pub` (including
+/// `pub(crate)`) are reported, since downstream crates cannot use such a spec
+/// as stated. External paths are assumed to be fine.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (function, line_number, clause_kind, clause, item,
+/// item_kind), without the suppressed issues (lint name `spec_visibility`, see
+/// `find_unused_lemmas`); raises ValueError if the file does not parse
+#[pyfunction]
+fn spec_visibility_lint(content: &str) -> PyResult<Vec<VisibilityIssue>> {
+    let (issues, _) = spec_visibility_lint_core(content).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(issues)
+}
+
+/// Flag exec-only constructs in spec clauses
+///
+/// A syntactic check (no type information) for sub-expressions of requires,
+/// ensures and decreases clauses that are almost certainly wrong there:
+/// `&mut` borrows, assignment operators, `return`/`break`, macro calls other
+/// than `seq!`/`set!`/`map!` (so `vec!` too), and `.clone()`.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (function, clause_kind, clause, kind, expr, line_number,
+/// column) in source order, without the suppressed issues (lint name
+/// `spec_expr`, see `find_unused_lemmas`); raises ValueError if the file does
+/// not parse
+#[pyfunction]
+fn lint_spec_exprs(content: &str) -> PyResult<Vec<SpecExprIssue>> {
+    let (issues, _) = lint_spec_exprs_core(content).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(issues)
+}
+
+/// Find lemmas that prove nothing
+///
+/// Flags proof functions whose every ensures conjunct is `true`, a comparison
+/// between literal expressions (`1 + 1 == 2`), or a restatement of one of the
+/// requires conjuncts, compared as expression trees with quantified variables
+/// renamed (so `forall|j| ...` restates `forall|i| ...`). Lemmas without
+/// ensures are not reported.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of the FunctionSpecs of the flagged lemmas in source order, without
+/// the suppressed ones (lint name `trivial_lemma`, see `find_unused_lemmas`);
+/// raises ValueError if the file does not parse
+#[pyfunction]
+fn lint_trivial_lemmas(content: &str) -> PyResult<Vec<FunctionSpecs>> {
+    let (trivial, _) = lint_trivial_lemmas_core(content).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(trivial)
+}
+
+/// Inventory invariant usage for concurrency review
+///
+/// Reports `open_local_invariant!`/`open_atomic_invariant!` blocks (with the
+/// opened expression, the inner binding and the block's line range, nested
+/// openings included), `LocalInvariant`/`AtomicInvariant` types among a
+/// function's parameter and return types, and `LocalInvariant::new`/
+/// `AtomicInvariant::new` calls.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (function, kind, atomic, expr, binding, line_number,
+/// end_line), ordered by line; raises ValueError if the file does not parse
+#[pyfunction]
+fn invariant_usage(content: &str) -> PyResult<Vec<InvariantUsage>> {
+    invariant_usage_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Extract and check the code examples in function doc comments
+///
+/// Takes the fenced code blocks of `///` comments that have no language or
+/// are tagged `rust` or `verus` (`rust,ignore` included), with the `# `
+/// prefix of rustdoc's hidden lines removed. Each is parsed as Verus items
+/// (as if inside `verus!`) or else as the statements of a function body;
+/// examples that parse neither way are reported with the error rather than
+/// left out.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+///
+/// # Returns
+/// A list of dicts (function, line_number, code, parsed, error, calls),
+/// ordered by line, calls being the paths called in examples that parse;
+/// raises ValueError if the file does not parse
+#[pyfunction]
+fn extract_doc_examples(content: &str) -> PyResult<Vec<DocExample>> {
+    extract_doc_examples_core(content).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Rename a function and update its same-file references
+///
+/// Renames the definition and every reference resolving to it: calls and paths
+/// ending in the name (bare or rooted in `crate`, `self`, `super`, `Self` or a
+/// module or type of the file; a bare name only when called or in `use`), including inside `assert ... by` blocks and
+/// macro bodies such as `calc!` steps, and `.name(...)` calls if the function
+/// is a method. All other bytes are left untouched.
+///
+/// # Arguments
+/// * `content` - The source code content to rewrite
+/// * `old_name` - Current name of the function
+/// * `new_name` - New name
+///
+/// # Returns
+/// The rewritten source; raises ValueError if the file does not parse, the
+/// function is missing or defined more than once, or `new_name` is invalid or
+/// already defined
+#[pyfunction]
+fn rename_function(content: &str, old_name: &str, new_name: &str) -> PyResult<String> {
+    rename_function_core(content, old_name, new_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Export a spec clause as a JSON expression tree
+///
+/// The schema (binary/unary operators, calls, method calls, quantifiers with
+/// their binders, field and index accesses, casts, ifs, paths, literals with
+/// their type suffix, and an opaque fallback holding the source text) is
+/// documented on `ClauseAst`.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - The function whose clause to export
+/// * `kind` - "requires", "ensures" or "decreases"
+/// * `index` - Position of the clause within its kind (0-based)
+///
+/// # Returns
+/// The tree as a JSON string; raises ValueError if the file does not parse or
+/// the function or clause does not exist
+#[pyfunction]
+fn clause_ast(content: &str, function_name: &str, kind: &str, index: usize) -> PyResult<String> {
+    let ast = clause_ast_core(content, function_name, kind, index).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    serde_json::to_string(&ast).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+}
+
+/// List the fields and method-call chains each spec clause of a function uses
+///
+/// Paths are rooted at a parameter, `self` or the return value binding and
+/// follow field accesses (`self.bytes`, `r.0.limbs`); `old(x)` counts as `x`
+/// and indexing ends a path, so `self.bytes[31] & 0x80 == 0` uses
+/// `self.bytes`. Method calls are kept apart: `self.bytes.len()` is the
+/// field `self.bytes` and the method call chain `self.bytes.len()`.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - The function whose clauses to read
+///
+/// # Returns
+/// A list of dicts (clause_kind, clause, fields, method_calls), requires
+/// clauses first; raises ValueError if the file does not parse or the
+/// function does not exist
+#[pyfunction]
+fn clause_field_paths(content: &str, function_name: &str) -> PyResult<Vec<ClauseFieldPaths>> {
+    clause_field_paths_core(content, function_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the functions whose ensures constrain a field of a type
+///
+/// A function matches when an ensures clause accesses `field_name` directly
+/// on a parameter, `self` or the return value (see `clause_field_paths`)
+/// whose type is `type_name`. Types are compared by their last path segment
+/// without generics or references, and `Self` is the impl's self type;
+/// method calls like `.len()` are never taken for fields.
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+/// * `type_name` - The type owning the field, e.g. "Scalar"
+/// * `field_name` - The field, e.g. "bytes"
+///
+/// # Returns
+/// The FunctionSpecs of the matching functions, in file and source order;
+/// raises OSError if a file cannot be read and ValueError if one does not parse
+#[pyfunction]
+fn lemmas_constraining_field(
+    content_or_paths: &Bound<'_, PyAny>,
+    type_name: &str,
+    field_name: &str,
+) -> PyResult<Vec<FunctionSpecs>> {
+    let files = read_content_or_paths(content_or_paths)?;
+    lemmas_constraining_field_core(&files, type_name, field_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// List the requires and ensures conjuncts of a function that are a single
+/// predicate call
+///
+/// A conjunct qualifies when it is a call (`inv(result)`, `Self::wf(self)`)
+/// or a method call (`self.wf()`, the receiver counting as the first
+/// argument). Each argument is classified as a bare parameter (`self`
+/// included), `old(param)`, the return value binding, or a complex expression.
+///
+/// # Arguments
+/// * `content` - The source code content to parse
+/// * `function_name` - The function whose clauses to read
+///
+/// # Returns
+/// A list of dicts (clause_kind, conjunct, callee, is_method, args), args
+/// being (kind, text) tuples with kind "param", "old", "result" or "complex";
+/// raises ValueError if the file does not parse or the function does not exist
+#[pyfunction]
+fn predicate_calls(content: &str, function_name: &str) -> PyResult<Vec<PredicateCall>> {
+    predicate_calls_core(content, function_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the functions that establish a predicate
+///
+/// A function establishes `predicate_name` when one of its ensures conjuncts
+/// is a call of it (see `predicate_calls`) with the return value or `self`
+/// as an argument, as in `ensures inv(result)` or `ensures self.wf()`.
+/// Requiring the predicate, or ensuring it of `old(self)` or another
+/// expression, does not count. A name without `::` matches the last segment of
+/// qualified callees.
+///
+/// # Arguments
+/// * `content_or_paths` - Source code as a string, or a list of file paths
+/// * `predicate_name` - The predicate, e.g. "inv" or "Self::wf"
+///
+/// # Returns
+/// The FunctionSpecs of the functions, in file and source order; raises
+/// OSError if a file cannot be read and ValueError if one does not parse
+#[pyfunction]
+fn establishes_predicate(content_or_paths: &Bound<'_, PyAny>, predicate_name: &str) -> PyResult<Vec<FunctionSpecs>> {
+    let files = read_content_or_paths(content_or_paths)?;
+    establishes_predicate_core(&files, predicate_name).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Save extraction results to a compact binary file
+///
+/// The file starts with a versioned header, so files written before a change to
+/// the result format are rejected by `load_specs` instead of misread.
+///
+/// # Arguments
+/// * `specs` - List of FunctionSpecs dicts, as returned by the parser
+/// * `path` - File to write
+///
+/// # Returns
+/// None; raises OSError if the file cannot be written
+#[pyfunction]
+fn save_specs(specs: Vec<FunctionSpecs>, path: &str) -> PyResult<()> {
+    let bytes = encode_specs(&specs).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Load extraction results saved by `save_specs`
+///
+/// # Arguments
+/// * `path` - File written by `save_specs`
+///
+/// # Returns
+/// List of FunctionSpecs dicts; raises OSError if the file cannot be read and
+/// ValueError if it is not a specs file or was written in another format version
+#[pyfunction]
+fn load_specs(path: &str) -> PyResult<Vec<FunctionSpecs>> {
+    let bytes = std::fs::read(path)?;
+    decode_specs(&bytes).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Get the version of verus_parser
+#[pyfunction]
+fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Report what this build of the parser supports
+///
+/// Grammar features are probed by parsing a minimal program for each one
+/// (`spec_fn`, `named_return`, `returns_clause`, `broadcast_group`,
+/// `assume_specification`, ...) once, when the module is imported.
+///
+/// # Returns
+/// A dict with `version`, `verus_syn_version` (locked at build time),
+/// `specs_format_version`, `debug_build` and `features`, a dict from feature
+/// name to bool
+#[pyfunction]
+fn capabilities() -> Capabilities {
+    capabilities_core()
+}
+
+/// Python module definition
+#[pymodule]
+fn verus_parser(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    grammar_features();
+    m.add_function(wrap_pyfunction!(parse_verus_file, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verus_file_with_diagnostics, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verus_contents, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verus_directory, m)?)?;
+    m.add_function(wrap_pyfunction!(verus_files, m)?)?;
+    m.add_function(wrap_pyfunction!(to_verus_source, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_function_specs, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_proof_functions, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_exec_functions, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_checked_specs, m)?)?;
+    m.add_function(wrap_pyfunction!(nontrivial_proofs, m)?)?;
+    m.add_function(wrap_pyfunction!(duplicate_definitions, m)?)?;
+    m.add_function(wrap_pyfunction!(specs_outside_verus, m)?)?;
+    m.add_function(wrap_pyfunction!(is_valid_verus, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_spec_exprs, m)?)?;
+    m.add_function(wrap_pyfunction!(specs_digest, m)?)?;
+    m.add_function(wrap_pyfunction!(erase_verus, m)?)?;
+    m.add_function(wrap_pyfunction!(filter_by_requires, m)?)?;
+    m.add_function(wrap_pyfunction!(applicable_lemmas, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pattern, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_loops, m)?)?;
+    m.add_function(wrap_pyfunction!(termination_report, m)?)?;
+    m.add_function(wrap_pyfunction!(spec_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(render_markdown, m)?)?;
+    m.add_function(wrap_pyfunction!(group_lemmas, m)?)?;
+    m.add_function(wrap_pyfunction!(siblings, m)?)?;
+    m.add_function(wrap_pyfunction!(lemma_spec_associations, m)?)?;
+    m.add_function(wrap_pyfunction!(file_attributes, m)?)?;
+    m.add_function(wrap_pyfunction!(verus_block_ranges, m)?)?;
+    m.add_function(wrap_pyfunction!(external_lemma_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(wrapper_lemmas, m)?)?;
+    m.add_function(wrap_pyfunction!(save_specs, m)?)?;
+    m.add_function(wrap_pyfunction!(load_specs, m)?)?;
+    m.add_function(wrap_pyfunction!(fuzzy_find, m)?)?;
+    m.add_function(wrap_pyfunction!(spec_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_directories, m)?)?;
+    m.add_function(wrap_pyfunction!(clause_ast, m)?)?;
+    m.add_function(wrap_pyfunction!(clause_field_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(lemmas_constraining_field, m)?)?;
+    m.add_function(wrap_pyfunction!(predicate_calls, m)?)?;
+    m.add_function(wrap_pyfunction!(establishes_predicate, m)?)?;
+    m.add_function(wrap_pyfunction!(spec_visibility_lint, m)?)?;
+    m.add_function(wrap_pyfunction!(lint_spec_exprs, m)?)?;
+    m.add_function(wrap_pyfunction!(lint_trivial_lemmas, m)?)?;
+    m.add_function(wrap_pyfunction!(invariant_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_doc_examples, m)?)?;
+    m.add_function(wrap_pyfunction!(rename_function, m)?)?;
+    m.add_function(wrap_pyfunction!(usage_examples, m)?)?;
+    m.add_function(wrap_pyfunction!(context_for_error, m)?)?;
+    m.add_function(wrap_pyfunction!(call_context, m)?)?;
+    m.add_function(wrap_pyfunction!(ensures_unfolding, m)?)?;
+    m.add_function(wrap_pyfunction!(explain, m)?)?;
+    m.add_function(wrap_pyfunction!(truncation_report, m)?)?;
+    m.add_function(wrap_pyfunction!(lemma_fan_in, m)?)?;
+    m.add_function(wrap_pyfunction!(find_unused_lemmas, m)?)?;
+    m.add_function(wrap_pyfunction!(suppressed_findings, m)?)?;
+    m.add_function(wrap_pyfunction!(proof_todos, m)?)?;
+    m.add_function(wrap_pyfunction!(find_assert_by_label, m)?)?;
+    m.add_function(wrap_pyfunction!(to_dot, m)?)?;
+    m.add_function(wrap_pyfunction!(untriggered_quantifiers, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verus_directory_with_progress, m)?)?;
+    m.add_function(wrap_pyfunction!(public_surface, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verus_crate, m)?)?;
+    m.add_function(wrap_pyfunction!(spec_literals, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_traits, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_type_aliases, m)?)?;
+    m.add_function(wrap_pyfunction!(module_docs, m)?)?;
+    m.add_function(wrap_pyfunction!(module_tree, m)?)?;
+    m.add_function(wrap_pyfunction!(refinement_lint, m)?)?;
+    m.add_function(wrap_pyfunction!(functions_in_range, m)?)?;
+    m.add_function(wrap_pyfunction!(functions_touched_by_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(to_scip, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_proofs, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_lemma_name, m)?)?;
+    m.add_function(wrap_pyfunction!(draft_lemma_from_assert, m)?)?;
+    m.add_function(wrap_pyfunction!(reparse_incremental, m)?)?;
+    m.add_class::<ParsedVerusFile>()?;
+    m.add_function(wrap_pyfunction!(version, m)?)?;
+    m.add_function(wrap_pyfunction!(capabilities, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,2467 @@
+//! The syntax-tree visitor that collects function specs, the parsers for
+//! `verus!` macro bodies, and the signature and attribute helpers it uses
+
+use crate::extract::*;
+use crate::model::*;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use verus_syn::spanned::Spanned;
+use verus_syn::visit::Visit;
+use verus_syn::{Expr, FnMode, ImplItemFn, Item, ItemFn, ItemMacro, Signature, TraitItemFn};
+
+/// AST visitor to find functions and extract their specifications
+pub(crate) struct FunctionFinder {
+    /// Function name we're looking for (None = collect all)
+    target_name: Option<String>,
+    /// Collected function specs
+    pub(crate) functions: Vec<FunctionSpecs>,
+    /// Signatures of the collected functions (parallel to `functions`)
+    pub(crate) signatures: Vec<Signature>,
+    /// Whether to fill `signatures`; the plain parse paths skip it, since
+    /// cloning a very long clause recurses once per operator
+    pub(crate) keep_signatures: bool,
+    /// Byte ranges of the collected functions' headers in the parsed text
+    /// (parallel to `functions`, only filled with `keep_header_ranges`)
+    pub(crate) header_ranges: Vec<Range<usize>>,
+    /// Whether to fill `header_ranges`
+    pub(crate) keep_header_ranges: bool,
+    /// Generic bounds of the enclosing impl or trait, if any
+    enclosing_bounds: Vec<(String, Vec<String>)>,
+    /// Names of the enclosing modules
+    pub(crate) module_stack: Vec<String>,
+    /// Breadcrumbs of the enclosing items and macro invocations ("mod m", "verus!", ...)
+    pub(crate) context: Vec<String>,
+    /// Whether the enclosing items include a `#[cfg(test)]` one
+    pub(crate) in_test: bool,
+    /// Conditions of the `#[cfg(...)]` attributes of the enclosing items
+    pub(crate) enclosing_cfgs: Vec<String>,
+    /// Attributes (paths, globs allowed) excluding the functions carrying them
+    /// or inside an item carrying them
+    pub(crate) exclude_attributes: Vec<String>,
+    /// Name globs excluding the functions they match
+    pub(crate) exclude_name_patterns: Vec<String>,
+    /// Why the enclosing items exclude their functions, outermost first
+    pub(crate) enclosing_exclusions: Vec<String>,
+    /// Whether a module named `tests` counts as test-only
+    pub(crate) tests_module_heuristic: bool,
+    /// Whether to parse the bodies of other item macros as items
+    pub(crate) item_macros: bool,
+    /// Names of macros to treat like `verus!` besides `verus` itself
+    pub(crate) verus_macro_names: Vec<String>,
+    /// Name of the enclosing non-`verus!` item macro, if any
+    from_macro: Option<String>,
+    /// Whether the items being visited are inside a `verus!` block
+    pub(crate) in_verus: bool,
+    /// Provenance of the innermost enclosing `verus!` block, if any
+    /// ("verus_macro" or "verus_macro_in_impl")
+    pub(crate) verus_entry: Option<&'static str>,
+    /// Whether to replace `Self` in method specs with the impl's self type
+    pub(crate) resolve_self: bool,
+    /// Whether to read `#[requires(...)]`/`#[ensures(...)]` attributes as clauses
+    pub(crate) attribute_specs: bool,
+    /// Self type of the enclosing impl, if any
+    self_ty: Option<verus_syn::Type>,
+    /// Trait of the enclosing impl, if it is a trait impl
+    impl_trait: Option<String>,
+    /// Places where extraction was lossy
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    /// Maximum number of items to visit (None = unlimited)
+    pub(crate) max_items: Option<usize>,
+    /// Number of items visited so far
+    item_count: usize,
+    /// Set once a limit is exceeded; traversal stops from then on
+    pub(crate) limit_error: Option<ParseFailure>,
+}
+
+impl FunctionFinder {
+    pub(crate) fn new(target_name: Option<String>) -> Self {
+        Self {
+            target_name,
+            functions: Vec::new(),
+            signatures: Vec::new(),
+            keep_signatures: true,
+            header_ranges: Vec::new(),
+            keep_header_ranges: false,
+            enclosing_bounds: Vec::new(),
+            module_stack: Vec::new(),
+            context: Vec::new(),
+            in_test: false,
+            enclosing_cfgs: Vec::new(),
+            exclude_attributes: Vec::new(),
+            exclude_name_patterns: Vec::new(),
+            enclosing_exclusions: Vec::new(),
+            tests_module_heuristic: false,
+            item_macros: false,
+            verus_macro_names: Vec::new(),
+            from_macro: None,
+            in_verus: false,
+            verus_entry: None,
+            resolve_self: false,
+            attribute_specs: false,
+            self_ty: None,
+            impl_trait: None,
+            diagnostics: Vec::new(),
+            max_items: None,
+            item_count: 0,
+            limit_error: None,
+        }
+    }
+
+    /// Record the header range of a function just collected, from the start of
+    /// `sig` to the start of `end` (the body's opening brace or the `;`)
+    fn note_header(&mut self, sig: &Signature, end: proc_macro2::Span) {
+        if self.keep_header_ranges {
+            self.header_ranges.push(sig.span().byte_range().start..end.byte_range().start);
+        }
+    }
+
+    /// Count an item about to be visited; false once the item limit is exceeded
+    fn enter_item(&mut self) -> bool {
+        if self.limit_error.is_some() {
+            return false;
+        }
+        self.item_count += 1;
+        match self.max_items {
+            Some(max) if self.item_count > max => {
+                self.limit_error = Some(ParseFailure::new("max_items", format!("More than {} items", max)));
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Add the cfg conditions of an item being entered, and the reason it
+    /// excludes the functions inside, if any; returns the lengths to go back
+    /// to with `leave_cfgs` when leaving it
+    fn enter_cfgs(&mut self, attrs: &[verus_syn::Attribute]) -> (usize, usize) {
+        let outer = (self.enclosing_cfgs.len(), self.enclosing_exclusions.len());
+        self.enclosing_cfgs.extend(cfg_conditions(attrs));
+        self.enclosing_exclusions.extend(self.attribute_exclusion(attrs));
+        outer
+    }
+
+    fn leave_cfgs(&mut self, (cfgs, exclusions): (usize, usize)) {
+        self.enclosing_cfgs.truncate(cfgs);
+        self.enclosing_exclusions.truncate(exclusions);
+    }
+
+    /// The reason `attrs` exclude what carries them, if any
+    fn attribute_exclusion(&self, attrs: &[verus_syn::Attribute]) -> Option<String> {
+        attrs.iter().find_map(|a| {
+            let meta = &a.meta;
+            excluding_attribute(&self.exclude_attributes, quote::quote!(#meta))
+        })
+    }
+
+    /// Whether a function is left out by `exclude_attributes` (its own or an
+    /// enclosing item's) or `exclude_name_patterns`; an excluded function is
+    /// recorded as a "function_excluded" diagnostic
+    fn is_excluded(&mut self, sig: &Signature, attrs: &[verus_syn::Attribute]) -> bool {
+        let name = sig.ident.to_string();
+        let reason = self.attribute_exclusion(attrs).or_else(|| self.enclosing_exclusions.first().cloned()).or_else(|| {
+            let pattern = self.exclude_name_patterns.iter().find(|pattern| glob_match(pattern, &name))?;
+            Some(format!("name pattern `{}`", pattern))
+        });
+        let Some(reason) = reason else {
+            return false;
+        };
+        let line = sig.ident.span().start().line;
+        self.diagnostics.push(Diagnostic::new("function_excluded", line, Some(name), reason));
+        true
+    }
+
+    /// Record a `verus!` body that is skipped because it does not parse
+    fn skip_verus_body(&mut self, mac: &verus_syn::Macro, error: &verus_syn::Error) {
+        let line = mac.path.span().start().line;
+        self.diagnostics.push(Diagnostic::new("verus_body_skipped", line, None, error.to_string()));
+    }
+
+    /// Record the attributes of a collected function that are not interpreted
+    fn note_attributes(&mut self, name: &str, attrs: &[verus_syn::Attribute]) {
+        for attr in attrs.iter().filter(|a| a.path().is_ident("cfg_attr")) {
+            let meta = &attr.meta;
+            let line = attr.span().start().line;
+            let detail = render_tokens(quote::quote!(#meta));
+            self.diagnostics.push(Diagnostic::new("cfg_attr_ignored", line, Some(name.to_string()), detail));
+        }
+        if self.attribute_specs {
+            for attr in attrs.iter().filter(|a| spec_attribute_kind(a).is_some()) {
+                if spec_attribute_expr(attr).is_none() {
+                    let meta = &attr.meta;
+                    let line = attr.span().start().line;
+                    let detail = render_tokens(quote::quote!(#meta));
+                    let function = Some(name.to_string());
+                    self.diagnostics.push(Diagnostic::new("spec_attribute_unparsed", line, function, detail));
+                }
+            }
+        }
+    }
+
+    /// Specs of an impl method; with `resolve_self`, `Self` is replaced by the
+    /// impl's self type and the specs as written are kept under `unresolved`
+    fn extract_method_specs<S: Spanned>(
+        &mut self,
+        sig: &Signature,
+        attrs: &[verus_syn::Attribute],
+        spanned: &S,
+    ) -> FunctionSpecs {
+        let mut specs = self.extract_specs_from_signature(sig, attrs, spanned);
+        specs.impl_type = self.self_ty.as_ref().map(|self_ty| render_tokens(quote::quote!(#self_ty)));
+        specs.trait_path = self.impl_trait.clone();
+        let Some(self_ty) = self.self_ty.as_ref().filter(|_| self.resolve_self) else {
+            return specs;
+        };
+        let (tokens, replaced) = substitute_self(quote::quote!(#sig), self_ty);
+        if !replaced {
+            return specs;
+        }
+        let resolved = match verus_syn::parse2::<Signature>(tokens) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                let line = sig.ident.span().start().line;
+                self.diagnostics.push(Diagnostic::new("self_unresolved", line, Some(specs.name.clone()), e.to_string()));
+                return specs;
+            }
+        };
+        let mut resolved_specs = self.extract_specs_from_signature(&resolved, attrs, spanned);
+        resolved_specs.impl_type = specs.impl_type;
+        resolved_specs.trait_path = specs.trait_path;
+        resolved_specs.unresolved = Some(UnresolvedSpecs {
+            self_type: render_tokens(quote::quote!(#self_ty)),
+            signature: specs.signature,
+            requires: specs.requires,
+            ensures: specs.ensures,
+            decreases: specs.decreases,
+            recommends: specs.recommends,
+            params: specs.params,
+            returns: specs.returns,
+        });
+        resolved_specs
+    }
+
+    /// Extract specs from a verus_syn Signature, with span information
+    fn extract_specs_from_signature<S: Spanned>(
+        &self,
+        sig: &Signature,
+        attrs: &[verus_syn::Attribute],
+        spanned: &S,
+    ) -> FunctionSpecs {
+        let name = sig.ident.to_string();
+
+        // Check if this is a proof function
+        let is_proof = matches!(sig.mode, FnMode::Proof(_));
+        let mode = mode_name(&sig.mode).to_string();
+
+        // Build the signature strings
+        let signature = render_signature(sig);
+        let signature_raw = quote::quote!(#sig).to_string();
+
+        // Get line numbers from span
+        let span = spanned.span();
+        let line_number = Some(span.start().line);
+        let end_line = Some(span.end().line);
+        let sig_end = header_end(sig);
+
+        // Spec attributes (`#[requires(...)]`, only with `attribute_specs`)
+        // come before the clauses, as in the source
+        let (attr_requires, attr_ensures) = if self.attribute_specs {
+            (spec_attributes(attrs, "requires"), spec_attributes(attrs, "ensures"))
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let requires_exprs: Vec<&Expr> =
+            attr_requires.iter().chain(clause_exprs(sig, "requires").unwrap_or_default()).collect();
+        let ensures_exprs: Vec<&Expr> =
+            attr_ensures.iter().chain(clause_exprs(sig, "ensures").unwrap_or_default()).collect();
+        let has_attributes = !attr_requires.is_empty() || !attr_ensures.is_empty();
+        let has_clauses = sig.spec.requires.is_some() || sig.spec.ensures.is_some();
+        let spec_style = match (has_attributes, has_clauses) {
+            (true, true) => "mixed",
+            (true, false) => "attribute",
+            (false, _) => "verus",
+        };
+
+        // Extract requires and ensures clauses
+        let requires = clause_texts(requires_exprs.iter().copied());
+        let ensures = clause_texts(ensures_exprs.iter().copied());
+
+        // Extract recommends clauses from sig.spec
+        let recommends: Vec<String> = sig
+            .spec
+            .recommends
+            .as_ref()
+            .map(|rec| clause_texts(&rec.exprs.exprs))
+            .unwrap_or_default();
+
+        // Extract decreases clauses from sig.spec
+        let decreases: Vec<String> = sig
+            .spec
+            .decreases
+            .as_ref()
+            .map(|dec| clause_texts(&dec.decreases.exprs.exprs))
+            .unwrap_or_default();
+
+        let spec_fingerprint =
+            compute_spec_fingerprint(&normalized_signature(sig), &requires, &ensures, &decreases);
+
+        let mut bounds = self.enclosing_bounds.clone();
+        collect_generic_bounds(&sig.generics, &mut bounds);
+
+        let mut locals = clause_locals(sig);
+        if !attr_ensures.is_empty() && return_binding(sig).is_none() {
+            // Attribute-style ensures name the return value `result`
+            locals.push("result".to_string());
+        }
+        let mentions = |exprs: &[&Expr]| -> Vec<ClauseMentions> {
+            exprs.iter().map(|e| clause_mentions(e, &locals)).collect()
+        };
+        let requires_mentions = mentions(&requires_exprs);
+        let ensures_mentions = mentions(&ensures_exprs);
+        let is_self_contained = requires_mentions.iter().chain(&ensures_mentions).all(|m| m.externals.is_empty());
+
+        FunctionSpecs {
+            name,
+            file_path: Arc::default(),
+            line_number,
+            end_line,
+            sig_end_line: Some(sig_end.line),
+            sig_end_column: Some(sig_end.column),
+            requires,
+            ensures,
+            decreases,
+            decreases_measure: decreases_measure(sig),
+            recommends,
+            requires_raw: Vec::new(),
+            ensures_raw: Vec::new(),
+            requires_parent: Vec::new(),
+            ensures_parent: Vec::new(),
+            requires_offsets: Vec::new(),
+            ensures_offsets: Vec::new(),
+            requires_mentions,
+            ensures_mentions,
+            is_self_contained,
+            has_requires: !requires_exprs.is_empty() || sig.spec.requires.is_some(),
+            has_ensures: !ensures_exprs.is_empty() || sig.spec.ensures.is_some(),
+            spec_style: spec_style.to_string(),
+            has_decreases: sig.spec.decreases.is_some(),
+            signature,
+            signature_raw,
+            // Filled in from the source text by the parse entry points
+            header_source: None,
+            params: param_info(sig),
+            returns: return_info(sig),
+            is_proof,
+            mode,
+            is_broadcast: is_broadcast(sig),
+            is_memoized: attrs.iter().any(|a| is_verifier_attr(a, "memoize")),
+            is_inlined_spec: attrs.iter().any(|a| is_verifier_attr(a, "inline")),
+            attributes: verifier_attributes(attrs),
+            verifier_attributes: verifier_attribute_parts(attrs),
+            suppressed: attribute_suppressions(attrs),
+            todo_markers: Vec::new(),
+            cfgs: cfg_conditions(attrs),
+            effective_cfgs: self.enclosing_cfgs.iter().cloned().chain(cfg_conditions(attrs)).collect(),
+            module_path: self.module_stack.join("::"),
+            impl_type: None,
+            trait_path: None,
+            path: std::iter::once("crate".to_string())
+                .chain(self.context.iter().cloned())
+                .chain(std::iter::once(format!("fn {}", sig.ident)))
+                .collect(),
+            in_test: self.in_test || is_cfg_test(attrs),
+            from_macro: self.from_macro.clone(),
+            in_verus_block: self.in_verus,
+            provenance: String::new(),
+            module_doc: None,
+            is_externally_reachable: false,
+            unresolved: None,
+            proof_blocks: Vec::new(),
+            asserts: Vec::new(),
+            casts: Vec::new(),
+            body_stmt_count: None,
+            is_trivial_proof: false,
+            bounds,
+            spec_fingerprint,
+            parse_error: None,
+            error_kind: None,
+        }
+    }
+
+    /// Check if we should collect this function (based on target_name filter)
+    fn should_collect(&self, name: &str) -> bool {
+        match &self.target_name {
+            Some(target) => name == target,
+            None => true,
+        }
+    }
+
+    /// Provenance of a function found here: the innermost macro it came from,
+    /// else `member` ("top_level", "impl" or "trait")
+    fn provenance(&self, member: &str) -> String {
+        match (&self.from_macro, self.verus_entry) {
+            (Some(name), _) => format!("macro:{}", name),
+            (None, Some(entry)) => entry.to_string(),
+            (None, None) => member.to_string(),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FunctionFinder {
+    // Count items (including impl and trait members) against the item limit
+    fn visit_item(&mut self, node: &'ast Item) {
+        if self.enter_item() {
+            verus_syn::visit::visit_item(self, node);
+        }
+    }
+
+    fn visit_impl_item(&mut self, node: &'ast verus_syn::ImplItem) {
+        if self.enter_item() {
+            verus_syn::visit::visit_impl_item(self, node);
+        }
+    }
+
+    fn visit_trait_item(&mut self, node: &'ast verus_syn::TraitItem) {
+        if self.enter_item() {
+            verus_syn::visit::visit_trait_item(self, node);
+        }
+    }
+
+    // Nested items live in bodies, never in signatures, and walking a huge
+    // clause recursively could exhaust the stack
+    fn visit_signature(&mut self, _node: &'ast Signature) {}
+
+    // Handle top-level functions
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        let name = node.sig.ident.to_string();
+
+        if self.should_collect(&name) && !self.is_excluded(&node.sig, &node.attrs) {
+            let mut specs = self.extract_specs_from_signature(&node.sig, &node.attrs, node);
+            specs.provenance = self.provenance("top_level");
+            add_body_facts(&mut specs, &node.sig, &node.block);
+            self.note_attributes(&name, &node.attrs);
+            self.functions.push(specs);
+            if self.keep_signatures {
+                self.signatures.push(node.sig.clone());
+            }
+            self.note_header(&node.sig, node.block.brace_token.span.open());
+        }
+
+        // Continue visiting nested items
+        self.context.push(format!("fn {}", name));
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+        verus_syn::visit::visit_item_fn(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.context.pop();
+    }
+
+    // Handle methods in impl blocks
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        let name = node.sig.ident.to_string();
+
+        if self.should_collect(&name) && !self.is_excluded(&node.sig, &node.attrs) {
+            let mut specs = self.extract_method_specs(&node.sig, &node.attrs, node);
+            specs.provenance = self.provenance("impl");
+            add_body_facts(&mut specs, &node.sig, &node.block);
+            self.note_attributes(&name, &node.attrs);
+            self.functions.push(specs);
+            if self.keep_signatures {
+                self.signatures.push(node.sig.clone());
+            }
+            self.note_header(&node.sig, node.block.brace_token.span.open());
+        }
+
+        // Continue visiting nested items
+        self.context.push(format!("fn {}", name));
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+        verus_syn::visit::visit_impl_item_fn(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.context.pop();
+    }
+
+    // Handle trait method declarations
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        let name = node.sig.ident.to_string();
+
+        if self.should_collect(&name) && !self.is_excluded(&node.sig, &node.attrs) {
+            let mut specs = self.extract_specs_from_signature(&node.sig, &node.attrs, node);
+            specs.provenance = self.provenance("trait");
+            match &node.default {
+                Some(block) => add_body_facts(&mut specs, &node.sig, block),
+                None => {
+                    let end = node.span().end();
+                    specs.sig_end_line = Some(end.line);
+                    specs.sig_end_column = Some(end.column);
+                }
+            }
+            self.note_attributes(&name, &node.attrs);
+            self.functions.push(specs);
+            if self.keep_signatures {
+                self.signatures.push(node.sig.clone());
+            }
+            match (&node.default, &node.semi_token) {
+                (Some(block), _) => self.note_header(&node.sig, block.brace_token.span.open()),
+                (None, Some(semi)) => self.note_header(&node.sig, semi.span),
+                (None, None) => self.note_header(&node.sig, node.span()),
+            }
+        }
+
+        // Continue visiting nested items
+        self.context.push(format!("fn {}", name));
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+        verus_syn::visit::visit_trait_item_fn(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.context.pop();
+    }
+
+    // Traverse into impl blocks - must manually check for macros
+    fn visit_item_impl(&mut self, node: &'ast verus_syn::ItemImpl) {
+        let mut impl_bounds = Vec::new();
+        collect_generic_bounds(&node.generics, &mut impl_bounds);
+        let outer_bounds = std::mem::replace(&mut self.enclosing_bounds, impl_bounds);
+        self.context.push(impl_label(node));
+        let outer_self_ty = self.self_ty.replace((*node.self_ty).clone());
+        let outer_impl_trait = std::mem::replace(&mut self.impl_trait, impl_trait_path(node));
+        let outer_in_test = self.in_test;
+        self.in_test |= is_cfg_test(&node.attrs);
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+
+        // Check each item in the impl block for macros
+        for item in &node.items {
+            if let verus_syn::ImplItem::Macro(mac) = item {
+                // Check if this is a verus! macro
+                let is_verus = is_verus_macro(&mac.mac.path, &self.verus_macro_names);
+                if is_verus || self.item_macros {
+                    // Try to parse the macro body as impl items (silently skipping
+                    // macros that aren't item-shaped)
+                    let parsed = verus_syn::parse2::<VerusImplMacroBody>(mac.mac.tokens.clone());
+                    if let (Err(e), true) = (&parsed, is_verus) {
+                        self.skip_verus_body(&mac.mac, e);
+                    }
+                    if let Ok(body) = parsed {
+                        let outer = self.from_macro.clone();
+                        let outer_in_verus = self.in_verus;
+                        let outer_entry = self.verus_entry;
+                        if is_verus {
+                            self.verus_entry = Some("verus_macro_in_impl");
+                        } else {
+                            self.from_macro = Some(macro_name(&mac.mac));
+                        }
+                        self.in_verus |= is_verus;
+                        self.context.push(format!("{}!", macro_name(&mac.mac)));
+                        let macro_cfgs = self.enter_cfgs(&mac.attrs);
+                        for impl_item in body.items {
+                            self.visit_impl_item(&impl_item);
+                        }
+                        self.leave_cfgs(macro_cfgs);
+                        self.context.pop();
+                        self.from_macro = outer;
+                        self.in_verus = outer_in_verus;
+                        self.verus_entry = outer_entry;
+                    }
+                }
+            }
+        }
+        // Continue with default traversal for non-macro items
+        verus_syn::visit::visit_item_impl(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.in_test = outer_in_test;
+        self.self_ty = outer_self_ty;
+        self.impl_trait = outer_impl_trait;
+        self.context.pop();
+        self.enclosing_bounds = outer_bounds;
+    }
+
+    // Traverse into trait definitions
+    fn visit_item_trait(&mut self, node: &'ast verus_syn::ItemTrait) {
+        let mut trait_bounds = Vec::new();
+        collect_generic_bounds(&node.generics, &mut trait_bounds);
+        let outer_bounds = std::mem::replace(&mut self.enclosing_bounds, trait_bounds);
+        self.context.push(format!("trait {}", node.ident));
+        let outer_in_test = self.in_test;
+        self.in_test |= is_cfg_test(&node.attrs);
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+        verus_syn::visit::visit_item_trait(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.in_test = outer_in_test;
+        self.context.pop();
+        self.enclosing_bounds = outer_bounds;
+    }
+
+    // Traverse into modules
+    fn visit_item_mod(&mut self, node: &'ast verus_syn::ItemMod) {
+        self.module_stack.push(node.ident.to_string());
+        self.context.push(format!("mod {}", node.ident));
+        let outer_in_test = self.in_test;
+        self.in_test |= is_cfg_test(&node.attrs) || (self.tests_module_heuristic && node.ident == "tests");
+        let outer_cfgs = self.enter_cfgs(&node.attrs);
+        verus_syn::visit::visit_item_mod(self, node);
+        self.leave_cfgs(outer_cfgs);
+        self.in_test = outer_in_test;
+        self.context.pop();
+        self.module_stack.pop();
+    }
+
+    // Handle verus! macro blocks (and, if enabled, other item macros) by parsing their contents
+    fn visit_item_macro(&mut self, node: &'ast ItemMacro) {
+        // Check if this is a verus! macro
+        let is_verus = is_verus_macro(&node.mac.path, &self.verus_macro_names);
+        if is_verus || self.item_macros {
+            // Try to parse the macro body as items (silently skipping macros
+            // that aren't item-shaped)
+            let parsed = verus_syn::parse2::<VerusMacroBody>(node.mac.tokens.clone());
+            if let (Err(e), true) = (&parsed, is_verus) {
+                self.skip_verus_body(&node.mac, e);
+            }
+            if let Ok(items) = parsed {
+                let outer = self.from_macro.clone();
+                let outer_in_verus = self.in_verus;
+                let outer_entry = self.verus_entry;
+                if is_verus {
+                    self.verus_entry = Some("verus_macro");
+                } else {
+                    self.from_macro = Some(macro_name(&node.mac));
+                }
+                self.in_verus |= is_verus;
+                self.context.push(format!("{}!", macro_name(&node.mac)));
+                let outer_cfgs = self.enter_cfgs(&node.attrs);
+                for item in items.items {
+                    self.visit_item(&item);
+                }
+                self.leave_cfgs(outer_cfgs);
+                self.context.pop();
+                self.from_macro = outer;
+                self.in_verus = outer_in_verus;
+                self.verus_entry = outer_entry;
+            }
+        }
+        // Continue with default traversal
+        verus_syn::visit::visit_item_macro(self, node);
+    }
+}
+
+/// Breadcrumb for an impl block: "impl Scalar" or "impl Add for Scalar"
+fn impl_label(node: &verus_syn::ItemImpl) -> String {
+    let self_ty = &node.self_ty;
+    let self_ty = render_tokens(quote::quote!(#self_ty));
+    match impl_trait_path(node) {
+        Some(trait_path) => format!("impl {} for {}", trait_path, self_ty),
+        None => format!("impl {}", self_ty),
+    }
+}
+
+/// The trait an impl block implements, as written (`!` included for a negative impl)
+fn impl_trait_path(node: &verus_syn::ItemImpl) -> Option<String> {
+    let (bang, path, _) = node.trait_.as_ref()?;
+    let bang = if bang.is_some() { "!" } else { "" };
+    Some(format!("{}{}", bang, render_tokens(quote::quote!(#path))))
+}
+
+/// Replace each `Self` token with the tokens of `self_ty`, spanned like the
+/// `Self` they replace, and report whether there was any
+///
+/// Where `Self` starts an expression or qualified path (`Self::ZERO`,
+/// `Self(x)`), generic arguments take the turbofish form (`Scalar::<T>::ZERO`)
+/// and a non-path self type is wrapped (`<[T]>::len`).
+fn substitute_self(tokens: TokenStream, self_ty: &verus_syn::Type) -> (TokenStream, bool) {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut out = Vec::with_capacity(tts.len());
+    let mut replaced = false;
+    for (i, tt) in tts.iter().enumerate() {
+        match tt {
+            TokenTree::Ident(ident) if ident == "Self" => {
+                let in_path = match tts.get(i + 1) {
+                    Some(TokenTree::Punct(p)) => p.as_char() == ':' && p.spacing() == Spacing::Joint,
+                    Some(TokenTree::Group(g)) => g.delimiter() == Delimiter::Parenthesis,
+                    _ => false,
+                };
+                out.extend(respan(type_tokens(self_ty, in_path), ident.span()));
+                replaced = true;
+            }
+            TokenTree::Group(group) => {
+                let (stream, inner) = substitute_self(group.stream(), self_ty);
+                let mut new_group = proc_macro2::Group::new(group.delimiter(), stream);
+                new_group.set_span(group.span());
+                out.push(TokenTree::Group(new_group));
+                replaced |= inner;
+            }
+            _ => out.push(tt.clone()),
+        }
+    }
+    (out.into_iter().collect(), replaced)
+}
+
+/// The tokens of `ty` standing in for a type name: at the start of an
+/// expression or qualified path (`in_path`), generic arguments take the
+/// turbofish form and a non-path type is wrapped in `<...>`
+pub(crate) fn type_tokens(ty: &verus_syn::Type, in_path: bool) -> TokenStream {
+    match ty {
+        verus_syn::Type::Path(path) if in_path && path.qself.is_none() => {
+            let mut path = path.clone();
+            if let Some(last) = path.path.segments.last_mut() {
+                if let verus_syn::PathArguments::AngleBracketed(args) = &mut last.arguments {
+                    args.colon2_token = Some(Default::default());
+                }
+            }
+            quote::quote!(#path)
+        }
+        verus_syn::Type::Path(_) => quote::quote!(#ty),
+        _ if in_path => quote::quote!(<#ty>),
+        _ => quote::quote!(#ty),
+    }
+}
+
+/// Give all tokens (nested ones included) the span `span`
+pub(crate) fn respan(tokens: TokenStream, span: proc_macro2::Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Group(group) => {
+                let mut new_group = proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                new_group.set_span(span);
+                TokenTree::Group(new_group)
+            }
+            mut tt => {
+                tt.set_span(span);
+                tt
+            }
+        })
+        .collect()
+}
+
+/// The path of a macro invocation without the `!`, e.g. "define_lemma"
+pub(crate) fn macro_name(mac: &verus_syn::Macro) -> String {
+    let segments: Vec<String> = mac.path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+    segments.join("::")
+}
+
+/// Render tokens with the spacing rustfmt would use in a signature: `a::b`,
+/// `&'a mut T`, `Seq<T>`, `x: T`, `(a, b)`, `T: A + B`, `-> R`
+pub(crate) fn render_tokens(tokens: TokenStream) -> String {
+    // Merge multi-character operators first
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut pieces: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < tts.len() {
+        let piece = match &tts[i] {
+            TokenTree::Group(group) => {
+                let inner = render_tokens(group.stream());
+                match group.delimiter() {
+                    Delimiter::Parenthesis => format!("({})", inner),
+                    Delimiter::Bracket => format!("[{}]", inner),
+                    Delimiter::Brace => format!("{{{}}}", inner),
+                    Delimiter::None => inner,
+                }
+            }
+            TokenTree::Punct(p) if p.as_char() == '\'' => match tts.get(i + 1) {
+                // A lifetime, kept as one piece
+                Some(TokenTree::Ident(ident)) => {
+                    i += 1;
+                    format!("'{}", ident)
+                }
+                _ => p.to_string(),
+            },
+            TokenTree::Punct(p) if p.spacing() == Spacing::Joint => {
+                let next = match tts.get(i + 1) {
+                    Some(TokenTree::Punct(next)) => Some(next.as_char()),
+                    _ => None,
+                };
+                match (p.as_char(), next) {
+                    (':', Some(':')) | ('-', Some('>')) | ('=', Some('>')) | ('&', Some('&'))
+                    | ('=', Some('=')) | ('!', Some('=')) | ('<', Some('=')) | ('>', Some('=')) => {
+                        i += 1;
+                        format!("{}{}", p.as_char(), next.unwrap_or_default())
+                    }
+                    _ => p.to_string(),
+                }
+            }
+            tt => tt.to_string(),
+        };
+        pieces.push(piece);
+        i += 1;
+    }
+
+    let mut out = String::new();
+    let mut prev: Option<&str> = None;
+    for piece in &pieces {
+        let cur = piece.as_str();
+        const SPACED: &[&str] = &["->", "=", "+", "=>"];
+        let space = match prev {
+            None => false,
+            Some(prev) if SPACED.contains(&prev) || SPACED.contains(&cur) => true,
+            Some(prev) if [",", ";", ":"].contains(&prev) => true,
+            Some(_) if [",", ";", ":", "::", "<", ">"].contains(&cur) => false,
+            // `&mut [T]`, `&'a (A, B)`: a slice or tuple type, not a call or index
+            Some(prev) if cur.starts_with('(') || cur.starts_with('[') => prev == "mut" || prev.starts_with('\''),
+            Some(prev) => !["::", "<", "&", "&&", "*", "'", "?", "!", "#"].contains(&prev),
+        };
+        if space {
+            out.push(' ');
+        }
+        out.push_str(cur);
+        prev = Some(cur);
+    }
+    out
+}
+
+/// Render a signature as `mode fn name<generics>(params) -> (binding: Type)`,
+/// followed by the where clause if any; visibility, publish modifiers and
+/// spec clauses are left out
+fn render_signature(sig: &Signature) -> String {
+    let mode = &sig.mode;
+    let generics = &sig.generics;
+    let mut out = render_tokens(quote::quote!(#mode));
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    out.push_str(&format!("fn {}{}", sig.ident, render_tokens(quote::quote!(#generics))));
+    let inputs: Vec<String> = sig.inputs.iter().map(|arg| render_tokens(quote::quote!(#arg))).collect();
+    out.push_str(&format!("({})", inputs.join(", ")));
+    let output = &sig.output;
+    let output = render_tokens(quote::quote!(#output));
+    if !output.is_empty() {
+        out.push(' ');
+        out.push_str(&output);
+    }
+    if let Some(where_clause) = &sig.generics.where_clause {
+        let predicates: Vec<String> =
+            where_clause.predicates.iter().map(|p| render_tokens(quote::quote!(#p))).collect();
+        if !predicates.is_empty() {
+            out.push_str(&format!(" where {}", predicates.join(", ")));
+        }
+    }
+    out
+}
+
+/// Render the parts of a signature that define its interface: mode, name,
+/// generics, parameters, return type and where clause (no spec clauses,
+/// no trailing commas)
+fn normalized_signature(sig: &Signature) -> String {
+    let mode = &sig.mode;
+    let ident = &sig.ident;
+    let generics = &sig.generics;
+    let inputs: Vec<String> = sig.inputs.iter().map(|arg| quote::quote!(#arg).to_string()).collect();
+    let output = &sig.output;
+    let where_clause = &sig.generics.where_clause;
+    format!(
+        "{} fn {} {} ({}) {} {}",
+        quote::quote!(#mode),
+        ident,
+        quote::quote!(#generics),
+        inputs.join(" , "),
+        quote::quote!(#output),
+        quote::quote!(#where_clause)
+    )
+}
+
+/// Hash the normalized signature and spec clauses of a function.
+///
+/// All inputs come from `quote!` rendering, so whitespace and comments are
+/// already normalized away. Clauses are sorted within each kind so that
+/// reordering them does not change the fingerprint.
+pub(crate) fn compute_spec_fingerprint(signature: &str, requires: &[String], ensures: &[String], decreases: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"signature\0");
+    hasher.update(signature.as_bytes());
+    for (kind, clauses) in [("requires", requires), ("ensures", ensures), ("decreases", decreases)] {
+        let mut sorted: Vec<&String> = clauses.iter().collect();
+        sorted.sort();
+        hasher.update(b"\0");
+        hasher.update(kind.as_bytes());
+        for clause in sorted {
+            hasher.update(b"\0");
+            hasher.update(clause.as_bytes());
+        }
+    }
+    to_hex(&hasher.finalize())
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Helper struct to parse verus! macro body as a list of items (top-level)
+pub(crate) struct VerusMacroBody {
+    pub(crate) items: Vec<Item>,
+}
+
+impl verus_syn::parse::Parse for VerusMacroBody {
+    fn parse(input: verus_syn::parse::ParseStream) -> verus_syn::Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(VerusMacroBody { items })
+    }
+}
+
+/// Helper struct to parse verus! macro body inside impl blocks
+pub(crate) struct VerusImplMacroBody {
+    pub(crate) items: Vec<verus_syn::ImplItem>,
+}
+
+impl verus_syn::parse::Parse for VerusImplMacroBody {
+    fn parse(input: verus_syn::parse::ParseStream) -> verus_syn::Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(VerusImplMacroBody { items })
+    }
+}
+
+/// Render a trait bound as a compact path (`View`, `vstd::view::View`, `Fn(int)->bool`, `'a`)
+pub(crate) fn bound_text(bound: &verus_syn::TypeParamBound) -> String {
+    quote::quote!(#bound).to_string().replace(' ', "")
+}
+
+/// Add `param -> bounds` entries, merging with existing entries for the same parameter
+fn add_bounds<'b>(
+    bounds: &mut Vec<(String, Vec<String>)>,
+    param: String,
+    new_bounds: impl IntoIterator<Item = &'b verus_syn::TypeParamBound>,
+) {
+    let index = match bounds.iter().position(|(name, _)| *name == param) {
+        Some(index) => index,
+        None => {
+            bounds.push((param, Vec::new()));
+            bounds.len() - 1
+        }
+    };
+    for bound in new_bounds {
+        let text = bound_text(bound);
+        if !bounds[index].1.contains(&text) {
+            bounds[index].1.push(text);
+        }
+    }
+}
+
+/// Collect the bounds of every generic type parameter, from the parameter
+/// declarations and the where clause. Where-clause predicates on other types
+/// (e.g. `Vec<T>: Foo`) are keyed by the bounded type's text.
+fn collect_generic_bounds(generics: &verus_syn::Generics, bounds: &mut Vec<(String, Vec<String>)>) {
+    for param in &generics.params {
+        if let verus_syn::GenericParam::Type(ty) = param {
+            add_bounds(bounds, ty.ident.to_string(), &ty.bounds);
+        }
+    }
+    if let Some(where_clause) = &generics.where_clause {
+        for predicate in &where_clause.predicates {
+            if let verus_syn::WherePredicate::Type(predicate) = predicate {
+                let bounded_ty = &predicate.bounded_ty;
+                let name = quote::quote!(#bounded_ty).to_string().replace(' ', "");
+                add_bounds(bounds, name, &predicate.bounds);
+            }
+        }
+    }
+}
+
+/// Name of a function mode as reported in `FunctionSpecs::mode`
+fn mode_name(mode: &FnMode) -> &'static str {
+    match mode {
+        FnMode::Spec(_) => "spec",
+        FnMode::SpecChecked(_) => "spec(checked)",
+        FnMode::Proof(_) => "proof",
+        _ => "exec",
+    }
+}
+
+/// Names of a function's parameters (`self` for receivers).
+///
+/// Works on the rendered tokens so `tracked`/`mut` prefixes and receivers
+/// are handled uniformly: the name is the last identifier before the `:`.
+pub(crate) fn param_names(sig: &Signature) -> Vec<String> {
+    sig.inputs
+        .iter()
+        .filter_map(|arg| {
+            let mut name = None;
+            for tt in quote::quote!(#arg) {
+                match tt {
+                    TokenTree::Punct(p) if p.as_char() == ':' => break,
+                    TokenTree::Ident(ident) if ident != "mut" && ident != "tracked" && ident != "ghost" => {
+                        name = Some(ident.to_string())
+                    }
+                    _ => {}
+                }
+            }
+            name
+        })
+        .collect()
+}
+
+/// The parameters of a function with their reference-ness, lifetime and
+/// slice/array shape, read from the rendered tokens like `param_names`
+fn param_info(sig: &Signature) -> Vec<ParamInfo> {
+    let mut params = Vec::new();
+    for arg in &sig.inputs {
+        let tts: Vec<TokenTree> = quote::quote!(#arg).into_iter().collect();
+        let is_colon = |i: usize| matches!(&tts[i], TokenTree::Punct(p) if p.as_char() == ':');
+        // The `:` after the name, not one of a `::` path separator
+        let colon = (0..tts.len()).find(|&i| {
+            is_colon(i) && !(i > 0 && is_colon(i - 1)) && !(i + 1 < tts.len() && is_colon(i + 1))
+        });
+        let (pattern, ty) = match colon {
+            Some(colon) => (&tts[..colon], &tts[colon + 1..]),
+            // Receiver: `self`, `&self`, `&'a mut self`
+            None => (&tts[tts.len().saturating_sub(1)..], &tts[..tts.len().saturating_sub(1)]),
+        };
+        let Some(name) = pattern.iter().rev().find_map(|tt| match tt {
+            TokenTree::Ident(ident) if ident != "mut" && ident != "tracked" && ident != "ghost" => Some(ident.to_string()),
+            _ => None,
+        }) else {
+            continue;
+        };
+
+        let mut info = ParamInfo { name, reference: "by_value".to_string(), ..Default::default() };
+        let mut rest = ty;
+        if let [TokenTree::Punct(amp), after @ ..] = rest {
+            if amp.as_char() == '&' {
+                info.reference = "&".to_string();
+                rest = after;
+                if let [TokenTree::Punct(quote), TokenTree::Ident(lifetime), after @ ..] = rest {
+                    if quote.as_char() == '\'' {
+                        info.lifetime = Some(format!("'{}", lifetime));
+                        rest = after;
+                    }
+                }
+                if let [TokenTree::Ident(kw), after @ ..] = rest {
+                    if kw == "mut" {
+                        info.reference = "&mut".to_string();
+                        rest = after;
+                    }
+                }
+            }
+        }
+        if colon.is_none() {
+            // A receiver (`mut self` included) has type `Self`
+            info.ty = "Self".to_string();
+            params.push(info);
+            continue;
+        }
+        info.ty = render_tokens(rest.iter().cloned().collect());
+        if let [TokenTree::Group(group)] = rest {
+            if group.delimiter() == Delimiter::Bracket {
+                let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+                match inner.iter().position(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ';')) {
+                    Some(semi) => {
+                        info.is_array = true;
+                        if let [TokenTree::Literal(len)] = &inner[semi + 1..] {
+                            let digits: String = len.to_string().chars().take_while(char::is_ascii_digit).collect();
+                            info.array_len = digits.parse().ok();
+                        }
+                    }
+                    None => info.is_slice = true,
+                }
+            }
+        }
+        params.push(info);
+    }
+    params
+}
+
+/// Strip parentheses and invisible groups around an expression
+pub(crate) fn strip_parens(mut expr: &Expr) -> &Expr {
+    loop {
+        match expr {
+            Expr::Paren(inner) => expr = &inner.expr,
+            Expr::Group(inner) => expr = &inner.expr,
+            _ => return expr,
+        }
+    }
+}
+
+/// Render clause expressions as token text. Each clause is written once
+/// into a reused buffer, which after the first long clause already has room
+/// for the next, and then copied out at its exact length.
+fn clause_texts<'a>(exprs: impl IntoIterator<Item = &'a Expr>) -> Vec<String> {
+    use std::fmt::Write;
+    let mut buf = String::new();
+    exprs
+        .into_iter()
+        .map(|e| {
+            let mut tokens = TokenStream::new();
+            quote::ToTokens::to_tokens(e, &mut tokens);
+            buf.clear();
+            let _ = write!(buf, "{}", tokens);
+            buf.as_str().to_owned()
+        })
+        .collect()
+}
+
+/// The expressions of a function's `requires`, `ensures` or `decreases` clause
+pub(crate) fn clause_exprs<'s>(sig: &'s Signature, kind: &str) -> Result<Vec<&'s Expr>, String> {
+    let exprs = match kind {
+        "requires" => sig.spec.requires.as_ref().map(|req| req.exprs.exprs.iter().collect()),
+        "ensures" => sig.spec.ensures.as_ref().map(|ens| ens.exprs.exprs.iter().collect()),
+        "decreases" => sig.spec.decreases.as_ref().map(|dec| dec.decreases.exprs.exprs.iter().collect()),
+        _ => return Err(format!("Unknown clause kind '{}' (expected requires, ensures or decreases)", kind)),
+    };
+    Ok(exprs.unwrap_or_default())
+}
+
+/// The head identifier of each parameter's type (`s: &Seq<int>` gives `s -> Seq`)
+pub(crate) fn param_type_heads(sig: &Signature) -> HashMap<String, String> {
+    let mut heads = HashMap::new();
+    for arg in &sig.inputs {
+        let tokens: Vec<TokenTree> = quote::quote!(#arg).into_iter().collect();
+        let Some(colon) = tokens.iter().position(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ':')) else {
+            continue;
+        };
+        let name = tokens[..colon].iter().rev().find_map(|tt| match tt {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            _ => None,
+        });
+        let head = tokens[colon + 1..].iter().find_map(|tt| match tt {
+            TokenTree::Ident(ident) if ident != "mut" && ident != "dyn" && ident != "impl" => Some(ident.to_string()),
+            _ => None,
+        });
+        if let (Some(name), Some(head)) = (name, head) {
+            heads.insert(name, head);
+        }
+    }
+    heads
+}
+
+/// Whether a macro path names `verus!` (or one of `extra_names`), matching on
+/// the final segment so `vstd::prelude::verus!` counts
+pub(crate) fn is_verus_macro(path: &verus_syn::Path, extra_names: &[String]) -> bool {
+    path.segments.last().is_some_and(|seg| seg.ident == "verus" || extra_names.iter().any(|name| seg.ident == name))
+}
+
+/// Items inside a `verus! { ... }` macro invocation, or one of `extra_names`
+/// (empty for other macros or if the body does not parse)
+pub(crate) fn verus_macro_items(mac: &verus_syn::Macro, extra_names: &[String]) -> Vec<Item> {
+    if !is_verus_macro(&mac.path, extra_names) {
+        return Vec::new();
+    }
+    verus_syn::parse2::<VerusMacroBody>(mac.tokens.clone())
+        .map(|body| body.items)
+        .unwrap_or_default()
+}
+
+/// Impl items inside a `verus! { ... }` macro invocation (or one of
+/// `extra_names`) in an impl block
+pub(crate) fn verus_impl_macro_items(mac: &verus_syn::Macro, extra_names: &[String]) -> Vec<verus_syn::ImplItem> {
+    if !is_verus_macro(&mac.path, extra_names) {
+        return Vec::new();
+    }
+    verus_syn::parse2::<VerusImplMacroBody>(mac.tokens.clone())
+        .map(|body| body.items)
+        .unwrap_or_default()
+}
+
+/// Whether the attribute is `#[verifier::<name>]` (or the older `#[verifier(<name>)]`)
+pub(crate) fn is_verifier_attr(attr: &verus_syn::Attribute, name: &str) -> bool {
+    let meta = &attr.meta;
+    let text = quote::quote!(#meta).to_string().replace(' ', "");
+    text == format!("verifier::{}", name)
+        || text.starts_with(&format!("verifier::{}(", name))
+        || text == format!("verifier({})", name)
+}
+
+/// "requires" or "ensures" for a `#[requires(...)]`/`#[ensures(...)]`
+/// attribute (also path-qualified, e.g. `#[prusti_contracts::requires(...)]`)
+fn spec_attribute_kind(attr: &verus_syn::Attribute) -> Option<&'static str> {
+    let last = attr.path().segments.last()?;
+    ["requires", "ensures"].into_iter().find(|kind| last.ident == kind)
+}
+
+/// The expression argument of a spec attribute, if it parses as one
+fn spec_attribute_expr(attr: &verus_syn::Attribute) -> Option<Expr> {
+    let list = attr.meta.require_list().ok()?;
+    verus_syn::parse2(list.tokens.clone()).ok()
+}
+
+/// The expressions of the `kind` ("requires" or "ensures") spec attributes, in order
+fn spec_attributes(attrs: &[verus_syn::Attribute], kind: &str) -> Vec<Expr> {
+    attrs.iter().filter(|a| spec_attribute_kind(a) == Some(kind)).filter_map(spec_attribute_expr).collect()
+}
+
+/// Whether a signature has the `broadcast` modifier (before `fn`)
+fn is_broadcast(sig: &Signature) -> bool {
+    has_prefix_keyword(sig, "broadcast")
+}
+
+/// Whether `keyword` (`broadcast`, `closed`, `uninterp`, ...) appears before `fn` in the signature
+pub(crate) fn has_prefix_keyword(sig: &Signature, keyword: &str) -> bool {
+    quote::quote!(#sig)
+        .into_iter()
+        .take_while(|tt| !matches!(tt, TokenTree::Ident(ident) if ident == "fn"))
+        .any(|tt| matches!(tt, TokenTree::Ident(ident) if ident == keyword))
+}
+
+/// If the path of an attribute (its tokens inside `#[...]`) matches one of
+/// `patterns` (globs over the path as written without spaces, e.g.
+/// `automatically_derived` or `verifier::*`), the reason it excludes functions
+pub(crate) fn excluding_attribute(patterns: &[String], meta: TokenStream) -> Option<String> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let path: String = meta
+        .into_iter()
+        .take_while(|tt| matches!(tt, TokenTree::Ident(_)) || matches!(tt, TokenTree::Punct(p) if p.as_char() == ':'))
+        .map(|tt| tt.to_string())
+        .collect();
+    let pattern = patterns.iter().find(|pattern| glob_match(pattern, &path))?;
+    Some(format!("attribute `{}`", pattern))
+}
+
+/// Whether `attrs` include a `#[cfg(...)]` that requires `test`
+fn is_cfg_test(attrs: &[verus_syn::Attribute]) -> bool {
+    attrs.iter().any(|a| {
+        let meta = &a.meta;
+        cfg_requires_test(quote::quote!(#meta))
+    })
+}
+
+/// The conditions of the `#[cfg(...)]` attributes in `attrs`, rendered
+fn cfg_conditions(attrs: &[verus_syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|a| {
+            let meta = &a.meta;
+            cfg_condition(quote::quote!(#meta))
+        })
+        .collect()
+}
+
+/// The condition of an attribute's tokens (inside `#[...]`) if it is a `cfg(...)`
+pub(crate) fn cfg_condition(meta: TokenStream) -> Option<String> {
+    match meta.into_iter().collect::<Vec<_>>().as_slice() {
+        [TokenTree::Ident(cfg), TokenTree::Group(args)] if cfg == "cfg" && args.delimiter() == Delimiter::Parenthesis => {
+            Some(render_tokens(args.stream()))
+        }
+        _ => None,
+    }
+}
+
+/// Whether the tokens of an attribute (`cfg(all(test, ...))`) are a cfg that
+/// only holds in test builds; `test` under `not(...)` or `any(...)` does not count
+pub(crate) fn cfg_requires_test(meta: TokenStream) -> bool {
+    fn requires_test(tokens: TokenStream) -> bool {
+        let tts: Vec<TokenTree> = tokens.into_iter().collect();
+        tts.iter().enumerate().any(|(i, tt)| match tt {
+            TokenTree::Ident(ident) if ident == "test" => true,
+            TokenTree::Ident(ident) if ident == "all" => match tts.get(i + 1) {
+                Some(TokenTree::Group(group)) => requires_test(group.stream()),
+                _ => false,
+            },
+            _ => false,
+        })
+    }
+    let tts: Vec<TokenTree> = meta.into_iter().collect();
+    match tts.as_slice() {
+        [TokenTree::Ident(cfg), TokenTree::Group(args)] if cfg == "cfg" && args.delimiter() == Delimiter::Parenthesis => {
+            requires_test(args.stream())
+        }
+        _ => false,
+    }
+}
+
+/// A `#[verifier...]` attribute as (name, argument text), the old
+/// `verifier(name)` form written as `verifier::name`; None for other
+/// attributes. Attributes this crate does not know are handled like any
+/// other: the arguments are the attribute's tokens as written (string
+/// literals untouched), or the value of a `verifier::name = value` attribute.
+fn verifier_attribute(attr: &verus_syn::Attribute) -> Option<(String, Option<String>)> {
+    if attr.path().segments.first()?.ident != "verifier" {
+        return None;
+    }
+    let path_text = |path: &verus_syn::Path| {
+        path.segments.iter().map(|seg| seg.ident.to_string()).collect::<Vec<_>>().join("::")
+    };
+    Some(match &attr.meta {
+        verus_syn::Meta::Path(path) => (path_text(path), None),
+        verus_syn::Meta::NameValue(nv) => {
+            let value = &nv.value;
+            (path_text(&nv.path), Some(render_tokens(quote::quote!(#value))))
+        }
+        verus_syn::Meta::List(list) if list.path.is_ident("verifier") => {
+            // `verifier(name)` or `verifier(name(args))`
+            let tts: Vec<TokenTree> = list.tokens.clone().into_iter().collect();
+            match tts.as_slice() {
+                [TokenTree::Ident(name)] => (format!("verifier::{}", name), None),
+                [TokenTree::Ident(name), TokenTree::Group(args)] => {
+                    (format!("verifier::{}", name), Some(render_tokens(args.stream())))
+                }
+                _ => ("verifier".to_string(), Some(render_tokens(list.tokens.clone()))),
+            }
+        }
+        verus_syn::Meta::List(list) => (path_text(&list.path), Some(render_tokens(list.tokens.clone()))),
+    })
+}
+
+/// The `#[verifier...]` attributes in `attrs` as (name, argument text)
+fn verifier_attribute_parts(attrs: &[verus_syn::Attribute]) -> Vec<(String, Option<String>)> {
+    attrs.iter().filter_map(verifier_attribute).collect()
+}
+
+/// The `#[verifier...]` attributes in `attrs` as text: `name`, `name(args)`
+/// or `name = value`
+pub(crate) fn verifier_attributes(attrs: &[verus_syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            let (name, args) = verifier_attribute(attr)?;
+            Some(match (args, &attr.meta) {
+                (None, _) => name,
+                (Some(value), verus_syn::Meta::NameValue(_)) => format!("{} = {}", name, value),
+                (Some(args), _) => format!("{}({})", name, args),
+            })
+        })
+        .collect()
+}
+
+/// The built-in lints, by the name used to suppress them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lint {
+    /// `find_unused_lemmas`
+    UnusedLemma,
+    /// `lint_trivial_lemmas`
+    TrivialLemma,
+    /// `specs_outside_verus`
+    SpecsOutsideVerus,
+    /// `spec_visibility_lint`
+    SpecVisibility,
+    /// `lint_spec_exprs`
+    SpecExpr,
+    /// `refinement_lint`
+    Refinement,
+}
+
+impl Lint {
+    pub(crate) const ALL: [Lint; 6] = [
+        Lint::UnusedLemma,
+        Lint::TrivialLemma,
+        Lint::SpecsOutsideVerus,
+        Lint::SpecVisibility,
+        Lint::SpecExpr,
+        Lint::Refinement,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedLemma => "unused_lemma",
+            Lint::TrivialLemma => "trivial_lemma",
+            Lint::SpecsOutsideVerus => "specs_outside_verus",
+            Lint::SpecVisibility => "spec_visibility",
+            Lint::SpecExpr => "spec_expr",
+            Lint::Refinement => "refinement",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+/// The built-in lint names in the `allow(...)` lists of some tokens (others are ignored)
+pub(crate) fn allowed_lints(tokens: TokenStream, lints: &mut Vec<String>) {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    for pair in tts.windows(2) {
+        if let (TokenTree::Ident(ident), TokenTree::Group(group)) = (&pair[0], &pair[1]) {
+            if ident != "allow" || group.delimiter() != Delimiter::Parenthesis {
+                continue;
+            }
+            for tt in group.stream() {
+                let name = tt.to_string();
+                if Lint::from_name(&name).is_some() && !lints.contains(&name) {
+                    lints.push(name);
+                }
+            }
+        }
+    }
+}
+
+/// Lints suppressed by `#[cfg_attr(any(), allow(...))]` attributes, which
+/// rustc and Verus never apply
+fn attribute_suppressions(attrs: &[verus_syn::Attribute]) -> Vec<String> {
+    let mut lints = Vec::new();
+    for attr in attrs {
+        let verus_syn::Meta::List(list) = &attr.meta else { continue };
+        if !list.path.is_ident("cfg_attr") {
+            continue;
+        }
+        let tts: Vec<TokenTree> = list.tokens.clone().into_iter().collect();
+        let Some(comma) = tts.iter().position(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ',')) else {
+            continue;
+        };
+        if render_tokens(tts[..comma].iter().cloned().collect()) == "any()" {
+            allowed_lints(tts[comma + 1..].iter().cloned().collect(), &mut lints);
+        }
+    }
+    lints
+}
+
+/// Collects the names an expression may refer to in the same file: single-segment
+/// paths, the last segment of `crate::`/`self::`/`super::`/`Self::` paths, and
+/// method names
+#[derive(Default)]
+pub(crate) struct ReferencedNames {
+    pub(crate) names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for ReferencedNames {
+    fn visit_expr_path(&mut self, node: &'ast verus_syn::ExprPath) {
+        let segments = &node.path.segments;
+        let local = segments.len() == 1
+            || segments.first().is_some_and(|seg| ["crate", "self", "super", "Self"].iter().any(|s| seg.ident == s));
+        if let (true, Some(last)) = (local, segments.last()) {
+            self.names.push(last.ident.to_string());
+        }
+        verus_syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast verus_syn::ExprMethodCall) {
+        self.names.push(node.method.to_string());
+        verus_syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// The names a closure (or quantifier) binds
+pub(crate) fn closure_param_names(node: &verus_syn::ExprClosure) -> Vec<String> {
+    let mut names = Vec::new();
+    for input in &node.inputs {
+        for tt in quote::quote!(#input) {
+            match tt {
+                TokenTree::Ident(ident) => names.push(ident.to_string()),
+                // Only the names, not the type after `:`
+                TokenTree::Punct(p) if p.as_char() == ':' => break,
+                _ => {}
+            }
+        }
+    }
+    names
+}
+
+/// Free-name analysis of a clause: which names are the function's locals
+/// (parameters, return binding) and which come from outside. Closure
+/// parameters (quantifier variables) are bound within their body.
+struct FreeNames<'a> {
+    locals: &'a [String],
+    bound: Vec<String>,
+    mentions: ClauseMentions,
+}
+
+impl FreeNames<'_> {
+    fn record(&mut self, name: String, external: bool) {
+        let list = if external { &mut self.mentions.externals } else { &mut self.mentions.params };
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+
+    /// Whether an expression uses a local or bound variable
+    fn uses_variables(&self, expr: &Expr) -> bool {
+        referenced_names(expr).iter().any(|name| self.locals.contains(name) || self.bound.contains(name))
+    }
+}
+
+impl<'ast> Visit<'ast> for FreeNames<'_> {
+    fn visit_expr_closure(&mut self, node: &'ast verus_syn::ExprClosure) {
+        let outer = self.bound.len();
+        self.bound.extend(closure_param_names(node));
+        self.visit_expr(&node.body);
+        self.bound.truncate(outer);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast verus_syn::ExprCall) {
+        let applied = node.args.iter().any(|arg| self.uses_variables(arg));
+        match strip_parens(&node.func) {
+            // A spec fn applied to variables depends on them, not on global state
+            Expr::Path(_) if applied => {}
+            func => self.visit_expr(func),
+        }
+        for arg in &node.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast verus_syn::ExprPath) {
+        let path = &node.path;
+        let name = render_tokens(quote::quote!(#path));
+        if self.bound.contains(&name) || BUILTIN_CALLS.contains(&name.as_str()) || name == "None" {
+            return;
+        }
+        let external = !self.locals.contains(&name);
+        self.record(name, external);
+    }
+}
+
+/// The names a function's clauses may use without them being free: the
+/// parameters (`self` included) and the return value binding
+pub(crate) fn clause_locals(sig: &Signature) -> Vec<String> {
+    let mut locals = param_names(sig);
+    locals.extend(return_binding(sig));
+    locals
+}
+
+/// The locals and free names a clause refers to
+pub(crate) fn clause_mentions(expr: &Expr, locals: &[String]) -> ClauseMentions {
+    let mut finder = FreeNames { locals, bound: Vec::new(), mentions: ClauseMentions::default() };
+    finder.visit_expr(expr);
+    finder.mentions
+}
+
+/// Where a function header ends: the end of the last token of the signature
+/// or of its spec clauses, whichever comes later
+fn header_end(sig: &Signature) -> proc_macro2::LineColumn {
+    let mut end = sig.ident.span().end();
+    let mut extend = |span: proc_macro2::Span| {
+        let span_end = span.end();
+        if (span_end.line, span_end.column) > (end.line, end.column) {
+            end = span_end;
+        }
+    };
+    for tt in quote::quote!(#sig) {
+        extend(tt.span());
+    }
+    for kind in ["requires", "ensures", "decreases"] {
+        for expr in clause_exprs(sig, kind).unwrap_or_default() {
+            extend(expr.span());
+        }
+    }
+    end
+}
+
+/// Classify the `decreases` measure of a function (None without one)
+fn decreases_measure(sig: &Signature) -> Option<DecreasesMeasure> {
+    let exprs: Vec<&Expr> = sig.spec.decreases.as_ref()?.decreases.exprs.exprs.iter().collect();
+    let locals = param_names(sig);
+    let mut params: Vec<String> = Vec::new();
+    for expr in &exprs {
+        for name in clause_mentions(expr, &locals).params {
+            if !params.contains(&name) {
+                params.push(name);
+            }
+        }
+    }
+    let is_param = |expr: &Expr| match strip_parens(expr) {
+        Expr::Path(path) => path.qself.is_none() && path.path.get_ident().is_some_and(|i| locals.contains(&i.to_string())),
+        _ => false,
+    };
+    let kind = match exprs.as_slice() {
+        [_, _, ..] => "lexicographic",
+        [expr] if is_param(expr) => "single_var",
+        [expr] => match strip_parens(expr) {
+            Expr::MethodCall(call) if is_param(&call.receiver) => "call_on_param",
+            Expr::Call(call) if call.args.iter().any(is_param) => "call_on_param",
+            _ => "other",
+        },
+        [] => "other",
+    };
+    Some(DecreasesMeasure { kind: kind.to_string(), params, components: exprs.len() })
+}
+
+/// The name bound to the return value (`r` in `-> (r: T)`), if any, and the
+/// tokens of the return type; None when the function returns `()`
+pub(crate) fn return_parts(sig: &Signature) -> Option<(Option<String>, TokenStream)> {
+    fn skip_tracked(tts: &[TokenTree]) -> &[TokenTree] {
+        match tts {
+            [TokenTree::Ident(tracked), rest @ ..] if tracked == "tracked" => rest,
+            _ => tts,
+        }
+    }
+    let output = &sig.output;
+    let tts: Vec<TokenTree> = quote::quote!(#output).into_iter().collect();
+    let rest = match tts.as_slice() {
+        [TokenTree::Punct(dash), TokenTree::Punct(gt), rest @ ..] if dash.as_char() == '-' && gt.as_char() == '>' => {
+            skip_tracked(rest)
+        }
+        _ => return None,
+    };
+    if let [TokenTree::Group(group)] = rest {
+        if group.delimiter() == Delimiter::Parenthesis {
+            let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+            if let [TokenTree::Ident(name), TokenTree::Punct(colon), ty @ ..] = skip_tracked(&inner) {
+                let is_path = matches!(ty.first(), Some(TokenTree::Punct(p)) if p.as_char() == ':');
+                if colon.as_char() == ':' && !is_path {
+                    return Some((Some(name.to_string()), ty.iter().cloned().collect()));
+                }
+            }
+        }
+    }
+    Some((None, rest.iter().cloned().collect()))
+}
+
+/// The name bound to the return value (`r` in `-> (r: T)`), if any
+pub(crate) fn return_binding(sig: &Signature) -> Option<String> {
+    return_parts(sig)?.0
+}
+
+/// The return binding and type of a function, with tuple types split into elements
+fn return_info(sig: &Signature) -> Option<ReturnInfo> {
+    let (binding, ty) = return_parts(sig)?;
+    let elements = match verus_syn::parse2::<verus_syn::Type>(ty.clone()) {
+        Ok(verus_syn::Type::Tuple(tuple)) => tuple
+            .elems
+            .iter()
+            .enumerate()
+            .map(|(index, elem)| TupleElement {
+                index,
+                ty: render_tokens(quote::quote!(#elem)),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Some(ReturnInfo {
+        binding,
+        ty: render_tokens(ty),
+        elements,
+    })
+}
+
+/// The `::`-joined path of a call's callee (without generic arguments), if it is a path
+pub(crate) fn call_path(call: &verus_syn::ExprCall) -> Option<String> {
+    match strip_parens(&call.func) {
+        Expr::Path(path) => {
+            let segments: Vec<String> = path.path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+            Some(segments.join("::"))
+        }
+        _ => None,
+    }
+}
+
+/// Whether an expression is a `proof { ... }` block.
+///
+/// Checked on tokens so it does not depend on how verus_syn represents ghost
+/// blocks; only expression kinds that can hold one are rendered.
+pub(crate) fn is_proof_block(expr: &Expr) -> bool {
+    match expr {
+        Expr::Unary(_) | Expr::Verbatim(_) => {}
+        Expr::Array(_)
+        | Expr::Assign(_)
+        | Expr::Binary(_)
+        | Expr::Block(_)
+        | Expr::Call(_)
+        | Expr::Cast(_)
+        | Expr::Closure(_)
+        | Expr::Field(_)
+        | Expr::ForLoop(_)
+        | Expr::If(_)
+        | Expr::Index(_)
+        | Expr::Lit(_)
+        | Expr::Loop(_)
+        | Expr::Macro(_)
+        | Expr::Match(_)
+        | Expr::MethodCall(_)
+        | Expr::Paren(_)
+        | Expr::Path(_)
+        | Expr::Reference(_)
+        | Expr::Return(_)
+        | Expr::Struct(_)
+        | Expr::Tuple(_)
+        | Expr::While(_) => return false,
+        _ => {}
+    }
+    let mut tokens = quote::quote!(#expr).into_iter();
+    matches!(tokens.next(), Some(TokenTree::Ident(ident)) if ident == "proof")
+        && matches!(tokens.next(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace)
+}
+
+/// Collects the paths called within an expression
+#[derive(Default)]
+struct CalledPaths {
+    paths: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for CalledPaths {
+    fn visit_expr_call(&mut self, node: &'ast verus_syn::ExprCall) {
+        self.paths.extend(call_path(node));
+        verus_syn::visit::visit_expr_call(self, node);
+    }
+}
+
+/// Body visitor collecting `proof { ... }` blocks, including ones nested in
+/// loops, matches and other blocks
+#[derive(Default)]
+struct ProofBlockFinder {
+    blocks: Vec<ProofBlock>,
+}
+
+impl<'ast> Visit<'ast> for ProofBlockFinder {
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        if is_proof_block(node) {
+            let span = node.span();
+            let mut calls = CalledPaths::default();
+            calls.visit_expr(node);
+            self.blocks.push(ProofBlock {
+                line_number: Some(span.start().line),
+                end_line: Some(span.end().line),
+                calls: calls.paths,
+            });
+            return;
+        }
+        verus_syn::visit::visit_expr(self, node);
+    }
+
+    // Nested items are separate functions
+    fn visit_item(&mut self, _node: &'ast Item) {}
+}
+
+/// All `proof { ... }` blocks in a function body
+fn collect_proof_blocks(block: &verus_syn::Block) -> Vec<ProofBlock> {
+    let mut finder = ProofBlockFinder::default();
+    finder.visit_block(block);
+    finder.blocks
+}
+
+/// Counts statements, including those of nested blocks
+#[derive(Default)]
+struct StmtCounter {
+    count: usize,
+}
+
+impl<'ast> Visit<'ast> for StmtCounter {
+    fn visit_stmt(&mut self, node: &'ast verus_syn::Stmt) {
+        self.count += 1;
+        verus_syn::visit::visit_stmt(self, node);
+    }
+}
+
+/// Fill in what is known from a function's body: proof blocks, asserts, size
+/// and (for exec functions) integer casts
+fn add_body_facts(specs: &mut FunctionSpecs, sig: &Signature, block: &verus_syn::Block) {
+    specs.proof_blocks = collect_proof_blocks(block);
+    specs.asserts = collect_asserts(block);
+    let mut counter = StmtCounter::default();
+    counter.visit_block(block);
+    specs.body_stmt_count = Some(counter.count);
+    specs.is_trivial_proof = specs.is_proof && counter.count == 0;
+    if specs.mode == "exec" {
+        let mut finder = CastFinder { var_types: param_type_heads(sig), ..Default::default() };
+        finder.visit_block(block);
+        specs.casts = finder.casts;
+    }
+}
+
+/// Signedness and possible widths in bits of a fixed-width integer type
+/// (`usize`/`isize` may be 32 or 64 bits wide)
+pub(crate) fn int_type_info(ty: &str) -> Option<(bool, u32, u32)> {
+    match ty {
+        "usize" => Some((false, 32, 64)),
+        "isize" => Some((true, 32, 64)),
+        _ => {
+            let signed = ty.starts_with('i');
+            let bits: u32 = ty.strip_prefix(['u', 'i'])?.parse().ok()?;
+            [8, 16, 32, 64, 128].contains(&bits).then_some((signed, bits, bits))
+        }
+    }
+}
+
+/// Kind of an unannotated cast: "widening" if every `from` value fits in `to`,
+/// "narrowing" if some may not, "unknown" without a source type
+pub(crate) fn cast_kind(from: Option<&str>, to: &str) -> &'static str {
+    let (Some((from_signed, _, from_max)), Some((to_signed, to_min, _))) = (from.and_then(int_type_info), int_type_info(to))
+    else {
+        return "unknown";
+    };
+    let fits = match (from_signed, to_signed) {
+        (false, false) | (true, true) => to_min >= from_max,
+        (false, true) => to_min > from_max,
+        (true, false) => false,
+    };
+    if fits {
+        "widening"
+    } else {
+        "narrowing"
+    }
+}
+
+/// Collects `as` casts to integer types, with the source type when it is a
+/// parameter, a typed `let`, a suffixed literal or another cast
+#[derive(Default)]
+struct CastFinder {
+    /// Declared type head of each known variable
+    var_types: HashMap<String, String>,
+    /// Set while visiting the operand of `#[verifier::truncate]`
+    truncating: bool,
+    casts: Vec<CastInfo>,
+}
+
+impl CastFinder {
+    fn source_type(&self, expr: &Expr) -> Option<String> {
+        match strip_parens(expr) {
+            Expr::Path(path) => path.path.get_ident().and_then(|ident| self.var_types.get(&ident.to_string()).cloned()),
+            Expr::Lit(verus_syn::ExprLit { lit: verus_syn::Lit::Int(int), .. }) => {
+                Some(int.suffix().to_string()).filter(|suffix| !suffix.is_empty())
+            }
+            Expr::Cast(cast) => {
+                let ty = &cast.ty;
+                Some(quote::quote!(#ty).to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for CastFinder {
+    fn visit_local(&mut self, node: &'ast verus_syn::Local) {
+        if let verus_syn::Pat::Type(pat_type) = &node.pat {
+            if let verus_syn::Pat::Ident(ident) = &*pat_type.pat {
+                let ty = &pat_type.ty;
+                self.var_types.insert(ident.ident.to_string(), quote::quote!(#ty).to_string());
+            }
+        }
+        verus_syn::visit::visit_local(self, node);
+    }
+
+    fn visit_expr_paren(&mut self, node: &'ast verus_syn::ExprParen) {
+        let outer = self.truncating;
+        self.truncating |= node.attrs.iter().any(|attr| is_verifier_attr(attr, "truncate"));
+        verus_syn::visit::visit_expr_paren(self, node);
+        self.truncating = outer;
+    }
+
+    fn visit_expr_cast(&mut self, node: &'ast verus_syn::ExprCast) {
+        let ty = &node.ty;
+        let to_ty = quote::quote!(#ty).to_string();
+        let truncate = self.truncating || node.attrs.iter().any(|attr| is_verifier_attr(attr, "truncate"));
+        if int_type_info(&to_ty).is_some() {
+            let from_ty = self.source_type(&node.expr);
+            let kind = if truncate { "truncate" } else { cast_kind(from_ty.as_deref(), &to_ty) };
+            self.casts.push(CastInfo {
+                line_number: Some(node.span().start().line),
+                expr: quote::quote!(#node).to_string(),
+                from_ty,
+                to_ty,
+                kind: kind.to_string(),
+            });
+        }
+        // Only the outermost cast is annotated
+        let outer = std::mem::replace(&mut self.truncating, false);
+        verus_syn::visit::visit_expr_cast(self, node);
+        self.truncating = outer;
+    }
+}
+
+/// The comma-separated parts of a token stream (commas inside groups do not
+/// separate; a trailing comma adds no empty part)
+fn split_top_level_commas(tokens: TokenStream) -> Vec<TokenStream> {
+    let mut parts = vec![TokenStream::new()];
+    for tt in tokens {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == ',' => parts.push(TokenStream::new()),
+            tt => parts.last_mut().unwrap().extend([tt]),
+        }
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Collect the `assert(...)` statements and `assert!(...)` calls in a block
+/// with their string literal arguments and the prover of their `by (...)`
+/// clause, if any
+fn collect_asserts(block: &verus_syn::Block) -> Vec<AssertInfo> {
+    fn scan(tokens: TokenStream, asserts: &mut Vec<AssertInfo>) {
+        let tts: Vec<TokenTree> = tokens.into_iter().collect();
+        for (i, tt) in tts.iter().enumerate() {
+            match tt {
+                TokenTree::Ident(ident) if ident == "assert" => {
+                    let bang = matches!(tts.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '!');
+                    let Some(TokenTree::Group(args)) = tts.get(i + 1 + usize::from(bang)) else {
+                        continue;
+                    };
+                    if args.delimiter() != Delimiter::Parenthesis {
+                        continue;
+                    }
+                    let prover = match (tts.get(i + 2), tts.get(i + 3)) {
+                        (Some(TokenTree::Ident(by)), Some(TokenTree::Group(prover)))
+                            if !bang && by == "by" && prover.delimiter() == Delimiter::Parenthesis =>
+                        {
+                            Some(prover)
+                        }
+                        _ => None,
+                    };
+                    // The condition comes first; after it, `assert!` takes a
+                    // message, possibly a format string with its arguments
+                    let mut parts = split_top_level_commas(args.stream()).into_iter();
+                    let expr = parts.next().map(|first| first.to_string()).unwrap_or_default();
+                    let labels = parts
+                        .filter_map(|arg| verus_syn::parse2::<verus_syn::LitStr>(arg).ok())
+                        .map(|lit| lit.value())
+                        .collect();
+                    asserts.push(AssertInfo {
+                        line_number: Some(ident.span().start().line),
+                        end_line: Some(prover.unwrap_or(args).span().end().line),
+                        expr,
+                        prover: prover.map(|prover| prover.stream().to_string()),
+                        labels,
+                        comment: None,
+                    });
+                }
+                TokenTree::Group(group) => scan(group.stream(), asserts),
+                _ => {}
+            }
+        }
+    }
+    let mut asserts = Vec::new();
+    scan(quote::quote!(#block), &mut asserts);
+    asserts
+}
+
+/// Calls that are constructors or Verus builtins rather than lemma invocations
+pub(crate) const BUILTIN_CALLS: &[&str] = &["Some", "Ok", "Err", "old"];
+
+/// Names referenced by an expression (see `ReferencedNames`), deduplicated in order
+pub(crate) fn referenced_names(expr: &Expr) -> Vec<String> {
+    let mut referenced = ReferencedNames::default();
+    referenced.visit_expr(expr);
+    let mut names: Vec<String> = Vec::new();
+    for name in referenced.names {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{GENERIC_LEMMAS, SAMPLE_VERUS};
+
+    #[test]
+    fn test_parse_sample() {
+        let result = parse_verus_file_core(SAMPLE_VERUS);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let funcs = result.unwrap();
+        assert_eq!(funcs.len(), 2, "Should find 2 functions");
+        assert!(funcs.iter().all(|f| f.provenance == "top_level"));
+        println!("Found {} functions", funcs.len());
+        for f in &funcs {
+            println!("  - {} (proof={}, lines {:?}-{:?})", f.name, f.is_proof, f.line_number, f.end_line);
+        }
+    }
+
+    #[test]
+    fn test_with_verus_macro() {
+        let code = r#"
+verus! {
+    fn simple_fn(x: u32) -> u32
+        requires x > 0,
+        ensures result == x,
+    {
+        x
+    }
+    
+    proof fn lemma_foo()
+        ensures true,
+    {
+    }
+}
+"#;
+        let result = parse_verus_file_core(code);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let funcs = result.unwrap();
+        assert_eq!(funcs.len(), 2, "Should find 2 functions inside verus! macro");
+        
+        let names: Vec<_> = funcs.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"simple_fn"));
+        assert!(names.contains(&"lemma_foo"));
+        assert!(funcs.iter().all(|f| f.provenance == "verus_macro"));
+        
+        println!("With verus! macro:");
+        for f in &funcs {
+            println!("  - {} (proof={}, requires={:?})", f.name, f.is_proof, f.requires);
+        }
+    }
+
+    #[test]
+    fn test_impl_block() {
+        let code = r#"
+struct Foo {}
+
+impl Foo {
+    fn method_one(&self) -> u32
+        requires true,
+        ensures result > 0,
+    {
+        1
+    }
+    
+    proof fn lemma_method(&self)
+        ensures true,
+    {
+    }
+}
+"#;
+        let result = parse_verus_file_core(code);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let funcs = result.unwrap();
+        assert_eq!(funcs.len(), 2, "Should find 2 methods in impl block");
+        
+        let names: Vec<_> = funcs.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"method_one"));
+        assert!(names.contains(&"lemma_method"));
+        assert!(funcs.iter().all(|f| f.provenance == "impl"));
+        
+        println!("Impl block methods:");
+        for f in &funcs {
+            println!("  - {} (proof={})", f.name, f.is_proof);
+        }
+    }
+
+    #[test]
+    fn test_verus_macro_with_impl() {
+        let code = r#"
+verus! {
+    struct Bar {}
+    
+    impl Bar {
+        proof fn bar_lemma(&self)
+            requires true,
+            ensures true,
+        {
+        }
+    }
+}
+"#;
+        let result = parse_verus_file_core(code);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let funcs = result.unwrap();
+        assert_eq!(funcs.len(), 1, "Should find 1 method in impl inside verus!");
+        assert_eq!(funcs[0].name, "bar_lemma");
+        assert!(funcs[0].is_proof);
+        assert_eq!(funcs[0].provenance, "verus_macro");
+        
+        println!("verus! with impl:");
+        for f in &funcs {
+            println!("  - {} (proof={})", f.name, f.is_proof);
+        }
+    }
+    
+    #[test]
+    fn test_verus_inside_impl() {
+        // This tests the case where verus! is INSIDE an impl block (not around it)
+        // This is common in real Verus code like curve25519-dalek
+        let code = r#"
+struct Scalar {}
+
+impl Scalar {
+    verus! {
+        pub fn from_bytes_mod_order(bytes: [u8; 32]) -> (result: Scalar)
+            ensures 
+                result.bytes[31] & 0x80 == 0,
+        {
+            Scalar {}
+        }
+        
+        proof fn internal_lemma()
+            requires true,
+            ensures true,
+        {
+        }
+    }
+    
+    // Regular non-verus method
+    fn regular_method(&self) {}
+}
+"#;
+        let result = parse_verus_file_core(code);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let funcs = result.unwrap();
+        
+        println!("verus! INSIDE impl block:");
+        for f in &funcs {
+            println!("  - {} (proof={}, ensures={:?})", f.name, f.is_proof, f.ensures);
+        }
+        
+        // Should find: from_bytes_mod_order, internal_lemma, regular_method
+        assert!(funcs.len() >= 2, "Should find at least 2 functions inside verus! in impl");
+        
+        let names: Vec<_> = funcs.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"from_bytes_mod_order"), "Should find from_bytes_mod_order");
+        assert!(names.contains(&"internal_lemma"), "Should find internal_lemma");
+        
+        // Check that ensures was extracted
+        let from_bytes = funcs.iter().find(|f| f.name == "from_bytes_mod_order").unwrap();
+        assert!(!from_bytes.ensures.is_empty(), "from_bytes_mod_order should have ensures");
+        assert_eq!(from_bytes.path, vec!["crate", "impl Scalar", "verus!", "fn from_bytes_mod_order"]);
+        assert_eq!(from_bytes.provenance, "verus_macro_in_impl");
+        let regular = funcs.iter().find(|f| f.name == "regular_method").unwrap();
+        assert_eq!(regular.path, vec!["crate", "impl Scalar", "fn regular_method"]);
+        assert_eq!(regular.provenance, "impl");
+    }
+
+    #[test]
+    fn test_bounds_inline_where_and_impl() {
+        let funcs = parse_verus_file_core(GENERIC_LEMMAS).unwrap();
+        let bounds_of = |name: &str| funcs.iter().find(|f| f.name == name).unwrap().bounds.clone();
+        let owned = |pairs: &[(&str, &[&str])]| -> Vec<(String, Vec<String>)> {
+            pairs
+                .iter()
+                .map(|(p, bs)| (p.to_string(), bs.iter().map(|b| b.to_string()).collect()))
+                .collect()
+        };
+
+        assert_eq!(bounds_of("lemma_inline"), owned(&[("T", &["View", "Clone"])]));
+        assert_eq!(
+            bounds_of("lemma_where"),
+            owned(&[("T", &["SpecOrd"]), ("U", &["vstd::view::View"])])
+        );
+        assert_eq!(
+            bounds_of("lemma_method"),
+            owned(&[("A", &["View"]), ("B", &["SpecOrd"])])
+        );
+    }
+
+    #[test]
+    fn test_proof_blocks_in_exec_fn() {
+        let code = r#"
+verus! {
+
+fn sum(n: u64) -> u64 {
+    let mut i = 0;
+    proof {
+        lemma_start(n as int);
+    }
+    while i < n
+        invariant i <= n,
+    {
+        i = i + 1;
+        proof {
+            vstd::arithmetic::mul::lemma_mul_is_commutative(i as int, 2);
+            lemma_step(i as int);
+        }
+    }
+    i
+}
+
+fn no_ghost_code(x: u64) -> u64 {
+    x
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let sum = funcs.iter().find(|f| f.name == "sum").unwrap();
+        assert_eq!(
+            sum.proof_blocks,
+            vec![
+                ProofBlock {
+                    line_number: Some(6),
+                    end_line: Some(8),
+                    calls: vec!["lemma_start".to_string()],
+                },
+                ProofBlock {
+                    line_number: Some(13),
+                    end_line: Some(16),
+                    calls: vec![
+                        "vstd::arithmetic::mul::lemma_mul_is_commutative".to_string(),
+                        "lemma_step".to_string()
+                    ],
+                },
+            ]
+        );
+        let plain = funcs.iter().find(|f| f.name == "no_ghost_code").unwrap();
+        assert!(plain.proof_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_render_signature() {
+        let code = r#"
+verus! {
+
+pub proof fn lemma_bounded<T: View + Clone>(x: int, s: Seq<T>) -> (r: bool)
+    requires
+        x > 0,
+    ensures
+        r ==> s.len() > 0,
+{
+    true
+}
+
+pub open spec fn open_double(x: int) -> int {
+    2 * x
+}
+
+pub closed spec(checked) fn closed_half(x: int) -> int
+    recommends x % 2 == 0,
+{
+    x / 2
+}
+
+struct Buf<'a> { data: &'a mut Vec<u8> }
+
+impl<'a> Buf<'a> {
+    fn get(&self, i: usize) -> (v: &u8)
+        requires i < 10,
+    {
+        &self.data[i]
+    }
+
+    exec fn push(&mut self, b: u8) {}
+}
+
+proof fn take_token(tracked t: Tracked<u8>, ghost_len: Ghost<nat>) -> (tracked out: Tracked<u8>)
+    ensures out == t,
+{
+    t
+}
+
+fn pair<A, B>(a: A, b: (B, [u8; 4])) -> (A, B) where A: Copy, B: Into<u64> {
+    (a, b)
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let signature = |name: &str| funcs.iter().find(|f| f.name == name).unwrap().signature.clone();
+        assert_eq!(
+            signature("lemma_bounded"),
+            "proof fn lemma_bounded<T: View + Clone>(x: int, s: Seq<T>) -> (r: bool)"
+        );
+        assert_eq!(signature("open_double"), "spec fn open_double(x: int) -> int");
+        assert_eq!(signature("closed_half"), "spec(checked) fn closed_half(x: int) -> int");
+        assert_eq!(signature("get"), "fn get(&self, i: usize) -> (v: &u8)");
+        assert_eq!(signature("push"), "exec fn push(&mut self, b: u8)");
+        assert_eq!(
+            signature("take_token"),
+            "proof fn take_token(tracked t: Tracked<u8>, ghost_len: Ghost<nat>) -> (tracked out: Tracked<u8>)"
+        );
+        assert_eq!(signature("pair"), "fn pair<A, B>(a: A, b: (B, [u8; 4])) -> (A, B) where A: Copy, B: Into<u64>");
+
+        let raw = &funcs.iter().find(|f| f.name == "lemma_bounded").unwrap().signature_raw;
+        assert!(raw.starts_with("proof fn lemma_bounded"));
+    }
+
+    #[test]
+    fn test_function_paths() {
+        let code = r#"
+verus! {
+
+mod arithmetic {
+    mod mul {
+        proof fn lemma_mul_pos(a: int, b: int)
+            requires a > 0, b > 0,
+            ensures a * b > 0,
+        {
+        }
+    }
+
+    impl Add for Scalar {
+        fn add(self, other: Scalar) -> Scalar {
+            fn helper() {}
+            self
+        }
+    }
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let path = |name: &str| funcs.iter().find(|f| f.name == name).unwrap().path.clone();
+        assert_eq!(path("lemma_mul_pos"), vec!["crate", "verus!", "mod arithmetic", "mod mul", "fn lemma_mul_pos"]);
+        assert_eq!(path("add"), vec!["crate", "verus!", "mod arithmetic", "impl Add for Scalar", "fn add"]);
+        assert_eq!(path("helper"), vec!["crate", "verus!", "mod arithmetic", "impl Add for Scalar", "fn add", "fn helper"]);
+    }
+
+    #[test]
+    fn test_checked_spec_recommends() {
+        let code = r#"
+verus! {
+
+spec fn half(x: int) -> int
+    recommends x % 2 == 0,
+{
+    x / 2
+}
+
+spec(checked) fn checked_half(x: int) -> int
+    recommends
+        x % 2 == 0,
+        x >= 0,
+{
+    x / 2
+}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let checked: Vec<_> = funcs.iter().filter(|f| f.mode == "spec(checked)").collect();
+        assert_eq!(checked.len(), 1);
+        assert_eq!(checked[0].name, "checked_half");
+        assert_eq!(checked[0].recommends, vec!["x % 2 == 0", "x >= 0"]);
+        let half = funcs.iter().find(|f| f.name == "half").unwrap();
+        assert_eq!(half.mode, "spec");
+        assert_eq!(half.recommends, vec!["x % 2 == 0"]);
+    }
+
+    #[test]
+    fn test_return_info() {
+        let code = r#"
+verus! {
+
+fn split(x: usize) -> (r: (usize, bool))
+    ensures
+        r.0 <= x,
+        r.1 == (x > 0),
+{
+    (x, x > 0)
+}
+
+fn plain(x: u32) -> u32 {
+    x
+}
+
+fn pair() -> (Seq<int>, u8) {
+    (Seq::empty(), 0)
+}
+
+fn unit() {}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let split = &funcs[0];
+        let returns = split.returns.as_ref().unwrap();
+        assert_eq!(returns.binding.as_deref(), Some("r"));
+        assert_eq!(returns.ty, "(usize, bool)");
+        let elements: Vec<(usize, &str)> = returns.elements.iter().map(|e| (e.index, e.ty.as_str())).collect();
+        assert_eq!(elements, vec![(0, "usize"), (1, "bool")]);
+        assert_eq!(split.ensures, vec!["r . 0 <= x", "r . 1 == (x > 0)"]);
+
+        let plain = funcs[1].returns.as_ref().unwrap();
+        assert_eq!(plain.binding, None);
+        assert_eq!(plain.ty, "u32");
+        assert!(plain.elements.is_empty());
+
+        let pair = funcs[2].returns.as_ref().unwrap();
+        assert_eq!(pair.binding, None);
+        assert_eq!(pair.elements.len(), 2);
+        assert_eq!(pair.elements[0].ty, "Seq<int>");
+
+        assert_eq!(funcs[3].returns, None);
+    }
+
+    #[test]
+    fn test_param_info() {
+        let code = r#"
+verus! {
+
+struct Buf {}
+
+impl Buf {
+    fn fill<'a>(&mut self, src: &[u8], key: [u8; 32], out: &'a mut Vec<u8>, n: usize, tracked t: &'a Seq<int>) {}
+}
+
+}
+"#;
+        let functions = parse_verus_file_core(code).unwrap();
+        let params: Vec<String> = functions[0]
+            .params
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} {} {} {:?} slice={} array={} {:?}",
+                    p.name, p.reference, p.ty, p.lifetime, p.is_slice, p.is_array, p.array_len
+                )
+            })
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                "self &mut Self None slice=false array=false None",
+                "src & [u8] None slice=true array=false None",
+                "key by_value [u8; 32] None slice=false array=true Some(32)",
+                "out &mut Vec<u8> Some(\"'a\") slice=false array=false None",
+                "n by_value usize None slice=false array=false None",
+                "t & Seq<int> Some(\"'a\") slice=false array=false None",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clause_mentions() {
+        let code = r#"
+verus! {
+
+pub const MAX: u64 = 100;
+
+spec fn double(x: int) -> int { 2 * x }
+spec fn limit() -> int { 7 }
+
+proof fn lemma_pure(a: int, b: int) -> (r: int)
+    requires a > 0,
+    ensures
+        r == double(a) + b,
+        forall|i: int| 0 <= i && i < a ==> double(i) < double(a),
+{
+    double(a) + b
+}
+
+proof fn lemma_global(a: u64)
+    requires a < MAX,
+    ensures a + 1 <= crate::MAX, a < limit(),
+{
+}
+
+}
+"#;
+        let functions = parse_verus_file_core(code).unwrap();
+        let pure = functions.iter().find(|f| f.name == "lemma_pure").unwrap();
+        assert!(pure.is_self_contained);
+        assert_eq!(pure.ensures_mentions[0].params, vec!["r", "a", "b"]);
+        assert_eq!(pure.ensures_mentions[1].params, vec!["a"]);
+        assert!(pure.ensures_mentions[1].externals.is_empty());
+
+        let global = functions.iter().find(|f| f.name == "lemma_global").unwrap();
+        assert!(!global.is_self_contained);
+        let externals: Vec<Vec<String>> =
+            global.requires_mentions.iter().chain(&global.ensures_mentions).map(|m| m.externals.clone()).collect();
+        assert_eq!(externals, vec![vec!["MAX"], vec!["crate::MAX"], vec!["limit"]]);
+    }
+
+    #[test]
+    fn test_decreases_measure() {
+        let code = r#"
+verus! {
+
+spec fn sum_to(n: nat) -> nat
+    decreases n,
+{
+    if n == 0 { 0 } else { n + sum_to((n - 1) as nat) }
+}
+
+proof fn lemma_len(s: Seq<int>)
+    decreases s.len(),
+{
+}
+
+proof fn lemma_tree(t: Tree)
+    decreases height(t),
+{
+}
+
+spec fn ackermann(m: nat, n: nat) -> nat
+    decreases m, n,
+{
+    0
+}
+
+proof fn lemma_gap(i: int, n: int)
+    decreases n - i,
+{
+}
+
+proof fn lemma_plain() {}
+
+}
+"#;
+        let funcs = parse_verus_file_core(code).unwrap();
+        let measure = |name: &str| {
+            let m = funcs.iter().find(|f| f.name == name).unwrap().decreases_measure.clone();
+            m.map(|m| (m.kind, m.params.join(","), m.components))
+        };
+        let expect = |kind: &str, params: &str, components| Some((kind.to_string(), params.to_string(), components));
+        assert_eq!(measure("sum_to"), expect("single_var", "n", 1));
+        assert_eq!(measure("lemma_len"), expect("call_on_param", "s", 1));
+        assert_eq!(measure("lemma_tree"), expect("call_on_param", "t", 1));
+        assert_eq!(measure("ackermann"), expect("lexicographic", "m,n", 2));
+        assert_eq!(measure("lemma_gap"), expect("other", "n,i", 1));
+        assert_eq!(measure("lemma_plain"), None);
+    }
+}
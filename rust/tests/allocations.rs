@@ -0,0 +1,51 @@
+//! Allocation benchmarks, in their own binary so the counting allocator
+//! only sees this crate's tests
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+
+use verus_parser::{parse_verus_file_core, set_file_path};
+
+/// System allocator that counts the allocations of each thread
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Number of allocations made by `f` on the current thread
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_file_path_allocations() {
+    let code: String = (0..500).map(|i| format!("verus! {{ proof fn lemma_{}() {{}} }}\n", i)).collect();
+    let mut functions = parse_verus_file_core(&code).unwrap();
+    let path = "src/very/deeply/nested/module/of/the/project/lemmas.rs";
+
+    let shared = count_allocations(|| set_file_path(&mut functions, path));
+    let mut copies = vec![String::new(); functions.len()];
+    let cloned = count_allocations(|| copies.iter_mut().for_each(|copy| *copy = path.to_string()));
+    println!("stamping {} functions: {} allocation(s) shared, {} as String", functions.len(), shared, cloned);
+    assert_eq!(shared, 1);
+    assert_eq!(cloned, functions.len());
+    assert!(functions.iter().all(|f| Arc::ptr_eq(&f.file_path, &functions[0].file_path)));
+}
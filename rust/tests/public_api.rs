@@ -0,0 +1,50 @@
+//! The result types are usable from Rust through the crate root
+
+use verus_parser::{FunctionSpecs, ParamInfo};
+
+#[test]
+fn test_core_types_reexported() {
+    let specs = FunctionSpecs {
+        name: "lemma_add_zero".to_string(),
+        impl_type: Some("Point".to_string()),
+        params: vec![ParamInfo { name: "x".to_string(), ..Default::default() }],
+        ensures: vec!["x + 0 == x".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(specs.qualified_name(), "Point::lemma_add_zero");
+
+    let json = serde_json::to_string(&specs).unwrap();
+    let back: FunctionSpecs = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, specs);
+}
+
+#[test]
+fn test_parse_functions_reexported() {
+    let code = "verus! { proof fn lemma_add_zero(x: int) ensures x + 0 == x {} }";
+    let functions = verus_parser::parse_verus_file_core(code).unwrap();
+    assert_eq!(functions[0].ensures, vec!["x + 0 == x"]);
+
+    let options = verus_parser::ParseOptions { mode: Some("spec".to_string()), ..Default::default() };
+    assert!(verus_parser::parse_guarded(code, None, &options).unwrap().is_empty());
+    let failure = verus_parser::parse_guarded("fn broken(", None, &options).unwrap_err();
+    assert_eq!(failure.kind, "parse_error");
+}
+
+#[test]
+fn test_analysis_functions_reexported() {
+    let lemmas = "verus! {\nproof fn lemma_used() ensures true {}\nproof fn lemma_unused() {}\n}\n";
+    let user = "verus! {\nproof fn lemma_caller() { lemma_used(); }\n}\n";
+    let files = vec![("a.rs".to_string(), lemmas.to_string()), ("b.rs".to_string(), user.to_string())];
+
+    let (unused, suppressed) = verus_parser::find_unused_lemmas_core(&files).unwrap();
+    let names: Vec<&str> = unused.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!((names, suppressed), (vec!["lemma_unused", "lemma_caller"], 0));
+    let fan_in = verus_parser::lemma_fan_in_core(&files).unwrap();
+    assert_eq!((fan_in[0].0.name.as_str(), fan_in[0].1), ("lemma_used", 1));
+
+    let (trivial, _) = verus_parser::lint_trivial_lemmas_core(lemmas).unwrap();
+    assert!(trivial.iter().any(|f| f.name == "lemma_used"));
+    let erased = verus_parser::erase_verus_core(lemmas, &[]).unwrap();
+    assert!(!erased.contains("proof fn"));
+    assert_ne!(verus_parser::capabilities_core().verus_syn_version, "unknown");
+}
@@ -0,0 +1,153 @@
+"""
+Integration tests for the verus_parser Rust extension module.
+
+Checks that the module keeps exposing the same functions and signatures,
+so the Rust side can be reorganized without breaking Python callers.
+"""
+
+import inspect
+
+import pytest
+
+verus_parser = pytest.importorskip("verus_parser")
+
+FUNCTIONS = [
+    "parse_verus_file",
+    "parse_verus_file_with_diagnostics",
+    "parse_verus_contents",
+    "parse_verus_directory",
+    "verus_files",
+    "to_verus_source",
+    "extract_function_specs",
+    "extract_proof_functions",
+    "extract_exec_functions",
+    "extract_checked_specs",
+    "nontrivial_proofs",
+    "duplicate_definitions",
+    "specs_outside_verus",
+    "is_valid_verus",
+    "validate_spec_exprs",
+    "specs_digest",
+    "erase_verus",
+    "filter_by_requires",
+    "applicable_lemmas",
+    "find_pattern",
+    "extract_loops",
+    "termination_report",
+    "spec_stats",
+    "render_markdown",
+    "group_lemmas",
+    "siblings",
+    "lemma_spec_associations",
+    "file_attributes",
+    "verus_block_ranges",
+    "external_lemma_usage",
+    "wrapper_lemmas",
+    "save_specs",
+    "load_specs",
+    "fuzzy_find",
+    "spec_diff",
+    "diff_directories",
+    "clause_ast",
+    "clause_field_paths",
+    "lemmas_constraining_field",
+    "predicate_calls",
+    "establishes_predicate",
+    "spec_visibility_lint",
+    "lint_spec_exprs",
+    "lint_trivial_lemmas",
+    "invariant_usage",
+    "extract_doc_examples",
+    "rename_function",
+    "usage_examples",
+    "context_for_error",
+    "call_context",
+    "ensures_unfolding",
+    "explain",
+    "truncation_report",
+    "lemma_fan_in",
+    "find_unused_lemmas",
+    "suppressed_findings",
+    "proof_todos",
+    "find_assert_by_label",
+    "to_dot",
+    "untriggered_quantifiers",
+    "parse_verus_directory_with_progress",
+    "public_surface",
+    "parse_verus_crate",
+    "spec_literals",
+    "extract_traits",
+    "extract_type_aliases",
+    "module_docs",
+    "module_tree",
+    "refinement_lint",
+    "functions_in_range",
+    "functions_touched_by_diff",
+    "to_scip",
+    "compute_proofs",
+    "suggest_lemma_name",
+    "draft_lemma_from_assert",
+    "reparse_incremental",
+    "version",
+    "capabilities",
+]
+
+
+class TestModuleSurface:
+    """Test the functions and classes exposed by the module"""
+
+    @pytest.mark.parametrize("name", FUNCTIONS)
+    def test_function_exposed(self, name):
+        """Test each function is present and callable"""
+        assert callable(getattr(verus_parser, name))
+
+    def test_parsed_file_class_exposed(self):
+        """Test the ParsedVerusFile class is present"""
+        assert inspect.isclass(verus_parser.ParsedVerusFile)
+
+    def test_core_signatures(self):
+        """Test the signatures the lemma finder relies on"""
+        assert list(inspect.signature(verus_parser.parse_verus_file).parameters)[0] == "content"
+        params = inspect.signature(verus_parser.extract_function_specs).parameters
+        assert list(params)[:2] == ["content", "function_name"]
+
+    def test_parse_options_are_explicit(self):
+        """Test parse options are named keyword-only parameters"""
+        params = inspect.signature(verus_parser.parse_verus_file).parameters
+        for name in ["item_macros", "module_doc", "max_bytes", "mode", "file_path", "line_offset"]:
+            assert params[name].kind == inspect.Parameter.KEYWORD_ONLY
+        with pytest.raises(TypeError):
+            verus_parser.parse_verus_file("fn f() {}", no_such_option=True)
+
+
+class TestModuleBehavior:
+    """Test the module still parses through its Python entry points"""
+
+    SOURCE = """verus! {
+proof fn lemma_add_zero(x: int)
+    ensures
+        x + 0 == x,
+{
+}
+}
+"""
+
+    def test_extract_function_specs(self):
+        """Test extracting the specs of a lemma from source text"""
+        specs = verus_parser.extract_function_specs(self.SOURCE, "lemma_add_zero")
+        assert specs["name"] == "lemma_add_zero"
+        assert specs["ensures"] == ["x + 0 == x"]
+
+    def test_lints_return_lists(self):
+        """Test lints return their findings alone, suppressions counted apart"""
+        source = "verus! {\n// verus-lemma-finder: allow(trivial_lemma)\nproof fn lemma_t() ensures true {}\n}\n"
+        assert verus_parser.lint_trivial_lemmas(source) == []
+        assert verus_parser.suppressed_findings(source)["trivial_lemma"] == 1
+
+    def test_applicable_lemmas_shape(self):
+        """Test applicable lemmas always come with their coercions"""
+        source = "verus! {\nproof fn lemma_pos(x: int)\n    requires x > 0,\n    ensures x >= 1,\n{\n}\n}\n"
+        [(specs, bindings, coercions)] = verus_parser.applicable_lemmas(source, ["a > 0"])
+        assert specs["name"] == "lemma_pos"
+        assert bindings == {"x": "a"}
+        assert coercions == []